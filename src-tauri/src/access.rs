@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Decides which files commands may touch. Paths arrive from the webview, so
+/// every file operation is checked here first: anything under one of `roots`
+/// (the notes root, which holds the workspaces and the assets next to their
+/// notes) is allowed, as are single files the user picked in a native dialog.
+pub struct AccessControl {
+    roots: Vec<PathBuf>,
+    /// Folders outside the roots that workspaces take notes from, kept in
+    /// step with the config.
+    extra_roots: Mutex<Vec<PathBuf>>,
+    granted: Mutex<HashSet<PathBuf>>,
+}
+
+impl AccessControl {
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        Self {
+            roots,
+            extra_roots: Mutex::new(Vec::new()),
+            granted: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_extra_roots(&self, roots: Vec<PathBuf>) {
+        *self.extra_roots.lock().unwrap() = roots;
+    }
+
+    /// Allows `path` for the rest of the session. Only for paths that came
+    /// from the user directly, never from the webview.
+    pub fn grant(&self, path: &Path) -> Result<(), String> {
+        let resolved = resolve(path)?;
+        self.granted.lock().unwrap().insert(resolved);
+        Ok(())
+    }
+
+    pub fn check(&self, path: &Path) -> Result<(), String> {
+        let resolved = resolve(path)?;
+        // Roots are resolved on each check since they may not exist yet, or
+        // may be reached through a symlink.
+        let extra_roots = self.extra_roots.lock().unwrap();
+        let in_root = self
+            .roots
+            .iter()
+            .chain(extra_roots.iter())
+            .any(|root| resolve(root).is_ok_and(|root| resolved.starts_with(root)));
+        if in_root || self.granted.lock().unwrap().contains(&resolved) {
+            Ok(())
+        } else {
+            Err(format!("Access to {} isn't allowed", path.display()))
+        }
+    }
+}
+
+/// Absolute, symlink-free form of `path`. The path may not exist yet, so its
+/// deepest existing ancestor is canonicalized and the rest appended.
+fn resolve(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Expected an absolute path, got {}", path.display()));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("Path {} can't contain \"..\"", path.display()));
+    }
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = existing.canonicalize() {
+            resolved.extend(missing.iter().rev());
+            return Ok(resolved);
+        }
+        missing.push(existing.file_name().ok_or("Invalid path")?);
+        existing = existing.parent().ok_or("Invalid path")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_check_allows_roots_and_granted_files_only() {
+        let base = std::env::temp_dir().join(format!("write-access-{}", std::process::id()));
+        let root = base.join("Notes");
+        fs::create_dir_all(root.join("Personal")).unwrap();
+        fs::write(base.join("secret.txt"), "x").unwrap();
+        let access = AccessControl::new(vec![root.clone()]);
+
+        assert!(access.check(&root.join("Personal/1-a.md")).is_ok());
+        assert!(access.check(&root.join("New/1-b.md")).is_ok());
+        assert!(access.check(&base.join("secret.txt")).is_err());
+        assert!(access.check(&root.join("Personal/../../secret.txt")).is_err());
+        assert!(access.check(Path::new("relative.md")).is_err());
+
+        fs::create_dir_all(base.join("docs")).unwrap();
+        assert!(access.check(&base.join("docs/setup.md")).is_err());
+        access.set_extra_roots(vec![base.join("docs")]);
+        assert!(access.check(&base.join("docs/setup.md")).is_ok());
+
+        access.grant(&base.join("secret.txt")).unwrap();
+        assert!(access.check(&base.join("secret.txt")).is_ok());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
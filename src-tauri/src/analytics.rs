@@ -0,0 +1,225 @@
+use serde::Serialize;
+
+/// Prose statistics for a note. Frontmatter, fenced code and markdown syntax
+/// are left out of every count.
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct TextStats {
+    pub words: usize,
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub sentences: usize,
+    pub paragraphs: usize,
+    pub avg_sentence_length: f64,
+    /// Flesch reading ease: higher is easier, 60–70 is plain English.
+    pub reading_ease: f64,
+    /// Flesch–Kincaid grade level.
+    pub grade_level: f64,
+}
+
+pub fn analyze(content: &str) -> TextStats {
+    let paragraphs = prose_paragraphs(content);
+    let mut stats = TextStats {
+        paragraphs: paragraphs.iter().filter(|p| !p.is_heading).count(),
+        ..TextStats::default()
+    };
+    let mut syllables = 0;
+    for paragraph in &paragraphs {
+        stats.characters += paragraph.text.chars().count();
+        stats.characters_no_spaces += paragraph.text.chars().filter(|c| !c.is_whitespace()).count();
+        let words: Vec<&str> = words(&paragraph.text).collect();
+        stats.words += words.len();
+        syllables += words.iter().map(|w| count_syllables(w)).sum::<usize>();
+        stats.sentences += count_sentences(&paragraph.text);
+    }
+    if stats.words > 0 && stats.sentences > 0 {
+        let words_per_sentence = stats.words as f64 / stats.sentences as f64;
+        let syllables_per_word = syllables as f64 / stats.words as f64;
+        stats.avg_sentence_length = round1(words_per_sentence);
+        stats.reading_ease = round1(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word);
+        stats.grade_level = round1(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59);
+    }
+    stats
+}
+
+/// Prose words in `content`, counted the way `analyze` counts them.
+pub fn count_words(content: &str) -> usize {
+    prose_paragraphs(content).iter().map(|p| words(&p.text).count()).sum()
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+struct Paragraph {
+    text: String,
+    is_heading: bool,
+}
+
+/// Blocks of prose with markdown markers removed. Headings are their own
+/// block so an unpunctuated heading counts as one sentence.
+fn prose_paragraphs(content: &str) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut lines = content.lines().peekable();
+    if lines.peek().is_some_and(|l| l.trim_end() == "---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if matches!(line.trim_end(), "---" | "...") {
+                break;
+            }
+        }
+    }
+
+    let flush = |current: &mut String, paragraphs: &mut Vec<Paragraph>, is_heading: bool| {
+        if words(current).next().is_some() {
+            paragraphs.push(Paragraph {
+                text: current.trim().to_string(),
+                is_heading,
+            });
+        }
+        current.clear();
+    };
+    let mut in_fence = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            flush(&mut current, &mut paragraphs, false);
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut current, &mut paragraphs, false);
+            continue;
+        }
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && (heading.is_empty() || heading.starts_with(' ')) {
+            flush(&mut current, &mut paragraphs, false);
+            current.push_str(heading.trim());
+            flush(&mut current, &mut paragraphs, true);
+            continue;
+        }
+        let text = strip_block_markers(trimmed);
+        // List items are separate thoughts even without a blank line between.
+        if text.len() != trimmed.len() && !trimmed.starts_with('>') {
+            flush(&mut current, &mut paragraphs, false);
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(text);
+    }
+    flush(&mut current, &mut paragraphs, false);
+    paragraphs
+}
+
+fn strip_block_markers(line: &str) -> &str {
+    let mut text = line.trim_start_matches('>').trim_start();
+    if let Some(rest) = text.strip_prefix(['-', '*', '+']).filter(|r| r.starts_with(' ')) {
+        text = rest.trim_start();
+    } else {
+        let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 && text[digits..].starts_with(". ") {
+            text = text[digits + 1..].trim_start();
+        }
+    }
+    for checkbox in ["[ ] ", "[x] ", "[X] "] {
+        if let Some(rest) = text.strip_prefix(checkbox) {
+            return rest.trim_start();
+        }
+    }
+    text
+}
+
+/// Tokens with at least one letter or digit, excluding URLs.
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .filter(|w| w.chars().any(char::is_alphanumeric) && !w.contains("://"))
+}
+
+/// Sentences end in `.`, `!` or `?` before whitespace; a trailing fragment
+/// without one still counts.
+fn count_sentences(text: &str) -> usize {
+    let mut count = 0;
+    let mut has_words = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() {
+            has_words = true;
+        } else if matches!(c, '.' | '!' | '?') && has_words {
+            while chars.peek().is_some_and(|n| matches!(n, '.' | '!' | '?' | '"' | '\'' | ')' | '”' | '’')) {
+                chars.next();
+            }
+            if chars.peek().is_none_or(|n| n.is_whitespace()) {
+                count += 1;
+                has_words = false;
+            }
+        }
+    }
+    count + usize::from(has_words)
+}
+
+/// Vowel groups, less a silent final `e`; at least one per word.
+fn count_syllables(word: &str) -> usize {
+    let word: String = word.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_lowercase).collect();
+    if word.is_empty() {
+        return 0;
+    }
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let mut groups = 0;
+    let mut prev_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !prev_vowel {
+            groups += 1;
+        }
+        prev_vowel = vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && groups > 1 {
+        groups -= 1;
+    }
+    groups.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_counts_prose_only() {
+        let content = "---\ntags: [a]\n---\n# Plan\n\nThe cat sat. It was happy!\nDone\n\n- [ ] buy milk\n- call home\n\n```\nlet code = 1;\n```\n";
+        let stats = analyze(content);
+        assert_eq!(stats.words, 12);
+        assert_eq!(stats.sentences, 6);
+        assert_eq!(stats.paragraphs, 3);
+        assert_eq!(stats.avg_sentence_length, 2.0);
+    }
+
+    #[test]
+    fn test_count_sentences_handles_abbreviated_endings() {
+        assert_eq!(count_sentences("Wait... what?! Version 1.2 is out"), 3);
+        assert_eq!(count_sentences("..."), 0);
+    }
+
+    #[test]
+    fn test_count_syllables() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("readability"), 5);
+    }
+
+    #[test]
+    fn test_count_words_matches_analyze() {
+        let content = "---\ntags: [a]\n---\n# Title\n\nSome *prose* here.\n\n```\nlet code = 1;\n```\n";
+        assert_eq!(count_words(content), analyze(content).words);
+        assert_eq!(count_words(content), 4);
+    }
+
+    #[test]
+    fn test_analyze_empty_note() {
+        assert_eq!(analyze("# \n\n"), TextStats::default());
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::clip::{self, Clip};
+use crate::watcher::WorkspaceWatcher;
+use crate::{
+    add_note, check_note_path, note_size_limit, open_note_content, reader, scan_notes, search_workspace, secrets, AppState,
+};
+
+pub const DEFAULT_PORT: u16 = 27183;
+
+/// Request bodies past this are refused.
+const MAX_BODY_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ApiServerInfo {
+    pub url: String,
+    pub token: String,
+}
+
+struct Running {
+    server: Arc<Server>,
+    port: u16,
+    token: Arc<Mutex<String>>,
+}
+
+/// The opt-in HTTP API for scripts and launcher or browser extensions. It
+/// only listens on localhost and every request needs the token.
+#[derive(Default)]
+pub struct ApiServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl ApiServer {
+    pub fn start(&self, app: AppHandle, port: u16) -> Result<ApiServerInfo, String> {
+        let mut running = self.running.lock().unwrap();
+        if let Some(current) = running.as_ref().filter(|r| r.port == port) {
+            return Ok(current.info());
+        }
+        if let Some(previous) = running.take() {
+            previous.server.unblock();
+        }
+        let token = Arc::new(Mutex::new(token()?));
+        let server = Arc::new(
+            Server::http(("127.0.0.1", port)).map_err(|e| format!("Couldn't listen on port {}: {}", port, e))?,
+        );
+        let listener = Arc::clone(&server);
+        let expected = Arc::clone(&token);
+        std::thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                let token = expected.lock().unwrap().clone();
+                handle(&app, &token, request);
+            }
+        });
+        let started = Running { server, port, token };
+        let info = started.info();
+        *running = Some(started);
+        Ok(info)
+    }
+
+    /// Makes a running server accept `token` instead of the old one.
+    pub fn set_token(&self, token: &str) {
+        if let Some(running) = self.running.lock().unwrap().as_ref() {
+            *running.token.lock().unwrap() = token.to_string();
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.server.unblock();
+        }
+    }
+
+    pub fn info(&self) -> Option<ApiServerInfo> {
+        self.running.lock().unwrap().as_ref().map(Running::info)
+    }
+}
+
+impl Running {
+    fn info(&self) -> ApiServerInfo {
+        ApiServerInfo {
+            url: format!("http://127.0.0.1:{}", self.port),
+            token: self.token.lock().unwrap().clone(),
+        }
+    }
+}
+
+/// The token clients send as `Authorization: Bearer …`, kept in the
+/// keychain and made on first use.
+fn token() -> Result<String, String> {
+    if let Some(token) = secrets::get(secrets::API_TOKEN)? {
+        return Ok(token);
+    }
+    let token = secrets::generate()?;
+    secrets::set(secrets::API_TOKEN, &token)?;
+    Ok(token)
+}
+
+/// Replaces the token, so clients holding the old one are locked out.
+pub fn regenerate_token() -> Result<String, String> {
+    let token = secrets::generate()?;
+    secrets::set(secrets::API_TOKEN, &token)?;
+    Ok(token)
+}
+
+#[derive(Deserialize)]
+struct NewNote {
+    content: String,
+}
+
+fn handle(app: &AppHandle, token: &str, mut request: Request) {
+    let (status, body) = if !is_authorized(&request, token) {
+        (401, json!({ "error": "Missing or wrong API token" }))
+    } else {
+        match route(app, &mut request) {
+            Ok(body) => (200, body),
+            Err((status, message)) => (status, json!({ "error": message })),
+        }
+    };
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).expect("valid header");
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    let _ = request.respond(response);
+}
+
+fn route(app: &AppHandle, request: &mut Request) -> Result<Value, (u16, String)> {
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let (path, query) = split_url(request.url());
+    let method = request.method().clone();
+    let param = |name: &str| query_param(&query, name).ok_or((400, format!("Missing \"{}\" parameter", name)));
+    let internal = |e: String| (500, e);
+    match (&method, path.as_str()) {
+        (Method::Get, "/notes") => {
+            let notes = scan_notes(&state, &watcher, true).map_err(internal)?;
+            Ok(json!(notes))
+        }
+        (Method::Get, "/notes/search") => {
+            let limit = query_param(&query, "limit").and_then(|l| l.parse().ok());
+            let hits = search_workspace(&state, &watcher, &param("q")?, limit).map_err(internal)?;
+            Ok(json!(hits))
+        }
+        (Method::Get, "/note") => {
+            let path = param("path")?;
+            let note_path = Path::new(&path);
+            check_note_path(&state, note_path).map_err(|e| (403, e.to_string()))?;
+            let content = reader::read_whole(note_path, note_size_limit(&state))
+                .and_then(|content| open_note_content(&state, note_path, content))
+                .map_err(|e| (404, e))?;
+            Ok(json!({ "path": path, "content": content }))
+        }
+        (Method::Post, "/notes") => {
+            let note: NewNote =
+                serde_json::from_str(&read_body(request)?).map_err(|e| (400, format!("Invalid note: {}", e)))?;
+            let _guard = state.write_lock.lock().unwrap();
+            let path = add_note(app, &state, &watcher, &note.content).map_err(internal)?;
+            Ok(json!({ "path": path.to_string_lossy() }))
+        }
+        (Method::Post, "/clip") => {
+            let clip: Clip =
+                serde_json::from_str(&read_body(request)?).map_err(|e| (400, format!("Invalid clip: {}", e)))?;
+            let clipped = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+            let content = clip::to_note(&clip, &clipped).map_err(|e| (400, e))?;
+            let _guard = state.write_lock.lock().unwrap();
+            let path = add_note(app, &state, &watcher, &content).map_err(internal)?;
+            Ok(json!({ "path": path.to_string_lossy() }))
+        }
+        (_, "/notes" | "/notes/search" | "/note" | "/clip") => Err((405, "Method not allowed".to_string())),
+        _ => Err((404, "Not found".to_string())),
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<String, (u16, String)> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, e.to_string()))?;
+    Ok(body)
+}
+
+fn is_authorized(request: &Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|sent| constant_time_eq(sent.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn split_url(url: &str) -> (String, String) {
+    match url.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (url.to_string(), String::new()),
+    }
+}
+
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 3;
+                        continue;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_param_decodes_values() {
+        let (path, query) = split_url("/notes/search?q=caf%C3%A9+plans&limit=5");
+        assert_eq!(path, "/notes/search");
+        assert_eq!(query_param(&query, "q").as_deref(), Some("café plans"));
+        assert_eq!(query_param(&query, "limit").as_deref(), Some("5"));
+        assert_eq!(query_param(&query, "path"), None);
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Months};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Folder in a workspace that archived notes are moved into. It's a
+/// subfolder, so they drop out of the note list but stay on disk.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Event emitted with the `ArchiveReport`s of a run that archived notes.
+pub const NOTES_ARCHIVED: &str = "notes:auto-archived";
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Archives a workspace's notes once they've gone `months` without an edit.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ArchivePolicy {
+    pub months: u32,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ArchivedNote {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ArchiveReport {
+    pub workspace_id: String,
+    pub archived: Vec<ArchivedNote>,
+}
+
+/// Of `notes`, with their last modified time in unix seconds, those last
+/// modified more than `months` before `now`.
+pub fn due(notes: &[(PathBuf, u64)], months: u32, now: u64) -> Vec<PathBuf> {
+    let cutoff = DateTime::from_timestamp(now as i64, 0).and_then(|now| now.checked_sub_months(Months::new(months)));
+    let Some(cutoff) = cutoff else {
+        return Vec::new();
+    };
+    let cutoff = cutoff.timestamp().max(0) as u64;
+    let mut due: Vec<PathBuf> = notes
+        .iter()
+        .filter(|(_, modified)| *modified < cutoff)
+        .map(|(path, _)| path.clone())
+        .collect();
+    due.sort();
+    due
+}
+
+/// Where `note` goes in the archive of `workspace_dir`: under the same
+/// name, numbered if a note of that name was archived before.
+pub fn target(workspace_dir: &Path, note: &Path) -> Result<PathBuf, String> {
+    let archive_dir = workspace_dir.join(ARCHIVE_DIR);
+    fs::create_dir_all(&archive_dir).map_err(|e| e.to_string())?;
+    let stem = note.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = note.extension().unwrap_or_default().to_string_lossy();
+    let mut target = archive_dir.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while target.exists() {
+        target = archive_dir.join(format!("{}-{}.{}", stem, n, extension));
+        n += 1;
+    }
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due() {
+        // 2026-10-15T00:00:00Z
+        let now = 1_792_022_400;
+        let day = 86_400;
+        let notes = vec![
+            (PathBuf::from("/w/3-recent.md"), now - 10 * day),
+            (PathBuf::from("/w/2-old.md"), now - 200 * day),
+            (PathBuf::from("/w/1-edge.md"), now - 93 * day),
+        ];
+        assert_eq!(
+            due(&notes, 3, now),
+            vec![PathBuf::from("/w/1-edge.md"), PathBuf::from("/w/2-old.md")]
+        );
+        assert_eq!(due(&notes, 12, now), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn test_target() {
+        let dir = std::env::temp_dir().join(format!("write-archive-{}", std::process::id()));
+        let note = dir.join("1-a.md");
+        assert_eq!(target(&dir, &note).unwrap(), dir.join(ARCHIVE_DIR).join("1-a.md"));
+        fs::write(dir.join(ARCHIVE_DIR).join("1-a.md"), "archived before").unwrap();
+        assert_eq!(target(&dir, &note).unwrap(), dir.join(ARCHIVE_DIR).join("1-a-2.md"));
+        assert_eq!(target(&dir, &dir.join("2-b.org")).unwrap(), dir.join(ARCHIVE_DIR).join("2-b.org"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
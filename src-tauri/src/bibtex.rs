@@ -0,0 +1,306 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The bibliography file in a workspace folder that `[@key]` citations
+/// refer to.
+pub const BIBLIOGRAPHY_FILE: &str = "references.bib";
+
+/// An entry of a BibTeX file, with field names lowercased and braces
+/// taken out of the values.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub key: String,
+    pub kind: String,
+    pub title: String,
+    pub author: String,
+    pub year: String,
+    #[serde(skip)]
+    pub fields: HashMap<String, String>,
+}
+
+impl Entry {
+    fn field(&self, name: &str) -> &str {
+        self.fields.get(name).map_or("", String::as_str)
+    }
+
+    /// `Smith`, `Smith and Doe` or `Smith et al.`, from the authors, or
+    /// the editors when there are none.
+    fn short_authors(&self) -> String {
+        let people = if self.author.is_empty() { self.field("editor") } else { &self.author };
+        let surnames: Vec<&str> = people.split(" and ").map(surname).filter(|s| !s.is_empty()).collect();
+        match surnames.as_slice() {
+            [] => self.key.clone(),
+            [one] => one.to_string(),
+            [one, two] => format!("{} and {}", one, two),
+            [one, ..] => format!("{} et al.", one),
+        }
+    }
+}
+
+/// The family name in `Smith, John` or `John Smith`.
+fn surname(person: &str) -> &str {
+    let person = person.trim();
+    match person.split_once(',') {
+        Some((last, _)) => last.trim(),
+        None => person.rsplit(' ').next().unwrap_or(person),
+    }
+}
+
+/// The entries in BibTeX `text`. `@comment`, `@string` and `@preamble`
+/// are skipped, as is anything that doesn't parse.
+pub fn parse(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let kind = rest[..open].trim().to_lowercase();
+        let close = if rest.as_bytes()[open] == b'{' { b'}' } else { b')' };
+        let Some(len) = balanced_len(&rest[open..], close) else {
+            break;
+        };
+        let body = &rest[open + 1..open + len - 1];
+        rest = &rest[open + len..];
+        if matches!(kind.as_str(), "comment" | "string" | "preamble") || kind.is_empty() {
+            continue;
+        }
+        if let Some(entry) = parse_entry(kind, body) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+fn parse_entry(kind: String, body: &str) -> Option<Entry> {
+    let (key, mut rest) = body.split_once(',').unwrap_or((body, ""));
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    let mut fields = HashMap::new();
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().trim_start_matches(',').trim().to_lowercase();
+        let after = rest[eq + 1..].trim_start();
+        let (value, len) = match after.as_bytes().first() {
+            Some(b'{') => {
+                let len = balanced_len(after, b'}')?;
+                (&after[1..len - 1], len)
+            }
+            Some(b'"') => {
+                let len = after[1..].find('"')? + 2;
+                (&after[1..len - 1], len)
+            }
+            _ => {
+                let len = after.find(',').unwrap_or(after.len());
+                (after[..len].trim(), len)
+            }
+        };
+        let value: String = value.chars().filter(|c| !matches!(c, '{' | '}')).collect();
+        fields.insert(name, value.split_whitespace().collect::<Vec<_>>().join(" "));
+        rest = &after[len..];
+    }
+    let get = |name: &str| fields.get(name).cloned().unwrap_or_default();
+    Some(Entry {
+        key: key.to_string(),
+        kind,
+        title: get("title"),
+        author: get("author"),
+        year: get("year"),
+        fields,
+    })
+}
+
+/// Bytes up to and including the `close` that matches the opening bracket
+/// `text` starts with, counting nested braces.
+fn balanced_len(text: &str, close: u8) -> Option<usize> {
+    let open = *text.as_bytes().first()?;
+    let mut depth = 0;
+    for (i, byte) in text.bytes().enumerate() {
+        match byte {
+            b if b == open => depth += 1,
+            b if b == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `content` with `[@key]` citations, like `[@smith2020, p. 4; @doe2019]`,
+/// written as `(Smith 2020, p. 4; Doe 2019)`, and a References section
+/// listing the cited entries. Citations of unknown keys and citations in
+/// code are left alone.
+pub fn cite(content: &str, entries: &[Entry]) -> String {
+    let by_key: HashMap<&str, &Entry> = entries.iter().map(|e| (e.key.as_str(), e)).collect();
+    let mut cited: Vec<&Entry> = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    let mut in_fence = false;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            out.push_str(line);
+            continue;
+        }
+        out.push_str(&cite_line(line, &by_key, &mut cited));
+    }
+    if cited.is_empty() {
+        return out;
+    }
+    cited.sort_by(|a, b| a.short_authors().cmp(&b.short_authors()).then(a.year.cmp(&b.year)));
+    let references: Vec<String> = cited.iter().map(|entry| format!("- {}", reference(entry))).collect();
+    format!("{}\n\n## References\n\n{}\n", out.trim_end(), references.join("\n"))
+}
+
+fn cite_line<'a>(line: &str, by_key: &HashMap<&str, &'a Entry>, cited: &mut Vec<&'a Entry>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_code = false;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        if rest.starts_with('`') {
+            in_code = !in_code;
+        } else if !in_code && rest.starts_with("[@") {
+            if let Some(len) = rest.find(']') {
+                if let Some(text) = citation(&rest[1..len], by_key, cited) {
+                    out.push_str(&text);
+                    i += len + 1;
+                    continue;
+                }
+            }
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+/// `(Smith 2020, p. 4; Doe 2019)` for the inside of a citation, when every
+/// key in it is known.
+fn citation<'a>(inner: &str, by_key: &HashMap<&str, &'a Entry>, cited: &mut Vec<&'a Entry>) -> Option<String> {
+    let mut parts = Vec::new();
+    let mut entries = Vec::new();
+    for part in inner.split(';') {
+        let part = part.trim().strip_prefix('@')?;
+        let (key, locator) = match part.split_once(',') {
+            Some((key, locator)) => (key.trim(), Some(locator.trim())),
+            None => (part.trim(), None),
+        };
+        let entry = *by_key.get(key)?;
+        let mut text = match entry.year.as_str() {
+            "" => entry.short_authors(),
+            year => format!("{} {}", entry.short_authors(), year),
+        };
+        if let Some(locator) = locator.filter(|l| !l.is_empty()) {
+            text = format!("{}, {}", text, locator);
+        }
+        parts.push(text);
+        entries.push(entry);
+    }
+    for entry in entries {
+        if !cited.iter().any(|e| e.key == entry.key) {
+            cited.push(entry);
+        }
+    }
+    Some(format!("({})", parts.join("; ")))
+}
+
+/// An entry in the References list: authors, year, title and where it was
+/// published.
+fn reference(entry: &Entry) -> String {
+    let mut text = match entry.author.as_str() {
+        "" => entry.short_authors(),
+        authors => authors.to_string(),
+    };
+    if !entry.year.is_empty() {
+        text = format!("{} ({})", text, entry.year);
+    }
+    if !entry.title.is_empty() {
+        text = format!("{}. *{}*", text, entry.title);
+    }
+    let venue = ["journal", "booktitle", "publisher", "howpublished", "school"]
+        .iter()
+        .map(|name| entry.field(name))
+        .find(|value| !value.is_empty());
+    if let Some(venue) = venue {
+        text = format!("{}. {}", text, venue);
+    }
+    format!("{}.", text)
+}
+
+/// Entries matching `query` by key, title or author, best first.
+pub fn lookup<'a>(entries: &'a [Entry], query: &str, limit: usize) -> Vec<&'a Entry> {
+    let query = query.trim().trim_start_matches('@');
+    let mut matched: Vec<(u8, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| {
+            let tier = [&entry.key, &entry.title, &entry.author]
+                .iter()
+                .filter_map(|text| crate::links::match_tier(text, query))
+                .min()?;
+            Some((tier, entry))
+        })
+        .collect();
+    matched.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.key.cmp(&b.1.key)));
+    matched.into_iter().take(limit).map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIB: &str = r#"
+@comment{ignored}
+@article{smith2020,
+  author = {Smith, John and Doe, Jane},
+  title = {A {Great} Title},
+  journal = "Journal of Notes",
+  year = 2020,
+}
+@book{lee2019, title={Writing}, author={Ann Lee and Bo Kim and Cy Park}, year={2019}, publisher={Press}}
+"#;
+
+    #[test]
+    fn test_parse() {
+        let entries = parse(BIB);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key, "smith2020");
+        assert_eq!(entries[0].kind, "article");
+        assert_eq!(entries[0].title, "A Great Title");
+        assert_eq!(entries[0].author, "Smith, John and Doe, Jane");
+        assert_eq!(entries[0].year, "2020");
+        assert_eq!(entries[0].field("journal"), "Journal of Notes");
+        assert_eq!(entries[1].short_authors(), "Lee et al.");
+    }
+
+    #[test]
+    fn test_cite() {
+        let entries = parse(BIB);
+        let content = "# Essay\n\nAs shown [@smith2020, p. 4; @lee2019], but [@missing].\n\n`[@smith2020]`\n";
+        assert_eq!(
+            cite(content, &entries),
+            "# Essay\n\nAs shown (Smith and Doe 2020, p. 4; Lee et al. 2019), but [@missing].\n\n`[@smith2020]`\n\n## References\n\n- Ann Lee and Bo Kim and Cy Park (2019). *Writing*. Press.\n- Smith, John and Doe, Jane (2020). *A Great Title*. Journal of Notes.\n"
+        );
+        assert_eq!(cite("No citations\n", &entries), "No citations\n");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let entries = parse(BIB);
+        let keys = |query| lookup(&entries, query, 10).into_iter().map(|e| e.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys("@smi"), vec!["smith2020"]);
+        assert_eq!(keys("writ"), vec!["lee2019"]);
+        assert_eq!(keys("doe"), vec!["smith2020"]);
+    }
+}
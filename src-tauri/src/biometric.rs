@@ -0,0 +1,73 @@
+#[cfg(target_os = "macos")]
+mod platform {
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use std::sync::mpsc;
+
+    const POLICY: LAPolicy = LAPolicy::DeviceOwnerAuthenticationWithBiometrics;
+
+    pub fn is_available() -> bool {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(POLICY) }.is_ok()
+    }
+
+    /// Shows the Touch ID prompt and blocks until it's answered.
+    pub fn authenticate(reason: &str) -> Result<(), String> {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(POLICY) }.map_err(|e| e.localizedDescription().to_string())?;
+        let (tx, rx) = mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, error: *mut NSError| {
+            let result = if success.as_bool() {
+                Ok(())
+            } else {
+                Err(unsafe { error.as_ref() }
+                    .map(|e| e.localizedDescription().to_string())
+                    .unwrap_or_else(|| "Authentication failed".to_string()))
+            };
+            let _ = tx.send(result);
+        });
+        unsafe { context.evaluatePolicy_localizedReason_reply(POLICY, &NSString::from_str(reason), &reply) };
+        rx.recv().map_err(|_| "Authentication was interrupted".to_string())?
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub fn is_available() -> bool {
+        UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|op| op.get())
+            .is_ok_and(|availability| availability == UserConsentVerifierAvailability::Available)
+    }
+
+    /// Shows the Windows Hello prompt and blocks until it's answered.
+    pub fn authenticate(reason: &str) -> Result<(), String> {
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+            .and_then(|op| op.get())
+            .map_err(|e| e.message().to_string())?;
+        match result {
+            UserConsentVerificationResult::Verified => Ok(()),
+            UserConsentVerificationResult::Canceled => Err("Authentication was canceled".to_string()),
+            _ => Err("Authentication failed".to_string()),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    pub fn is_available() -> bool {
+        false
+    }
+
+    pub fn authenticate(_reason: &str) -> Result<(), String> {
+        Err("Biometric unlock isn't supported on this platform".to_string())
+    }
+}
+
+pub use platform::{authenticate, is_available};
@@ -0,0 +1,303 @@
+use crate::tags;
+use crate::tasks::{self, Task};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// What a board is built from.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardSource {
+    /// A note whose `##` headings are the columns and whose top-level list
+    /// items under them are the cards.
+    Note(String),
+    /// Tasks across the workspace tagged under this tag: `#status/doing` is
+    /// a card in the `doing` column of the `status` board.
+    Tag(String),
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+pub struct Board {
+    pub columns: Vec<Column>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Column {
+    pub name: String,
+    pub cards: Vec<Card>,
+}
+
+/// A card, pointing at the 1-based `line` it starts on. `done` is set when
+/// it's a task.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Card {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+    pub done: Option<bool>,
+}
+
+/// A column of a note board: its heading's line index and the line ranges
+/// of its cards.
+struct Section {
+    name: String,
+    heading: usize,
+    cards: Vec<Range<usize>>,
+}
+
+/// The board in the note at `path`.
+pub fn note_board(path: &str, content: &str) -> Board {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let columns = sections(&lines)
+        .into_iter()
+        .map(|section| Column {
+            name: section.name,
+            cards: section
+                .cards
+                .iter()
+                .filter_map(|range| {
+                    let (text, done) = card_text(list_item(lines[range.start])?);
+                    Some(Card {
+                        path: path.to_string(),
+                        line: range.start + 1,
+                        text: text.to_string(),
+                        done,
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+    Board { columns }
+}
+
+/// The board of `tasks` tagged under `tag`, with a column per nested tag in
+/// the order they first appear.
+pub fn tag_board(tasks: impl IntoIterator<Item = Task>, tag: &str) -> Board {
+    let mut board = Board::default();
+    for task in tasks {
+        let Some(name) = tag_column(&task.text, tag) else {
+            continue;
+        };
+        let card = Card {
+            path: task.path,
+            line: task.line,
+            text: task.text,
+            done: Some(task.done),
+        };
+        match board.columns.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&name)) {
+            Some(column) => column.cards.push(card),
+            None => board.columns.push(Column { name, cards: vec![card] }),
+        }
+    }
+    board
+}
+
+/// `content` with the card starting on 1-based `line` moved to `column`,
+/// before its card at `position` or at the end. Fails when that line no
+/// longer holds a card reading `expected_text`.
+pub fn move_in_note(
+    content: &str,
+    line: usize,
+    expected_text: &str,
+    column: &str,
+    position: Option<usize>,
+) -> Result<String, String> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let card = sections(&lines)
+        .into_iter()
+        .flat_map(|section| section.cards)
+        .find(|range| range.start + 1 == line)
+        .ok_or("Card is no longer on that line")?;
+    let text = list_item(lines[card.start]).map(|item| card_text(item).0);
+    if text != Some(expected_text.trim()) {
+        return Err("Card has changed since the board was loaded".to_string());
+    }
+    let mut moved = lines[card.clone()].concat();
+    if !moved.ends_with('\n') {
+        moved.push('\n');
+    }
+    let rest: Vec<&str> = lines[..card.start].iter().chain(&lines[card.end..]).copied().collect();
+    let target = sections(&rest)
+        .into_iter()
+        .find(|section| section.name.eq_ignore_ascii_case(column.trim()))
+        .ok_or_else(|| format!("No column {}", column))?;
+    let at = match position.and_then(|p| target.cards.get(p)) {
+        Some(before) => before.start,
+        None => match target.cards.last() {
+            Some(last) => last.end,
+            None => target.heading + 1 + usize::from(rest.get(target.heading + 1).is_some_and(|l| l.trim().is_empty())),
+        },
+    };
+    if rest.get(at).is_some_and(|next| !next.trim().is_empty() && list_item(next).is_none()) {
+        moved.push('\n');
+    }
+    let mut out = rest[..at].concat();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&moved);
+    out.push_str(&rest[at..].concat());
+    Ok(out)
+}
+
+/// `content` with the task on 1-based `line` retagged into `column` of the
+/// `tag` board. Fails when that line no longer holds the task reading
+/// `expected_text`, or it isn't on the board.
+pub fn move_tagged(content: &str, line: usize, expected_text: &str, tag: &str, column: &str) -> Result<String, String> {
+    let task = tasks::parse_tasks(content)
+        .into_iter()
+        .find(|(task_line, ..)| *task_line == line)
+        .filter(|(_, text, ..)| text == expected_text.trim())
+        .ok_or("Card has changed since the board was loaded")?;
+    let from = tag_column(&task.1, tag).ok_or("Card isn't on the board")?;
+    let column = column.trim().trim_matches(tags::SEPARATOR);
+    let to = format!("{}{}{}", tag, tags::SEPARATOR, column);
+    if !tags::is_tag(&to) {
+        return Err(format!("Invalid column {}", column));
+    }
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+    let from = format!("{}{}{}", tag, tags::SEPARATOR, from);
+    if let Some(retagged) = tags::rename_tag(&lines[line - 1], &from, &to) {
+        lines[line - 1] = retagged;
+    }
+    Ok(lines.concat())
+}
+
+/// The column of `text` on the `tag` board: the rest of its first tag
+/// nested under `tag`.
+fn tag_column(text: &str, tag: &str) -> Option<String> {
+    tags::parse_tags(text)
+        .into_iter()
+        .find(|t| t.len() > tag.len() && tags::is_within(t, tag))
+        .map(|t| t[tag.len() + 1..].to_string())
+}
+
+/// The `##` sections of a note, outside code, with their cards: each
+/// top-level list item and the indented lines right after it.
+fn sections(lines: &[&str]) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("## ") {
+            sections.push(Section {
+                name: name.trim().trim_end_matches('#').trim().to_string(),
+                heading: i,
+                cards: Vec::new(),
+            });
+        } else if line.starts_with("# ") {
+            sections.push(Section {
+                name: String::new(),
+                heading: i,
+                cards: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            let continues = line.starts_with([' ', '\t']) && !trimmed.is_empty();
+            match section.cards.last_mut() {
+                Some(card) if card.end == i && continues => card.end = i + 1,
+                _ if list_item(line).is_some() => section.cards.push(i..i + 1),
+                _ => {}
+            }
+        }
+    }
+    sections.retain(|section| !section.name.is_empty());
+    sections
+}
+
+/// The text of a top-level list item on `line`, after its marker.
+fn list_item(line: &str) -> Option<&str> {
+    let marker_len = if line.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || !line[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let text = line[marker_len..].strip_prefix([' ', '\t'])?;
+    Some(text.trim_end_matches(['\n', '\r']))
+}
+
+/// A list item's text without its checkbox, and whether that's ticked.
+fn card_text(item: &str) -> (&str, Option<bool>) {
+    let done = match item.get(..3) {
+        Some("[ ]") => Some(false),
+        Some("[x]" | "[X]") => Some(true),
+        _ => None,
+    };
+    match done {
+        Some(_) if item.len() == 3 || item[3..].starts_with(' ') => (item[3..].trim(), done),
+        _ => (item.trim(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "# Launch\n\nSome context.\n\n## Todo\n\n- Write docs\n  with examples\n- [ ] Record demo\n\n## Doing\n\n* Fix login\n\n## Done\n\n```\n- not a card\n```\n";
+
+    #[test]
+    fn test_note_board() {
+        let board = note_board("/notes/launch.md", NOTE);
+        type Card<'a> = (usize, &'a str, Option<bool>);
+        let columns: Vec<(&str, Vec<Card>)> = board
+            .columns
+            .iter()
+            .map(|c| {
+                let cards = c.cards.iter().map(|card| (card.line, card.text.as_str(), card.done)).collect();
+                (c.name.as_str(), cards)
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("Todo", vec![(7, "Write docs", None), (9, "Record demo", Some(false))]),
+                ("Doing", vec![(13, "Fix login", None)]),
+                ("Done", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_move_in_note() {
+        let moved = move_in_note(NOTE, 7, "Write docs", "doing", Some(0)).unwrap();
+        assert_eq!(
+            moved,
+            "# Launch\n\nSome context.\n\n## Todo\n\n- [ ] Record demo\n\n## Doing\n\n- Write docs\n  with examples\n* Fix login\n\n## Done\n\n```\n- not a card\n```\n"
+        );
+        let moved = move_in_note(NOTE, 13, "Fix login", "Done", None).unwrap();
+        assert!(moved.ends_with("## Doing\n\n\n## Done\n\n* Fix login\n\n```\n- not a card\n```\n"));
+        assert!(move_in_note(NOTE, 7, "Write tests", "Doing", None).is_err());
+        assert!(move_in_note(NOTE, 7, "Write docs", "Later", None).is_err());
+    }
+
+    #[test]
+    fn test_tagged_cards() {
+        let content = "- [ ] Ship it #status/doing\n- [x] Plan #status/done #team\n- [ ] Untracked #status\n";
+        let tasks = tasks::parse_tasks(content).into_iter().map(|(line, text, done, depth)| Task {
+            path: "/notes/a.md".to_string(),
+            note_title: "A".to_string(),
+            line,
+            text,
+            done,
+            depth,
+            due: None,
+        });
+        let board = tag_board(tasks, "status");
+        let names: Vec<&str> = board.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["doing", "done"]);
+        assert_eq!(
+            move_tagged(content, 1, "Ship it #status/doing", "status", "done").unwrap(),
+            "- [ ] Ship it #status/done\n- [x] Plan #status/done #team\n- [ ] Untracked #status\n"
+        );
+        assert!(move_tagged(content, 3, "Untracked #status", "status", "done").is_err());
+    }
+}
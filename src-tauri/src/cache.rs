@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::Path;
+
+use crate::store::MetaStore;
+use crate::NoteHeader;
+
+/// Identifies one version of a file's content without reading it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct FileStamp {
+    pub mtime_ns: u64,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let mtime_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Self {
+            mtime_ns,
+            size: metadata.len(),
+        }
+    }
+
+    pub fn modified_secs(&self) -> u64 {
+        self.mtime_ns / 1_000_000_000
+    }
+}
+
+/// When the file was created, in unix seconds, on file systems that record
+/// it (APFS, NTFS, and ext4 or btrfs through statx).
+pub fn birth_secs(metadata: &Metadata) -> Option<u64> {
+    let created = metadata.created().ok()?;
+    created.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Metadata derived from a note's content, reused while the file is unchanged.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct CachedNote {
+    #[serde(flatten)]
+    pub stamp: FileStamp,
+    pub title: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// Note metadata cache persisted in the metadata store, so listing a large
+/// workspace doesn't open every file on each call. Changes are kept in memory
+/// and written on `save`.
+pub struct NoteCache {
+    store: MetaStore,
+    entries: HashMap<String, CachedNote>,
+    /// Paths changed since the last save; `None` marks a removal.
+    pending: HashMap<String, Option<CachedNote>>,
+}
+
+impl NoteCache {
+    pub fn load(store: MetaStore) -> Self {
+        let entries = load_entries(&store).unwrap_or_default();
+        Self {
+            store,
+            entries,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached entry for `path` if the file is unchanged.
+    pub fn get(&self, path: &Path, stamp: FileStamp) -> Option<&CachedNote> {
+        let entry = self.entries.get(&*path.to_string_lossy())?;
+        (entry.stamp == stamp).then_some(entry)
+    }
+
+    /// Returns the cached entry for `path` if it was computed from a file with
+    /// the same stamp, otherwise computes a fresh one with `compute`.
+    pub fn get_or_insert(&mut self, path: &Path, stamp: FileStamp, compute: impl FnOnce() -> NoteHeader) -> CachedNote {
+        if let Some(entry) = self.get(path, stamp) {
+            return entry.clone();
+        }
+        let header = compute();
+        let entry = CachedNote {
+            stamp,
+            title: header.title,
+            aliases: header.aliases,
+        };
+        let key = path.to_string_lossy().to_string();
+        self.entries.insert(key.clone(), entry.clone());
+        self.pending.insert(key, Some(entry.clone()));
+        entry
+    }
+
+    /// Drops entries for files that weren't seen in the latest listing of `dir`.
+    pub fn retain_listed(&mut self, dir: &Path, listed: &[String]) {
+        let prefix = dir.to_string_lossy().to_string();
+        let pending = &mut self.pending;
+        self.entries.retain(|key, _| {
+            let keep = !Path::new(key).starts_with(&prefix) || listed.contains(key);
+            if !keep {
+                pending.insert(key.clone(), None);
+            }
+            keep
+        });
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.store.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (path, entry) in &self.pending {
+            match entry {
+                Some(entry) => tx.execute(
+                    // `created` keeps the earliest modification time seen, for
+                    // file systems without birth times.
+                    "INSERT INTO notes (path, mtime_ns, size, title, aliases, created) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (path) DO UPDATE SET mtime_ns = ?2, size = ?3, title = ?4, aliases = ?5,
+                        created = COALESCE(created, mtime_ns / 1000000000)",
+                    (
+                        path,
+                        entry.stamp.mtime_ns as i64,
+                        entry.stamp.size as i64,
+                        &entry.title,
+                        serde_json::to_string(&entry.aliases).unwrap_or_default(),
+                        entry.stamp.modified_secs() as i64,
+                    ),
+                ),
+                None => tx.execute("DELETE FROM notes WHERE path = ?1", [path]),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+/// Rows cached before aliases were tracked have none recorded and are left
+/// out, so those notes are read again.
+fn load_entries(store: &MetaStore) -> rusqlite::Result<HashMap<String, CachedNote>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare("SELECT path, mtime_ns, size, title, aliases FROM notes WHERE aliases IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| {
+        let stamp = FileStamp {
+            mtime_ns: row.get::<_, i64>(1)? as u64,
+            size: row.get::<_, i64>(2)? as u64,
+        };
+        let aliases: String = row.get(4)?;
+        Ok((
+            row.get(0)?,
+            CachedNote {
+                stamp,
+                title: row.get(3)?,
+                aliases: serde_json::from_str(&aliases).unwrap_or_default(),
+            },
+        ))
+    })?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn header(title: &str) -> NoteHeader {
+        NoteHeader {
+            title: title.to_string(),
+            aliases: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_note_cache_reuses_until_file_changes() {
+        let dir = std::env::temp_dir().join(format!("write-note-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("1-a.md");
+        fs::write(&note, "# A").unwrap();
+
+        let store = MetaStore::open_in_memory().unwrap();
+        let mut cache = NoteCache::load(store.clone());
+        let stamp = FileStamp::from_metadata(&fs::metadata(&note).unwrap());
+        assert_eq!(cache.get_or_insert(&note, stamp, || header("A")).title, "A");
+        assert_eq!(cache.get_or_insert(&note, stamp, || header("B")).title, "A");
+
+        fs::write(&note, "# Longer").unwrap();
+        let stamp = FileStamp::from_metadata(&fs::metadata(&note).unwrap());
+        assert_eq!(cache.get_or_insert(&note, stamp, || header("Longer")).title, "Longer");
+
+        cache.save().unwrap();
+        let mut reloaded = NoteCache::load(store);
+        assert_eq!(reloaded.get_or_insert(&note, stamp, || header("X")).title, "Longer");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
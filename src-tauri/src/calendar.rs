@@ -0,0 +1,368 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use serde::Serialize;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An event on one day, with times in local time.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct DayEvent {
+    pub title: String,
+    /// `HH:MM`, or none for all-day events.
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub all_day: bool,
+    pub location: Option<String>,
+}
+
+/// Calendar text from an `.ics` file path or an `http(s)://`/`webcal://` URL,
+/// like the ones macOS Calendar and Google Calendar publish.
+pub fn load(source: &str) -> Result<String, String> {
+    let url = match source.strip_prefix("webcal://") {
+        Some(rest) => Some(format!("https://{}", rest)),
+        None if source.starts_with("http://") || source.starts_with("https://") => Some(source.to_string()),
+        None => None,
+    };
+    match url {
+        Some(url) => ureq::get(&url)
+            .timeout(REQUEST_TIMEOUT)
+            .call()
+            .map_err(|e| format!("Couldn't fetch calendar {}: {}", source, e))?
+            .into_string()
+            .map_err(|e| e.to_string()),
+        None => std::fs::read_to_string(source).map_err(|e| format!("Couldn't read calendar {}: {}", source, e)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    until: Option<NaiveDate>,
+    count: Option<u32>,
+    by_day: Vec<Weekday>,
+}
+
+#[derive(Debug, Default)]
+struct Event {
+    summary: String,
+    location: Option<String>,
+    start: Option<Moment>,
+    end: Option<Moment>,
+    rule: Option<Rule>,
+    exceptions: Vec<NaiveDate>,
+    cancelled: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Moment {
+    Date(NaiveDate),
+    Time(NaiveDateTime),
+}
+
+impl Moment {
+    fn date(self) -> NaiveDate {
+        match self {
+            Moment::Date(date) => date,
+            Moment::Time(time) => time.date(),
+        }
+    }
+}
+
+/// Events of the `ics` calendar that happen on `date`, all-day events first,
+/// then by start time.
+pub fn events_on(ics: &str, date: NaiveDate) -> Vec<DayEvent> {
+    let mut events: Vec<DayEvent> = parse(ics)
+        .into_iter()
+        .filter(|e| !e.cancelled && occurs_on(e, date))
+        .filter_map(|e| {
+            let start = e.start?;
+            let time = |m: Option<Moment>| match m {
+                Some(Moment::Time(t)) => Some(t.format("%H:%M").to_string()),
+                _ => None,
+            };
+            Some(DayEvent {
+                title: e.summary,
+                start: time(Some(start)),
+                end: time(e.end),
+                all_day: matches!(start, Moment::Date(_)),
+                location: e.location,
+            })
+        })
+        .collect();
+    sort(&mut events);
+    events
+}
+
+/// All-day events first, then by start time, for merging several calendars.
+pub fn sort(events: &mut [DayEvent]) {
+    events.sort_by(|a, b| b.all_day.cmp(&a.all_day).then_with(|| a.start.cmp(&b.start)));
+}
+
+/// The events as a markdown list, for daily notes.
+pub fn agenda_markdown(events: &[DayEvent]) -> String {
+    events
+        .iter()
+        .map(|e| {
+            let when = match (&e.start, &e.end) {
+                (Some(start), Some(end)) => format!("{}–{} ", start, end),
+                (Some(start), None) => format!("{} ", start),
+                _ => String::new(),
+            };
+            let location = e.location.as_ref().map(|l| format!(" ({})", l)).unwrap_or_default();
+            format!("- {}{}{}\n", when, e.title, location)
+        })
+        .collect()
+}
+
+fn parse(ics: &str) -> Vec<Event> {
+    let mut events = Vec::new();
+    let mut current: Option<Event> = None;
+    for line in unfold(ics) {
+        let Some((head, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut params = head.split(';');
+        let name = params.next().unwrap_or("").to_ascii_uppercase();
+        let is_date = params.any(|p| p.eq_ignore_ascii_case("VALUE=DATE"));
+        if name == "BEGIN" && value.eq_ignore_ascii_case("VEVENT") {
+            current = Some(Event::default());
+            continue;
+        }
+        if name == "END" && value.eq_ignore_ascii_case("VEVENT") {
+            events.extend(current.take());
+            continue;
+        }
+        let Some(event) = current.as_mut() else {
+            continue;
+        };
+        match name.as_str() {
+            "SUMMARY" => event.summary = unescape(value),
+            "LOCATION" => event.location = Some(unescape(value)).filter(|l| !l.is_empty()),
+            "DTSTART" => event.start = parse_moment(value, is_date),
+            "DTEND" => event.end = parse_moment(value, is_date),
+            "RRULE" => event.rule = parse_rule(value),
+            "EXDATE" => event
+                .exceptions
+                .extend(value.split(',').filter_map(|v| parse_moment(v, is_date)).map(Moment::date)),
+            "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// Joins folded lines, which continue with a leading space or tab.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+/// A date, or a date-time in local time. UTC times are converted; times with
+/// a `TZID` are taken as local, which is right for the user's own calendars.
+fn parse_moment(value: &str, is_date: bool) -> Option<Moment> {
+    let value = value.trim();
+    if is_date || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(Moment::Date);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        let local: DateTime<Local> = Utc.from_utc_datetime(&time).with_timezone(&Local);
+        return Some(Moment::Time(local.naive_local()));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().map(Moment::Time)
+}
+
+fn parse_rule(value: &str) -> Option<Rule> {
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        until: None,
+        count: None,
+        by_day: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in value.split(';') {
+        let (key, val) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match val.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => rule.interval = val.parse().ok().filter(|&i| i > 0)?,
+            "UNTIL" => rule.until = parse_moment(val, false).map(Moment::date),
+            "COUNT" => rule.count = val.parse().ok(),
+            "BYDAY" => {
+                rule.by_day = val
+                    .split(',')
+                    .filter_map(|d| {
+                        // Ordinals like `1MO` need month arithmetic and are skipped.
+                        match d {
+                            "MO" => Some(Weekday::Mon),
+                            "TU" => Some(Weekday::Tue),
+                            "WE" => Some(Weekday::Wed),
+                            "TH" => Some(Weekday::Thu),
+                            "FR" => Some(Weekday::Fri),
+                            "SA" => Some(Weekday::Sat),
+                            "SU" => Some(Weekday::Sun),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    rule.frequency = frequency?;
+    Some(rule)
+}
+
+fn occurs_on(event: &Event, date: NaiveDate) -> bool {
+    let Some(start) = event.start.map(Moment::date) else {
+        return false;
+    };
+    // All-day events end on the day before DTEND; timed ones are listed on
+    // the day they start.
+    let span = match (event.start, event.end) {
+        (Some(Moment::Date(_)), Some(end)) => (end.date() - start).num_days().max(1) - 1,
+        _ => 0,
+    };
+    if event.exceptions.contains(&date) {
+        return false;
+    }
+    let Some(rule) = &event.rule else {
+        return date >= start && (date - start).num_days() <= span;
+    };
+    // The occurrence covering `date` starts up to `span` days earlier.
+    (0..=span).any(|offset| {
+        let day = date - chrono::Duration::days(offset);
+        day >= start
+            && rule.until.is_none_or(|until| day <= until)
+            && !event.exceptions.contains(&day)
+            && matches_rule(rule, start, day)
+            && rule.count.is_none_or(|count| occurrence_index(rule, start, day) < count)
+    })
+}
+
+fn matches_rule(rule: &Rule, start: NaiveDate, day: NaiveDate) -> bool {
+    let interval = rule.interval as i64;
+    match rule.frequency {
+        Frequency::Daily => (day - start).num_days() % interval == 0,
+        Frequency::Weekly => {
+            let week = |d: NaiveDate| d.week(Weekday::Mon).first_day();
+            let weeks = (week(day) - week(start)).num_days() / 7;
+            let on_day = if rule.by_day.is_empty() {
+                day.weekday() == start.weekday()
+            } else {
+                rule.by_day.contains(&day.weekday())
+            };
+            weeks % interval == 0 && on_day
+        }
+        Frequency::Monthly => {
+            let months = (day.year() - start.year()) as i64 * 12 + day.month() as i64 - start.month() as i64;
+            day.day() == start.day() && months % interval == 0
+        }
+        Frequency::Yearly => {
+            day.month() == start.month() && day.day() == start.day() && (day.year() - start.year()) as i64 % interval == 0
+        }
+    }
+}
+
+/// How many occurrences come before the one on `day`.
+fn occurrence_index(rule: &Rule, start: NaiveDate, day: NaiveDate) -> u32 {
+    start
+        .iter_days()
+        .take_while(|d| *d < day)
+        .filter(|d| matches_rule(rule, start, *d))
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Standup\r\nDTSTART:20241014T093000\r\nDTEND:20241014T094500\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR\r\nEXDATE:20241016T093000\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Offsite\\, day one\r\nLOCATION:Lisbon\r\nDTSTART;VALUE=DATE:20241017\r\n\
+DTEND;VALUE=DATE:20241019\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Planning with a very long\r\n  title\r\nDTSTART:20241018T140000\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Cancelled\r\nSTATUS:CANCELLED\r\nDTSTART:20241018T100000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 10, d).unwrap()
+    }
+
+    fn titles(date: NaiveDate) -> Vec<String> {
+        events_on(ICS, date).into_iter().map(|e| e.title).collect()
+    }
+
+    #[test]
+    fn test_recurring_events_and_exceptions() {
+        assert_eq!(titles(day(14)), ["Standup"]);
+        assert!(titles(day(15)).is_empty());
+        assert!(titles(day(16)).is_empty());
+        assert_eq!(titles(day(28)), ["Standup"]);
+    }
+
+    #[test]
+    fn test_all_day_span_and_folding() {
+        assert_eq!(titles(day(17)), ["Offsite, day one"]);
+        assert_eq!(titles(day(18)), ["Offsite, day one", "Standup", "Planning with a very long title"]);
+        assert!(titles(day(19)).is_empty());
+    }
+
+    #[test]
+    fn test_agenda_markdown() {
+        let events = events_on(ICS, day(18));
+        assert_eq!(
+            agenda_markdown(&events),
+            "- Offsite, day one (Lisbon)\n- 09:30–09:45 Standup\n- 14:00 Planning with a very long title\n"
+        );
+    }
+
+    #[test]
+    fn test_count_limits_occurrences() {
+        let ics = "BEGIN:VEVENT\nSUMMARY:Course\nDTSTART:20241001T180000\nRRULE:FREQ=DAILY;INTERVAL=2;COUNT=3\nEND:VEVENT\n";
+        let on = |d| !events_on(ics, day(d)).is_empty();
+        assert!(on(1) && on(3) && on(5));
+        assert!(!on(2) && !on(7));
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long an empty untitled note is left alone, so one just created and
+/// still open for typing isn't taken away.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Anything bigger has something in it; even encrypted empty notes are
+/// smaller.
+pub const MAX_EMPTY_BYTES: u64 = 4 * 1024;
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct EmptyNote {
+    pub path: String,
+    pub workspace_id: String,
+    /// Unix seconds of the last save.
+    pub modified: u64,
+}
+
+/// Whether a note file named `stem` still has the name new notes get,
+/// `12-untitled` or `12-untitled-3`.
+pub fn is_untitled(stem: &str) -> bool {
+    let Some((number, slug)) = stem.split_once('-') else {
+        return false;
+    };
+    number.parse::<u64>().is_ok()
+        && (slug == "untitled" || slug.strip_prefix("untitled-").is_some_and(|n| n.parse::<u32>().is_ok()))
+}
+
+/// Whether an untitled note last saved at `modified` is old enough to clean
+/// up at `now`, both in unix seconds.
+pub fn is_abandoned(stem: &str, content: &str, modified: u64, now: u64) -> bool {
+    is_untitled(stem) && content.trim().is_empty() && now.saturating_sub(modified) >= GRACE_PERIOD.as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_untitled() {
+        assert!(is_untitled("12-untitled"));
+        assert!(is_untitled("3-untitled-2"));
+        assert!(!is_untitled("untitled"));
+        assert!(!is_untitled("3-untitled-draft"));
+        assert!(!is_untitled("3-shopping"));
+    }
+
+    #[test]
+    fn test_is_abandoned() {
+        let day = GRACE_PERIOD.as_secs();
+        let now = 10 * day;
+        assert!(is_abandoned("1-untitled", " \n\n", now - day, now));
+        assert!(!is_abandoned("1-untitled", "\n", now - day + 1, now));
+        assert!(!is_abandoned("1-untitled", "Idea", now - 5 * day, now));
+        assert!(!is_abandoned("1-groceries", "", now - 5 * day, now));
+    }
+}
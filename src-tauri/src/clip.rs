@@ -0,0 +1,249 @@
+use serde::Deserialize;
+use std::io::Read;
+use std::time::Duration;
+use url::Url;
+
+use crate::email::yaml_string;
+use crate::html;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Pages and images past this are refused.
+const MAX_DOWNLOAD_BYTES: u64 = 20 * 1024 * 1024;
+
+/// A page or selection sent by the browser extension.
+#[derive(Deserialize, Debug)]
+pub struct Clip {
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// The selection, or the whole page when nothing is selected.
+    pub html: String,
+}
+
+/// The note for `clip`: source metadata in the frontmatter, then the clipped
+/// HTML as markdown with its links and images pointing back at the page.
+/// `clipped` is the local time the clip was taken.
+pub fn to_note(clip: &Clip, clipped: &str) -> Result<String, String> {
+    let base = web_url(&clip.url)?;
+    let body = absolutize_links(&html::to_markdown(&clip.html), &base);
+    Ok(note(&base, clip.title.as_deref(), clipped, &[], &body))
+}
+
+/// The readable part of a web page, as markdown with absolute links.
+pub struct Article {
+    pub url: Url,
+    pub title: String,
+    pub body: String,
+}
+
+/// Fetches the page at `url` and keeps its main content, leaving out
+/// navigation, ads and comments the way reader modes do.
+pub fn fetch_article(url: &str) -> Result<Article, String> {
+    let url = web_url(url)?;
+    let response = ureq::get(url.as_str())
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+        .map_err(|e| format!("Couldn't fetch {}: {}", url, e))?;
+    if !response.content_type().contains("html") {
+        return Err(format!("{} isn't a web page", url));
+    }
+    let mut page = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES)
+        .read_to_end(&mut page)
+        .map_err(|e| e.to_string())?;
+    let product = readability::extractor::extract(&mut page.as_slice(), &url)
+        .map_err(|e| format!("Couldn't find an article on {}: {}", url, e))?;
+    Ok(Article {
+        body: absolutize_links(&html::to_markdown(&product.content), &url),
+        title: product.title,
+        url,
+    })
+}
+
+/// The note for a captured `article`, with `body` standing in for its
+/// markdown once images are localized, tagged `tags`.
+pub fn article_note(article: &Article, clipped: &str, tags: &[&str], body: &str) -> String {
+    note(&article.url, Some(&article.title), clipped, tags, body)
+}
+
+/// An image downloaded for a note, with the file extension its type calls
+/// for.
+pub fn download_image(url: &str) -> Result<(Vec<u8>, &'static str), String> {
+    let response = ureq::get(url)
+        .timeout(REQUEST_TIMEOUT)
+        .call()
+        .map_err(|e| format!("Couldn't fetch {}: {}", url, e))?;
+    let extension = match response.content_type() {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        other => return Err(format!("{} isn't an image ({})", url, other)),
+    };
+    let mut data = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES)
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    Ok((data, extension))
+}
+
+/// Replaces the targets of the markdown images whose URL `localize` can
+/// store, keeping the remote URL for the rest.
+pub fn localize_images(markdown: &str, mut localize: impl FnMut(&str) -> Result<String, String>) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("![") {
+        let Some(open) = rest[start..].find("](").map(|i| start + i + 2) else {
+            break;
+        };
+        let end = rest[open..]
+            .find(|c: char| c == ')' || c.is_whitespace())
+            .map_or(rest.len(), |i| open + i);
+        out.push_str(&rest[..open]);
+        let target = &rest[open..end];
+        let is_remote = target.starts_with("http://") || target.starts_with("https://");
+        match is_remote.then(|| localize(target)) {
+            Some(Ok(local)) => out.push_str(&local),
+            _ => out.push_str(target),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn web_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Only web pages can be clipped, not {}", url));
+    }
+    Ok(parsed)
+}
+
+fn note(source: &Url, title: Option<&str>, clipped: &str, tags: &[&str], body: &str) -> String {
+    let title = title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| t.replace('\n', " "))
+        .or_else(|| source.host_str().map(str::to_string))
+        .unwrap_or_else(|| source.to_string());
+    let mut note = format!(
+        "---\nsource: {}\nclipped: {}\n---\n\n# {}\n\n",
+        yaml_string(source.as_str()),
+        clipped,
+        title
+    );
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|t| format!("#{}", t)).collect();
+        note.push_str(&tags.join(" "));
+        note.push_str("\n\n");
+    }
+    if !body.is_empty() {
+        note.push_str(body);
+        note.push('\n');
+    }
+    note
+}
+
+/// Resolves the relative targets of markdown links and images against
+/// `base`, since they meant something on the page but not in a note.
+pub fn absolutize_links(markdown: &str, base: &Url) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("](") {
+        let (before, after) = rest.split_at(start + 2);
+        out.push_str(before);
+        let end = after
+            .find(|c: char| c == ')' || c.is_whitespace())
+            .unwrap_or(after.len());
+        let target = &after[..end];
+        match Url::parse(target) {
+            Err(url::ParseError::RelativeUrlWithoutBase) if !target.is_empty() => match base.join(target) {
+                Ok(resolved) => out.push_str(resolved.as_str()),
+                Err(_) => out.push_str(target),
+            },
+            _ => out.push_str(target),
+        }
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_note() {
+        let clip = Clip {
+            url: "https://example.com/posts/1".to_string(),
+            title: Some("  A post ".to_string()),
+            html: "<p>See <a href=\"/about\">about</a> and <img src=\"img/a.png\" alt=\"chart\"></p>\
+                   <script>track()</script>"
+                .to_string(),
+        };
+        assert_eq!(
+            to_note(&clip, "2026-10-15 09:30").unwrap(),
+            "---\nsource: \"https://example.com/posts/1\"\nclipped: 2026-10-15 09:30\n---\n\n# A post\n\n\
+             See [about](https://example.com/about) and ![chart](https://example.com/posts/img/a.png)\n"
+        );
+    }
+
+    #[test]
+    fn test_to_note_needs_a_web_url() {
+        let clip = |url: &str| Clip {
+            url: url.to_string(),
+            title: None,
+            html: String::new(),
+        };
+        assert!(to_note(&clip("file:///etc/passwd"), "").is_err());
+        assert!(to_note(&clip("not a url"), "").is_err());
+        assert!(to_note(&clip("https://example.com"), "").unwrap().contains("# example.com\n"));
+    }
+
+    #[test]
+    fn test_localize_images() {
+        let markdown = "![a](https://x.org/a.png) [b](https://x.org/b) ![c](https://x.org/c.png \"C\") ![d](data:x)";
+        let localized = localize_images(markdown, |url| {
+            if url.ends_with("a.png") {
+                Ok("assets/a.png".to_string())
+            } else {
+                Err("failed".to_string())
+            }
+        });
+        assert_eq!(
+            localized,
+            "![a](assets/a.png) [b](https://x.org/b) ![c](https://x.org/c.png \"C\") ![d](data:x)"
+        );
+    }
+
+    #[test]
+    fn test_article_note_is_tagged() {
+        let article = Article {
+            url: Url::parse("https://example.com/post").unwrap(),
+            title: "Post".to_string(),
+            body: String::new(),
+        };
+        assert_eq!(
+            article_note(&article, "2026-10-15 09:30", &["read-later"], "Text"),
+            "---\nsource: \"https://example.com/post\"\nclipped: 2026-10-15 09:30\n---\n\n# Post\n\n#read-later\n\nText\n"
+        );
+    }
+
+    #[test]
+    fn test_absolutize_links_keeps_absolute_targets() {
+        let base = Url::parse("https://example.com/a/").unwrap();
+        assert_eq!(
+            absolutize_links("[x](https://other.org) [y](mailto:a@b.c) [z](#top)", &base),
+            "[x](https://other.org) [y](mailto:a@b.c) [z](https://example.com/a/#top)"
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::Message as Frame;
+use yrs::sync::{Message, SyncMessage};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::Encode;
+use yrs::{Doc, GetString, ReadTxn, Text, TextRef, Transact, Update};
+
+use crate::api::percent_decode;
+use crate::watcher::is_note_path;
+use crate::{check_note_unlocked, get_workspace_dir, is_workspace_encrypted, secrets, AppState};
+
+pub const DEFAULT_PORT: u16 = 27185;
+
+/// Name of the shared text in each note's document, as clients must use it.
+pub const TEXT_NAME: &str = "content";
+
+/// How long a connection waits for a frame before passing on its peers'.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CollabInfo {
+    pub workspace_id: String,
+    pub port: u16,
+    /// Reachable from other devices on the network, not just this one.
+    pub lan: bool,
+    pub token: String,
+}
+
+struct Running {
+    stopped: Arc<AtomicBool>,
+    info: CollabInfo,
+}
+
+/// Live collaborative editing of one workspace's notes. Each open note is a
+/// Yjs document (through yrs) synced with the standard y-websocket protocol
+/// at `ws://host:port/<note file name>?token=...`, so concurrent edits from
+/// several devices merge instead of producing conflict copies. Merged text
+/// lands on disk like any outside edit, so the watcher keeps the app in step.
+#[derive(Default)]
+pub struct CollabServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl CollabServer {
+    /// Serves `workspace_id` on `port`, on every interface when `lan` is set.
+    /// Traffic isn't encrypted, so LAN mode is for trusted networks.
+    pub fn start(&self, app: AppHandle, workspace_id: &str, port: u16, lan: bool) -> Result<CollabInfo, String> {
+        let state = app.state::<AppState>();
+        if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == workspace_id) {
+            return Err("Workspace not found".to_string());
+        }
+        if is_workspace_encrypted(&state, workspace_id) {
+            return Err("Encrypted workspaces can't be edited collaboratively".to_string());
+        }
+        self.stop();
+        let token = token()?;
+        let host = if lan { "0.0.0.0" } else { "127.0.0.1" };
+        let listener = TcpListener::bind((host, port)).map_err(|e| format!("Couldn't listen on port {}: {}", port, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let info = CollabInfo {
+            workspace_id: workspace_id.to_string(),
+            port,
+            lan,
+            token,
+        };
+        let stopped = Arc::new(AtomicBool::new(false));
+        let hub = Arc::new(Hub {
+            app,
+            root: get_workspace_dir(workspace_id),
+            token: info.token.clone(),
+            rooms: Mutex::new(HashMap::new()),
+            next_peer: AtomicU64::new(0),
+            stopped: Arc::clone(&stopped),
+        });
+        std::thread::spawn(move || {
+            while !hub.stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let hub = Arc::clone(&hub);
+                        std::thread::spawn(move || hub.serve(stream));
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+                    Err(_) => break,
+                }
+            }
+        });
+        *self.running.lock().unwrap() = Some(Running {
+            stopped,
+            info: info.clone(),
+        });
+        Ok(info)
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn info(&self) -> Option<CollabInfo> {
+        self.running.lock().unwrap().as_ref().map(|r| r.info.clone())
+    }
+}
+
+fn token() -> Result<String, String> {
+    if let Some(token) = secrets::get(secrets::COLLAB_TOKEN)? {
+        return Ok(token);
+    }
+    let token = secrets::generate()?;
+    secrets::set(secrets::COLLAB_TOKEN, &token)?;
+    Ok(token)
+}
+
+struct Hub {
+    app: AppHandle,
+    root: PathBuf,
+    token: String,
+    /// Open notes by file name. A room goes away with its last peer, so the
+    /// next one starts from the file again.
+    rooms: Mutex<HashMap<String, Room>>,
+    next_peer: AtomicU64,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Hub {
+    // The handshake callback's error type is tungstenite's, not ours to box.
+    #[allow(clippy::result_large_err)]
+    fn serve(&self, stream: TcpStream) {
+        // Accepted sockets inherit the listener's non-blocking mode on macOS.
+        if stream.set_nonblocking(false).is_err() {
+            return;
+        }
+        let mut requested = None;
+        let accepted = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+            let query = request.uri().query().unwrap_or("");
+            let sent = query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+                .map(percent_decode)
+                .unwrap_or_default();
+            if sent != self.token {
+                return Err(reject(401, "Missing or wrong token"));
+            }
+            requested = Some(percent_decode(request.uri().path().trim_start_matches('/')));
+            Ok(response)
+        });
+        let (Some(mut socket), Some(name)) = (accepted.ok(), requested) else {
+            return;
+        };
+        let path = match self.note_path(&name) {
+            Ok(path) => path,
+            Err(_) => {
+                let _ = socket.close(None);
+                return;
+            }
+        };
+        if socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+            return;
+        }
+
+        let peer = self.next_peer.fetch_add(1, Ordering::Relaxed);
+        let (sender, outbox) = mpsc::channel();
+        let greeting = {
+            let mut rooms = self.rooms.lock().unwrap();
+            if !rooms.contains_key(&name) {
+                let Ok(content) = fs::read_to_string(&path) else {
+                    return;
+                };
+                rooms.insert(name.clone(), Room::new(&content));
+            }
+            let room = rooms.get_mut(&name).expect("room was just opened");
+            room.peers.push((peer, sender));
+            room.sync_step1()
+        };
+
+        let mut frames = vec![greeting];
+        while !self.stopped.load(Ordering::Relaxed) {
+            for frame in frames.drain(..).chain(outbox.try_iter()) {
+                if socket.send(Frame::Binary(frame)).is_err() {
+                    break;
+                }
+            }
+            match socket.read() {
+                Ok(Frame::Binary(data)) => {
+                    let mut rooms = self.rooms.lock().unwrap();
+                    let Some(room) = rooms.get_mut(&name) else { break };
+                    match room.receive(peer, &data) {
+                        Ok((reply, changed)) => {
+                            frames.extend(reply);
+                            if changed {
+                                self.persist(&path, &room.content());
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                }
+                Ok(Frame::Close(_)) => break,
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(_) => break,
+            }
+        }
+
+        let mut rooms = self.rooms.lock().unwrap();
+        if let Some(room) = rooms.get_mut(&name) {
+            room.peers.retain(|(id, _)| *id != peer);
+            if room.peers.is_empty() {
+                rooms.remove(&name);
+            }
+        }
+    }
+
+    /// A note directly in the workspace folder; nothing else can be opened.
+    fn note_path(&self, name: &str) -> Result<PathBuf, String> {
+        let path = self.root.join(name);
+        let plain = Path::new(name).file_name().is_some_and(|n| n == name) && !name.starts_with('.');
+        if !plain || !is_note_path(&path) || !path.is_file() {
+            return Err(format!("No note \"{}\"", name));
+        }
+        Ok(path)
+    }
+
+    /// Writes merged text unless the note is locked or already has it.
+    fn persist(&self, path: &Path, content: &str) {
+        let state = self.app.state::<AppState>();
+        let _guard = state.write_lock.lock().unwrap();
+        if check_note_unlocked(&state, path).is_err() {
+            return;
+        }
+        if fs::read_to_string(path).is_ok_and(|current| current == content) {
+            return;
+        }
+        let _ = fs::write(path, content);
+    }
+}
+
+fn reject(status: u16, message: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(message.to_string()));
+    *response.status_mut() = tungstenite::http::StatusCode::from_u16(status).expect("valid status");
+    response
+}
+
+/// The y-protocols message type of document sync frames.
+const MESSAGE_SYNC: u8 = 0;
+
+/// One note's shared document and the peers editing it.
+struct Room {
+    doc: Doc,
+    text: TextRef,
+    peers: Vec<(u64, Sender<Vec<u8>>)>,
+}
+
+impl Room {
+    fn new(content: &str) -> Self {
+        let doc = Doc::new();
+        let text = doc.get_or_insert_text(TEXT_NAME);
+        text.insert(&mut doc.transact_mut(), 0, content);
+        Self {
+            doc,
+            text,
+            peers: Vec::new(),
+        }
+    }
+
+    fn content(&self) -> String {
+        self.text.get_string(&self.doc.transact())
+    }
+
+    fn sync_step1(&self) -> Vec<u8> {
+        Message::Sync(SyncMessage::SyncStep1(self.doc.transact().state_vector())).encode_v1()
+    }
+
+    /// Handles a frame from `from`: returns the frames to answer with and
+    /// whether the text changed. Updates go to the other peers, as do
+    /// awareness and other non-sync frames, untouched.
+    fn receive(&mut self, from: u64, data: &[u8]) -> Result<(Vec<Vec<u8>>, bool), String> {
+        if data.first() != Some(&MESSAGE_SYNC) {
+            self.broadcast(from, data.to_vec());
+            return Ok((vec![], false));
+        }
+        match Message::decode_v1(data).map_err(|e| e.to_string())? {
+            Message::Sync(SyncMessage::SyncStep1(state_vector)) => {
+                let update = self.doc.transact().encode_state_as_update_v1(&state_vector);
+                Ok((vec![Message::Sync(SyncMessage::SyncStep2(update)).encode_v1()], false))
+            }
+            Message::Sync(SyncMessage::SyncStep2(update)) | Message::Sync(SyncMessage::Update(update)) => {
+                let before = self.content();
+                let decoded = Update::decode_v1(&update).map_err(|e| e.to_string())?;
+                self.doc.transact_mut().apply_update(decoded).map_err(|e| e.to_string())?;
+                self.broadcast(from, Message::Sync(SyncMessage::Update(update)).encode_v1());
+                Ok((vec![], self.content() != before))
+            }
+            _ => Ok((vec![], false)),
+        }
+    }
+
+    fn broadcast(&mut self, from: u64, frame: Vec<u8>) {
+        self.peers
+            .retain(|(id, peer)| *id == from || peer.send(frame.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update_of(doc: &Doc, since: &yrs::StateVector) -> Vec<u8> {
+        Message::Sync(SyncMessage::Update(doc.transact().encode_state_as_update_v1(since))).encode_v1()
+    }
+
+    #[test]
+    fn test_peers_converge_through_the_room() {
+        let mut room = Room::new("Hello");
+        let (sender, other_peer) = mpsc::channel();
+        room.peers.push((1, sender));
+
+        // A fresh client asks for the document and gets the file's text.
+        let client = Doc::new();
+        let text = client.get_or_insert_text(TEXT_NAME);
+        let step1 = Message::Sync(SyncMessage::SyncStep1(client.transact().state_vector())).encode_v1();
+        let (reply, changed) = room.receive(2, &step1).unwrap();
+        assert!(!changed);
+        let Message::Sync(SyncMessage::SyncStep2(update)) = Message::decode_v1(&reply[0]).unwrap() else {
+            panic!("expected sync step 2");
+        };
+        client
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&client.transact()), "Hello");
+
+        // Its edit reaches the room and the other peer.
+        let before = client.transact().state_vector();
+        text.insert(&mut client.transact_mut(), 5, " world");
+        let (_, changed) = room.receive(2, &update_of(&client, &before)).unwrap();
+        assert!(changed);
+        assert_eq!(room.content(), "Hello world");
+        assert!(other_peer.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_awareness_is_relayed_to_others_only() {
+        let mut room = Room::new("");
+        let (first, first_inbox) = mpsc::channel();
+        let (second, second_inbox) = mpsc::channel();
+        room.peers.push((1, first));
+        room.peers.push((2, second));
+        let awareness = vec![1, 0];
+        assert_eq!(room.receive(1, &awareness).unwrap(), (vec![], false));
+        assert!(first_inbox.try_recv().is_err());
+        assert_eq!(second_inbox.try_recv().unwrap(), awareness);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+/// A span of a note in UTF-16 offsets, like the editor's.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextRange {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A margin comment on a note, kept in the metadata store rather than the
+/// markdown.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Comment {
+    pub id: i64,
+    pub range: TextRange,
+    /// The text the comment was left on, used to find it again after edits.
+    pub quote: String,
+    pub text: String,
+    /// Unix seconds.
+    pub created: u64,
+    pub resolved: bool,
+    /// Whether `range` still covers `quote`. Comments whose text was edited
+    /// away keep their last range.
+    pub anchored: bool,
+}
+
+/// The text `range` covers in `content`, if it's a valid span.
+pub fn quote(content: &str, range: TextRange) -> Option<String> {
+    let from = byte_offset(content, range.from)?;
+    let to = byte_offset(content, range.to)?;
+    (from <= to).then(|| content[from..to].to_string())
+}
+
+/// Moves `comment` to where its quote is now: where it was if unchanged,
+/// otherwise the occurrence nearest its old position.
+pub fn reanchor(content: &str, comment: &mut Comment) {
+    if quote(content, comment.range).as_deref() == Some(comment.quote.as_str()) {
+        comment.anchored = true;
+        return;
+    }
+    let quote_len: usize = comment.quote.encode_utf16().count();
+    let nearest = content
+        .match_indices(comment.quote.as_str())
+        .map(|(byte, _)| content[..byte].encode_utf16().count())
+        .min_by_key(|from| from.abs_diff(comment.range.from));
+    match nearest {
+        Some(from) if !comment.quote.is_empty() => {
+            comment.range = TextRange {
+                from,
+                to: from + quote_len,
+            };
+            comment.anchored = true;
+        }
+        _ => comment.anchored = false,
+    }
+}
+
+/// The byte index of UTF-16 offset `utf16` in `text`, if it falls on a
+/// character boundary.
+fn byte_offset(text: &str, utf16: usize) -> Option<usize> {
+    let mut units = 0;
+    for (byte, c) in text.char_indices() {
+        if units == utf16 {
+            return Some(byte);
+        }
+        if units > utf16 {
+            return None;
+        }
+        units += c.len_utf16();
+    }
+    (units == utf16).then_some(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(from: usize, to: usize, quote: &str) -> Comment {
+        Comment {
+            id: 1,
+            range: TextRange { from, to },
+            quote: quote.to_string(),
+            text: "Cite this".to_string(),
+            created: 0,
+            resolved: false,
+            anchored: true,
+        }
+    }
+
+    #[test]
+    fn test_quote_uses_utf16_offsets() {
+        let content = "😀 café plans";
+        assert_eq!(quote(content, TextRange { from: 3, to: 7 }).as_deref(), Some("café"));
+        assert_eq!(quote(content, TextRange { from: 1, to: 4 }), None);
+        assert_eq!(quote(content, TextRange { from: 3, to: 99 }), None);
+    }
+
+    #[test]
+    fn test_reanchor_follows_the_quote() {
+        let mut moved = comment(0, 5, "plans");
+        reanchor("New intro. plans, then plans", &mut moved);
+        assert_eq!(moved.range, TextRange { from: 11, to: 16 });
+        assert!(moved.anchored);
+
+        let mut gone = comment(0, 5, "plans");
+        reanchor("Rewritten", &mut gone);
+        assert_eq!(gone.range, TextRange { from: 0, to: 5 });
+        assert!(!gone.anchored);
+    }
+}
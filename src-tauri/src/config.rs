@@ -0,0 +1,59 @@
+use serde_json::{Map, Value};
+
+/// Each entry upgrades a `workspaces.json` one version, from its index to
+/// the next. Append-only, like the metadata store's migrations.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[
+    // Files from before versioning. Everything added since has a serde
+    // default, so they only need stamping.
+    |_| {},
+];
+
+/// The version `workspaces.json` is written at.
+pub const CONFIG_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// The version a config file was written at. Files without one predate
+/// versioning.
+pub fn version(config: &Value) -> u32 {
+    config.get("version").and_then(Value::as_u64).unwrap_or(0) as u32
+}
+
+/// Upgrades a parsed `workspaces.json` to [`CONFIG_VERSION`] in place.
+/// Returns whether anything ran. Files from a newer version of the app are
+/// left alone.
+pub fn migrate(config: &mut Value) -> bool {
+    let from = version(config);
+    let Some(fields) = config.as_object_mut() else {
+        return false;
+    };
+    if from >= CONFIG_VERSION {
+        return false;
+    }
+    for step in &MIGRATIONS[from as usize..] {
+        step(fields);
+    }
+    fields.insert("version".to_string(), CONFIG_VERSION.into());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unversioned_config_is_stamped() {
+        let mut config = json!({ "workspaces": [], "active_workspace_id": "Personal" });
+        assert!(migrate(&mut config));
+        assert_eq!(version(&config), CONFIG_VERSION);
+        assert_eq!(config["active_workspace_id"], "Personal");
+        assert!(!migrate(&mut config));
+    }
+
+    #[test]
+    fn test_newer_config_is_left_alone() {
+        let mut config = json!({ "version": CONFIG_VERSION + 1, "workspaces": [] });
+        let before = config.clone();
+        assert!(!migrate(&mut config));
+        assert_eq!(config, before);
+    }
+}
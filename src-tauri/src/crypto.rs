@@ -0,0 +1,180 @@
+use crate::cache::FileStamp;
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::{ExposeSecret, Secret};
+use age::x25519;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// First line of an ASCII-armored age file, which is how encrypted notes are
+/// stored so they stay text and keep their `.md` name.
+pub const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Title listed for notes that can't be read without a key.
+pub const ENCRYPTED_TITLE: &str = "Encrypted note";
+
+/// An encrypted workspace's key, itself encrypted with the workspace
+/// passphrase, kept next to the notes it unlocks.
+pub const KEY_FILE: &str = ".write-key.age";
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(ARMOR_BEGIN)
+}
+
+/// Encrypts `plaintext` with a passphrase as an armored age file.
+pub fn encrypt_with_passphrase(plaintext: &str, passphrase: &str) -> Result<String, String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase can't be empty".to_string());
+    }
+    let encryptor = age::Encryptor::with_user_passphrase(Secret::new(passphrase.to_string()));
+    armor(encryptor, plaintext)
+}
+
+pub fn decrypt_with_passphrase(ciphertext: &str, passphrase: &str) -> Result<String, String> {
+    let decryptor = match age::Decryptor::new(ArmoredReader::new(ciphertext.as_bytes())).map_err(|e| e.to_string())? {
+        age::Decryptor::Passphrase(d) => d,
+        _ => return Err("Note isn't passphrase-encrypted".to_string()),
+    };
+    let reader = decryptor
+        .decrypt(&Secret::new(passphrase.to_string()), None)
+        .map_err(|e| match e {
+            age::DecryptError::DecryptionFailed | age::DecryptError::NoMatchingKeys => "Wrong passphrase".to_string(),
+            e => e.to_string(),
+        })?;
+    read_plaintext(reader)
+}
+
+/// A new workspace key and its passphrase-encrypted form for `KEY_FILE`.
+/// Notes are encrypted to the key rather than the passphrase so saves don't
+/// pay for a passphrase derivation each time.
+pub fn create_workspace_key(passphrase: &str) -> Result<(x25519::Identity, String), String> {
+    let identity = x25519::Identity::generate();
+    let key_file = encrypt_with_passphrase(&export_key(&identity), passphrase)?;
+    Ok((identity, key_file))
+}
+
+pub fn unlock_workspace_key(key_file: &str, passphrase: &str) -> Result<x25519::Identity, String> {
+    import_key(&decrypt_with_passphrase(key_file, passphrase)?)
+}
+
+/// The key as an `AGE-SECRET-KEY-…` string, for storage somewhere already
+/// protected.
+pub fn export_key(identity: &x25519::Identity) -> String {
+    identity.to_string().expose_secret().clone()
+}
+
+pub fn import_key(secret: &str) -> Result<x25519::Identity, String> {
+    x25519::Identity::from_str(secret.trim()).map_err(|e| format!("Invalid workspace key: {}", e))
+}
+
+pub fn encrypt_with_key(plaintext: &str, identity: &x25519::Identity) -> Result<String, String> {
+    let recipient: Box<dyn age::Recipient + Send> = Box::new(identity.to_public());
+    let encryptor = age::Encryptor::with_recipients(vec![recipient]).ok_or("No recipients to encrypt to")?;
+    armor(encryptor, plaintext)
+}
+
+pub fn decrypt_with_key(ciphertext: &str, identity: &x25519::Identity) -> Result<String, String> {
+    let decryptor = match age::Decryptor::new(ArmoredReader::new(ciphertext.as_bytes())).map_err(|e| e.to_string())? {
+        age::Decryptor::Recipients(d) => d,
+        _ => return Err("Note is encrypted with its own passphrase".to_string()),
+    };
+    let reader = decryptor
+        .decrypt(std::iter::once(identity as &dyn age::Identity))
+        .map_err(|e| e.to_string())?;
+    read_plaintext(reader)
+}
+
+struct UnlockedWorkspace {
+    identity: Arc<x25519::Identity>,
+    /// Decrypted titles, so listings don't decrypt every note every time.
+    titles: HashMap<PathBuf, (FileStamp, String)>,
+}
+
+/// Keys of the encrypted workspaces unlocked this session. They only live in
+/// memory; locking or quitting forgets them.
+#[derive(Default)]
+pub struct WorkspaceKeys {
+    unlocked: Mutex<HashMap<String, UnlockedWorkspace>>,
+}
+
+impl WorkspaceKeys {
+    pub fn unlock(&self, workspace_id: &str, identity: x25519::Identity) {
+        self.unlocked.lock().unwrap().insert(
+            workspace_id.to_string(),
+            UnlockedWorkspace {
+                identity: Arc::new(identity),
+                titles: HashMap::new(),
+            },
+        );
+    }
+
+    pub fn lock(&self, workspace_id: &str) {
+        self.unlocked.lock().unwrap().remove(workspace_id);
+    }
+
+    pub fn identity(&self, workspace_id: &str) -> Option<Arc<x25519::Identity>> {
+        self.unlocked
+            .lock()
+            .unwrap()
+            .get(workspace_id)
+            .map(|w| Arc::clone(&w.identity))
+    }
+
+    pub fn cached_title(&self, workspace_id: &str, path: &Path, stamp: FileStamp) -> Option<String> {
+        let unlocked = self.unlocked.lock().unwrap();
+        let (cached_stamp, title) = unlocked.get(workspace_id)?.titles.get(path)?;
+        (*cached_stamp == stamp).then(|| title.clone())
+    }
+
+    pub fn cache_title(&self, workspace_id: &str, path: &Path, stamp: FileStamp, title: String) {
+        if let Some(workspace) = self.unlocked.lock().unwrap().get_mut(workspace_id) {
+            workspace.titles.insert(path.to_path_buf(), (stamp, title));
+        }
+    }
+}
+
+fn armor(encryptor: age::Encryptor, plaintext: &str) -> Result<String, String> {
+    let mut out = Vec::new();
+    let armored = ArmoredWriter::wrap_output(&mut out, Format::AsciiArmor).map_err(|e| e.to_string())?;
+    let mut writer = encryptor.wrap_output(armored).map_err(|e| e.to_string())?;
+    writer.write_all(plaintext.as_bytes()).map_err(|e| e.to_string())?;
+    writer
+        .finish()
+        .and_then(|armored| armored.finish())
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(out).map_err(|e| e.to_string())
+}
+
+fn read_plaintext(mut reader: impl Read) -> Result<String, String> {
+    let mut plaintext = String::new();
+    reader
+        .read_to_string(&mut plaintext)
+        .map_err(|_| "Decrypted note isn't valid UTF-8".to_string())?;
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_round_trip() {
+        let ciphertext = encrypt_with_passphrase("# Secret\n\nplans", "hunter2").unwrap();
+        assert!(is_encrypted(&ciphertext));
+        assert!(!ciphertext.contains("Secret"));
+        assert_eq!(decrypt_with_passphrase(&ciphertext, "hunter2").unwrap(), "# Secret\n\nplans");
+        assert_eq!(decrypt_with_passphrase(&ciphertext, "wrong").unwrap_err(), "Wrong passphrase");
+    }
+
+    #[test]
+    fn test_workspace_key_round_trip() {
+        let (identity, key_file) = create_workspace_key("correct horse").unwrap();
+        let unlocked = unlock_workspace_key(&key_file, "correct horse").unwrap();
+        let ciphertext = encrypt_with_key("# Diary", &identity).unwrap();
+        assert_eq!(decrypt_with_key(&ciphertext, &unlocked).unwrap(), "# Diary");
+        assert!(unlock_workspace_key(&key_file, "wrong").is_err());
+        assert!(decrypt_with_key(&ciphertext, &x25519::Identity::generate()).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::NoteEntry;
+
+/// Changes since the listing identified by `cursor`. When the caller's cursor
+/// is stale or missing, `full` is set and `upserted` holds every note.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NotesDelta {
+    pub cursor: u64,
+    pub full: bool,
+    pub upserted: Vec<NoteEntry>,
+    pub removed: Vec<String>,
+    /// Paths in display order, only present when the order changed.
+    pub order: Option<Vec<String>>,
+}
+
+/// The last listing handed out, used to compute the next delta.
+#[derive(Default)]
+pub struct ListingSnapshot {
+    cursor: u64,
+    workspace_id: String,
+    entries: HashMap<String, NoteEntry>,
+    order: Vec<String>,
+}
+
+impl ListingSnapshot {
+    /// Whether `cursor` is the latest one handed out for `workspace_id`, so
+    /// only the notes changed since can be sent.
+    pub fn is_current(&self, workspace_id: &str, cursor: Option<u64>) -> bool {
+        self.cursor != 0 && cursor == Some(self.cursor) && self.workspace_id == workspace_id
+    }
+
+    pub fn update(&mut self, workspace_id: &str, cursor: Option<u64>, entries: Vec<NoteEntry>) -> NotesDelta {
+        let order: Vec<String> = entries.iter().map(|e| e.path.clone()).collect();
+        let full = !self.is_current(workspace_id, cursor);
+
+        let (upserted, removed, order_changed) = if full {
+            (entries.clone(), vec![], true)
+        } else {
+            let upserted = entries
+                .iter()
+                .filter(|e| self.entries.get(&e.path) != Some(*e))
+                .cloned()
+                .collect();
+            let removed = self
+                .order
+                .iter()
+                .filter(|p| !order.contains(p))
+                .cloned()
+                .collect();
+            (upserted, removed, order != self.order)
+        };
+
+        self.workspace_id = workspace_id.to_string();
+        self.entries = entries.into_iter().map(|e| (e.path.clone(), e)).collect();
+        self.advance(full, upserted, removed, order, order_changed)
+    }
+
+    /// Brings the current listing up to date with just the notes that changed
+    /// since: `changed` holds their entries and `removed` the paths of the
+    /// ones no longer listed. `sort` puts the listing in display order.
+    pub fn apply(
+        &mut self,
+        changed: Vec<NoteEntry>,
+        removed: Vec<String>,
+        sort: impl FnOnce(&mut [NoteEntry]),
+    ) -> NotesDelta {
+        let upserted: Vec<NoteEntry> = changed
+            .into_iter()
+            .filter(|e| self.entries.get(&e.path) != Some(e))
+            .collect();
+        for entry in &upserted {
+            self.entries.insert(entry.path.clone(), entry.clone());
+        }
+        let removed: Vec<String> = removed
+            .into_iter()
+            .filter(|p| self.entries.remove(p).is_some())
+            .collect();
+
+        let mut entries: Vec<NoteEntry> = self.entries.values().cloned().collect();
+        sort(&mut entries);
+        let order: Vec<String> = entries.into_iter().map(|e| e.path).collect();
+        let order_changed = order != self.order;
+        self.advance(false, upserted, removed, order, order_changed)
+    }
+
+    fn advance(
+        &mut self,
+        full: bool,
+        upserted: Vec<NoteEntry>,
+        removed: Vec<String>,
+        order: Vec<String>,
+        order_changed: bool,
+    ) -> NotesDelta {
+        self.cursor += 1;
+        self.order = order;
+        NotesDelta {
+            cursor: self.cursor,
+            full,
+            upserted,
+            removed,
+            order: order_changed.then(|| self.order.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(path: &str, title: &str) -> NoteEntry {
+        NoteEntry {
+            name: path.trim_end_matches(".md").to_string(),
+            path: path.to_string(),
+            modified: 1,
+            created: None,
+            id: None,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_first_listing_is_full() {
+        let mut snapshot = ListingSnapshot::default();
+        let delta = snapshot.update("Personal", None, vec![note("2-b.md", "B"), note("1-a.md", "A")]);
+        assert!(delta.full);
+        assert_eq!(delta.upserted.len(), 2);
+        assert_eq!(delta.order, Some(vec!["2-b.md".to_string(), "1-a.md".to_string()]));
+    }
+
+    #[test]
+    fn test_delta_reports_changes_only() {
+        let mut snapshot = ListingSnapshot::default();
+        let first = snapshot.update("Personal", None, vec![note("2-b.md", "B"), note("1-a.md", "A")]);
+
+        let delta = snapshot.update("Personal", Some(first.cursor), vec![note("2-b.md", "B"), note("1-a.md", "A2")]);
+        assert!(!delta.full);
+        assert_eq!(delta.upserted, vec![note("1-a.md", "A2")]);
+        assert!(delta.removed.is_empty());
+        assert_eq!(delta.order, None);
+
+        let delta = snapshot.update("Personal", Some(delta.cursor), vec![note("1-a.md", "A2")]);
+        assert_eq!(delta.removed, vec!["2-b.md".to_string()]);
+        assert_eq!(delta.order, Some(vec!["1-a.md".to_string()]));
+    }
+
+    #[test]
+    fn test_apply_changed_notes() {
+        let mut snapshot = ListingSnapshot::default();
+        let first = snapshot.update("Personal", None, vec![note("2-b.md", "B"), note("1-a.md", "A")]);
+        assert!(snapshot.is_current("Personal", Some(first.cursor)));
+        let by_path = |entries: &mut [NoteEntry]| entries.sort_by(|a, b| b.path.cmp(&a.path));
+
+        let delta = snapshot.apply(vec![note("1-a.md", "A2"), note("2-b.md", "B")], vec![], by_path);
+        assert!(!delta.full);
+        assert_eq!(delta.upserted, vec![note("1-a.md", "A2")]);
+        assert_eq!(delta.order, None);
+
+        let removed = vec!["2-b.md".to_string(), "9-gone.md".to_string()];
+        let delta = snapshot.apply(vec![note("3-c.md", "C")], removed, by_path);
+        assert_eq!(delta.upserted, vec![note("3-c.md", "C")]);
+        assert_eq!(delta.removed, vec!["2-b.md".to_string()]);
+        assert_eq!(delta.order, Some(vec!["3-c.md".to_string(), "1-a.md".to_string()]));
+        assert!(snapshot.is_current("Personal", Some(delta.cursor)));
+    }
+
+    #[test]
+    fn test_stale_cursor_or_workspace_switch_is_full() {
+        let mut snapshot = ListingSnapshot::default();
+        let first = snapshot.update("Personal", None, vec![note("1-a.md", "A")]);
+        assert!(snapshot.update("Personal", Some(first.cursor + 5), vec![note("1-a.md", "A")]).full);
+        let cursor = snapshot.cursor;
+        assert!(snapshot.update("work", Some(cursor), vec![note("1-a.md", "A")]).full);
+    }
+}
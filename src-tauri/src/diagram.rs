@@ -0,0 +1,79 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// mermaid-cli's command line tool, looked up on `PATH` unless the tool
+/// settings name another.
+pub const DEFAULT_COMMAND: &str = "mmdc";
+
+/// Folder in the app data dir holding rendered diagrams, named by a hash
+/// of their source so each is only rendered once.
+pub const CACHE_DIR: &str = "diagrams";
+
+/// The SVG for mermaid `source`, rendered with mermaid-cli `command` unless
+/// it's already in `cache_dir`.
+pub fn mermaid(command: &str, source: &str, cache_dir: &Path) -> Result<String, String> {
+    let hash = cache_key(source);
+    let svg_path = cache_dir.join(format!("{}.svg", hash));
+    if let Ok(svg) = fs::read_to_string(&svg_path) {
+        return Ok(svg);
+    }
+    fs::create_dir_all(cache_dir).map_err(|e| e.to_string())?;
+    let input = cache_dir.join(format!("{}.mmd", hash));
+    fs::write(&input, source).map_err(|e| e.to_string())?;
+    let output = Command::new(command)
+        .arg("--input")
+        .arg(&input)
+        .arg("--output")
+        .arg(&svg_path)
+        .args(["--backgroundColor", "transparent", "--quiet"])
+        .output();
+    let _ = fs::remove_file(&input);
+    let output = output.map_err(|e| match e.kind() {
+        ErrorKind::NotFound => format!("mermaid-cli isn't installed ({} wasn't found)", command),
+        _ => e.to_string(),
+    })?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&svg_path);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("Diagram failed: {}", reason.trim()));
+    }
+    fs::read_to_string(&svg_path).map_err(|e| e.to_string())
+}
+
+/// The file name, without extension, `source` is cached under.
+fn cache_key(source: &str) -> String {
+    Sha256::digest(source.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let key = cache_key("graph TD; A-->B");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key("graph TD; A-->B"));
+        assert_ne!(key, cache_key("graph TD; A-->C"));
+    }
+
+    #[test]
+    fn test_mermaid_uses_cache_and_reports_missing_command() {
+        let dir = std::env::temp_dir().join(format!("write-diagram-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let command = "write-no-such-mmdc";
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.svg", cache_key("graph TD; A-->B"))), "<svg/>").unwrap();
+        assert_eq!(mermaid(command, "graph TD; A-->B", &dir).as_deref(), Ok("<svg/>"));
+
+        let err = mermaid(command, "graph TD; A-->C", &dir).unwrap_err();
+        assert!(err.contains("isn't installed"), "{}", err);
+        assert!(!dir.join(format!("{}.mmd", cache_key("graph TD; A-->C"))).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use mailparse::{DispositionType, MailHeaderMap, ParsedMail};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::html;
+
+/// Keychain name of the IMAP account's password.
+pub const PASSWORD: &str = "imap-password";
+
+/// The workspace mail is filed into, created on first use.
+pub const WORKSPACE_NAME: &str = "Inbox";
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Held while a poll runs, so the timer and a manual check don't both file
+/// the same messages.
+static POLLING: Mutex<()> = Mutex::new(());
+
+/// The IMAP mailbox to turn into notes. Messages there are expected to be
+/// meant for Write, like those sent to a dedicated address or filtered into
+/// their own folder.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct EmailSettings {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: String,
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+}
+
+fn default_port() -> u16 {
+    993
+}
+
+fn default_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Attachment {
+    pub file_name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub uid: u32,
+    pub subject: String,
+    pub from: String,
+    /// Local time as `YYYY-MM-DD HH:MM`.
+    pub date: Option<String>,
+    /// The body as markdown, converted from HTML when the message has it.
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// Files every unread message in the mailbox with `save` and marks it read
+/// once saved, so a message that fails is retried on the next poll. Returns
+/// how many were saved.
+pub fn poll(settings: &EmailSettings, password: &str, mut save: impl FnMut(Message) -> Result<(), String>) -> Result<usize, String> {
+    let Ok(_polling) = POLLING.try_lock() else {
+        return Ok(0);
+    };
+    let tls = native_tls::TlsConnector::new().map_err(|e| e.to_string())?;
+    let client = imap::connect((settings.host.as_str(), settings.port), &settings.host, &tls)
+        .map_err(|e| format!("Couldn't connect to {}: {}", settings.host, e))?;
+    let mut session = client
+        .login(&settings.username, password)
+        .map_err(|(e, _)| format!("IMAP login failed: {}", e))?;
+    session.select(&settings.mailbox).map_err(|e| e.to_string())?;
+
+    let mut uids: Vec<u32> = session
+        .uid_search("UNSEEN")
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    uids.sort_unstable();
+    let mut saved = 0;
+    for uid in uids {
+        let fetches = session
+            .uid_fetch(uid.to_string(), "BODY.PEEK[]")
+            .map_err(|e| e.to_string())?;
+        let Some(raw) = fetches.iter().find_map(|f| f.body()) else {
+            continue;
+        };
+        save(parse(uid, raw)?)?;
+        session
+            .uid_store(uid.to_string(), "+FLAGS (\\Seen)")
+            .map_err(|e| e.to_string())?;
+        saved += 1;
+    }
+    let _ = session.logout();
+    Ok(saved)
+}
+
+pub fn parse(uid: u32, raw: &[u8]) -> Result<Message, String> {
+    let mail = mailparse::parse_mail(raw).map_err(|e| format!("Couldn't parse message {}: {}", uid, e))?;
+    let subject = mail.headers.get_first_value("Subject").unwrap_or_default();
+    let date = mail
+        .headers
+        .get_first_value("Date")
+        .and_then(|d| mailparse::dateparse(&d).ok())
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+
+    let mut parts = Parts::default();
+    collect(&mail, &mut parts);
+    let body = match (parts.html, parts.plain) {
+        (Some(html), _) => html::to_markdown(&html),
+        (None, Some(plain)) => plain.replace("\r\n", "\n").trim_end().to_string(),
+        (None, None) => String::new(),
+    };
+    Ok(Message {
+        uid,
+        subject: subject.trim().to_string(),
+        from: mail.headers.get_first_value("From").unwrap_or_default().trim().to_string(),
+        date,
+        body,
+        attachments: parts.attachments,
+    })
+}
+
+#[derive(Default)]
+struct Parts {
+    plain: Option<String>,
+    html: Option<String>,
+    attachments: Vec<Attachment>,
+}
+
+/// Walks the MIME tree, keeping the first plain and HTML bodies and every
+/// part that carries a file.
+fn collect(part: &ParsedMail, parts: &mut Parts) {
+    if !part.subparts.is_empty() {
+        for sub in &part.subparts {
+            collect(sub, parts);
+        }
+        return;
+    }
+    let disposition = part.get_content_disposition();
+    let file_name = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned();
+    let mimetype = part.ctype.mimetype.as_str();
+    let is_body = disposition.disposition != DispositionType::Attachment
+        && file_name.is_none()
+        && (mimetype == "text/plain" || mimetype == "text/html");
+    if is_body {
+        let slot = if mimetype == "text/html" { &mut parts.html } else { &mut parts.plain };
+        if slot.is_none() {
+            *slot = part.get_body().ok();
+        }
+    } else if let Ok(data) = part.get_body_raw() {
+        parts.attachments.push(Attachment {
+            file_name: safe_file_name(file_name.as_deref().unwrap_or("attachment")),
+            data,
+        });
+    }
+}
+
+/// The last path component of a sender-chosen name, without characters that
+/// could escape the assets folder or break a markdown link.
+fn safe_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.');
+    if cleaned.is_empty() {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// The note for `message`, with sender and date in the frontmatter and
+/// `assets` (paths relative to the note, one per attachment) linked below
+/// the body.
+pub fn to_note(message: &Message, assets: &[String]) -> String {
+    let mut note = String::from("---\n");
+    note.push_str(&format!("from: {}\n", yaml_string(&message.from)));
+    if let Some(date) = &message.date {
+        note.push_str(&format!("date: {}\n", date));
+    }
+    note.push_str("source: email\n---\n\n");
+    let subject = if message.subject.is_empty() { "(no subject)" } else { &message.subject };
+    note.push_str(&format!("# {}\n\n", subject));
+    if !message.body.is_empty() {
+        note.push_str(&message.body);
+        note.push('\n');
+    }
+    if !assets.is_empty() {
+        note.push_str("\n## Attachments\n\n");
+        for (attachment, path) in message.attachments.iter().zip(assets) {
+            let link: Vec<String> = path.split('/').map(crate::render::percent_encode).collect();
+            let bang = if is_image(&attachment.file_name) { "!" } else { "" };
+            note.push_str(&format!("- {}[{}]({})\n", bang, attachment.file_name, link.join("/")));
+        }
+    }
+    note
+}
+
+fn is_image(file_name: &str) -> bool {
+    let lower = file_name.to_lowercase();
+    [".png", ".jpg", ".jpeg", ".gif", ".webp", ".svg"].iter().any(|ext| lower.ends_with(ext))
+}
+
+/// `value` as a double-quoted YAML scalar, for frontmatter.
+pub fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "From: Ada <ada@example.com>\r\n\
+Subject: Trip plans\r\n\
+Date: Tue, 13 Oct 2026 09:30:00 +0000\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=\"inner\"\r\n\
+\r\n\
+--inner\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Pack light\r\n\
+--inner\r\n\
+Content-Type: text/html\r\n\
+\r\n\
+<p>Pack <strong>light</strong></p>\r\n\
+--inner--\r\n\
+--outer\r\n\
+Content-Type: application/pdf\r\n\
+Content-Disposition: attachment; filename=\"../tickets.pdf\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+JVBERg==\r\n\
+--outer--\r\n";
+
+    #[test]
+    fn test_parse_prefers_html_and_keeps_attachments() {
+        let message = parse(7, MESSAGE.as_bytes()).unwrap();
+        assert_eq!(message.subject, "Trip plans");
+        assert_eq!(message.from, "Ada <ada@example.com>");
+        assert_eq!(message.body, "Pack **light**");
+        assert_eq!(
+            message.attachments,
+            vec![Attachment {
+                file_name: "tickets.pdf".to_string(),
+                data: b"%PDF".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_to_note() {
+        let message = Message {
+            uid: 7,
+            subject: "Trip plans".to_string(),
+            from: "Ada \"A\" <ada@example.com>".to_string(),
+            date: Some("2026-10-13 11:30".to_string()),
+            body: "Pack light".to_string(),
+            attachments: vec![Attachment {
+                file_name: "my map.png".to_string(),
+                data: vec![],
+            }],
+        };
+        assert_eq!(
+            to_note(&message, &["assets/7-my map.png".to_string()]),
+            "---\nfrom: \"Ada \\\"A\\\" <ada@example.com>\"\ndate: 2026-10-13 11:30\nsource: email\n---\n\n\
+             # Trip plans\n\nPack light\n\n## Attachments\n\n- ![my map.png](assets/7-my%20map.png)\n"
+        );
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(safe_file_name("C:\\temp\\a?.txt"), "a.txt");
+        assert_eq!(safe_file_name(".."), "attachment");
+    }
+}
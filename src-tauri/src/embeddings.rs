@@ -0,0 +1,120 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// A small embedding model available from ollama (`ollama pull
+/// nomic-embed-text`). Any model served on the OpenAI embeddings API works.
+pub const DEFAULT_MODEL: &str = "nomic-embed-text";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Only a note's beginning is embedded, which is what fits the context of
+/// small embedding models.
+const MAX_INPUT_CHARS: usize = 8_000;
+
+/// Notes are sent to the model in batches of this many.
+pub const BATCH_SIZE: usize = 16;
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// One vector per input from the model server at `endpoint`, in input order.
+pub fn embed(endpoint: &str, model: &str, inputs: &[&str], allow_remote: bool) -> Result<Vec<Vec<f32>>, String> {
+    crate::summarize::check_endpoint(endpoint, allow_remote)?;
+    let inputs: Vec<&str> = inputs
+        .iter()
+        .map(|text| match text.char_indices().nth(MAX_INPUT_CHARS) {
+            Some((end, _)) => &text[..end],
+            None => text,
+        })
+        .collect();
+    let url = format!("{}/v1/embeddings", endpoint.trim_end_matches('/'));
+    let body = ureq::post(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&json!({ "model": model, "input": inputs }).to_string())
+        .map_err(|e| match e {
+            ureq::Error::Status(code, _) => format!("Model server returned {}", code),
+            ureq::Error::Transport(_) => format!("Model server isn't reachable at {}", endpoint),
+        })?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse_response(&body, inputs.len())
+}
+
+fn parse_response(body: &str, expected: usize) -> Result<Vec<Vec<f32>>, String> {
+    let mut response: EmbeddingResponse =
+        serde_json::from_str(body).map_err(|e| format!("Unexpected model server response: {}", e))?;
+    if response.data.len() != expected {
+        return Err(format!(
+            "Model server returned {} embeddings for {} inputs",
+            response.data.len(),
+            expected
+        ));
+    }
+    response.data.sort_by_key(|e| e.index);
+    Ok(response.data.into_iter().map(|e| e.embedding).collect())
+}
+
+/// Cosine similarity, 0 for vectors of different lengths or zero length.
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Little-endian bytes, for storing a vector as a blob.
+pub fn to_bytes(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity() {
+        assert!((similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let vector = vec![0.5, -1.25, 3.0];
+        assert_eq!(from_bytes(&to_bytes(&vector)), vector);
+    }
+
+    #[test]
+    fn test_parse_response_orders_by_index() {
+        let body = r#"{"data": [{"index": 1, "embedding": [0.2]}, {"index": 0, "embedding": [0.1]}]}"#;
+        assert_eq!(parse_response(body, 2).unwrap(), vec![vec![0.1], vec![0.2]]);
+        assert!(parse_response(body, 3).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct EmojiMatch {
+    pub shortcode: String,
+    pub emoji: String,
+    pub name: String,
+}
+
+/// Emoji whose shortcode starts with `prefix`, then those containing it,
+/// shortest shortcode first within each group.
+pub fn lookup(prefix: &str, limit: usize) -> Vec<EmojiMatch> {
+    let prefix = prefix.trim_matches(':').to_lowercase();
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let mut matches: Vec<(bool, &str, &emojis::Emoji)> = emojis::iter()
+        .flat_map(|emoji| emoji.shortcodes().map(move |code| (code, emoji)))
+        .filter(|(code, _)| code.contains(&prefix))
+        .map(|(code, emoji)| (!code.starts_with(&prefix), code, emoji))
+        .collect();
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.len().cmp(&b.1.len())).then(a.1.cmp(b.1)));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, code, emoji)| EmojiMatch {
+            shortcode: code.to_string(),
+            emoji: emoji.as_str().to_string(),
+            name: emoji.name().to_string(),
+        })
+        .collect()
+}
+
+/// Replaces known `:shortcode:`s in `text`; unknown ones are left as typed.
+pub fn expand(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let code_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')))
+            .unwrap_or(after.len());
+        let code = &after[..code_len];
+        match emojis::get_by_shortcode(code) {
+            Some(emoji) if !code.is_empty() && after[code_len..].starts_with(':') => {
+                out.push_str(emoji.as_str());
+                rest = &after[code_len + 1..];
+            }
+            _ => {
+                out.push(':');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_known_shortcodes_only() {
+        assert_eq!(expand("Ship it :rocket: at 10:30: :not_an_emoji: ::"), "Ship it 🚀 at 10:30: :not_an_emoji: ::");
+        assert_eq!(expand(":+1::tada:"), "👍🎉");
+    }
+
+    #[test]
+    fn test_lookup_prefers_prefix_matches() {
+        let found = lookup(":rock", 5);
+        assert!(found[0].shortcode.starts_with("rock"));
+        assert!(found.iter().any(|m| m.emoji == "🚀"));
+        assert!(lookup("", 5).is_empty());
+    }
+}
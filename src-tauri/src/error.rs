@@ -0,0 +1,124 @@
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+/// An error the frontend can branch on. Serialized as
+/// `{ "kind": "not_found", "message": "..." }`, with `io` errors adding the
+/// `io_kind` they came from.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppError {
+    NotFound { message: String },
+    /// Something already exists or changed in the way, like a note with the
+    /// requested name.
+    Conflict { message: String },
+    PermissionDenied { message: String },
+    InvalidInput { message: String },
+    /// A file over the size limit for reading whole, in bytes. It can still
+    /// be read in chunks.
+    TooLarge { message: String, size: u64, limit: u64 },
+    Io { io_kind: String, message: String },
+    /// Errors that haven't been given a kind yet.
+    Other { message: String },
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound { message: message.into() }
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict { message: message.into() }
+    }
+
+    pub fn permission_denied(message: impl Into<String>) -> Self {
+        Self::PermissionDenied { message: message.into() }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput { message: message.into() }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound { message }
+            | Self::Conflict { message }
+            | Self::PermissionDenied { message }
+            | Self::InvalidInput { message }
+            | Self::TooLarge { message, .. }
+            | Self::Io { message, .. }
+            | Self::Other { message } => message,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        let message = e.to_string();
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::NotFound { message },
+            io::ErrorKind::AlreadyExists => Self::Conflict { message },
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { message },
+            io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData => Self::InvalidInput { message },
+            kind => Self::Io {
+                io_kind: format!("{:?}", kind),
+                message,
+            },
+        }
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Other { message }
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Other {
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Lets helpers that return `String` errors call ones that return
+/// `AppError`.
+impl From<AppError> for String {
+    fn from(e: AppError) -> Self {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_errors_keep_their_kind() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert_eq!(AppError::from(missing), AppError::not_found("gone"));
+        let other = AppError::from(io::Error::new(io::ErrorKind::Interrupted, "interrupted"));
+        assert_eq!(
+            serde_json::to_value(&other).unwrap(),
+            serde_json::json!({ "kind": "io", "io_kind": "Interrupted", "message": "interrupted" })
+        );
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        assert_eq!(
+            serde_json::to_value(AppError::conflict("taken")).unwrap(),
+            serde_json::json!({ "kind": "conflict", "message": "taken" })
+        );
+        assert_eq!(String::from(AppError::invalid_input("bad")), "bad");
+    }
+}
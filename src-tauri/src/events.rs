@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+pub const NOTE_CREATED: &str = "note:created";
+pub const NOTE_DELETED: &str = "note:deleted";
+pub const NOTE_RENAMED: &str = "note:renamed";
+pub const NOTE_REORDERED: &str = "note:reordered";
+
+/// Payload for note mutations made through backend commands. `old_path` is
+/// absent for creations and `new_path` for deletions.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct NoteLifecycleEvent {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub workspace_id: String,
+}
+
+impl NoteLifecycleEvent {
+    pub fn new(old_path: Option<&Path>, new_path: Option<&Path>) -> Self {
+        let workspace_id = new_path
+            .or(old_path)
+            .map(workspace_id_for_path)
+            .unwrap_or_default();
+        Self {
+            old_path: old_path.map(|p| p.to_string_lossy().to_string()),
+            new_path: new_path.map(|p| p.to_string_lossy().to_string()),
+            workspace_id,
+        }
+    }
+}
+
+/// Folders outside the notes root that workspaces take notes from, with
+/// the id of the workspace each belongs to.
+static EXTRA_ROOTS: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+pub fn set_extra_roots(roots: Vec<(PathBuf, String)>) {
+    *EXTRA_ROOTS.lock().unwrap() = roots;
+}
+
+/// Notes live directly in their workspace directory, so the parent directory
+/// name is the workspace id, unless it's one of a workspace's extra roots.
+pub fn workspace_id_for_path(path: &Path) -> String {
+    path.parent().map(workspace_id_for_dir).unwrap_or_default()
+}
+
+/// The id of the workspace whose notes are in `dir`.
+pub fn workspace_id_for_dir(dir: &Path) -> String {
+    let extra = EXTRA_ROOTS.lock().unwrap();
+    if let Some((_, workspace_id)) = extra.iter().find(|(root, _)| root == dir) {
+        return workspace_id.clone();
+    }
+    dir.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn emit_note_event(app: &AppHandle, name: &str, old_path: Option<&Path>, new_path: Option<&Path>) {
+    let _ = app.emit(name, NoteLifecycleEvent::new(old_path, new_path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_lifecycle_event_workspace_from_path() {
+        let old = PathBuf::from("/Notes/Personal/1-old.md");
+        let new = PathBuf::from("/Notes/Personal/1-new.md");
+        let event = NoteLifecycleEvent::new(Some(old.as_path()), Some(new.as_path()));
+        assert_eq!(event.workspace_id, "Personal");
+        assert_eq!(event.old_path.as_deref(), Some("/Notes/Personal/1-old.md"));
+        assert_eq!(event.new_path.as_deref(), Some("/Notes/Personal/1-new.md"));
+    }
+
+    #[test]
+    fn test_lifecycle_event_deleted() {
+        let old = PathBuf::from("/Notes/work/2-todo.md");
+        let event = NoteLifecycleEvent::new(Some(old.as_path()), None);
+        assert_eq!(event.workspace_id, "work");
+        assert_eq!(event.new_path, None);
+    }
+
+    #[test]
+    fn test_workspace_of_extra_root() {
+        set_extra_roots(vec![(PathBuf::from("/src/project/docs"), "work".to_string())]);
+        assert_eq!(workspace_id_for_path(Path::new("/src/project/docs/setup.md")), "work");
+        assert_eq!(workspace_id_for_path(Path::new("/src/project/README.md")), "project");
+    }
+}
@@ -0,0 +1,84 @@
+use crate::comments::{self, TextRange};
+use crate::render;
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+
+/// The part of a note to export.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Fragment {
+    /// A selection, in the editor's UTF-16 offsets.
+    Range(TextRange),
+    /// A heading, by its text, and everything under it up to the next
+    /// heading of the same or a higher level.
+    Heading(String),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// The fragment's markdown as written, for the clipboard.
+    Markdown,
+    /// A standalone page, which can also be printed to PDF.
+    Html,
+}
+
+/// The markdown of `fragment` in `content`.
+pub fn fragment(content: &str, fragment: &Fragment) -> Result<String, String> {
+    match fragment {
+        Fragment::Range(range) => comments::quote(content, *range).ok_or_else(|| "Range is outside the note".to_string()),
+        Fragment::Heading(heading) => {
+            section(content, heading).ok_or_else(|| format!("No heading \"{}\" in the note", heading))
+        }
+    }
+}
+
+/// The section under the first heading whose text is `heading`, ignoring
+/// case and inline formatting, including the heading itself.
+fn section(content: &str, heading: &str) -> Option<String> {
+    let mut headings: Vec<(HeadingLevel, usize, String)> = Vec::new();
+    let mut current: Option<(HeadingLevel, usize, String)> = None;
+    for (event, range) in Parser::new_ext(content, Options::ENABLE_YAML_STYLE_METADATA_BLOCKS).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => current = Some((level, range.start, String::new())),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, heading)) = current.as_mut() {
+                    heading.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => headings.extend(current.take()),
+            _ => {}
+        }
+    }
+    let wanted = render::plain_text(heading);
+    let index = headings.iter().position(|(_, _, text)| text.trim().eq_ignore_ascii_case(wanted.trim()))?;
+    let (level, start) = (headings[index].0, headings[index].1);
+    let end = headings[index + 1..]
+        .iter()
+        .find(|(other, _, _)| *other <= level)
+        .map_or(content.len(), |(_, start, _)| *start);
+    Some(content[start..end].trim_end().to_string() + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str = "# Plan\n\nIntro\n\n## Goals\n\nShip it\n\n```\n# not a heading\n```\n\n### Stretch\n\nMore\n\n## Risks\n\nNone\n";
+
+    #[test]
+    fn test_heading_section_runs_to_next_sibling() {
+        assert_eq!(
+            fragment(NOTE, &Fragment::Heading("goals".to_string())).unwrap(),
+            "## Goals\n\nShip it\n\n```\n# not a heading\n```\n\n### Stretch\n\nMore\n"
+        );
+        assert_eq!(fragment(NOTE, &Fragment::Heading("Risks".to_string())).unwrap(), "## Risks\n\nNone\n");
+        assert!(fragment(NOTE, &Fragment::Heading("Budget".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        let range = TextRange { from: 8, to: 13 };
+        assert_eq!(fragment(NOTE, &Fragment::Range(range)).unwrap(), "Intro");
+    }
+}
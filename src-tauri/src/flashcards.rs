@@ -0,0 +1,161 @@
+use crate::tags;
+
+/// A study card written in a note.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Card {
+    /// A `Q:` line, or lines, answered by the `A:` lines after it.
+    Basic { question: String, answer: String },
+    /// A paragraph with Anki's `{{c1::hidden}}` deletions in it.
+    Cloze { text: String },
+}
+
+/// The cards in `content` outside fenced code, in order. A `Q:` card's
+/// answer runs to the next blank line or `Q:`.
+pub fn parse_cards(content: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let mut in_fence = false;
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in content.lines().chain([""]) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if line.trim().is_empty() {
+            cards.extend(paragraph_cards(&paragraph));
+            paragraph.clear();
+        } else {
+            paragraph.push(line);
+        }
+    }
+    cards
+}
+
+fn paragraph_cards(lines: &[&str]) -> Vec<Card> {
+    let text = lines.join("\n");
+    if text.contains("{{c") && text.contains("::") && text.contains("}}") {
+        return vec![Card::Cloze { text }];
+    }
+    let mut cards = Vec::new();
+    let mut question: Vec<&str> = Vec::new();
+    let mut answer: Option<Vec<&str>> = None;
+    let mut finish = |question: &mut Vec<&str>, answer: &mut Option<Vec<&str>>| {
+        if let Some(lines) = answer.take() {
+            if !question.is_empty() {
+                cards.push(Card::Basic {
+                    question: question.join("\n").trim().to_string(),
+                    answer: lines.join("\n").trim().to_string(),
+                });
+            }
+        }
+        question.clear();
+    };
+    for line in lines {
+        if let Some(rest) = strip_label(line, "Q:") {
+            finish(&mut question, &mut answer);
+            question.push(rest);
+        } else if let Some(rest) = strip_label(line, "A:").filter(|_| !question.is_empty() && answer.is_none()) {
+            answer = Some(vec![rest]);
+        } else if let Some(lines) = answer.as_mut() {
+            lines.push(line);
+        } else if !question.is_empty() {
+            question.push(line);
+        }
+    }
+    finish(&mut question, &mut answer);
+    cards
+}
+
+fn strip_label<'a>(line: &'a str, label: &str) -> Option<&'a str> {
+    let line = line.trim_start();
+    line.get(..label.len())
+        .filter(|prefix| prefix.eq_ignore_ascii_case(label))
+        .map(|_| line[label.len()..].trim())
+}
+
+/// `cards` and their note's tags as a tab-separated file Anki's File ▸
+/// Import reads, with basic and cloze cards going to those note types.
+pub fn to_anki(cards: &[(Card, Vec<String>)]) -> String {
+    let mut out = String::from("#separator:tab\n#html:true\n#notetype column:1\n#tags column:4\n");
+    for (card, note_tags) in cards {
+        let (note_type, front, back) = match card {
+            Card::Basic { question, answer } => ("Basic", question.as_str(), answer.as_str()),
+            Card::Cloze { text } => ("Cloze", text.as_str(), ""),
+        };
+        // Anki tags can't have spaces and nest with `::`.
+        let note_tags: Vec<String> = note_tags.iter().map(|tag| tag.replace(tags::SEPARATOR, "::")).collect();
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            note_type,
+            field(front),
+            field(back),
+            note_tags.join(" ")
+        ));
+    }
+    out
+}
+
+/// `text` as an HTML field on one line.
+fn field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cards() {
+        let content = "# Biology\n\nQ: What makes ATP?\nA: The mitochondria,\nmostly.\nQ: Second?\nA: Yes\n\nThe {{c1::nucleus}} holds the DNA.\n\n```\nQ: in code\nA: skipped\n```\n\nA: stray answer\n\nq: lower case\na: works";
+        assert_eq!(
+            parse_cards(content),
+            vec![
+                Card::Basic {
+                    question: "What makes ATP?".to_string(),
+                    answer: "The mitochondria,\nmostly.".to_string(),
+                },
+                Card::Basic {
+                    question: "Second?".to_string(),
+                    answer: "Yes".to_string(),
+                },
+                Card::Cloze {
+                    text: "The {{c1::nucleus}} holds the DNA.".to_string(),
+                },
+                Card::Basic {
+                    question: "lower case".to_string(),
+                    answer: "works".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_anki() {
+        let cards = vec![
+            (
+                Card::Basic {
+                    question: "1 < 2?".to_string(),
+                    answer: "Yes\nalways".to_string(),
+                },
+                vec!["math/basics".to_string()],
+            ),
+            (
+                Card::Cloze {
+                    text: "{{c1::Paris}} is in France".to_string(),
+                },
+                vec![],
+            ),
+        ];
+        assert_eq!(
+            to_anki(&cards),
+            "#separator:tab\n#html:true\n#notetype column:1\n#tags column:4\nBasic\t1 &lt; 2?\tYes<br>always\tmath::basics\nCloze\t{{c1::Paris}} is in France\t\t\n"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What `renumber` changed, with labels as they read afterwards.
+#[derive(Serialize, Clone, PartialEq, Debug, Default)]
+pub struct FootnoteReport {
+    /// Footnotes now numbered in order of their first reference.
+    pub renumbered: usize,
+    /// References with no definition.
+    pub orphaned: Vec<String>,
+    /// Definitions nothing refers to, kept after the others.
+    pub unused: Vec<String>,
+}
+
+/// A `[^label]: …` definition: its label and lines, including indented
+/// continuation lines.
+struct Definition<'a> {
+    label: &'a str,
+    lines: Vec<&'a str>,
+}
+
+/// `content` with its footnotes numbered 1, 2, 3… in the order they're
+/// first referenced and their definitions gathered at the end in that
+/// order. Code is left alone.
+pub fn renumber(content: &str) -> (String, FootnoteReport) {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut body: Vec<&str> = Vec::new();
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut in_fence = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        let label = (!in_fence).then(|| definition_label(line)).flatten();
+        let Some(label) = label else {
+            body.push(line);
+            i += 1;
+            continue;
+        };
+        let mut end = i + 1;
+        while end < lines.len() {
+            let next = lines[end..].iter().position(|l| !l.trim().is_empty()).map(|n| end + n);
+            match next {
+                Some(n) if lines[n].starts_with("    ") || lines[n].starts_with('\t') => end = n + 1,
+                _ => break,
+            }
+        }
+        definitions.push(Definition {
+            label,
+            lines: lines[i..end].to_vec(),
+        });
+        i = end;
+        // Blank lines around the definition collapse into one.
+        while i < lines.len() && lines[i].trim().is_empty() && body.last().is_none_or(|l| l.trim().is_empty()) {
+            i += 1;
+        }
+    }
+    if definitions.is_empty() && references(&body).is_empty() {
+        return (content.to_string(), FootnoteReport::default());
+    }
+
+    // Numbered by first reference in the text, then in the definitions.
+    let mut order: Vec<&str> = Vec::new();
+    // The `[^label]:` a definition starts with doesn't refer to it.
+    let definition_lines: Vec<&str> = definitions
+        .iter()
+        .flat_map(|d| {
+            let first = d.lines[0].split_once("]:").map_or("", |(_, rest)| rest);
+            std::iter::once(first).chain(d.lines[1..].iter().copied())
+        })
+        .collect();
+    for label in references(&body).into_iter().chain(references(&definition_lines)) {
+        if !order.contains(&label) {
+            order.push(label);
+        }
+    }
+    let unused: Vec<&str> = definitions
+        .iter()
+        .map(|d| d.label)
+        .filter(|label| !order.contains(label))
+        .collect();
+    let numbers: HashMap<&str, String> = order
+        .iter()
+        .chain(&unused)
+        .enumerate()
+        .map(|(n, label)| (*label, (n + 1).to_string()))
+        .collect();
+
+    let mut out: String = rewrite(&body, &numbers);
+    let trimmed_len = out.trim_end().len();
+    out.truncate(trimmed_len);
+    // A label defined twice keeps its first definition.
+    let mut notes: Vec<(usize, String)> = Vec::new();
+    for definition in &definitions {
+        let number = order.iter().chain(&unused).position(|label| *label == definition.label).unwrap_or_default();
+        if !notes.iter().any(|(n, _)| *n == number) {
+            notes.push((number, rewrite(&definition.lines, &numbers).trim_end().to_string()));
+        }
+    }
+    notes.sort_by_key(|(n, _)| *n);
+    if !notes.is_empty() {
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        let notes: Vec<String> = notes.into_iter().map(|(_, text)| text).collect();
+        out.push_str(&notes.join("\n"));
+    }
+    out.push('\n');
+
+    let defined: Vec<&str> = definitions.iter().map(|d| d.label).collect();
+    let report = FootnoteReport {
+        renumbered: order.len() + unused.len(),
+        orphaned: order.iter().filter(|label| !defined.contains(*label)).map(|label| numbers[label].clone()).collect(),
+        unused: unused.iter().map(|label| numbers[label].clone()).collect(),
+    };
+    (out, report)
+}
+
+/// The label of a definition starting on `line`, indented at most three
+/// spaces.
+fn definition_label(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let (label, _) = line[indent..].strip_prefix("[^")?.split_once("]:")?;
+    is_label(label).then_some(label)
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty() && !label.contains(|c: char| c.is_whitespace() || c == ']' || c == '[')
+}
+
+/// Spans of `[^label]` in `line` outside inline code, with their labels.
+fn reference_spans(line: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut spans = Vec::new();
+    let mut in_code = false;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        if rest.starts_with('`') {
+            in_code = !in_code;
+        } else if !in_code && rest.starts_with("[^") {
+            if let Some(len) = rest[2..].find(']') {
+                let label = &rest[2..2 + len];
+                if is_label(label) {
+                    spans.push((i..i + 2 + len + 1, label));
+                    i += 2 + len + 1;
+                    continue;
+                }
+            }
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+    spans
+}
+
+/// Labels referenced in `lines` outside code, in order, repeats included.
+fn references<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let mut in_fence = false;
+    let mut labels = Vec::new();
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if !in_fence {
+            labels.extend(reference_spans(line).into_iter().map(|(_, label)| label));
+        }
+    }
+    labels
+}
+
+/// `lines` joined, with each `[^label]` outside code using its new number.
+fn rewrite(lines: &[&str], numbers: &HashMap<&str, String>) -> String {
+    let mut out = String::new();
+    let mut in_fence = false;
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            out.push_str(line);
+            continue;
+        }
+        let mut end = 0;
+        for (span, label) in reference_spans(line) {
+            out.push_str(&line[end..span.start]);
+            match numbers.get(label) {
+                Some(number) => out.push_str(&format!("[^{}]", number)),
+                None => out.push_str(&line[span.clone()]),
+            }
+            end = span.end;
+        }
+        out.push_str(&line[end..]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renumber() {
+        let content = "# Notes\n\nFirst[^7] and second[^smith].\n\n[^7]: Seven,\n    continued.\n\nAgain[^7], missing[^2], `[^code]`.\n\n[^old]: Unused.\n[^smith]: Smith, see[^7].\n\n```\n[^x]: in code\n```\n";
+        let (renumbered, report) = renumber(content);
+        assert_eq!(
+            renumbered,
+            "# Notes\n\nFirst[^1] and second[^2].\n\nAgain[^1], missing[^3], `[^code]`.\n\n```\n[^x]: in code\n```\n\n[^1]: Seven,\n    continued.\n[^2]: Smith, see[^1].\n[^4]: Unused.\n"
+        );
+        assert_eq!(
+            report,
+            FootnoteReport {
+                renumbered: 4,
+                orphaned: vec!["3".to_string()],
+                unused: vec!["4".to_string()],
+            }
+        );
+        assert_eq!(renumber(&renumbered).0, renumbered);
+    }
+
+    #[test]
+    fn test_renumber_without_footnotes() {
+        let content = "Plain [link](x) text";
+        assert_eq!(renumber(content), (content.to_string(), FootnoteReport::default()));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::typography;
+use serde::{Deserialize, Serialize};
+
+/// A workspace's markdown formatting rules. Frontmatter and fenced code are
+/// never touched.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct FormatRules {
+    /// Format in `write_note`, not just on `format_note`.
+    pub format_on_save: bool,
+    /// One blank line around ATX headings and one space after the `#`s.
+    pub heading_spacing: bool,
+    /// Bullet marker every `*`, `+` or `-` item is rewritten to.
+    pub list_marker: Option<char>,
+    /// Strips trailing whitespace, keeping two-space hard breaks, and ends the
+    /// file with a single newline.
+    pub trim_trailing_whitespace: bool,
+    /// Moves reference link definitions to the end of the note, in order of
+    /// first use, dropping duplicates.
+    pub tidy_reference_links: bool,
+    /// Curly quotes, en/em dashes and ellipses outside code.
+    pub smart_typography: bool,
+}
+
+impl Default for FormatRules {
+    fn default() -> Self {
+        Self {
+            format_on_save: false,
+            heading_spacing: true,
+            list_marker: Some('-'),
+            trim_trailing_whitespace: true,
+            tidy_reference_links: true,
+            smart_typography: false,
+        }
+    }
+}
+
+enum Line {
+    Verbatim(String),
+    Text(String),
+    Heading(String),
+}
+
+pub fn format(content: &str, rules: &FormatRules) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines = Vec::new();
+    let mut definitions: Vec<(String, String)> = Vec::new();
+    let mut raw = content.lines().peekable();
+
+    if raw.peek().is_some_and(|l| l.trim_end() == "---") {
+        lines.push(Line::Verbatim(raw.next().unwrap_or_default().to_string()));
+        for line in raw.by_ref() {
+            lines.push(Line::Verbatim(line.to_string()));
+            if matches!(line.trim_end(), "---" | "...") {
+                break;
+            }
+        }
+    }
+
+    let mut fence: Option<&str> = None;
+    for line in raw {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            lines.push(Line::Verbatim(line.to_string()));
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            lines.push(Line::Verbatim(line.to_string()));
+            continue;
+        }
+
+        let mut line = if rules.trim_trailing_whitespace {
+            trim_trailing(line)
+        } else {
+            line.to_string()
+        };
+        if rules.tidy_reference_links {
+            if let Some(definition) = parse_definition(&line) {
+                definitions.push(definition);
+                continue;
+            }
+        }
+        if rules.smart_typography {
+            line = typography::smarten_line(&line);
+        }
+        if let Some(marker) = rules.list_marker {
+            line = normalize_list_marker(&line, marker);
+        }
+        if rules.heading_spacing {
+            if let Some(heading) = normalize_heading(&line) {
+                lines.push(Line::Heading(heading));
+                continue;
+            }
+        }
+        lines.push(Line::Text(line));
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut after_heading = false;
+    for line in lines {
+        match line {
+            Line::Heading(heading) => {
+                trim_blank_tail(&mut out);
+                if !out.is_empty() {
+                    out.push(String::new());
+                }
+                out.push(heading);
+                after_heading = true;
+            }
+            Line::Text(text) if after_heading && text.trim().is_empty() => {}
+            Line::Text(text) | Line::Verbatim(text) => {
+                if after_heading {
+                    out.push(String::new());
+                    after_heading = false;
+                }
+                out.push(text);
+            }
+        }
+    }
+
+    if !definitions.is_empty() {
+        let body = out.join("\n").to_lowercase();
+        let mut seen = std::collections::HashSet::new();
+        definitions.retain(|(label, _)| seen.insert(label.to_lowercase()));
+        definitions.sort_by_key(|(label, _)| body.find(&format!("[{}]", label.to_lowercase())).unwrap_or(usize::MAX));
+        trim_blank_tail(&mut out);
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.extend(definitions.into_iter().map(|(label, rest)| format!("[{}]: {}", label, rest)));
+    }
+
+    let ends_with_newline = content.ends_with('\n');
+    if rules.trim_trailing_whitespace {
+        trim_blank_tail(&mut out);
+    }
+    let mut formatted = out.join(newline);
+    if rules.trim_trailing_whitespace || ends_with_newline {
+        formatted.push_str(newline);
+    }
+    formatted
+}
+
+fn trim_blank_tail(lines: &mut Vec<String>) {
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+}
+
+/// Trims trailing whitespace, leaving exactly two spaces where there were two
+/// or more, since that's a hard line break.
+fn trim_trailing(line: &str) -> String {
+    let trimmed = line.trim_end();
+    let trailing = &line[trimmed.len()..];
+    if !trimmed.is_empty() && trailing.starts_with("  ") && !trailing.contains('\t') {
+        format!("{}  ", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// `[label]: destination "title"`, split into the label and the rest. Footnote
+/// definitions (`[^1]:`) are content, not links, and stay where they are.
+fn parse_definition(line: &str) -> Option<(String, String)> {
+    let indent = line.len() - line.trim_start().len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = line.trim_start().strip_prefix('[')?;
+    let (label, rest) = rest.split_once("]:")?;
+    let destination = rest.trim();
+    if label.is_empty() || label.starts_with('^') || label.contains(['[', ']']) || destination.is_empty() {
+        return None;
+    }
+    Some((label.to_string(), destination.to_string()))
+}
+
+fn normalize_list_marker(line: &str, marker: char) -> String {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let Some(current) = rest.chars().next().filter(|c| matches!(c, '*' | '+' | '-')) else {
+        return line.to_string();
+    };
+    if current == marker || !rest[1..].starts_with([' ', '\t']) || is_thematic_break(rest) {
+        return line.to_string();
+    }
+    format!("{}{}{}", &line[..indent], marker, &rest[1..])
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && marks.iter().all(|c| *c == marks[0])
+}
+
+fn normalize_heading(line: &str) -> Option<String> {
+    let indent = line.len() - line.trim_start().len();
+    if indent > 3 {
+        return None;
+    }
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    let text = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let text = text.trim_start();
+    Some(if text.is_empty() {
+        "#".repeat(level)
+    } else {
+        format!("{} {}", "#".repeat(level), text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_spacing_and_markers() {
+        let content = "# Title\nIntro \nhard break  \n##   Plan\n\n\n* one\n+ two\n* * *\n*emphasis*\n```\n* code  \n```\n";
+        assert_eq!(
+            format(content, &FormatRules::default()),
+            "# Title\n\nIntro\nhard break  \n\n## Plan\n\n- one\n- two\n* * *\n*emphasis*\n```\n* code  \n```\n"
+        );
+    }
+
+    #[test]
+    fn test_format_moves_reference_definitions() {
+        let content = "---\ntitle: x\n---\n[b]: https://b.example\nSee [a][] and [b].\n[a]: https://a.example \"A\"\n[B]: https://dup.example\n[^1]: A footnote";
+        assert_eq!(
+            format(content, &FormatRules::default()),
+            "---\ntitle: x\n---\nSee [a][] and [b].\n[^1]: A footnote\n\n[a]: https://a.example \"A\"\n[b]: https://b.example\n"
+        );
+    }
+
+    #[test]
+    fn test_format_respects_disabled_rules() {
+        let rules = FormatRules {
+            heading_spacing: false,
+            list_marker: None,
+            trim_trailing_whitespace: false,
+            tidy_reference_links: false,
+            ..FormatRules::default()
+        };
+        let content = "#  Title\n* item  \n[a]: x";
+        assert_eq!(format(content, &rules), content);
+    }
+
+    #[test]
+    fn test_format_smart_typography_skips_code() {
+        let rules = FormatRules {
+            smart_typography: true,
+            ..FormatRules::default()
+        };
+        let content = "It's \"done\" -- mostly...\n```\nx = 'y'\n```\n[a]: http://x.com/a--b\n";
+        assert_eq!(
+            format(content, &rules),
+            "It’s “done” – mostly…\n```\nx = 'y'\n```\n\n[a]: http://x.com/a--b\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_crlf() {
+        assert_eq!(format("# A\r\ntext \r\n", &FormatRules::default()), "# A\r\n\r\ntext\r\n");
+    }
+}
@@ -0,0 +1,68 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// Git's command line tool, looked up on `PATH` unless configured.
+pub const DEFAULT_COMMAND: &str = "git";
+
+/// Transports clones may use. Others, like `ext::` or `file://`, run
+/// commands or read local files.
+const PROTOCOLS: &[&str] = &["https", "http", "ssh", "git"];
+
+/// Whether `url` is a remote repository to clone, like
+/// `https://github.com/me/notes.git` or `git@github.com:me/notes.git`.
+pub fn is_remote(url: &str) -> bool {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with('-') || url.contains(char::is_whitespace) {
+        return false;
+    }
+    if let Some((scheme, rest)) = url.split_once("://") {
+        return PROTOCOLS.contains(&scheme) && !rest.is_empty();
+    }
+    // The scp-like `user@host:path`.
+    url.split_once(':')
+        .is_some_and(|(host, path)| host.contains('@') && !host.contains('/') && !path.is_empty())
+}
+
+/// Clones the repository at `url` into `dest`, which mustn't exist yet,
+/// with the Git `command`. Credentials come from the user's Git setup;
+/// nothing is asked for interactively.
+pub fn clone(command: &str, url: &str, dest: &Path) -> Result<(), String> {
+    if !is_remote(url) {
+        return Err(format!("{} isn't a repository URL", url));
+    }
+    let output = Command::new(command)
+        .args(["clone", "--quiet", "--"])
+        .arg(url.trim())
+        .arg(dest)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ALLOW_PROTOCOL", PROTOCOLS.join(":"))
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("Git isn't installed ({} wasn't found)", command),
+            _ => e.to_string(),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("Cloning failed: {}", reason.trim().trim_start_matches("fatal: ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_remote() {
+        assert!(is_remote("https://github.com/me/notes.git"));
+        assert!(is_remote("git@github.com:me/notes.git"));
+        assert!(!is_remote("--upload-pack=touch /tmp/x"));
+        assert!(!is_remote("https://example.com/a b"));
+        assert!(!is_remote(" "));
+        assert!(!is_remote("ext::sh"));
+        assert!(!is_remote("file:///etc"));
+        assert!(!is_remote("/home/me/notes"));
+    }
+}
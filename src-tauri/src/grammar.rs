@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const DEFAULT_SERVER: &str = "http://localhost:8081";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A grammar or style issue. `from` and `to` are UTF-16 offsets into the
+/// checked text, which is what LanguageTool reports and the editor uses.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GrammarIssue {
+    pub message: String,
+    pub short_message: String,
+    pub from: usize,
+    pub to: usize,
+    pub replacements: Vec<String>,
+    pub rule_id: String,
+    pub category: String,
+}
+
+#[derive(Deserialize)]
+struct CheckResponse {
+    matches: Vec<Match>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Match {
+    message: String,
+    #[serde(default)]
+    short_message: String,
+    offset: usize,
+    length: usize,
+    #[serde(default)]
+    replacements: Vec<Replacement>,
+    rule: Rule,
+}
+
+#[derive(Deserialize)]
+struct Replacement {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Rule {
+    id: String,
+    category: Category,
+}
+
+#[derive(Deserialize)]
+struct Category {
+    name: String,
+}
+
+/// Checks `text` against the LanguageTool server at `server`, e.g. one started
+/// with `languagetool-server --port 8081`. Servers on another machine are
+/// refused unless `allow_remote` is set.
+pub fn check(server: &str, text: &str, language: &str, allow_remote: bool) -> Result<Vec<GrammarIssue>, String> {
+    if !server.starts_with("http://") && !server.starts_with("https://") {
+        return Err(format!("Invalid LanguageTool server URL \"{}\"", server));
+    }
+    crate::summarize::check_endpoint(server, allow_remote)?;
+    let url = format!("{}/v2/check", server.trim_end_matches('/'));
+    let body = ureq::post(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .send_form(&[("text", text), ("language", language)])
+        .map_err(|e| match e {
+            ureq::Error::Status(code, _) => format!("LanguageTool server returned {}", code),
+            ureq::Error::Transport(_) => format!("LanguageTool server isn't reachable at {}", server),
+        })?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse_response(&body)
+}
+
+fn parse_response(body: &str) -> Result<Vec<GrammarIssue>, String> {
+    let response: CheckResponse =
+        serde_json::from_str(body).map_err(|e| format!("Unexpected LanguageTool response: {}", e))?;
+    Ok(response
+        .matches
+        .into_iter()
+        .map(|m| GrammarIssue {
+            message: m.message,
+            short_message: m.short_message,
+            from: m.offset,
+            to: m.offset + m.length,
+            replacements: m.replacements.into_iter().map(|r| r.value).collect(),
+            rule_id: m.rule.id,
+            category: m.rule.category.name,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_maps_matches() {
+        let body = r#"{
+            "software": {"name": "LanguageTool"},
+            "matches": [{
+                "message": "Possible typo: you repeated a word.",
+                "shortMessage": "Word repetition",
+                "offset": 5,
+                "length": 7,
+                "replacements": [{"value": "the"}],
+                "rule": {"id": "ENGLISH_WORD_REPEAT_RULE", "category": {"id": "MISC", "name": "Miscellaneous"}}
+            }]
+        }"#;
+        let issues = parse_response(body).unwrap();
+        assert_eq!(
+            issues,
+            vec![GrammarIssue {
+                message: "Possible typo: you repeated a word.".to_string(),
+                short_message: "Word repetition".to_string(),
+                from: 5,
+                to: 12,
+                replacements: vec!["the".to_string()],
+                rule_id: "ENGLISH_WORD_REPEAT_RULE".to_string(),
+                category: "Miscellaneous".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_check_rejects_non_http_server() {
+        assert!(check("file:///etc", "text", "en-US", true).is_err());
+    }
+
+    #[test]
+    fn test_check_refuses_remote_server_by_default() {
+        let err = check("https://api.languagetool.org", "text", "en-US", false).unwrap_err();
+        assert!(err.contains("isn't on this computer"));
+    }
+}
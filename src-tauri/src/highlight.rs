@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+/// Theme used when the caller doesn't pick one. Light, to match the default
+/// preview and print styles.
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+fn syntaxes() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn themes() -> &'static ThemeSet {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    THEMES.get_or_init(ThemeSet::load_defaults)
+}
+
+pub fn theme_names() -> Vec<String> {
+    themes().themes.keys().cloned().collect()
+}
+
+/// Whether `lang`, a fence info string like `rust` or `py`, is known.
+pub fn supports(lang: &str) -> bool {
+    syntaxes().find_syntax_by_token(lang).is_some()
+}
+
+/// Highlights `code` as a `<pre>` with inline styles, so the HTML stays
+/// self-contained in exports. Unknown languages are rendered as plain text.
+pub fn highlight(code: &str, lang: &str, theme: &str) -> Result<String, String> {
+    let theme = themes()
+        .themes
+        .get(theme)
+        .ok_or_else(|| format!("Unknown code theme \"{}\"", theme))?;
+    let syntaxes = syntaxes();
+    let syntax = syntaxes
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+    highlighted_html_for_string(code, syntaxes, syntax, theme).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_styles_known_languages() {
+        let html = highlight("fn main() {}\n", "rust", DEFAULT_THEME).unwrap();
+        assert!(html.starts_with("<pre style=\""));
+        assert!(html.contains("<span style=\"color:"));
+        assert!(html.contains("main"));
+    }
+
+    #[test]
+    fn test_highlight_falls_back_to_plain_text() {
+        assert!(!supports("no-such-lang"));
+        let html = highlight("a < b\n", "no-such-lang", DEFAULT_THEME).unwrap();
+        assert!(html.contains("a &lt; b"));
+        assert!(highlight("x", "rust", "No Such Theme").is_err());
+    }
+}
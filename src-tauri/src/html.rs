@@ -0,0 +1,53 @@
+use htmd::HtmlToMarkdown;
+
+/// Markdown for an HTML document or fragment, as for mail bodies. Scripts,
+/// styles and other non-content elements are dropped.
+pub fn to_markdown(html: &str) -> String {
+    let converter = HtmlToMarkdown::builder()
+        .skip_tags(vec!["head", "script", "style", "noscript", "template", "iframe"])
+        .build();
+    let markdown = converter.convert(html).unwrap_or_default();
+    tidy(&markdown)
+}
+
+/// Trims trailing whitespace and collapses the runs of blank lines that
+/// layout tables and spacer elements leave behind.
+fn tidy(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len());
+    let mut blank = true;
+    for line in markdown.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if !blank {
+                out.push('\n');
+            }
+            blank = true;
+        } else {
+            out.push_str(line);
+            out.push('\n');
+            blank = false;
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_markdown_drops_scripts_and_styles() {
+        let markdown = to_markdown(
+            "<html><head><style>p { color: red }</style></head>\
+             <body><p>Hello <a href=\"https://example.com\">there</a></p>\
+             <script>alert(1)</script></body></html>",
+        );
+        assert!(markdown.contains("Hello [there](https://example.com)"));
+        assert!(!markdown.contains("color"));
+        assert!(!markdown.contains("alert"));
+    }
+
+    #[test]
+    fn test_tidy() {
+        assert_eq!(tidy("a  \n\n\n\nb\n\n"), "a\n\nb");
+    }
+}
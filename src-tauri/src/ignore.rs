@@ -0,0 +1,68 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Per-workspace ignore globs, matched against paths relative to the
+/// workspace folder.
+#[derive(Clone)]
+pub struct IgnoreRules {
+    set: GlobSet,
+}
+
+impl IgnoreRules {
+    pub fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern).map_err(|e| format!("Invalid ignore pattern \"{}\": {}", pattern, e))?;
+            builder.add(glob);
+        }
+        let set = builder.build().map_err(|e| e.to_string())?;
+        Ok(Self { set })
+    }
+
+    /// Builds rules from saved config, skipping patterns that no longer parse
+    /// rather than failing every listing.
+    pub fn lenient(patterns: &[String]) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for glob in patterns.iter().filter_map(|p| Glob::new(p).ok()) {
+            builder.add(glob);
+        }
+        Self {
+            set: builder.build().unwrap_or_else(|_| GlobSet::empty()),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self { set: GlobSet::empty() }
+    }
+
+    pub fn is_ignored(&self, root: &Path, path: &Path) -> bool {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        self.set.is_match(relative)
+    }
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules_match_relative_paths() {
+        let rules = IgnoreRules::new(&["drafts-old/**".to_string(), "*.sync-conflict*".to_string()]).unwrap();
+        let root = Path::new("/Notes/Personal");
+        assert!(rules.is_ignored(root, Path::new("/Notes/Personal/drafts-old/1-a.md")));
+        assert!(rules.is_ignored(root, Path::new("/Notes/Personal/1-a.sync-conflict-20240101.md")));
+        assert!(!rules.is_ignored(root, Path::new("/Notes/Personal/1-a.md")));
+    }
+
+    #[test]
+    fn test_ignore_rules_reject_invalid_pattern() {
+        assert!(IgnoreRules::new(&["[".to_string()]).is_err());
+        assert!(!IgnoreRules::lenient(&["[".to_string()]).is_ignored(Path::new("/"), Path::new("/a.md")));
+    }
+}
@@ -0,0 +1,492 @@
+use crate::{render, tags};
+use chrono::DateTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A note read from another app's export, ready to be added to a workspace.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportedNote {
+    pub content: String,
+    pub modified: Option<SystemTime>,
+    pub pinned: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimplenoteExport {
+    active_notes: Vec<SimplenoteNote>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimplenoteNote {
+    content: String,
+    last_modified: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    pinned: bool,
+}
+
+/// The notes in Simplenote's `notes.json` export, leaving out the trashed
+/// ones. The first line becomes the title and tags are added as `#tags`.
+pub fn simplenote(json: &str) -> Result<Vec<ImportedNote>, String> {
+    let export: SimplenoteExport =
+        serde_json::from_str(json).map_err(|e| format!("Not a Simplenote export: {}", e))?;
+    Ok(export
+        .active_notes
+        .into_iter()
+        .map(|note| ImportedNote {
+            content: with_tags(&titled(&note.content), &note.tags),
+            modified: note.last_modified.as_deref().and_then(parse_time),
+            pinned: note.pinned,
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepNote {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    list_content: Vec<KeepListItem>,
+    #[serde(default)]
+    labels: Vec<KeepLabel>,
+    #[serde(default)]
+    attachments: Vec<KeepAttachment>,
+    #[serde(default)]
+    is_trashed: bool,
+    #[serde(default)]
+    is_pinned: bool,
+    user_edited_timestamp_usec: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepListItem {
+    text: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+#[derive(Deserialize)]
+struct KeepLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepAttachment {
+    file_path: String,
+    #[serde(default)]
+    mimetype: String,
+}
+
+/// The notes in a Google Takeout `Keep` folder, one `.json` file each,
+/// leaving out the trashed ones. Attached files are handed to `add_asset`
+/// with the name to keep, which returns the path to link them by.
+pub fn keep(dir: &Path, mut add_asset: impl FnMut(&Path, &str) -> Result<String, String>) -> Result<Vec<ImportedNote>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    let mut notes = Vec::new();
+    for file in files {
+        let json = fs::read_to_string(&file).map_err(|e| format!("Couldn't read {}: {}", file.display(), e))?;
+        // Takeout puts other JSON next to the notes; anything else is skipped.
+        let Ok(note) = serde_json::from_str::<KeepNote>(&json) else {
+            continue;
+        };
+        if let Some(note) = keep_note(note, |name| add_asset(&keep_attachment(dir, name), name))? {
+            notes.push(note);
+        }
+    }
+    Ok(notes)
+}
+
+fn keep_note(note: KeepNote, mut add_asset: impl FnMut(&str) -> Result<String, String>) -> Result<Option<ImportedNote>, String> {
+    if note.is_trashed {
+        return Ok(None);
+    }
+    let mut body = note.text_content.trim_end().replace("\r\n", "\n");
+    if !note.list_content.is_empty() {
+        let items: Vec<String> = note
+            .list_content
+            .iter()
+            .map(|item| format!("- [{}] {}", if item.is_checked { "x" } else { " " }, item.text.trim()))
+            .collect();
+        body = join_blocks(&body, &items.join("\n"));
+    }
+    for attachment in &note.attachments {
+        let link: Vec<String> = add_asset(&attachment.file_path)?.split('/').map(render::percent_encode).collect();
+        let bang = if attachment.mimetype.starts_with("image/") { "!" } else { "" };
+        let name = attachment.file_path.replace(['[', ']'], "");
+        body = join_blocks(&body, &format!("{}[{}]({})", bang, name, link.join("/")));
+    }
+    let content = match note.title.trim() {
+        "" => titled(&body),
+        title => join_blocks(&format!("# {}", title), &body),
+    };
+    let labels: Vec<String> = note.labels.into_iter().map(|label| label.name).collect();
+    Ok(Some(ImportedNote {
+        content: with_tags(&content, &labels),
+        modified: Some(UNIX_EPOCH + Duration::from_micros(note.user_edited_timestamp_usec)),
+        pinned: note.is_pinned,
+    }))
+}
+
+/// A note from Joplin and the notebook it was in, named with its parent
+/// notebooks like `Work - Projects`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct JoplinNote {
+    pub notebook: Option<String>,
+    pub note: ImportedNote,
+}
+
+/// Joplin's item types, the `type_` in each file's metadata.
+const JOPLIN_NOTE: &str = "1";
+const JOPLIN_FOLDER: &str = "2";
+const JOPLIN_RESOURCE: &str = "4";
+const JOPLIN_TAG: &str = "5";
+const JOPLIN_NOTE_TAG: &str = "6";
+
+/// An item of Joplin's RAW export: a title line, a body, and a block of
+/// `key: value` metadata at the end.
+struct JoplinItem {
+    title: String,
+    body: String,
+    meta: HashMap<String, String>,
+}
+
+impl JoplinItem {
+    fn get(&self, key: &str) -> &str {
+        self.meta.get(key).map_or("", String::as_str)
+    }
+}
+
+enum JoplinLink {
+    Note(String),
+    Resource(String),
+}
+
+/// The notes in a folder exported from Joplin as RAW, with their tags and
+/// modification dates. Resources they link to are handed to `add_asset`
+/// with the linking note's notebook and the resource's original file name,
+/// which returns the path to link them by. Links between notes become
+/// wikilinks.
+pub fn joplin(
+    dir: &Path,
+    mut add_asset: impl FnMut(Option<&str>, &Path, &str) -> Result<String, String>,
+) -> Result<Vec<JoplinNote>, String> {
+    if dir.is_file() {
+        return Err("Extract the JEX file first: it's a tar archive of a RAW export".to_string());
+    }
+    let entries = fs::read_dir(dir).map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    files.sort();
+    let mut items = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file).map_err(|e| format!("Couldn't read {}: {}", file.display(), e))?;
+        items.push(joplin_item(&text));
+    }
+    let of_type = |kind: &'static str| items.iter().filter(move |item| item.get("type_") == kind);
+
+    let folders: HashMap<&str, &JoplinItem> = of_type(JOPLIN_FOLDER).map(|f| (f.get("id"), f)).collect();
+    let resources: HashMap<&str, &JoplinItem> = of_type(JOPLIN_RESOURCE).map(|r| (r.get("id"), r)).collect();
+    let titles: HashMap<&str, &str> = of_type(JOPLIN_NOTE).map(|n| (n.get("id"), n.title.as_str())).collect();
+    let tag_names: HashMap<&str, &str> = of_type(JOPLIN_TAG).map(|t| (t.get("id"), t.title.as_str())).collect();
+    let mut note_tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in of_type(JOPLIN_NOTE_TAG) {
+        if let Some(name) = tag_names.get(link.get("tag_id")) {
+            note_tags.entry(link.get("note_id")).or_default().push(name.to_string());
+        }
+    }
+    let notebook = |id: &str| {
+        let mut id = id;
+        let mut names = Vec::new();
+        // Parents are followed a bounded number of times in case they loop.
+        while let Some(folder) = folders.get(id).filter(|_| names.len() < 32) {
+            names.push(folder.title.as_str());
+            id = folder.get("parent_id");
+        }
+        names.reverse();
+        (!names.is_empty()).then(|| names.join(" - "))
+    };
+
+    let mut assets: HashMap<(Option<String>, String), String> = HashMap::new();
+    let mut notes = Vec::new();
+    for item in of_type(JOPLIN_NOTE) {
+        let notebook = notebook(item.get("parent_id"));
+        let body = rewrite_joplin_links(&item.body, |id| {
+            if let Some(title) = titles.get(id) {
+                return Ok(Some(JoplinLink::Note(title.replace(['[', ']', '|', '#'], ""))));
+            }
+            let Some(resource) = resources.get(id) else {
+                return Ok(None);
+            };
+            let key = (notebook.clone(), id.to_string());
+            if let Some(path) = assets.get(&key) {
+                return Ok(Some(JoplinLink::Resource(path.clone())));
+            }
+            let extension = resource.get("file_extension");
+            let file = dir.join("resources").join(format!("{}.{}", id, extension));
+            let name = match resource.title.trim() {
+                "" => format!("{}.{}", id, extension),
+                title if Path::new(title).extension().is_some() => title.to_string(),
+                title => format!("{}.{}", title, extension),
+            };
+            let path = add_asset(notebook.as_deref(), &file, &name)?;
+            assets.insert(key, path.clone());
+            Ok(Some(JoplinLink::Resource(path)))
+        })?;
+        let content = join_blocks(&format!("# {}", item.title.trim()), &body) + "\n";
+        let tags = note_tags.remove(item.get("id")).unwrap_or_default();
+        let updated = match item.get("user_updated_time") {
+            "" => item.get("updated_time"),
+            time => time,
+        };
+        notes.push(JoplinNote {
+            notebook,
+            note: ImportedNote {
+                content: with_tags(&content, &tags),
+                modified: parse_time(updated),
+                pinned: false,
+            },
+        });
+    }
+    Ok(notes)
+}
+
+fn joplin_item(text: &str) -> JoplinItem {
+    let lines: Vec<&str> = text.lines().collect();
+    let meta_start = lines.iter().rposition(|l| l.trim().is_empty()).map_or(0, |i| i + 1);
+    let meta: HashMap<String, String> = lines[meta_start..]
+        .iter()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let content = &lines[..meta_start];
+    JoplinItem {
+        title: content.first().map_or("", |l| l.trim()).to_string(),
+        body: content.get(1..).unwrap_or_default().join("\n").trim_matches('\n').to_string(),
+        meta,
+    }
+}
+
+/// `body` with Joplin's `[text](:/id)` links pointing where `target` says:
+/// a wikilink to another note by title, or a copied resource's path. Links
+/// `target` doesn't know are left alone.
+fn rewrite_joplin_links(
+    body: &str,
+    mut target: impl FnMut(&str) -> Result<Option<JoplinLink>, String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(i) = rest.find("](:/") {
+        let after = &rest[i + 4..];
+        let id = after
+            .get(..32)
+            .filter(|id| id.chars().all(|c| c.is_ascii_hexdigit()) && after[32..].starts_with(')'));
+        let (Some(id), Some(open)) = (id, rest[..i].rfind('[')) else {
+            out.push_str(&rest[..i + 4]);
+            rest = &rest[i + 4..];
+            continue;
+        };
+        let end = i + 4 + 32 + 1;
+        let text = &rest[open + 1..i];
+        match target(id)? {
+            Some(JoplinLink::Note(title)) => {
+                out.push_str(&rest[..open]);
+                if text.is_empty() || text == title {
+                    out.push_str(&format!("[[{}]]", title));
+                } else {
+                    out.push_str(&format!("[[{}|{}]]", title, text.replace(['[', ']', '|'], "")));
+                }
+            }
+            Some(JoplinLink::Resource(path)) => {
+                let link: Vec<String> = path.split('/').map(render::percent_encode).collect();
+                out.push_str(&format!("{}[{}]({})", &rest[..open], text, link.join("/")));
+            }
+            None => out.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// The file Keep's attachment `name` is in. Takeout sometimes names a
+/// `.jpeg` in the note that it saved as `.jpg`.
+fn keep_attachment(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if path.exists() {
+        return path;
+    }
+    match name.strip_suffix(".jpeg") {
+        Some(stem) => dir.join(format!("{}.jpg", stem)),
+        None => path,
+    }
+}
+
+fn join_blocks(first: &str, second: &str) -> String {
+    match (first.is_empty(), second.is_empty()) {
+        (true, _) => second.to_string(),
+        (_, true) => first.to_string(),
+        _ => format!("{}\n\n{}", first, second),
+    }
+}
+
+/// `content` with its first line made a `# ` heading, as apps that take the
+/// first line for the title don't mark it.
+fn titled(content: &str) -> String {
+    let content = content.trim_start_matches(['\n', '\r']).replace("\r\n", "\n");
+    match content.lines().next() {
+        Some(first) if !first.trim().is_empty() && !first.starts_with('#') => format!("# {}", content),
+        _ => content,
+    }
+}
+
+/// `content` with each of `names` added as a tag. Spaces become dashes and
+/// names that still aren't valid tags are dropped.
+fn with_tags(content: &str, names: &[String]) -> String {
+    let mut content = content.to_string();
+    for name in names {
+        let tag = name.trim().trim_start_matches('#').replace(char::is_whitespace, "-");
+        if tags::is_tag(&tag) {
+            if let Some(tagged) = tags::add_tag(&content, &tag) {
+                content = tagged;
+            }
+        }
+    }
+    content
+}
+
+fn parse_time(text: &str) -> Option<SystemTime> {
+    DateTime::parse_from_rfc3339(text).ok().map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplenote() {
+        let json = r##"{
+            "activeNotes": [
+                {
+                    "id": "a1",
+                    "content": "Groceries\r\n\r\nMilk",
+                    "creationDate": "2023-01-01T10:00:00.000Z",
+                    "lastModified": "2023-01-02T10:00:00.000Z",
+                    "tags": ["home", "to buy"],
+                    "pinned": true
+                },
+                { "id": "b2", "content": "# Already titled" }
+            ],
+            "trashedNotes": [{ "id": "c3", "content": "Gone" }]
+        }"##;
+        let notes = simplenote(json).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].content, "# Groceries\n\nMilk\n\n#home #to-buy\n");
+        assert_eq!(notes[0].modified, Some(UNIX_EPOCH + Duration::from_secs(1_672_653_600)));
+        assert!(notes[0].pinned);
+        assert_eq!(notes[1].content, "# Already titled");
+        assert!(!notes[1].pinned);
+        assert!(simplenote("[]").is_err());
+    }
+
+    #[test]
+    fn test_keep_note() {
+        let json = r#"{
+            "title": "Shopping",
+            "textContent": "",
+            "listContent": [
+                { "text": "Milk", "isChecked": false },
+                { "text": "Eggs", "isChecked": true }
+            ],
+            "labels": [{ "name": "Home" }],
+            "attachments": [{ "filePath": "1a2b.jpeg", "mimetype": "image/jpeg" }],
+            "isTrashed": false,
+            "isPinned": true,
+            "userEditedTimestampUsec": 1672653600000000
+        }"#;
+        let mut added = Vec::new();
+        let note = keep_note(serde_json::from_str(json).unwrap(), |name| {
+            added.push(name.to_string());
+            Ok(format!("assets/{}", name))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(added, vec!["1a2b.jpeg"]);
+        assert_eq!(
+            note.content,
+            "# Shopping\n\n- [ ] Milk\n- [x] Eggs\n\n![1a2b.jpeg](assets/1a2b.jpeg)\n\n#Home\n"
+        );
+        assert_eq!(note.modified, Some(UNIX_EPOCH + Duration::from_secs(1_672_653_600)));
+        assert!(note.pinned);
+
+        let untitled = r#"{ "textContent": "Call the bank\nabout the card", "userEditedTimestampUsec": 0 }"#;
+        let note = keep_note(serde_json::from_str(untitled).unwrap(), |_| unreachable!()).unwrap().unwrap();
+        assert_eq!(note.content, "# Call the bank\nabout the card");
+        let trashed = r#"{ "textContent": "Old", "isTrashed": true, "userEditedTimestampUsec": 0 }"#;
+        assert_eq!(keep_note(serde_json::from_str(trashed).unwrap(), |_| unreachable!()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_joplin() {
+        let dir = std::env::temp_dir().join(format!("write-joplin-{}", std::process::id()));
+        let id = |n: u8| format!("{:032x}", n);
+        let write = |n: u8, text: String| fs::write(dir.join(format!("{}.md", id(n))), text).unwrap();
+        fs::create_dir_all(dir.join("resources")).unwrap();
+        write(1, format!("Work\n\nid: {}\nparent_id: \ntype_: 2", id(1)));
+        write(2, format!("Projects\n\nid: {}\nparent_id: {}\ntype_: 2", id(2), id(1)));
+        write(
+            3,
+            format!(
+                "Launch plan\n\nSee [the budget](:/{}) and ![chart](:/{}).\n\nid: {}\nparent_id: {}\nupdated_time: 2023-01-02T10:00:00.000Z\ntype_: 1",
+                id(4),
+                id(5),
+                id(3),
+                id(2)
+            ),
+        );
+        write(4, format!("Budget\n\nNumbers\n\nid: {}\nparent_id: \ntype_: 1", id(4)));
+        write(5, format!("chart.png\n\nid: {}\nmime: image/png\nfile_extension: png\ntype_: 4", id(5)));
+        write(6, format!("q1\n\nid: {}\ntype_: 5", id(6)));
+        write(7, format!("id: {}\nnote_id: {}\ntag_id: {}\ntype_: 6", id(7), id(3), id(6)));
+        fs::write(dir.join("resources").join(format!("{}.png", id(5))), b"png").unwrap();
+
+        let mut added = Vec::new();
+        let notes = joplin(&dir, |notebook, file, name| {
+            assert_eq!(notebook, Some("Work - Projects"));
+            added.push((file.to_path_buf(), name.to_string()));
+            Ok(format!("assets/{}", name))
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(added, vec![(dir.join("resources").join(format!("{}.png", id(5))), "chart.png".to_string())]);
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].notebook.as_deref(), Some("Work - Projects"));
+        assert_eq!(
+            notes[0].note.content,
+            "# Launch plan\n\nSee [[Budget|the budget]] and ![chart](assets/chart.png).\n\n#q1\n"
+        );
+        assert_eq!(notes[0].note.modified, Some(UNIX_EPOCH + Duration::from_secs(1_672_653_600)));
+        assert_eq!(notes[1].notebook, None);
+        assert_eq!(notes[1].note.content, "# Budget\n\nNumbers\n");
+    }
+}
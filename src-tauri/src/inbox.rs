@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Folder in the inbox that imported files are moved into.
+pub const ARCHIVE_DIR: &str = "imported";
+
+const EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Held while the inbox is imported, so a watch event and a manual check
+/// don't both import the same files.
+pub static IMPORTING: Mutex<()> = Mutex::new(());
+
+/// A folder outside the notes root whose text files become notes in
+/// `workspace_id`, for scanners, scripts and other apps to drop files in.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct InboxSettings {
+    pub dir: String,
+    pub workspace_id: String,
+}
+
+/// Markdown and text files waiting in the inbox `dir`, oldest name first.
+/// Hidden files, which apps write while saving, are left for later.
+pub fn pending(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| !path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')))
+        .filter(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// The note for a file named `name` holding `content`, titled by the file
+/// name unless it starts with a heading.
+pub fn to_note(name: &str, content: &str) -> String {
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let content = content.trim_start_matches('\n');
+    if content.starts_with("# ") || content.starts_with("---\n") {
+        return content.to_string();
+    }
+    match content.trim_end() {
+        "" => format!("# {}\n", name),
+        _ => format!("# {}\n\n{}", name, content),
+    }
+}
+
+/// Moves `file` into the inbox's archive folder, numbering it when a file
+/// of that name was archived before.
+pub fn archive(dir: &Path, file: &Path) -> Result<PathBuf, String> {
+    let archive_dir = dir.join(ARCHIVE_DIR);
+    fs::create_dir_all(&archive_dir).map_err(|e| e.to_string())?;
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = file.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let mut target = archive_dir.join(format!("{}{}", stem, extension));
+    let mut n = 2;
+    while target.exists() {
+        target = archive_dir.join(format!("{}-{}{}", stem, n, extension));
+        n += 1;
+    }
+    fs::rename(file, &target).map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_and_archive() {
+        let dir = std::env::temp_dir().join(format!("write-inbox-{}", std::process::id()));
+        fs::create_dir_all(dir.join(ARCHIVE_DIR)).unwrap();
+        for name in ["b.txt", "a.md", "c.MARKDOWN", ".d.md", "scan.pdf"] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        fs::write(dir.join(ARCHIVE_DIR).join("a.md"), "old").unwrap();
+
+        let files = pending(&dir);
+        let names: Vec<String> = files
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["a.md", "b.txt", "c.MARKDOWN"]);
+
+        assert_eq!(archive(&dir, &files[0]).unwrap(), dir.join(ARCHIVE_DIR).join("a-2.md"));
+        assert!(!files[0].exists());
+        assert_eq!(pending(&dir).len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_to_note() {
+        assert_eq!(to_note("Scan 12", "Receipt\r\ntotal 4€\r\n"), "# Scan 12\n\nReceipt\ntotal 4€\n");
+        assert_eq!(to_note("x", "\n# Own title\n\nBody"), "# Own title\n\nBody");
+        assert_eq!(to_note("x", "---\ntags: [a]\n---\nBody"), "---\ntags: [a]\n---\nBody");
+        assert_eq!(to_note("Empty", "  \n"), "# Empty\n");
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{self, FileStamp};
+use crate::ignore::IgnoreRules;
+use crate::parse_file_number;
+use crate::search::SearchIndex;
+use crate::watcher::is_note_path;
+
+#[derive(Clone, Debug)]
+pub struct IndexedNote {
+    pub path: PathBuf,
+    pub name: String,
+    pub stamp: FileStamp,
+    /// Birth time in unix seconds, where the file system keeps one.
+    pub created: Option<u64>,
+    /// Filled in lazily; reset whenever the file changes.
+    pub title: Option<String>,
+    /// Hidden with the file system's hidden attribute or flag.
+    pub hidden: bool,
+}
+
+/// In-memory listing of the active workspace, built once per watch and kept
+/// current by the watcher and by commands that touch the folder, so listing,
+/// numbering and reordering don't rescan the directory.
+#[derive(Default)]
+pub struct WorkspaceIndex {
+    dir: PathBuf,
+    /// Folders besides `dir` whose notes belong to the workspace.
+    extra_roots: Vec<PathBuf>,
+    ignore: IgnoreRules,
+    notes: HashMap<PathBuf, IndexedNote>,
+    search: SearchIndex,
+    /// Paths refreshed since `take_changed` was last called, or `None` when
+    /// any note may have changed, like right after the index was built.
+    changed: Option<HashSet<PathBuf>>,
+}
+
+impl WorkspaceIndex {
+    pub fn build(dir: &Path, ignore: IgnoreRules) -> Self {
+        Self::build_with_roots(dir, &[], ignore)
+    }
+
+    /// An index of the notes in `dir` and in `extra_roots`.
+    pub fn build_with_roots(dir: &Path, extra_roots: &[PathBuf], ignore: IgnoreRules) -> Self {
+        let mut index = Self {
+            dir: dir.to_path_buf(),
+            extra_roots: extra_roots.to_vec(),
+            ignore,
+            notes: HashMap::new(),
+            search: SearchIndex::default(),
+            changed: None,
+        };
+        for root in std::iter::once(dir).chain(extra_roots.iter().map(PathBuf::as_path)) {
+            if let Ok(entries) = fs::read_dir(root) {
+                for entry in entries.filter_map(|e| e.ok()) {
+                    index.refresh(&entry.path());
+                }
+            }
+        }
+        index
+    }
+
+    /// The primary root, where new notes go.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether notes directly in `dir` belong to the index.
+    pub fn has_root(&self, dir: &Path) -> bool {
+        self.dir == dir || self.extra_roots.iter().any(|root| root == dir)
+    }
+
+    /// Re-stats `path` and adds, updates or drops its entry to match disk.
+    pub fn refresh(&mut self, path: &Path) {
+        let Some(root) = path.parent().filter(|parent| self.has_root(parent)) else {
+            return;
+        };
+        if !is_note_path(path) || self.ignore.is_ignored(root, path) {
+            return;
+        }
+        if let Some(changed) = &mut self.changed {
+            changed.insert(path.to_path_buf());
+        }
+        let Some(metadata) = fs::metadata(path).ok().filter(|m| m.is_file()) else {
+            self.notes.remove(path);
+            self.search.remove(path);
+            return;
+        };
+        let stamp = FileStamp::from_metadata(&metadata);
+        if let Some(existing) = self.notes.get_mut(path) {
+            existing.hidden = is_hidden(&metadata);
+            if existing.stamp != stamp {
+                existing.stamp = stamp;
+                existing.title = None;
+            }
+            return;
+        }
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            return;
+        };
+        self.notes.insert(
+            path.to_path_buf(),
+            IndexedNote {
+                path: path.to_path_buf(),
+                name,
+                stamp,
+                created: cache::birth_secs(&metadata),
+                title: None,
+                hidden: is_hidden(&metadata),
+            },
+        );
+    }
+
+    pub fn notes(&self) -> impl Iterator<Item = &IndexedNote> {
+        self.notes.values()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&IndexedNote> {
+        self.notes.get(path)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.notes.contains_key(path)
+    }
+
+    pub fn search(&self) -> &SearchIndex {
+        &self.search
+    }
+
+    pub fn search_mut(&mut self) -> &mut SearchIndex {
+        &mut self.search
+    }
+
+    pub fn set_title(&mut self, path: &Path, stamp: FileStamp, title: String) {
+        if let Some(note) = self.notes.get_mut(path) {
+            if note.stamp == stamp {
+                note.title = Some(title);
+            }
+        }
+    }
+
+    /// The paths refreshed since the last call, so a listing can be brought up
+    /// to date without looking at every note. `None` means it can't be.
+    pub fn take_changed(&mut self) -> Option<HashSet<PathBuf>> {
+        self.changed.replace(HashSet::new())
+    }
+
+    /// Makes the next `take_changed` return `None`, for changes to how notes
+    /// are listed that no refresh sees, like new hidden patterns.
+    pub fn mark_all_changed(&mut self) {
+        self.changed = None;
+    }
+
+    /// The number for a new note in the primary root.
+    pub fn next_number(&self) -> u64 {
+        self.notes
+            .values()
+            .filter(|n| n.path.parent() == Some(self.dir.as_path()))
+            .filter_map(|n| parse_file_number(&n.name))
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
+}
+
+#[cfg(windows)]
+fn is_hidden(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(target_os = "macos")]
+fn is_hidden(metadata: &fs::Metadata) -> bool {
+    use std::os::macos::fs::MetadataExt;
+    const UF_HIDDEN: u32 = 0x8000;
+    metadata.st_flags() & UF_HIDDEN != 0
+}
+
+// Elsewhere only dotfiles are hidden, and those aren't indexed at all.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn is_hidden(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_tracks_files_on_refresh() {
+        let dir = std::env::temp_dir().join(format!("write-index-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1-a.md"), "# A").unwrap();
+        fs::write(dir.join("3-c.md"), "# C").unwrap();
+        fs::write(dir.join("scan.pdf"), "").unwrap();
+
+        let mut index = WorkspaceIndex::build(&dir, IgnoreRules::empty());
+        assert_eq!(index.notes().count(), 2);
+        assert_eq!(index.next_number(), 4);
+
+        fs::remove_file(dir.join("3-c.md")).unwrap();
+        index.refresh(&dir.join("3-c.md"));
+        assert_eq!(index.next_number(), 2);
+
+        fs::write(dir.join("1-a.md"), "# A changed").unwrap();
+        index.refresh(&dir.join("1-a.md"));
+        assert!(index.notes().all(|n| n.title.is_none()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_index_records_changed_paths() {
+        let dir = std::env::temp_dir().join(format!("write-index-changed-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1-a.md"), "# A").unwrap();
+
+        let mut index = WorkspaceIndex::build(&dir, IgnoreRules::empty());
+        assert_eq!(index.take_changed(), None);
+
+        fs::write(dir.join("2-b.md"), "# B").unwrap();
+        index.refresh(&dir.join("2-b.md"));
+        index.refresh(&dir.join("scan.pdf"));
+        assert_eq!(index.take_changed(), Some(HashSet::from([dir.join("2-b.md")])));
+        assert_eq!(index.take_changed(), Some(HashSet::new()));
+
+        index.mark_all_changed();
+        assert_eq!(index.take_changed(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_index_spans_extra_roots() {
+        let base = std::env::temp_dir().join(format!("write-index-roots-{}", std::process::id()));
+        let dir = base.join("Work");
+        let docs = base.join("repo").join("docs");
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(dir.join("1-a.md"), "# A").unwrap();
+        fs::write(docs.join("7-setup.md"), "# Setup").unwrap();
+
+        let mut index = WorkspaceIndex::build_with_roots(&dir, std::slice::from_ref(&docs), IgnoreRules::empty());
+        assert_eq!(index.notes().count(), 2);
+        assert!(index.has_root(&docs));
+        assert_eq!(index.next_number(), 2);
+
+        fs::write(docs.join("guide.md"), "# Guide").unwrap();
+        index.refresh(&docs.join("guide.md"));
+        index.refresh(&base.join("repo").join("README.md"));
+        assert_eq!(index.notes().count(), 3);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    /// Timings for a 12k-note workspace. Run with
+    /// `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_large_workspace() {
+        use crate::search::tokenize;
+        use std::time::Instant;
+
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+        let dir = std::env::temp_dir().join(format!("write-bench-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 1..=12_000 {
+            let body = format!("# Note {i}\n\nSome text about topic{} and more words.\n", i % 97);
+            fs::write(dir.join(format!("{i}-note-{i}.md")), body).unwrap();
+        }
+
+        let start = Instant::now();
+        let mut index = WorkspaceIndex::build(&dir, IgnoreRules::empty());
+        tracing::info!("build index: {:?}", start.elapsed());
+
+        let start = Instant::now();
+        assert_eq!(index.next_number(), 12_001);
+        tracing::info!("next number: {:?}", start.elapsed());
+
+        let start = Instant::now();
+        index.refresh(&dir.join("5-note-5.md"));
+        tracing::info!("refresh one: {:?}", start.elapsed());
+
+        let start = Instant::now();
+        let notes: Vec<(PathBuf, FileStamp)> = index.notes().map(|n| (n.path.clone(), n.stamp)).collect();
+        for (path, stamp) in notes {
+            let words = tokenize(&fs::read_to_string(&path).unwrap());
+            index.search_mut().insert(&path, stamp, words);
+        }
+        tracing::info!("build search index: {:?}", start.elapsed());
+
+        let start = Instant::now();
+        assert!(!index.search().query("topic4").is_empty());
+        tracing::info!("query: {:?}", start.elapsed());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Previous,
+    Next,
+}
+
+/// The day a journal entry is for, from a title that starts with its
+/// `YYYY-MM-DD` date, like the daily notes' `2026-03-09` or
+/// `2026-03-09 Long walk`.
+pub fn entry_date(title: &str) -> Option<NaiveDate> {
+    let date = title.get(..10)?;
+    let rest = &title[10..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// The entry nearest to `date` in `direction`, skipping days without one.
+pub fn adjacent<T>(entries: &[(NaiveDate, T)], date: NaiveDate, direction: Direction) -> Option<&T> {
+    let candidates = entries.iter();
+    match direction {
+        Direction::Previous => candidates.filter(|(d, _)| *d < date).max_by_key(|(d, _)| *d),
+        Direction::Next => candidates.filter(|(d, _)| *d > date).min_by_key(|(d, _)| *d),
+    }
+    .map(|(_, entry)| entry)
+}
+
+/// Entries grouped by `YYYY-MM`, newest month and newest entry first.
+pub fn by_month<T>(mut entries: Vec<(NaiveDate, T)>) -> Vec<(String, Vec<T>)> {
+    entries.sort_by_key(|e| std::cmp::Reverse(e.0));
+    let mut months: Vec<(String, Vec<T>)> = Vec::new();
+    for (date, entry) in entries {
+        let month = date.format("%Y-%m").to_string();
+        match months.last_mut() {
+            Some((last, group)) if *last == month => group.push(entry),
+            _ => months.push((month, vec![entry])),
+        }
+    }
+    months
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_entry_date() {
+        assert_eq!(entry_date("2026-03-09"), Some(day(3, 9)));
+        assert_eq!(entry_date("2026-03-09 Long walk"), Some(day(3, 9)));
+        assert_eq!(entry_date("2026-03-091"), None);
+        assert_eq!(entry_date("2026-13-01"), None);
+        assert_eq!(entry_date("Groceries"), None);
+    }
+
+    #[test]
+    fn test_adjacent_skips_missing_days() {
+        let entries = vec![(day(3, 1), "a"), (day(3, 9), "b"), (day(2, 20), "c")];
+        assert_eq!(adjacent(&entries, day(3, 9), Direction::Previous), Some(&"a"));
+        assert_eq!(adjacent(&entries, day(3, 2), Direction::Next), Some(&"b"));
+        assert_eq!(adjacent(&entries, day(2, 20), Direction::Previous), None);
+        assert_eq!(adjacent(&entries, day(3, 9), Direction::Next), None);
+    }
+
+    #[test]
+    fn test_by_month() {
+        let entries = vec![(day(2, 20), "c"), (day(3, 1), "a"), (day(3, 9), "b")];
+        assert_eq!(
+            by_month(entries),
+            vec![("2026-03".to_string(), vec!["b", "a"]), ("2026-02".to_string(), vec!["c"])]
+        );
+    }
+}
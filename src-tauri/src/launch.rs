@@ -0,0 +1,96 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+/// Scheme of links that open a note, `write://open?path=/…/note.md` or
+/// `write://note/<id>`.
+pub const SCHEME: &str = "write";
+
+/// A note is waiting in `take_open_request`.
+pub const OPEN_REQUESTED: &str = "note:open-requested";
+
+/// A note the app was asked to open from outside.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Target {
+    Path(PathBuf),
+    Id(String),
+}
+
+/// What the frontend opens after `note:open-requested`. `workspace_id` is
+/// absent for files outside the notes folder.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct OpenRequest {
+    pub path: String,
+    pub workspace_id: Option<String>,
+}
+
+/// The note named by command line `args` (without the program name), taken
+/// from the first argument that isn't a flag. Relative paths are resolved
+/// against `cwd`.
+pub fn target_from_args(args: &[String], cwd: &Path) -> Option<Target> {
+    let arg = args.iter().find(|arg| !arg.starts_with('-'))?;
+    if arg.contains("://") {
+        return Url::parse(arg).ok().and_then(|url| target_from_url(&url));
+    }
+    let path = Path::new(arg);
+    Some(Target::Path(if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    }))
+}
+
+/// The note a `file://` or `write://` URL points at.
+pub fn target_from_url(url: &Url) -> Option<Target> {
+    match url.scheme() {
+        "file" => url.to_file_path().ok().map(Target::Path),
+        SCHEME => match url.host_str()? {
+            "open" => url
+                .query_pairs()
+                .find(|(key, _)| key == "path")
+                .map(|(_, path)| Target::Path(PathBuf::from(path.as_ref()))),
+            "note" => {
+                let id = url.path().trim_matches('/');
+                (!id.is_empty()).then(|| Target::Id(id.to_string()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_target_from_args() {
+        let cwd = Path::new("/home/me");
+        assert_eq!(
+            target_from_args(&args(&["--verbose", "notes/todo.md"]), cwd),
+            Some(Target::Path(PathBuf::from("/home/me/notes/todo.md")))
+        );
+        assert_eq!(
+            target_from_args(&args(&["/tmp/a.md"]), cwd),
+            Some(Target::Path(PathBuf::from("/tmp/a.md")))
+        );
+        assert_eq!(target_from_args(&args(&["--flag"]), cwd), None);
+    }
+
+    #[test]
+    fn test_target_from_url() {
+        let url = |s: &str| target_from_url(&Url::parse(s).unwrap());
+        assert_eq!(url("file:///tmp/a%20b.md"), Some(Target::Path(PathBuf::from("/tmp/a b.md"))));
+        assert_eq!(
+            url("write://open?path=%2FNotes%2FPersonal%2F1-a.md"),
+            Some(Target::Path(PathBuf::from("/Notes/Personal/1-a.md")))
+        );
+        assert_eq!(url("write://note/abc-123"), Some(Target::Id("abc-123".to_string())));
+        assert_eq!(url("write://note/"), None);
+        assert_eq!(url("https://example.com"), None);
+    }
+}
@@ -1,25 +1,213 @@
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::{BufReader, Read};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::menu::{AboutMetadata, MenuBuilder, SubmenuBuilder};
+use tauri::menu::{AboutMetadata, MenuBuilder, MenuItemBuilder, SubmenuBuilder};
+use tauri::{Emitter, Manager};
 
-#[derive(Serialize, Deserialize, Clone)]
+mod access;
+mod api;
+mod archive;
+mod analytics;
+mod bibtex;
+mod biometric;
+mod board;
+mod cache;
+mod calendar;
+mod cleanup;
+mod clip;
+mod collab;
+mod comments;
+mod config;
+mod crypto;
+mod delta;
+mod diagram;
+mod email;
+mod embeddings;
+mod error;
+mod emoji;
+mod events;
+mod export;
+mod flashcards;
+mod footnotes;
+mod format;
+mod git;
+mod grammar;
+mod highlight;
+mod html;
+mod ignore;
+mod import;
+mod inbox;
+mod index;
+mod journal;
+mod launch;
+mod links;
+mod logging;
+mod markup;
+mod mcp;
+mod ordering;
+mod pandoc;
+mod publish;
+mod reader;
+mod render;
+mod resurface;
+mod review;
+mod search;
+mod secrets;
+mod slug;
+mod snippets;
+mod spellcheck;
+mod stats;
+mod store;
+mod summarize;
+mod table;
+mod tags;
+mod tasks;
+mod templates;
+mod themes;
+mod tools;
+mod typography;
+mod updates;
+mod watcher;
+mod webdav;
+mod whisper;
+
+use access::AccessControl;
+use api::{ApiServer, ApiServerInfo};
+use analytics::TextStats;
+use cache::{FileStamp, NoteCache};
+use collab::{CollabInfo, CollabServer};
+use comments::{Comment, TextRange};
+use calendar::DayEvent;
+use crypto::WorkspaceKeys;
+use delta::{ListingSnapshot, NotesDelta};
+use archive::{ArchivePolicy, ArchiveReport};
+use email::EmailSettings;
+use inbox::InboxSettings;
+use error::AppError;
+use emoji::EmojiMatch;
+use events::{emit_note_event, workspace_id_for_dir, workspace_id_for_path, NOTE_CREATED, NOTE_DELETED, NOTE_RENAMED, NOTE_REORDERED};
+use format::FormatRules;
+use grammar::GrammarIssue;
+use ignore::IgnoreRules;
+use index::{IndexedNote, WorkspaceIndex};
+use markup::Markup;
+use journal::Direction;
+use launch::OpenRequest;
+use links::LinkCandidate;
+use logging::{LogLevel, Logging};
+use mcp::McpAccess;
+use ordering::OrderStore;
+use publish::{Publication, PublishProvider, PublishSettings};
+use reader::NoteChunk;
+use resurface::Resurfaced;
+use review::Change;
+use slug::slugify;
+use spellcheck::{Misspelling, SpellChecker};
+use stats::{DateRange, DayWords, GoalProgress, WordGoal, WritingSession};
+use store::MetaStore;
+use table::TableEdit;
+use tasks::{Task, TaskFilter};
+use templates::Template;
+use tools::{Tool, ToolPaths};
+use updates::{UpdateChannel, UpdateInfo};
+use watcher::{ConfigWatcher, InboxWatcher, NoteFsEvent, WorkspaceWatcher, NOTE_EXTERNALLY_CHANGED, WORKSPACES_CHANGED};
+use webdav::{WebDavInfo, WebDavServer};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct Workspace {
     pub id: String,
     pub name: String,
     pub shortcut: Option<String>,
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Globs of notes kept out of the note list, like `README.md`. Unlike
+    /// ignored files they're still watched, searched and linkable.
+    #[serde(default)]
+    pub hidden_patterns: Vec<String>,
+    #[serde(default)]
+    pub format_rules: FormatRules,
+    /// Notes are stored encrypted with the workspace key.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// The workspace key is in the OS keychain behind Touch ID or Windows
+    /// Hello.
+    #[serde(default)]
+    pub biometric_unlock: bool,
+    /// What assistants connected over MCP may do with the workspace's notes.
+    #[serde(default)]
+    pub mcp_access: McpAccess,
+    #[serde(default)]
+    pub word_goal: WordGoal,
+    /// Name of the theme exported and published pages are styled with.
+    #[serde(default)]
+    pub export_theme: Option<String>,
+    #[serde(default)]
+    pub auto_archive: Option<ArchivePolicy>,
+    /// Folders outside the notes root whose notes are listed, searched and
+    /// watched with the workspace's own. New notes still go in its folder.
+    #[serde(default)]
+    pub extra_roots: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct WorkspaceConfig {
+    /// Schema version the file was written at; see `config::migrate`.
+    #[serde(default)]
+    pub version: u32,
     pub workspaces: Vec<Workspace>,
     pub active_workspace_id: String,
+    /// `.ics` files and calendar URLs whose events go into daily notes.
+    #[serde(default)]
+    pub calendars: Vec<String>,
+    /// IMAP mailbox whose messages are filed into the Inbox workspace.
+    #[serde(default)]
+    pub email: Option<EmailSettings>,
+    /// Where `publish_note` uploads to.
+    #[serde(default)]
+    pub publish: PublishSettings,
+    /// Spell note file names in ASCII, for sync services and file systems
+    /// that mangle other characters.
+    #[serde(default)]
+    pub transliterate_file_names: bool,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub update_channel: UpdateChannel,
+    /// Notes bigger than this open read-only in chunks. Defaults to
+    /// `reader::MAX_READ_BYTES`.
+    #[serde(default)]
+    pub max_note_bytes: Option<u64>,
+    /// Folder whose text files are imported as notes.
+    #[serde(default)]
+    pub inbox: Option<InboxSettings>,
+    #[serde(default)]
+    pub tools: ToolPaths,
 }
 
 pub struct AppState {
     pub config: Mutex<WorkspaceConfig>,
+    pub store: MetaStore,
+    pub note_cache: Mutex<NoteCache>,
+    pub listing: Mutex<ListingSnapshot>,
+    pub order: Mutex<OrderStore>,
+    /// Serializes saves and title renames, which can otherwise overlap now
+    /// that they run on the blocking pool.
+    pub write_lock: Mutex<()>,
+    /// Held while a new note picks its number and name, so notes created
+    /// at once can't take the same ones.
+    pub numbering: Mutex<()>,
+    pub spell: SpellChecker,
+    pub keys: WorkspaceKeys,
+    pub access: AccessControl,
+    /// The writing session in progress, if any.
+    pub session: Mutex<Option<WritingSession>>,
+    /// A note the app was launched or asked by the OS to open, until the
+    /// frontend takes it.
+    pub pending_open: Mutex<Option<OpenRequest>>,
 }
 
 fn get_notes_root() -> PathBuf {
@@ -27,24 +215,127 @@ fn get_notes_root() -> PathBuf {
     home.join("Notes")
 }
 
-fn get_config_path() -> PathBuf {
+fn get_app_data_dir() -> PathBuf {
     let data_dir = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
-    data_dir.join("com.write.app").join("workspaces.json")
+    data_dir.join("com.write.app")
+}
+
+fn get_config_path() -> PathBuf {
+    get_app_data_dir().join("workspaces.json")
+}
+
+fn get_store_path() -> PathBuf {
+    get_app_data_dir().join("metadata.db")
+}
+
+/// Where hunspell `.aff`/`.dic` pairs are looked up, app-installed first.
+fn get_dictionary_dirs() -> Vec<PathBuf> {
+    let mut search_dirs = vec![get_app_data_dir().join("dictionaries")];
+    if let Some(home) = dirs::home_dir() {
+        search_dirs.push(home.join("Library/Spelling"));
+    }
+    search_dirs.push(PathBuf::from("/Library/Spelling"));
+    search_dirs.push(PathBuf::from("/usr/share/hunspell"));
+    search_dirs
+}
+
+/// Opens the metadata store, importing the JSON sidecars it replaces. Falls
+/// back to an in-memory store so a corrupt database only costs the cache.
+fn open_store() -> Result<MetaStore, String> {
+    let store = match MetaStore::open(&get_store_path()) {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("Can't open the metadata store, keeping it in memory: {}", e);
+            MetaStore::open_in_memory()?
+        }
+    };
+    let _ = store.import_legacy_order(&get_app_data_dir().join("note-order.json"));
+    let _ = fs::remove_file(get_app_data_dir().join("note-cache.json"));
+    Ok(store)
+}
+
+fn read_config() -> Option<WorkspaceConfig> {
+    read_config_file().map(|(config, _)| config)
+}
+
+/// The config on disk upgraded to the current schema, and the version the
+/// file was at.
+fn read_config_file() -> Option<(WorkspaceConfig, u32)> {
+    let content = fs::read_to_string(get_config_path()).ok()?;
+    let mut value: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let version = config::version(&value);
+    config::migrate(&mut value);
+    Some((serde_json::from_value(value).ok()?, version))
 }
 
 fn load_config() -> WorkspaceConfig {
-    let path = get_config_path();
-    if path.exists() {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
-            }
+    if let Some((config, version)) = read_config_file() {
+        // Upgraded files are written back once, keeping the old one beside
+        // them in case the upgrade lost something.
+        if version < config::CONFIG_VERSION {
+            tracing::info!("Upgrading workspaces.json from version {} to {}", version, config::CONFIG_VERSION);
+            let path = get_config_path();
+            let _ = fs::copy(&path, path.with_extension(format!("v{}.json", version)));
+            let _ = save_config(&config);
         }
+        return config;
     }
     WorkspaceConfig {
+        version: config::CONFIG_VERSION,
         workspaces: vec![],
         active_workspace_id: String::new(),
+        calendars: vec![],
+        email: None,
+        publish: PublishSettings::default(),
+        transliterate_file_names: false,
+        log_level: LogLevel::default(),
+        update_channel: UpdateChannel::default(),
+        max_note_bytes: None,
+        inbox: None,
+        tools: Default::default(),
+    }
+}
+
+/// Picks up edits to `workspaces.json` made by another instance or a sync
+/// tool. The local active workspace is kept unless it no longer exists.
+fn reload_config(app: &tauri::AppHandle) {
+    // A half-written or unparsable file is ignored; the next event will retry.
+    let Some(disk) = read_config() else {
+        return;
+    };
+
+    let state = app.state::<AppState>();
+    let mut config = state.config.lock().unwrap();
+    let active_workspace_id = if disk.workspaces.iter().any(|w| w.id == config.active_workspace_id) {
+        config.active_workspace_id.clone()
+    } else {
+        disk.active_workspace_id.clone()
+    };
+    let updated = WorkspaceConfig {
+        active_workspace_id,
+        ..disk
+    };
+    if *config == updated {
+        return;
     }
+
+    let active_changed = updated.active_workspace_id != config.active_workspace_id;
+    let inbox_changed = updated.inbox != config.inbox;
+    let roots = |config: &WorkspaceConfig| config.workspaces.iter().map(|w| w.extra_roots.clone()).collect::<Vec<_>>();
+    let roots_changed = roots(&updated) != roots(&config);
+    *config = updated.clone();
+    drop(config);
+
+    if roots_changed {
+        sync_extra_roots(&state);
+    }
+    if active_changed || roots_changed {
+        let _ = watch_workspace(app, &updated.active_workspace_id);
+    }
+    if inbox_changed {
+        let _ = watch_inbox(app);
+    }
+    let _ = app.emit(WORKSPACES_CHANGED, updated);
 }
 
 fn save_config(config: &WorkspaceConfig) -> Result<(), String> {
@@ -60,6 +351,103 @@ fn get_workspace_dir(workspace_id: &str) -> PathBuf {
     get_notes_root().join(workspace_id)
 }
 
+/// The folders besides its own that workspace `workspace_id` takes notes
+/// from.
+fn get_extra_roots(config: &WorkspaceConfig, workspace_id: &str) -> Vec<PathBuf> {
+    config
+        .workspaces
+        .iter()
+        .find(|w| w.id == workspace_id)
+        .map(|w| w.extra_roots.iter().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+/// A fresh index of every root of workspace `workspace_id`.
+fn workspace_index(config: &WorkspaceConfig, workspace_id: &str) -> WorkspaceIndex {
+    WorkspaceIndex::build_with_roots(
+        &get_workspace_dir(workspace_id),
+        &get_extra_roots(config, workspace_id),
+        get_ignore_rules(config, workspace_id),
+    )
+}
+
+/// Lets notes in the workspaces' extra roots be opened and tells which
+/// workspace they belong to.
+fn sync_extra_roots(state: &AppState) {
+    let roots: Vec<(PathBuf, String)> = state
+        .config
+        .lock()
+        .unwrap()
+        .workspaces
+        .iter()
+        .flat_map(|w| w.extra_roots.iter().map(|root| (PathBuf::from(root), w.id.clone())))
+        .collect();
+    state.access.set_extra_roots(roots.iter().map(|(root, _)| root.clone()).collect());
+    events::set_extra_roots(roots);
+}
+
+fn get_ignore_rules(config: &WorkspaceConfig, workspace_id: &str) -> IgnoreRules {
+    config
+        .workspaces
+        .iter()
+        .find(|w| w.id == workspace_id)
+        .map(|w| IgnoreRules::lenient(&w.ignore_patterns))
+        .unwrap_or_else(IgnoreRules::empty)
+}
+
+/// Queues the note `target` for the frontend to open, switching to its
+/// workspace first. Files outside the notes folder came from the user, so
+/// they're allowed for the session.
+fn request_open(app: &tauri::AppHandle, target: launch::Target) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let path = match target {
+        launch::Target::Path(path) => path,
+        launch::Target::Id(id) => PathBuf::from(resolve_note_ref(&state, &id)?),
+    };
+    if !path.is_file() {
+        return Err(format!("{} isn't a file", path.display()));
+    }
+    let in_notes_root = path.parent().and_then(|dir| dir.parent()) == Some(get_notes_root().as_path());
+    let workspace_id = Some(workspace_id_for_path(&path))
+        .filter(|id| in_notes_root && state.config.lock().unwrap().workspaces.iter().any(|w| w.id == *id));
+    match &workspace_id {
+        Some(id) => {
+            let mut config = state.config.lock().unwrap();
+            if config.active_workspace_id != *id {
+                config.active_workspace_id = id.clone();
+                save_config(&config)?;
+                let updated = config.clone();
+                drop(config);
+                watch_workspace(app, id)?;
+                let _ = app.emit(WORKSPACES_CHANGED, updated);
+            }
+        }
+        None => state.access.grant(&path)?,
+    }
+    *state.pending_open.lock().unwrap() = Some(OpenRequest {
+        path: path.to_string_lossy().to_string(),
+        workspace_id,
+    });
+    let _ = app.emit(launch::OPEN_REQUESTED, ());
+    Ok(())
+}
+
+/// The note waiting to be opened from the command line or the OS, if any.
+#[tauri::command]
+fn take_open_request(state: tauri::State<AppState>) -> Option<OpenRequest> {
+    state.pending_open.lock().unwrap().take()
+}
+
+fn watch_workspace(app: &tauri::AppHandle, workspace_id: &str) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let (extra_roots, ignore) = {
+        let config = state.config.lock().unwrap();
+        (get_extra_roots(&config, workspace_id), get_ignore_rules(&config, workspace_id))
+    };
+    app.state::<WorkspaceWatcher>()
+        .watch(app, workspace_id, &get_workspace_dir(workspace_id), &extra_roots, ignore)
+}
+
 fn migrate_existing_notes() -> Result<WorkspaceConfig, String> {
     let notes_root = get_notes_root();
     let personal_dir = notes_root.join("Personal");
@@ -87,12 +475,32 @@ fn migrate_existing_notes() -> Result<WorkspaceConfig, String> {
     }
 
     let config = WorkspaceConfig {
+        version: config::CONFIG_VERSION,
         workspaces: vec![Workspace {
             id: "Personal".to_string(),
             name: "Personal".to_string(),
             shortcut: Some("1".to_string()),
+            ignore_patterns: vec![],
+            hidden_patterns: vec![],
+            format_rules: FormatRules::default(),
+            encrypted: false,
+            biometric_unlock: false,
+            mcp_access: McpAccess::None,
+            word_goal: WordGoal::default(),
+            export_theme: None,
+            auto_archive: None,
+            extra_roots: vec![],
         }],
         active_workspace_id: "Personal".to_string(),
+        calendars: vec![],
+        email: None,
+        publish: PublishSettings::default(),
+        transliterate_file_names: false,
+        log_level: LogLevel::default(),
+        update_channel: UpdateChannel::default(),
+        max_note_bytes: None,
+        inbox: None,
+        tools: Default::default(),
     };
 
     save_config(&config)?;
@@ -105,12 +513,32 @@ fn init_workspaces() -> WorkspaceConfig {
         load_config()
     } else {
         migrate_existing_notes().unwrap_or_else(|_| WorkspaceConfig {
+            version: config::CONFIG_VERSION,
             workspaces: vec![Workspace {
                 id: "Personal".to_string(),
                 name: "Personal".to_string(),
                 shortcut: Some("1".to_string()),
+                ignore_patterns: vec![],
+                hidden_patterns: vec![],
+                format_rules: FormatRules::default(),
+                encrypted: false,
+                biometric_unlock: false,
+                mcp_access: McpAccess::None,
+                word_goal: WordGoal::default(),
+                export_theme: None,
+                auto_archive: None,
+                extra_roots: vec![],
             }],
             active_workspace_id: "Personal".to_string(),
+            calendars: vec![],
+            email: None,
+            publish: PublishSettings::default(),
+            transliterate_file_names: false,
+            log_level: LogLevel::default(),
+            update_channel: UpdateChannel::default(),
+            max_note_bytes: None,
+            inbox: None,
+            tools: Default::default(),
         })
     };
 
@@ -124,28 +552,6 @@ fn init_workspaces() -> WorkspaceConfig {
     config
 }
 
-fn slugify(text: &str) -> String {
-    let slug: String = text
-        .to_lowercase()
-        .chars()
-        .map(|c| if c.is_alphanumeric() { c } else { '-' })
-        .collect();
-    let mut result = String::new();
-    let mut prev_dash = false;
-    for c in slug.chars() {
-        if c == '-' {
-            if !prev_dash && !result.is_empty() {
-                result.push(c);
-            }
-            prev_dash = true;
-        } else {
-            result.push(c);
-            prev_dash = false;
-        }
-    }
-    result.trim_end_matches('-').to_string()
-}
-
 fn parse_file_number(name: &str) -> Option<u64> {
     let dash_pos = name.find('-')?;
     name[..dash_pos].parse().ok()
@@ -210,65 +616,343 @@ fn migrate_old_notes(notes_dir: &std::path::Path) {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct NoteEntry {
     pub name: String,
     pub path: String,
     pub modified: u64,
+    /// Unix seconds, from the file's birth time or else the first time the
+    /// app saw it. Unknown for notes not listed before.
+    #[serde(default)]
+    pub created: Option<u64>,
+    /// Stable id that survives renames and moves.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub title: String,
+}
+
+/// How far into a file to look for its H1, frontmatter included.
+const TITLE_SCAN_BYTES: u64 = 64 * 1024;
+/// Titles are cut to this many characters.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// What listings need from the top of a note: its title and any frontmatter
+/// `aliases`.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct NoteHeader {
     pub title: String,
+    pub aliases: Vec<String>,
 }
 
 fn parse_title(content: &str) -> String {
-    content
-        .lines()
-        .find(|line| line.starts_with("# "))
-        .map(|line| line.trim_start_matches("# ").to_string())
-        .unwrap_or_else(|| "Untitled".to_string())
+    read_header(content.as_bytes()).title
+}
+
+/// The title of the note at `path`, read the way its markup titles notes.
+fn note_title(path: &std::path::Path, content: &str) -> String {
+    match Markup::from_path(path) {
+        None | Some(Markup::Markdown) => parse_title(content),
+        Some(markup) => markup_header(markup, content).title,
+    }
+}
+
+fn read_header_from_file(path: &std::path::Path) -> NoteHeader {
+    let Ok(file) = File::open(path) else {
+        return NoteHeader {
+            title: "Untitled".to_string(),
+            aliases: Vec::new(),
+        };
+    };
+    let mut head = BufReader::new(file.take(TITLE_SCAN_BYTES));
+    match Markup::from_path(path) {
+        None | Some(Markup::Markdown) => read_header(head),
+        Some(markup) => {
+            let mut bytes = Vec::new();
+            let _ = head.read_to_end(&mut bytes);
+            bytes.truncate(reader::complete_utf8_len(&bytes));
+            markup_header(markup, &String::from_utf8_lossy(&bytes))
+        }
+    }
 }
 
-fn read_title_from_file(path: &std::path::Path) -> String {
-    let file = match File::open(path) {
-        Ok(f) => f,
-        Err(_) => return "Untitled".to_string(),
+/// The header of an Org or text note. Those have no aliases.
+fn markup_header(markup: Markup, content: &str) -> NoteHeader {
+    let title = if crypto::is_encrypted(content) {
+        crypto::ENCRYPTED_TITLE.to_string()
+    } else {
+        markup
+            .title(content)
+            .map(|t| render::plain_text(&t))
+            .filter(|t| !t.is_empty())
+            .map(|t| t.chars().take(MAX_TITLE_CHARS).collect())
+            .unwrap_or_else(|| "Untitled".to_string())
     };
-    let mut reader = BufReader::new(file);
-    let mut buf = [0u8; 200];
-    let n = reader.read(&mut buf).unwrap_or(0);
-    let content = String::from_utf8_lossy(&buf[..n]);
-    parse_title(&content)
+    NoteHeader {
+        title,
+        aliases: Vec::new(),
+    }
+}
+
+/// Returns the first H1, ATX (`# Title`) or setext (`Title` over `===`), as
+/// plain text. A leading `---` frontmatter block and fenced code are skipped
+/// so YAML and shell comments aren't mistaken for headings. Aliases are
+/// picked up from the frontmatter on the way.
+fn read_header(mut reader: impl BufRead) -> NoteHeader {
+    let mut line = Vec::new();
+    let mut first_line = true;
+    let mut in_frontmatter = false;
+    let mut in_alias_list = false;
+    let mut aliases = Vec::new();
+    // The opening fence of a code block being skipped.
+    let mut fence: Option<String> = None;
+    // Lines of the paragraph so far, which a `===` line turns into a heading.
+    let mut paragraph: Vec<String> = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if !line.ends_with(b"\n") {
+            // The scan limit may have cut the last line inside a character.
+            line.truncate(reader::complete_utf8_len(&line));
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end();
+        if text == crypto::ARMOR_BEGIN {
+            return NoteHeader {
+                title: crypto::ENCRYPTED_TITLE.to_string(),
+                aliases: Vec::new(),
+            };
+        }
+
+        if std::mem::take(&mut first_line) && text == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = text != "---" && text != "...";
+            if in_alias_list {
+                if let Some(item) = text.trim_start().strip_prefix("- ") {
+                    aliases.push(unquote(item));
+                    continue;
+                }
+                in_alias_list = false;
+            }
+            if let Some(value) = text.strip_prefix("aliases:").or_else(|| text.strip_prefix("alias:")) {
+                let value = value.trim();
+                if value.is_empty() {
+                    in_alias_list = true;
+                } else if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                    aliases.extend(list.split(',').map(unquote).filter(|a| !a.is_empty()));
+                } else {
+                    aliases.push(unquote(value));
+                }
+            }
+            continue;
+        }
+
+        let indent = text.len() - text.trim_start_matches(' ').len();
+        let block = text.trim_start();
+        if let Some(open) = &fence {
+            let marker = &open[..1];
+            if indent < 4 && block.starts_with(open.as_str()) && block.trim_start_matches(marker).trim().is_empty() {
+                fence = None;
+            }
+            continue;
+        }
+        if indent >= 4 && paragraph.is_empty() {
+            // Indented code.
+            continue;
+        }
+        if block.starts_with("```") || block.starts_with("~~~") {
+            let marker = &block[..1];
+            let len = block.len() - block.trim_start_matches(marker).len();
+            fence = Some(marker.repeat(len));
+            paragraph.clear();
+            continue;
+        }
+        let heading = if let Some(rest) = block.strip_prefix('#').filter(|r| r.is_empty() || r.starts_with([' ', '\t'])) {
+            Some(rest.to_string())
+        } else if !paragraph.is_empty() && block.chars().all(|c| c == '=') && !block.is_empty() {
+            Some(paragraph.join(" "))
+        } else {
+            None
+        };
+        if let Some(heading) = heading {
+            let title = render::plain_text(&heading);
+            if !title.is_empty() {
+                return NoteHeader {
+                    title: title.chars().take(MAX_TITLE_CHARS).collect(),
+                    aliases,
+                };
+            }
+        }
+        if block.is_empty() || block.starts_with('#') || block.chars().all(|c| c == '=' || c == '-') {
+            paragraph.clear();
+        } else {
+            paragraph.push(block.to_string());
+        }
+    }
+    NoteHeader {
+        title: "Untitled".to_string(),
+        aliases,
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
+}
+
+/// Folder in a workspace holding attachments and downloaded images, next to
+/// the notes that link them.
+const ASSETS_DIR: &str = "assets";
+
+const WORKSPACE_MISSING_ERROR: &str = "Workspace folder is missing";
+
+/// Fails with a recoverable error instead of a raw IO error once the watcher
+/// has seen the active workspace folder disappear.
+fn check_workspace_present(watcher: &WorkspaceWatcher) -> Result<(), AppError> {
+    if watcher.is_missing() {
+        return Err(AppError::not_found(WORKSPACE_MISSING_ERROR));
+    }
+    Ok(())
+}
+
+fn check_note_dir_present(path: &std::path::Path) -> Result<(), AppError> {
+    match path.parent() {
+        Some(parent) if !parent.is_dir() => Err(AppError::not_found(WORKSPACE_MISSING_ERROR)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks a note path from the frontend before it's read or written.
+fn check_note_path(state: &AppState, path: &std::path::Path) -> Result<(), AppError> {
+    state.access.check(path).map_err(AppError::permission_denied)?;
+    check_note_dir_present(path)
+}
+
+/// Checks where an export is written to. Only a path the user picked with
+/// `pick_save_file`, or one inside the notes folder, is allowed.
+fn check_export_dest(state: &AppState, dest: &str) -> Result<(), AppError> {
+    state.access.check(std::path::Path::new(dest)).map_err(AppError::permission_denied)
+}
+
+fn check_note_unlocked(state: &AppState, path: &std::path::Path) -> Result<(), AppError> {
+    if state.store.is_locked(&path.to_string_lossy())? {
+        return Err(AppError::permission_denied("Note is locked. Unlock it to change it"));
+    }
+    Ok(())
+}
+
+/// The path of the note `note` refers to, which is either its stable id or
+/// already a path.
+fn resolve_note_ref(state: &AppState, note: &str) -> Result<String, AppError> {
+    if !store::is_note_id(note) {
+        return Ok(note.to_string());
+    }
+    state
+        .store
+        .path_for_note_id(note)?
+        .ok_or_else(|| AppError::not_found(format!("No note with id {}", note)))
 }
 
 #[tauri::command]
-fn ensure_notes_dir(state: tauri::State<AppState>) -> Result<String, String> {
+fn ensure_notes_dir(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    watcher: tauri::State<WorkspaceWatcher>,
+) -> Result<String, AppError> {
+    check_workspace_present(&watcher)?;
     let config = state.config.lock().unwrap();
-    let notes_dir = get_workspace_dir(&config.active_workspace_id);
+    let workspace_id = config.active_workspace_id.clone();
     drop(config);
+    let notes_dir = get_workspace_dir(&workspace_id);
     if !notes_dir.exists() {
         fs::create_dir_all(&notes_dir).map_err(|e| e.to_string())?;
+        watch_workspace(&app, &workspace_id)?;
     }
     Ok(notes_dir.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn get_workspaces(state: tauri::State<AppState>) -> Result<WorkspaceConfig, String> {
+fn recreate_workspace_dir(app: tauri::AppHandle, state: tauri::State<AppState>) -> Result<String, AppError> {
+    let config = state.config.lock().unwrap();
+    let workspace_id = config.active_workspace_id.clone();
+    drop(config);
+    let notes_dir = get_workspace_dir(&workspace_id);
+    fs::create_dir_all(&notes_dir).map_err(|e| e.to_string())?;
+    watch_workspace(&app, &workspace_id)?;
+    Ok(notes_dir.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn get_workspaces(state: tauri::State<AppState>) -> Result<WorkspaceConfig, AppError> {
     let config = state.config.lock().unwrap();
     Ok(config.clone())
 }
 
 #[tauri::command]
-fn set_active_workspace(state: tauri::State<AppState>, workspace_id: String) -> Result<(), String> {
+fn set_active_workspace(app: tauri::AppHandle, state: tauri::State<AppState>, workspace_id: String) -> Result<(), AppError> {
     let mut config = state.config.lock().unwrap();
     if !config.workspaces.iter().any(|w| w.id == workspace_id) {
-        return Err("Workspace not found".to_string());
+        return Err(AppError::not_found("Workspace not found"));
     }
-    config.active_workspace_id = workspace_id;
-    save_config(&config)
+    config.active_workspace_id = workspace_id.clone();
+    save_config(&config)?;
+    drop(config);
+    Ok(watch_workspace(&app, &workspace_id)?)
 }
 
 #[tauri::command]
-fn create_workspace(state: tauri::State<AppState>, name: String) -> Result<Workspace, String> {
+fn create_workspace(state: tauri::State<AppState>, name: String) -> Result<Workspace, AppError> {
     let mut config = state.config.lock().unwrap();
+    let workspace = add_workspace(&mut config, name)?;
+    save_config(&config)?;
+    Ok(workspace)
+}
+
+/// Creates a workspace named `name` from a clone of the Git repository at
+/// `url`. Its `.git` folder is kept, so the notes stay a working copy.
+#[tauri::command]
+async fn create_workspace_from_git(
+    app: tauri::AppHandle,
+    url: String,
+    name: String,
+) -> Result<Workspace, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let id = slugify(&name);
+        if id.is_empty() {
+            return Err(AppError::invalid_input("Invalid workspace name"));
+        }
+        if state.config.lock().unwrap().workspaces.iter().any(|w| w.id == id) {
+            return Err(AppError::conflict("Workspace already exists"));
+        }
+        let dir = get_workspace_dir(&id);
+        if dir.exists() {
+            return Err(AppError::conflict(format!("There's already a folder named {} in the notes folder", id)));
+        }
+        if !git::is_remote(&url) {
+            return Err(AppError::invalid_input(format!("{} isn't a repository URL", url)));
+        }
+        fs::create_dir_all(get_notes_root()).map_err(|e| e.to_string())?;
+        git::clone(&tool_command(&state, Tool::Git)?, &url, &dir)?;
 
+        let mut config = state.config.lock().unwrap();
+        let added = add_workspace(&mut config, name).and_then(|workspace| save_config(&config).map(|_| workspace));
+        if added.is_err() {
+            config.workspaces.retain(|w| w.id != id);
+            let _ = fs::remove_dir_all(&dir);
+        }
+        Ok(added?)
+    })
+    .await
+}
+
+/// Adds a workspace named `name` and creates its folder. The caller saves
+/// the config.
+fn add_workspace(config: &mut WorkspaceConfig, name: String) -> Result<Workspace, String> {
     let id = slugify(&name);
     if id.is_empty() {
         return Err("Invalid workspace name".to_string());
@@ -288,20 +972,30 @@ fn create_workspace(state: tauri::State<AppState>, name: String) -> Result<Works
         id: id.clone(),
         name,
         shortcut: next_shortcut,
+        ignore_patterns: vec![],
+        hidden_patterns: vec![],
+        format_rules: FormatRules::default(),
+        encrypted: false,
+        biometric_unlock: false,
+        mcp_access: McpAccess::None,
+        word_goal: WordGoal::default(),
+        export_theme: None,
+        auto_archive: None,
+        extra_roots: vec![],
     };
 
     config.workspaces.push(workspace.clone());
-    save_config(&config)?;
-
     Ok(workspace)
 }
 
+/// Removes a workspace and moves its folder to the trash, or deletes it for
+/// good with `permanent`.
 #[tauri::command]
-fn delete_workspace(state: tauri::State<AppState>, workspace_id: String) -> Result<(), String> {
+fn delete_workspace(state: tauri::State<AppState>, workspace_id: String, permanent: Option<bool>) -> Result<(), AppError> {
     let mut config = state.config.lock().unwrap();
 
     if config.workspaces.len() <= 1 {
-        return Err("Cannot delete the last workspace".to_string());
+        return Err(AppError::invalid_input("Cannot delete the last workspace"));
     }
 
     let idx = config
@@ -310,17 +1004,25 @@ fn delete_workspace(state: tauri::State<AppState>, workspace_id: String) -> Resu
         .position(|w| w.id == workspace_id)
         .ok_or("Workspace not found")?;
 
-    config.workspaces.remove(idx);
+    let dir = get_workspace_dir(&workspace_id);
+    if dir.exists() {
+        discard(&dir, permanent.unwrap_or(false))?;
+    }
+    let removed = config.workspaces.remove(idx);
+    if removed.biometric_unlock {
+        let _ = secrets::delete(&secrets::workspace_key_name(&workspace_id));
+    }
+    state.keys.lock(&workspace_id);
 
     if config.active_workspace_id == workspace_id {
         config.active_workspace_id = config.workspaces[0].id.clone();
     }
 
-    save_config(&config)
+    Ok(save_config(&config)?)
 }
 
 #[tauri::command]
-fn rename_workspace(state: tauri::State<AppState>, workspace_id: String, new_name: String) -> Result<Workspace, String> {
+fn rename_workspace(state: tauri::State<AppState>, workspace_id: String, new_name: String) -> Result<Workspace, AppError> {
     let mut config = state.config.lock().unwrap();
 
     let workspace = config
@@ -336,138 +1038,4052 @@ fn rename_workspace(state: tauri::State<AppState>, workspace_id: String, new_nam
     Ok(updated)
 }
 
+/// Keeps notes matching `patterns` out of the workspace's note list.
 #[tauri::command]
-fn list_notes(state: tauri::State<AppState>) -> Result<Vec<NoteEntry>, String> {
-    let config = state.config.lock().unwrap();
-    let notes_dir = get_workspace_dir(&config.active_workspace_id);
-    drop(config);
-
-    if !notes_dir.exists() {
-        return Ok(vec![]);
-    }
-
-    let mut entries: Vec<NoteEntry> = fs::read_dir(&notes_dir)
-        .map_err(|e| e.to_string())?
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if path.extension()?.to_str()? == "md" {
-                let metadata = entry.metadata().ok()?;
-                let modified = metadata
-                    .modified()
-                    .ok()?
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .ok()?
-                    .as_secs();
-                let name = path.file_stem()?.to_string_lossy().to_string();
-                let title = read_title_from_file(&path);
-                Some(NoteEntry {
-                    name,
-                    path: path.to_string_lossy().to_string(),
-                    modified,
-                    title,
-                })
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    entries.sort_by(|a, b| {
-        let num_a = parse_file_number(&a.name);
-        let num_b = parse_file_number(&b.name);
-        match (num_a, num_b) {
-            (Some(a), Some(b)) => b.cmp(&a),
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => b.modified.cmp(&a.modified),
-        }
-    });
-    Ok(entries)
-}
-
-#[tauri::command]
-fn read_note(path: String) -> Result<String, String> {
-    fs::read_to_string(&path).map_err(|e| e.to_string())
+fn set_workspace_hidden_patterns(
+    state: tauri::State<AppState>,
+    watcher: tauri::State<WorkspaceWatcher>,
+    workspace_id: String,
+    patterns: Vec<String>,
+) -> Result<Workspace, AppError> {
+    IgnoreRules::new(&patterns)?;
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.hidden_patterns = patterns;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    watcher.with_index(WorkspaceIndex::mark_all_changed);
+    Ok(updated)
 }
 
 #[tauri::command]
-fn write_note(path: String, content: String) -> Result<String, String> {
-    fs::write(&path, &content).map_err(|e| e.to_string())?;
-
-    let old_path = PathBuf::from(&path);
-    let parent = old_path.parent().ok_or("Invalid path")?;
-    let old_name = old_path
-        .file_stem()
-        .ok_or("Invalid filename")?
-        .to_string_lossy()
-        .to_string();
-
-    let number = parse_file_number(&old_name);
-    if number.is_none() {
-        return Ok(path);
-    }
-    let number = number.unwrap();
-
-    let title = parse_title(&content);
-    let slug = if title == "Untitled" || title.is_empty() {
-        "untitled".to_string()
-    } else {
-        slugify(&title)
-    };
-
-    let new_name = format!("{}-{}", number, slug);
-    if new_name == old_name {
-        return Ok(path);
-    }
+fn set_workspace_ignore_patterns(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    workspace_id: String,
+    patterns: Vec<String>,
+) -> Result<Workspace, AppError> {
+    IgnoreRules::new(&patterns)?;
+    let mut config = state.config.lock().unwrap();
 
-    let new_path = parent.join(format!("{}.md", new_name));
-    if new_path.exists() && new_path != old_path {
-        return Ok(path);
-    }
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
 
-    fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
-    Ok(new_path.to_string_lossy().to_string())
-}
+    workspace.ignore_patterns = patterns;
+    let updated = workspace.clone();
 
-#[tauri::command]
-fn create_note(state: tauri::State<AppState>) -> Result<String, String> {
-    let config = state.config.lock().unwrap();
-    let notes_dir = get_workspace_dir(&config.active_workspace_id);
+    save_config(&config)?;
+    let is_active = config.active_workspace_id == workspace_id;
     drop(config);
 
-    if !notes_dir.exists() {
-        fs::create_dir_all(&notes_dir).map_err(|e| e.to_string())?;
+    if is_active {
+        watch_workspace(&app, &workspace_id)?;
     }
-
-    let number = get_next_number(&notes_dir);
-    let path = notes_dir.join(format!("{}-untitled.md", number));
-
-    fs::write(&path, "\n").map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
+    Ok(updated)
 }
 
 #[tauri::command]
-fn delete_note(path: String) -> Result<(), String> {
-    fs::remove_file(&path).map_err(|e| e.to_string())
+fn set_workspace_format_rules(
+    state: tauri::State<AppState>,
+    workspace_id: String,
+    rules: FormatRules,
+) -> Result<Workspace, AppError> {
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.format_rules = rules;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    Ok(updated)
 }
 
+/// Lets assistants connected over MCP read, or read and add to, the
+/// workspace's notes. Encrypted workspaces stay off limits either way.
 #[tauri::command]
-fn rename_note(old_path: String, new_name: String) -> Result<String, String> {
-    let old_path = PathBuf::from(&old_path);
-    let parent = old_path.parent().ok_or("Invalid path")?;
-    let new_path = parent.join(format!("{}.md", new_name));
-
-    if new_path.exists() {
-        return Err("A note with this name already exists".to_string());
-    }
-
-    fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
-    Ok(new_path.to_string_lossy().to_string())
+fn set_workspace_mcp_access(
+    state: tauri::State<AppState>,
+    workspace_id: String,
+    access: McpAccess,
+) -> Result<Workspace, AppError> {
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.mcp_access = access;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    Ok(updated)
 }
 
 #[tauri::command]
-fn reveal_in_finder(path: String) -> Result<(), String> {
+fn set_workspace_word_goal(state: tauri::State<AppState>, workspace_id: String, goal: WordGoal) -> Result<Workspace, AppError> {
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.word_goal = goal;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    Ok(updated)
+}
+
+/// Styles the workspace's exported and published pages with the theme
+/// `theme`, or the default look when `None`.
+#[tauri::command]
+fn set_workspace_export_theme(
+    state: tauri::State<AppState>,
+    workspace_id: String,
+    theme: Option<String>,
+) -> Result<Workspace, AppError> {
+    if let Some(name) = &theme {
+        let theme = themes::list(&get_app_data_dir(), &get_workspace_dir(&workspace_id))
+            .into_iter()
+            .find(|t| &t.name == name)
+            .ok_or_else(|| format!("No theme {}", name))?;
+        if let Some(error) = theme.error {
+            return Err(AppError::invalid_input(format!("Theme {} can't be used: {}", name, error)));
+        }
+    }
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.export_theme = theme;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    Ok(updated)
+}
+
+/// Sets the folders outside the notes root, like a project's `docs`
+/// folder, whose notes show up in the workspace alongside its own.
+#[tauri::command]
+fn set_workspace_roots(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    workspace_id: String,
+    roots: Vec<String>,
+) -> Result<Workspace, AppError> {
+    let notes_root = get_notes_root();
+    let updated = {
+        let mut config = state.config.lock().unwrap();
+        let current = config
+            .workspaces
+            .iter()
+            .find(|w| w.id == workspace_id)
+            .map(|w| w.extra_roots.clone())
+            .unwrap_or_default();
+        for root in &roots {
+            let dir = PathBuf::from(root);
+            if !dir.is_absolute() || !dir.is_dir() {
+                return Err(AppError::invalid_input(format!("{} isn't a folder", root)));
+            }
+            // A new root has to be one the user picked with `pick_folder`.
+            if !current.contains(root) {
+                state.access.check(&dir)?;
+            }
+            if dir.starts_with(&notes_root) || notes_root.starts_with(&dir) {
+                return Err(AppError::invalid_input(format!("{} overlaps the notes folder", root)));
+            }
+            let owner = config
+                .workspaces
+                .iter()
+                .find(|w| w.id != workspace_id && w.extra_roots.contains(root));
+            if let Some(owner) = owner {
+                return Err(AppError::conflict(format!("{} is already in workspace \"{}\"", root, owner.name)));
+            }
+        }
+        let workspace = config
+            .workspaces
+            .iter_mut()
+            .find(|w| w.id == workspace_id)
+            .ok_or("Workspace not found")?;
+        if workspace.encrypted && !roots.is_empty() {
+            return Err(AppError::conflict("Encrypted workspaces can't take notes from other folders"));
+        }
+        workspace.extra_roots = roots;
+        workspace.extra_roots.dedup();
+        let updated = workspace.clone();
+        save_config(&config)?;
+        updated
+    };
+    sync_extra_roots(&state);
+    if state.config.lock().unwrap().active_workspace_id == workspace_id {
+        watch_workspace(&app, &workspace_id)?;
+    }
+    Ok(updated)
+}
+
+/// Archives the workspace's notes once they've gone `policy.months`
+/// without an edit, or stops archiving when `None`. Applies right away.
+#[tauri::command]
+async fn set_workspace_auto_archive(
+    app: tauri::AppHandle,
+    workspace_id: String,
+    policy: Option<ArchivePolicy>,
+) -> Result<Vec<ArchiveReport>, AppError> {
+    blocking(move || {
+        if policy.is_some_and(|p| p.months == 0) {
+            return Err(AppError::invalid_input("Notes can only be archived after at least a month"));
+        }
+        let state = app.state::<AppState>();
+        {
+            let mut config = state.config.lock().unwrap();
+            let workspace = config
+                .workspaces
+                .iter_mut()
+                .find(|w| w.id == workspace_id)
+                .ok_or("Workspace not found")?;
+            workspace.auto_archive = policy;
+            save_config(&config)?;
+        }
+        Ok(auto_archive(&app, Some(&workspace_id))?)
+    })
+    .await
+}
+
+/// Applies the auto-archive policies now, of one workspace or of all of
+/// them, and reports what was archived.
+#[tauri::command]
+async fn run_auto_archive(app: tauri::AppHandle, workspace_id: Option<String>) -> Result<Vec<ArchiveReport>, AppError> {
+    blocking(move || Ok(auto_archive(&app, workspace_id.as_deref())?)).await
+}
+
+/// Moves notes past their workspace's auto-archive age into its archive
+/// folder. Pinned notes stay. Reports are emitted when anything moved.
+fn auto_archive(app: &tauri::AppHandle, only: Option<&str>) -> Result<Vec<ArchiveReport>, String> {
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let policies: Vec<(String, ArchivePolicy, IgnoreRules)> = {
+        let config = state.config.lock().unwrap();
+        config
+            .workspaces
+            .iter()
+            .filter(|w| only.is_none_or(|id| w.id == id))
+            .filter_map(|w| Some((w.id.clone(), w.auto_archive?, get_ignore_rules(&config, &w.id))))
+            .collect()
+    };
+    let pinned = state.store.list_paths(store::PINNED_LIST)?;
+    let now = now_secs();
+    let mut reports = Vec::new();
+    for (workspace_id, policy, ignore) in policies {
+        let _guard = state.write_lock.lock().unwrap();
+        let dir = get_workspace_dir(&workspace_id);
+        if !dir.is_dir() {
+            continue;
+        }
+        let notes: Vec<(PathBuf, u64)> = WorkspaceIndex::build(&dir, ignore)
+            .notes()
+            .filter(|n| !pinned.contains(&n.path.to_string_lossy().to_string()))
+            .map(|n| (n.path.clone(), n.stamp.modified_secs()))
+            .collect();
+        let mut archived = Vec::new();
+        for path in archive::due(&notes, policy.months, now) {
+            let target = archive::target(&dir, &path)?;
+            watcher.mark_self_write(&path);
+            if let Err(e) = fs::rename(&path, &target) {
+                tracing::warn!("Archiving {} failed: {}", path.display(), e);
+                continue;
+            }
+            watcher.refresh(&[&path]);
+            move_note_metadata(&state, &path, &target);
+            emit_note_event(app, NOTE_DELETED, Some(path.as_path()), None);
+            archived.push(archive::ArchivedNote {
+                from: path.to_string_lossy().to_string(),
+                to: target.to_string_lossy().to_string(),
+            });
+        }
+        if !archived.is_empty() {
+            tracing::info!("Archived {} notes in {}", archived.len(), workspace_id);
+            reports.push(ArchiveReport { workspace_id, archived });
+        }
+    }
+    if !reports.is_empty() {
+        let _ = app.emit(archive::NOTES_ARCHIVED, &reports);
+    }
+    Ok(reports)
+}
+
+#[tauri::command]
+fn set_transliterate_file_names(state: tauri::State<AppState>, enabled: bool) -> Result<(), AppError> {
+    let mut config = state.config.lock().unwrap();
+    config.transliterate_file_names = enabled;
+    Ok(save_config(&config)?)
+}
+
+/// Sets where Pandoc, Whisper and Git are installed. Paths must be absolute
+/// and end in the program's own name.
+#[tauri::command]
+fn set_tool_paths(state: tauri::State<AppState>, tools: ToolPaths) -> Result<(), AppError> {
+    tools.validate()?;
+    let mut config = state.config.lock().unwrap();
+    config.tools = tools;
+    Ok(save_config(&config)?)
+}
+
+/// The program to run for `tool`, as set in the settings.
+fn tool_command(state: &AppState, tool: Tool) -> Result<String, String> {
+    state.config.lock().unwrap().tools.command(tool)
+}
+
+/// The folder holding the app's log files, for attaching to bug reports.
+#[tauri::command]
+fn get_log_path(logging: tauri::State<Logging>) -> String {
+    logging.dir().to_string_lossy().to_string()
+}
+
+/// Sets the size above which notes open read-only; `None` restores the
+/// default.
+#[tauri::command]
+fn set_max_note_size(state: tauri::State<AppState>, bytes: Option<u64>) -> Result<(), AppError> {
+    if bytes == Some(0) {
+        return Err(AppError::invalid_input("The limit must be above zero"));
+    }
+    let mut config = state.config.lock().unwrap();
+    config.max_note_bytes = bytes;
+    Ok(save_config(&config)?)
+}
+
+#[tauri::command]
+fn set_update_channel(state: tauri::State<AppState>, channel: UpdateChannel) -> Result<(), AppError> {
+    let mut config = state.config.lock().unwrap();
+    config.update_channel = channel;
+    Ok(save_config(&config)?)
+}
+
+/// Looks for a newer release on the configured channel and, with `install`,
+/// downloads and installs it. Progress arrives as `update:progress` events.
+#[tauri::command]
+async fn check_for_updates(app: tauri::AppHandle, install: Option<bool>) -> Result<Option<UpdateInfo>, AppError> {
+    let channel = app.state::<AppState>().config.lock().unwrap().update_channel;
+    Ok(updates::check(&app, channel, install.unwrap_or(false)).await?)
+}
+
+#[tauri::command]
+fn set_log_level(state: tauri::State<AppState>, logging: tauri::State<Logging>, level: LogLevel) -> Result<(), AppError> {
+    logging.set_level(level)?;
+    let mut config = state.config.lock().unwrap();
+    config.log_level = level;
+    Ok(save_config(&config)?)
+}
+
+/// Formatting rules of the workspace a note lives in.
+fn format_rules_for(state: &AppState, path: &std::path::Path) -> FormatRules {
+    let workspace_id = workspace_id_for_path(path);
+    let config = state.config.lock().unwrap();
+    config
+        .workspaces
+        .iter()
+        .find(|w| w.id == workspace_id)
+        .map(|w| w.format_rules.clone())
+        .unwrap_or_default()
+}
+
+fn is_workspace_encrypted(state: &AppState, workspace_id: &str) -> bool {
+    let config = state.config.lock().unwrap();
+    config.workspaces.iter().any(|w| w.id == workspace_id && w.encrypted)
+}
+
+/// Note content as read from disk, decrypted if it's encrypted with its
+/// workspace's key.
+fn open_note_content(state: &AppState, path: &std::path::Path, content: String) -> Result<String, String> {
+    if !crypto::is_encrypted(&content) {
+        return Ok(content);
+    }
+    let workspace_id = workspace_id_for_path(path);
+    match state.keys.identity(&workspace_id) {
+        Some(identity) => crypto::decrypt_with_key(&content, &identity),
+        None if is_workspace_encrypted(state, &workspace_id) => {
+            Err("Workspace is locked. Unlock it to read its notes".to_string())
+        }
+        None => Err("Note is encrypted. Decrypt it with its passphrase to read it".to_string()),
+    }
+}
+
+/// Content to write to disk: encrypted in an encrypted workspace, as-is
+/// elsewhere.
+fn seal_note_content(state: &AppState, path: &std::path::Path, content: String) -> Result<String, String> {
+    let workspace_id = workspace_id_for_path(path);
+    if !is_workspace_encrypted(state, &workspace_id) {
+        return Ok(content);
+    }
+    let identity = state
+        .keys
+        .identity(&workspace_id)
+        .ok_or("Workspace is locked. Unlock it to save notes")?;
+    crypto::encrypt_with_key(&content, &identity)
+}
+
+/// Real titles for listed notes of an unlocked encrypted workspace, which
+/// the title cache only knows under the generic encrypted title.
+fn reveal_titles<'a>(state: &AppState, workspace_id: &str, titles: impl ParallelIterator<Item = (&'a str, &'a mut String)>) {
+    let Some(identity) = state.keys.identity(workspace_id) else {
+        return;
+    };
+    let limit = note_size_limit(state);
+    titles
+        .filter(|(_, title)| title.as_str() == crypto::ENCRYPTED_TITLE)
+        .for_each(|(path, title)| {
+            let path = std::path::Path::new(path);
+            let Ok(metadata) = fs::metadata(path) else {
+                return;
+            };
+            let stamp = FileStamp::from_metadata(&metadata);
+            if let Some(cached) = state.keys.cached_title(workspace_id, path, stamp) {
+                *title = cached;
+                return;
+            }
+            let revealed = reader::read_whole(path, limit)
+                .and_then(|content| crypto::decrypt_with_key(&content, &identity))
+                .map(|content| note_title(path, &content));
+            if let Ok(revealed) = revealed {
+                state.keys.cache_title(workspace_id, path, stamp, revealed.clone());
+                *title = revealed;
+            }
+        });
+}
+
+/// Encrypts every note in a workspace with a new key protected by
+/// `passphrase`, and gives numbered notes opaque file names. The workspace
+/// stays unlocked until it's locked or the app quits. Calling it again on a
+/// workspace whose encryption was interrupted finishes the job with the key
+/// it already has.
+#[tauri::command]
+async fn encrypt_workspace(app: tauri::AppHandle, workspace_id: String, passphrase: String) -> Result<Workspace, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let dir = get_workspace_dir(&workspace_id);
+        if !dir.is_dir() {
+            return Err(AppError::not_found("Workspace folder not found"));
+        }
+        let resuming = is_workspace_encrypted(&state, &workspace_id);
+        let (identity, key_file) = if resuming {
+            let identity = state
+                .keys
+                .identity(&workspace_id)
+                .ok_or_else(|| AppError::conflict("Workspace is already encrypted"))?;
+            ((*identity).clone(), None)
+        } else if dir.join(crypto::KEY_FILE).exists() {
+            return Err(AppError::conflict("Workspace already has a key file"));
+        } else {
+            let (identity, key_file) = crypto::create_workspace_key(&passphrase)?;
+            (identity, Some(key_file))
+        };
+
+        let mut notes: Vec<PathBuf> = fs::read_dir(&dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| watcher::is_note_path(path))
+            .collect();
+        notes.sort();
+        // Every note is read and encrypted before anything is written, so one
+        // that can't be read leaves the workspace as it was.
+        let limit = note_size_limit(&state);
+        let mut sealed: Vec<(&PathBuf, String)> = Vec::new();
+        for path in &notes {
+            let (content, _) = reader::read_whole_decoded(path, limit)?;
+            if !crypto::is_encrypted(&content) {
+                sealed.push((path, crypto::encrypt_with_key(&content, &identity)?));
+            }
+        }
+
+        if let Some(key_file) = key_file {
+            let key_path = dir.join(crypto::KEY_FILE);
+            fs::write(&key_path, key_file)?;
+            if let Err(e) = set_workspace_encrypted(&state, &workspace_id) {
+                let _ = fs::remove_file(&key_path);
+                return Err(e.into());
+            }
+            state.keys.unlock(&workspace_id, identity);
+        }
+        for (path, ciphertext) in sealed {
+            watcher.mark_self_write(path);
+            fs::write(path, ciphertext)?;
+        }
+
+        let stem = |path: &std::path::Path| path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let mut next_number = notes.iter().filter_map(|p| parse_file_number(&stem(p))).max().unwrap_or(0) + 1;
+        let opaque_slug = slugify(crypto::ENCRYPTED_TITLE);
+        for path in notes {
+            let number = parse_file_number(&stem(&path)).unwrap_or_else(|| {
+                next_number += 1;
+                next_number - 1
+            });
+            let extension = Markup::from_path(&path).unwrap_or(Markup::Markdown).extension();
+            let mut new_path = dir.join(format!("{}-{}.{}", number, opaque_slug, extension));
+            // Notes sharing a number would keep their titled names, so later
+            // ones get a fresh number instead.
+            while new_path != path && new_path.exists() {
+                new_path = dir.join(format!("{}-{}.{}", next_number, opaque_slug, extension));
+                next_number += 1;
+            }
+            if new_path != path {
+                watcher.mark_self_write(&path);
+                watcher.mark_self_write(&new_path);
+                fs::rename(&path, &new_path).map_err(|e| e.to_string())?;
+                move_note_metadata(&state, &path, &new_path);
+            }
+            state.store.clear_title(&new_path.to_string_lossy())?;
+        }
+
+        let config = state.config.lock().unwrap();
+        let updated = config
+            .workspaces
+            .iter()
+            .find(|w| w.id == workspace_id)
+            .cloned()
+            .ok_or("Workspace not found")?;
+        let is_active = config.active_workspace_id == workspace_id;
+        drop(config);
+        if is_active {
+            watch_workspace(&app, &workspace_id)?;
+        }
+        Ok(updated)
+    })
+    .await
+}
+
+/// Marks the workspace encrypted, in memory only once it's saved.
+fn set_workspace_encrypted(state: &AppState, workspace_id: &str) -> Result<(), String> {
+    let mut config = state.config.lock().unwrap();
+    let mut updated = config.clone();
+    let workspace = updated
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.encrypted = true;
+    save_config(&updated)?;
+    *config = updated;
+    Ok(())
+}
+
+#[tauri::command]
+async fn unlock_workspace(app: tauri::AppHandle, workspace_id: String, passphrase: String) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        if !is_workspace_encrypted(&state, &workspace_id) {
+            return Err(AppError::conflict("Workspace isn't encrypted"));
+        }
+        let key_file = fs::read_to_string(get_workspace_dir(&workspace_id).join(crypto::KEY_FILE))
+            .map_err(|_| "Workspace key is missing".to_string())?;
+        let identity = crypto::unlock_workspace_key(&key_file, &passphrase)?;
+        state.keys.unlock(&workspace_id, identity);
+        // Titles listed as encrypted can be shown now.
+        app.state::<WorkspaceWatcher>().with_index(WorkspaceIndex::mark_all_changed);
+        Ok(())
+    })
+    .await
+}
+
+#[tauri::command]
+fn biometric_available() -> bool {
+    biometric::is_available()
+}
+
+/// Lets an unlocked workspace be unlocked with biometrics from now on, by
+/// keeping its key in the OS keychain.
+#[tauri::command]
+async fn enable_biometric_unlock(app: tauri::AppHandle, workspace_id: String) -> Result<Workspace, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let identity = state
+            .keys
+            .identity(&workspace_id)
+            .ok_or("Unlock the workspace with its passphrase first")?;
+        biometric::authenticate("turn on biometric unlock for a workspace")?;
+        secrets::set(&secrets::workspace_key_name(&workspace_id), &crypto::export_key(&identity))?;
+        Ok(set_biometric_unlock(&state, &workspace_id, true)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn disable_biometric_unlock(app: tauri::AppHandle, workspace_id: String) -> Result<Workspace, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        secrets::delete(&secrets::workspace_key_name(&workspace_id))?;
+        Ok(set_biometric_unlock(&state, &workspace_id, false)?)
+    })
+    .await
+}
+
+fn set_biometric_unlock(state: &AppState, workspace_id: &str, enabled: bool) -> Result<Workspace, String> {
+    let mut config = state.config.lock().unwrap();
+    let workspace = config
+        .workspaces
+        .iter_mut()
+        .find(|w| w.id == workspace_id)
+        .ok_or("Workspace not found")?;
+    workspace.biometric_unlock = enabled;
+    let updated = workspace.clone();
+    save_config(&config)?;
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn unlock_workspace_biometric(app: tauri::AppHandle, workspace_id: String) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        if !is_workspace_encrypted(&state, &workspace_id) {
+            return Err(AppError::conflict("Workspace isn't encrypted"));
+        }
+        biometric::authenticate("unlock an encrypted workspace")?;
+        let secret = secrets::get(&secrets::workspace_key_name(&workspace_id))?
+            .ok_or("Biometric unlock isn't set up for this workspace")?;
+        let identity = crypto::import_key(&secret)?;
+        state.keys.unlock(&workspace_id, identity);
+        app.state::<WorkspaceWatcher>().with_index(WorkspaceIndex::mark_all_changed);
+        Ok(())
+    })
+    .await
+}
+
+/// Stores a credential in the OS keychain. Secrets can be replaced or removed
+/// from the frontend but never read back; only the backend uses them.
+#[tauri::command]
+async fn set_secret(name: String, secret: String) -> Result<(), AppError> {
+    blocking(move || {
+        secrets::validate_name(&name)?;
+        Ok(secrets::set(&name, &secret)?)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn has_secret(name: String) -> Result<bool, AppError> {
+    blocking(move || {
+        secrets::validate_name(&name)?;
+        Ok(secrets::get(&name)?.is_some())
+    })
+    .await
+}
+
+#[tauri::command]
+async fn delete_secret(name: String) -> Result<(), AppError> {
+    blocking(move || {
+        secrets::validate_name(&name)?;
+        Ok(secrets::delete(&name)?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn lock_workspace(state: tauri::State<AppState>, workspace_id: String) {
+    state.keys.lock(&workspace_id);
+}
+
+/// Runs a command's filesystem work on the blocking pool so large reads,
+/// scans and searches don't hold up the IPC thread.
+async fn blocking<T: Send + 'static, E: From<String> + Send + 'static>(
+    work: impl FnOnce() -> Result<T, E> + Send + 'static,
+) -> Result<T, E> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| E::from(e.to_string()))?
+}
+
+#[tauri::command]
+async fn list_notes(app: tauri::AppHandle, lazy_titles: Option<bool>) -> Result<Vec<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        Ok(listed_notes(&state, &watcher, !lazy_titles.unwrap_or(false))?)
+    })
+    .await
+}
+
+/// The notes shown in the note list: those of `scan_notes` less the ones
+/// hidden by the file system or the workspace's hidden patterns.
+fn listed_notes(state: &AppState, watcher: &WorkspaceWatcher, read_titles: bool) -> Result<Vec<NoteEntry>, String> {
+    let entries = scan_notes(state, watcher, read_titles)?;
+    let patterns = hidden_patterns(state);
+    let hidden: std::collections::HashSet<PathBuf> = watcher.with_index(|index| {
+        index
+            .notes()
+            .filter(|n| is_hidden_note(n, &patterns))
+            .map(|n| n.path.clone())
+            .collect()
+    });
+    if hidden.is_empty() {
+        return Ok(entries);
+    }
+    Ok(entries
+        .into_iter()
+        .filter(|e| !hidden.contains(std::path::Path::new(&e.path)))
+        .collect())
+}
+
+fn hidden_patterns(state: &AppState) -> IgnoreRules {
+    let config = state.config.lock().unwrap();
+    config
+        .workspaces
+        .iter()
+        .find(|w| w.id == config.active_workspace_id)
+        .map(|w| IgnoreRules::lenient(&w.hidden_patterns))
+        .unwrap_or_default()
+}
+
+/// Whether `note` is left out of the list, by the file system's hidden flag
+/// or the workspace's hidden `patterns`.
+fn is_hidden_note(note: &IndexedNote, patterns: &IgnoreRules) -> bool {
+    note.hidden || note.path.parent().is_some_and(|root| patterns.is_ignored(root, &note.path))
+}
+
+#[derive(Serialize)]
+pub struct NoteTitle {
+    pub path: String,
+    pub title: String,
+}
+
+/// Titles for just the given notes, for lists that render rows on demand and
+/// call `list_notes` with `lazy_titles`.
+#[tauri::command]
+async fn get_titles(app: tauri::AppHandle, paths: Vec<String>) -> Result<Vec<NoteTitle>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        Ok(load_titles(&state, &watcher, &paths))
+    })
+    .await
+}
+
+fn load_titles(state: &AppState, watcher: &WorkspaceWatcher, paths: &[String]) -> Vec<NoteTitle> {
+    let files: Vec<(PathBuf, FileStamp)> = paths
+        .iter()
+        .filter_map(|p| {
+            let path = PathBuf::from(p);
+            state.access.check(&path).ok()?;
+            let metadata = fs::metadata(&path).ok()?;
+            Some((path, FileStamp::from_metadata(&metadata)))
+        })
+        .collect();
+
+    let mut note_cache = state.note_cache.lock().unwrap();
+    let titles = resolve_titles(&mut note_cache, &files, true);
+    let _ = note_cache.save();
+    drop(note_cache);
+
+    watcher.with_index(|index| {
+        for ((path, stamp), title) in files.iter().zip(&titles) {
+            if let Some(title) = title {
+                index.set_title(path, *stamp, title.clone());
+            }
+        }
+    });
+
+    let mut note_titles: Vec<NoteTitle> = files
+        .into_iter()
+        .zip(titles)
+        .map(|((path, _), title)| NoteTitle {
+            path: path.to_string_lossy().to_string(),
+            title: title.unwrap_or_default(),
+        })
+        .collect();
+    if let Some(first) = note_titles.first() {
+        let workspace_id = workspace_id_for_path(std::path::Path::new(&first.path));
+        reveal_titles(state, &workspace_id, note_titles.par_iter_mut().map(|t| (t.path.as_str(), &mut t.title)));
+    }
+    note_titles
+}
+
+/// Fills in titles the lazy listing left empty.
+fn fill_titles(state: &AppState, watcher: &WorkspaceWatcher, entries: &mut [NoteEntry]) {
+    let missing: Vec<String> = entries
+        .iter()
+        .filter(|e| e.title.is_empty())
+        .map(|e| e.path.clone())
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    let titles: HashMap<String, String> = load_titles(state, watcher, &missing)
+        .into_iter()
+        .map(|t| (t.path, t.title))
+        .collect();
+    for entry in entries.iter_mut().filter(|e| e.title.is_empty()) {
+        if let Some(title) = titles.get(&entry.path) {
+            entry.title = title.clone();
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct NotesPage {
+    pub entries: Vec<NoteEntry>,
+    pub total: usize,
+}
+
+/// One page of the sorted listing. Only the page's titles are read from disk.
+#[tauri::command]
+async fn list_notes_page(app: tauri::AppHandle, offset: usize, limit: usize) -> Result<NotesPage, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let mut entries = listed_notes(&state, &watcher, false)?;
+        let total = entries.len();
+        let start = offset.min(total);
+        let end = offset.saturating_add(limit).min(total);
+        let mut page: Vec<NoteEntry> = entries.drain(start..end).collect();
+        fill_titles(&state, &watcher, &mut page);
+        Ok(NotesPage { entries: page, total })
+    })
+    .await
+}
+
+/// Notes whose content has a word starting with each term of `query`, in
+/// listing order.
+#[tauri::command]
+async fn search_notes(app: tauri::AppHandle, query: String, limit: Option<usize>) -> Result<Vec<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        Ok(search_workspace(&state, &watcher, &query, limit)?)
+    })
+    .await
+}
+
+fn search_workspace(
+    state: &AppState,
+    watcher: &WorkspaceWatcher,
+    query: &str,
+    limit: Option<usize>,
+) -> Result<Vec<NoteEntry>, String> {
+    check_workspace_present(watcher)?;
+
+    // Files changed since the last search are read outside the index lock so
+    // the watcher isn't blocked on a cold index.
+    let stale: Vec<(PathBuf, FileStamp)> = watcher.with_index(|index| {
+        index
+            .notes()
+            .filter(|n| !index.search().is_current(&n.path, n.stamp))
+            .map(|n| (n.path.clone(), n.stamp))
+            .collect()
+    });
+    let words: Vec<Vec<String>> = stale
+        .par_iter()
+        .map(|(path, stamp)| {
+            if stamp.size > reader::MAX_READ_BYTES {
+                return Vec::new();
+            }
+            fs::read_to_string(path)
+                .ok()
+                .and_then(|content| open_note_content(state, path, content).ok())
+                .map(|content| search::tokenize(&content))
+                .unwrap_or_default()
+        })
+        .collect();
+    let matches = watcher.with_index(|index| {
+        for ((path, stamp), words) in stale.iter().zip(words) {
+            if index.contains(path) {
+                index.search_mut().insert(path, *stamp, words);
+            }
+        }
+        index.search().query(query)
+    });
+
+    let mut hits: Vec<NoteEntry> = scan_notes(state, watcher, false)?
+        .into_iter()
+        .filter(|e| matches.contains(std::path::Path::new(&e.path)))
+        .take(limit.unwrap_or(50))
+        .collect();
+    fill_titles(state, watcher, &mut hits);
+    Ok(hits)
+}
+
+/// Notes closest in meaning to `query`, most similar first, using an
+/// embedding model on a local model server. Notes changed since the last
+/// search are embedded first and their vectors kept in the metadata store.
+#[tauri::command]
+async fn semantic_search(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+    endpoint: Option<String>,
+    model: Option<String>,
+    allow_remote: Option<bool>,
+) -> Result<Vec<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        // Vectors are stored unencrypted and give away what notes are about.
+        let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+        if is_workspace_encrypted(&state, &workspace_id) {
+            return Err(AppError::conflict("Semantic search isn't available in encrypted workspaces"));
+        }
+        let endpoint = endpoint.as_deref().unwrap_or(summarize::DEFAULT_ENDPOINT);
+        let model = model.as_deref().unwrap_or(embeddings::DEFAULT_MODEL);
+        let allow_remote = allow_remote.unwrap_or(false);
+
+        let notes: Vec<IndexedNote> = watcher.with_index(|index| index.notes().cloned().collect());
+        let mut vectors = state.store.embeddings(model)?;
+        let stale: Vec<(&IndexedNote, String)> = notes
+            .par_iter()
+            .filter(|n| n.stamp.size <= reader::MAX_READ_BYTES)
+            .filter(|n| vectors.get(&*n.path.to_string_lossy()).is_none_or(|(stamp, _)| *stamp != n.stamp))
+            .filter_map(|n| {
+                let content = fs::read_to_string(&n.path).ok()?;
+                // Notes encrypted with their own passphrase stay out of the index.
+                (!crypto::is_encrypted(&content)).then_some((n, content))
+            })
+            .collect();
+        for batch in stale.chunks(embeddings::BATCH_SIZE) {
+            let inputs: Vec<&str> = batch.iter().map(|(_, content)| content.as_str()).collect();
+            for ((note, _), vector) in batch.iter().zip(embeddings::embed(endpoint, model, &inputs, allow_remote)?) {
+                let path = note.path.to_string_lossy().to_string();
+                state.store.set_embedding(&path, note.stamp, model, &vector)?;
+                vectors.insert(path, (note.stamp, vector));
+            }
+        }
+
+        let query_vector = embeddings::embed(endpoint, model, &[&query], allow_remote)?
+            .pop()
+            .unwrap_or_default();
+        let scores: HashMap<String, f32> = notes
+            .iter()
+            .filter_map(|n| {
+                let path = n.path.to_string_lossy().to_string();
+                let score = embeddings::similarity(&query_vector, &vectors.get(&path)?.1);
+                Some((path, score))
+            })
+            .collect();
+        let mut hits: Vec<NoteEntry> = scan_notes(&state, &watcher, false)?
+            .into_iter()
+            .filter(|e| scores.contains_key(&e.path))
+            .collect();
+        hits.sort_by(|a, b| scores[&b.path].total_cmp(&scores[&a.path]));
+        hits.truncate(limit.unwrap_or(20));
+        fill_titles(&state, &watcher, &mut hits);
+        Ok(hits)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn list_notes_delta(app: tauri::AppHandle, cursor: Option<u64>) -> Result<NotesDelta, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+        let changed = watcher.with_index(WorkspaceIndex::take_changed);
+        let mut snapshot = state.listing.lock().unwrap();
+        let Some(changed) = changed.filter(|_| snapshot.is_current(&workspace_id, cursor)) else {
+            let entries = listed_notes(&state, &watcher, true)?;
+            return Ok(snapshot.update(&workspace_id, cursor, entries));
+        };
+
+        // Only the notes the watcher or a command refreshed since the last
+        // listing are looked at again.
+        let patterns = hidden_patterns(&state);
+        let (notes_dir, notes) = watcher.with_index(|index| {
+            let notes: Vec<IndexedNote> = changed
+                .iter()
+                .filter_map(|p| index.get(p))
+                .filter(|n| !is_hidden_note(n, &patterns))
+                .cloned()
+                .collect();
+            (index.dir().to_path_buf(), notes)
+        });
+        let removed = changed
+            .iter()
+            .filter(|p| !notes.iter().any(|n| &n.path == *p))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let entries = note_entries(&state, &watcher, &notes_dir, notes, true);
+        let order = state.order.lock().unwrap();
+        Ok(snapshot.apply(entries, removed, |entries| sort_entries(entries, &order, &workspace_id)))
+    })
+    .await
+}
+
+/// Looks up titles in the cache, reading the files that miss in parallel. With
+/// `read_missing` unset, misses are left as `None` for `get_titles`.
+fn resolve_titles(note_cache: &mut NoteCache, files: &[(PathBuf, FileStamp)], read_missing: bool) -> Vec<Option<String>> {
+    let cached: Vec<Option<String>> = files
+        .iter()
+        .map(|(path, stamp)| note_cache.get(path, *stamp).map(|c| c.title.clone()))
+        .collect();
+    // On a cold cache this is where almost all of the listing time goes.
+    let read: Vec<Option<NoteHeader>> = files
+        .par_iter()
+        .zip(&cached)
+        .map(|((path, _), title)| match title {
+            None if read_missing => Some(read_header_from_file(path)),
+            _ => None,
+        })
+        .collect();
+
+    files
+        .iter()
+        .zip(cached.into_iter().zip(read))
+        .map(|((path, stamp), titles)| match titles {
+            (Some(title), _) => Some(title),
+            (None, Some(header)) => Some(note_cache.get_or_insert(path, *stamp, || header).title),
+            (None, None) => None,
+        })
+        .collect()
+}
+
+fn scan_notes(state: &AppState, watcher: &WorkspaceWatcher, read_titles: bool) -> Result<Vec<NoteEntry>, String> {
+    let (notes_dir, notes) = watcher.with_index(|index| (index.dir().to_path_buf(), index.notes().cloned().collect::<Vec<_>>()));
+    let mut entries = note_entries(state, watcher, &notes_dir, notes, read_titles);
+    sort_entries(&mut entries, &state.order.lock().unwrap(), &workspace_id_for_dir(&notes_dir));
+
+    let listed: Vec<String> = entries.iter().map(|e| e.path.clone()).collect();
+    let mut note_cache = state.note_cache.lock().unwrap();
+    note_cache.retain_listed(&notes_dir, &listed);
+    let _ = note_cache.save();
+    Ok(entries)
+}
+
+/// Unsorted list entries for `notes` of the workspace in `notes_dir`.
+fn note_entries(
+    state: &AppState,
+    watcher: &WorkspaceWatcher,
+    notes_dir: &std::path::Path,
+    notes: Vec<IndexedNote>,
+    read_titles: bool,
+) -> Vec<NoteEntry> {
+    // Titles the index doesn't know yet come from the persisted cache or disk
+    // and are remembered in the index for the next listing.
+    let untitled: Vec<(PathBuf, FileStamp)> = notes
+        .iter()
+        .filter(|n| n.title.is_none())
+        .map(|n| (n.path.clone(), n.stamp))
+        .collect();
+    let titles = resolve_titles(&mut state.note_cache.lock().unwrap(), &untitled, read_titles);
+    watcher.with_index(|index| {
+        for ((path, stamp), title) in untitled.iter().zip(&titles) {
+            if let Some(title) = title {
+                index.set_title(path, *stamp, title.clone());
+            }
+        }
+    });
+    let mut resolved: HashMap<PathBuf, String> = untitled
+        .into_iter()
+        .zip(titles)
+        .filter_map(|((path, _), title)| Some((path, title?)))
+        .collect();
+
+    let recorded = if notes.iter().any(|n| n.created.is_none()) {
+        state.store.created_times().unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let mut entries: Vec<NoteEntry> = notes
+        .into_iter()
+        .map(|note| {
+            let path = note.path.to_string_lossy().to_string();
+            NoteEntry {
+                title: note
+                    .title
+                    .or_else(|| resolved.remove(&note.path))
+                    .unwrap_or_default(),
+                created: note.created.or_else(|| recorded.get(&path).copied()),
+                id: None,
+                path,
+                modified: note.stamp.modified_secs(),
+                name: note.name,
+            }
+        })
+        .collect();
+
+    let workspace_id = workspace_id_for_dir(notes_dir);
+    reveal_titles(state, &workspace_id, entries.par_iter_mut().map(|e| (e.path.as_str(), &mut e.title)));
+
+    let listed: Vec<String> = entries.iter().map(|e| e.path.clone()).collect();
+    let mut ids = state.store.note_ids(&listed).unwrap_or_default();
+    for entry in entries.iter_mut() {
+        entry.id = ids.remove(&entry.path);
+    }
+    entries
+}
+
+/// Notes that were moved by hand follow their stored order keys. Everything
+/// else, including new notes, sorts above them by file number (newest first)
+/// and then by modification time, so filename numbers only matter for notes
+/// that were never reordered.
+fn sort_entries(entries: &mut [NoteEntry], order: &OrderStore, workspace_id: &str) {
+    entries.sort_by(|a, b| {
+        let key_a = order.key(workspace_id, &a.name);
+        let key_b = order.key(workspace_id, &b.name);
+        match (key_a, key_b) {
+            (Some(a), Some(b)) => return a.total_cmp(&b),
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (None, None) => {}
+        }
+        let num_a = parse_file_number(&a.name);
+        let num_b = parse_file_number(&b.name);
+        match (num_a, num_b) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b.modified.cmp(&a.modified),
+        }
+    });
+}
+
+/// Moves a note's manual position and stored metadata over to its new path.
+fn move_note_metadata(state: &AppState, old_path: &std::path::Path, new_path: &std::path::Path) {
+    let _ = state
+        .store
+        .rename_path(&old_path.to_string_lossy(), &new_path.to_string_lossy());
+    let (Some(old_name), Some(new_name)) = (old_path.file_stem(), new_path.file_stem()) else {
+        return;
+    };
+    let mut order = state.order.lock().unwrap();
+    order.rename(
+        &workspace_id_for_path(old_path),
+        &old_name.to_string_lossy(),
+        &new_name.to_string_lossy(),
+    );
+    let _ = order.save();
+}
+
+fn drop_note_metadata(state: &AppState, path: &std::path::Path) {
+    let _ = state.store.remove_path(&path.to_string_lossy());
+    if let Some(name) = path.file_stem() {
+        let mut order = state.order.lock().unwrap();
+        order.remove(&workspace_id_for_path(path), &name.to_string_lossy());
+        let _ = order.save();
+    }
+}
+
+/// Keeps note metadata in step with renames made outside the app.
+pub(crate) fn handle_external_rename(app: &tauri::AppHandle, old_path: &std::path::Path, new_path: &std::path::Path) {
+    move_note_metadata(&app.state::<AppState>(), old_path, new_path);
+}
+
+/// The largest note read in one piece, from the config or `MAX_READ_BYTES`.
+fn note_size_limit(state: &AppState) -> u64 {
+    state.config.lock().unwrap().max_note_bytes.unwrap_or(reader::MAX_READ_BYTES)
+}
+
+#[tauri::command]
+async fn read_note(app: tauri::AppHandle, path: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let path = resolve_note_ref(&state, &path)?;
+        let note_path = std::path::Path::new(&path);
+        check_note_path(&state, note_path)?;
+        let (raw, encoding) = reader::read_whole_decoded(note_path, note_size_limit(&state))?;
+        let content = open_note_content(&state, note_path, raw)?;
+        let _ = state.store.record_visit(&path, now_secs());
+        if let Some(encoding) = encoding {
+            tracing::info!("Read {} as {}", path, encoding);
+            let _ = app.emit(NOTE_TRANSCODED, NoteTranscoded { path, encoding });
+        }
+        Ok(content)
+    })
+    .await
+}
+
+/// `read_note` converted a note that wasn't UTF-8. It's saved as UTF-8.
+const NOTE_TRANSCODED: &str = "note:transcoded";
+
+#[derive(Serialize, Clone)]
+struct NoteTranscoded {
+    path: String,
+    encoding: &'static str,
+}
+
+/// Everything the note info popover shows.
+#[derive(Serialize, Debug)]
+pub struct NoteInfo {
+    pub path: String,
+    pub name: String,
+    pub title: String,
+    /// Size on disk.
+    pub bytes: u64,
+    pub stats: TextStats,
+    pub lines: usize,
+    pub tags: Vec<String>,
+    pub created: Option<u64>,
+    pub modified: u64,
+    /// Notes in the workspace with a wikilink to this one.
+    pub backlinks: usize,
+    pub pinned: bool,
+    pub locked: bool,
+    pub encrypted: bool,
+    pub published_url: Option<String>,
+}
+
+#[tauri::command]
+async fn get_note_info(app: tauri::AppHandle, path: String) -> Result<NoteInfo, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let path = resolve_note_ref(&state, &path)?;
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        let metadata = fs::metadata(&note_path).map_err(|e| e.to_string())?;
+        let raw = reader::read_whole(&note_path, note_size_limit(&state))?;
+        let encrypted = crypto::is_encrypted(&raw);
+        let content = open_note_content(&state, &note_path, raw)?;
+
+        let dir = note_path.parent().ok_or("Invalid path")?.to_path_buf();
+        let notes = workspace_notes(&state, &watcher, &dir);
+        let backlinks = notes
+            .par_iter()
+            .filter(|note| note.path != note_path && note.stamp.size <= reader::MAX_READ_BYTES)
+            .filter(|note| {
+                fs::read_to_string(&note.path)
+                    .ok()
+                    .and_then(|content| open_note_content(&state, &note.path, content).ok())
+                    .is_some_and(|content| {
+                        links::parse_wikilinks(&content)
+                            .iter()
+                            .any(|target| resolve_wikilink(&notes, target).as_deref() == Some(note_path.as_path()))
+                    })
+            })
+            .count();
+
+        let created = cache::birth_secs(&metadata).or_else(|| state.store.created_times().ok()?.get(&path).copied());
+        Ok(NoteInfo {
+            name: note_path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            title: note_title(&note_path, &content),
+            bytes: metadata.len(),
+            stats: analytics::analyze(&content),
+            lines: content.lines().count(),
+            tags: tags::parse_tags(&content),
+            created,
+            modified: FileStamp::from_metadata(&metadata).modified_secs(),
+            backlinks,
+            pinned: state.store.list_paths(store::PINNED_LIST)?.contains(&path),
+            locked: state.store.is_locked(&path)?,
+            encrypted,
+            published_url: state.store.publication(&path)?.map(|p| p.url),
+            path,
+        })
+    })
+    .await
+}
+
+/// The stable id of the note at `path`, given to it now if it has none.
+#[tauri::command]
+fn get_note_id(state: tauri::State<AppState>, path: String) -> Result<String, AppError> {
+    check_note_path(&state, std::path::Path::new(&path))?;
+    let mut ids = state.store.note_ids(std::slice::from_ref(&path))?;
+    ids.remove(&path).ok_or_else(|| AppError::not_found("Note has no id"))
+}
+
+/// The current path of the note `note` refers to, by id or path.
+#[tauri::command]
+fn resolve_note(state: tauri::State<AppState>, note: String) -> Result<String, AppError> {
+    let path = resolve_note_ref(&state, &note)?;
+    check_note_path(&state, std::path::Path::new(&path))?;
+    Ok(path)
+}
+
+/// A link to the note at `path` with its current title, ready to insert into
+/// the note at `from`: a wikilink, or a markdown link relative to `from`'s
+/// folder.
+#[tauri::command]
+fn get_note_link(
+    state: tauri::State<AppState>,
+    path: String,
+    style: Option<links::LinkStyle>,
+    from: Option<String>,
+) -> Result<String, AppError> {
+    let path = PathBuf::from(resolve_note_ref(&state, &path)?);
+    check_note_path(&state, &path)?;
+    let title = note_title(&path, &open_note_content(&state, &path, reader::read_whole(&path, note_size_limit(&state))?)?);
+    Ok(match style.unwrap_or_default() {
+        links::LinkStyle::Wikilink => {
+            // Untitled notes can only be told apart by name.
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            links::wikilink(if title == "Untitled" { "" } else { &title }, &name)
+        }
+        links::LinkStyle::Markdown => {
+            let from = from.map(PathBuf::from).unwrap_or_else(|| path.clone());
+            let from_dir = from.parent().ok_or_else(|| AppError::invalid_input("Invalid path"))?;
+            links::markdown_link(&title, &path, from_dir)
+        }
+    })
+}
+
+/// The notes of the workspace with folder `dir`, or with `dir` as an extra
+/// root, from the watcher's index when it's the active workspace.
+fn workspace_notes(state: &AppState, watcher: &WorkspaceWatcher, dir: &std::path::Path) -> Vec<IndexedNote> {
+    let indexed = watcher.with_index(|index| index.has_root(dir).then(|| index.notes().cloned().collect()));
+    indexed.unwrap_or_else(|| {
+        let workspace_id = workspace_id_for_dir(dir);
+        workspace_index(&state.config.lock().unwrap(), &workspace_id)
+            .notes()
+            .cloned()
+            .collect()
+    })
+}
+
+#[tauri::command]
+async fn read_note_chunk(app: tauri::AppHandle, path: String, offset: u64, length: usize) -> Result<NoteChunk, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let path = resolve_note_ref(&state, &path)?;
+        let note_path = std::path::Path::new(&path);
+        check_note_path(&state, note_path)?;
+        // Ciphertext can't be read piecewise, so an encrypted note is
+        // decrypted whole and cut into chunks in memory.
+        let head = reader::read_chunk(note_path, 0, 1024)?;
+        if !crypto::is_encrypted(&head.content) {
+            return Ok(reader::read_chunk(note_path, offset, length)?);
+        }
+        let raw = reader::read_whole(note_path, note_size_limit(&state))?;
+        let content = open_note_content(&state, note_path, raw)?;
+        Ok(reader::chunk_of(content.as_bytes(), offset, length))
+    })
+    .await
+}
+
+#[tauri::command]
+fn list_dictionaries() -> Vec<String> {
+    spellcheck::available_languages(&get_dictionary_dirs())
+}
+
+/// Misspelled words in `text`, ignoring the active workspace's personal
+/// dictionary.
+#[tauri::command]
+async fn check_text(app: tauri::AppHandle, text: String, language: String) -> Result<Vec<Misspelling>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let dict = state.spell.dictionary(&language, &get_dictionary_dirs())?;
+        let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+        let personal = state
+            .store
+            .dictionary_words(&workspace_id)?
+            .into_iter()
+            .map(|w| w.to_lowercase())
+            .collect();
+        Ok(spellcheck::check(&dict, &personal, &text))
+    })
+    .await
+}
+
+#[tauri::command]
+fn add_to_dictionary(state: tauri::State<AppState>, word: String) -> Result<(), AppError> {
+    let word = word.trim();
+    if word.is_empty() || word.chars().any(char::is_whitespace) {
+        return Err(AppError::invalid_input("Only single words can be added to the dictionary"));
+    }
+    let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+    Ok(state.store.add_dictionary_word(&workspace_id, word)?)
+}
+
+/// Grammar and style issues in `text` from a LanguageTool server, a local one
+/// unless `server` says otherwise. Servers on other machines are only used
+/// when `allow_remote` is set.
+#[tauri::command]
+async fn check_grammar(
+    text: String,
+    language: Option<String>,
+    server: Option<String>,
+    allow_remote: Option<bool>,
+) -> Result<Vec<GrammarIssue>, AppError> {
+    blocking(move || {
+        Ok(grammar::check(
+            server.as_deref().unwrap_or(grammar::DEFAULT_SERVER),
+            &text,
+            language.as_deref().unwrap_or("auto"),
+            allow_remote.unwrap_or(false),
+        )?)
+    })
+    .await
+}
+
+/// A summary of the note at `path` from a local model server (ollama or
+/// llama.cpp) at `endpoint`. Servers on other machines are only used when
+/// `allow_remote` is set.
+#[tauri::command]
+async fn summarize_note(
+    app: tauri::AppHandle,
+    path: String,
+    endpoint: Option<String>,
+    model: Option<String>,
+    allow_remote: Option<bool>,
+) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let note_path = std::path::Path::new(&path);
+        check_note_path(&state, note_path)?;
+        let content = open_note_content(&state, note_path, reader::read_whole(note_path, note_size_limit(&state))?)?;
+        Ok(summarize::summarize(
+            endpoint.as_deref().unwrap_or(summarize::DEFAULT_ENDPOINT),
+            model.as_deref().unwrap_or(summarize::DEFAULT_MODEL),
+            &content,
+            allow_remote.unwrap_or(false),
+        )?)
+    })
+    .await
+}
+
+/// Replaces the calendars daily notes take their agenda from. Sources are
+/// `.ics` files the user picked or `http(s)://`/`webcal://` URLs.
+#[tauri::command]
+fn set_calendar_sources(state: tauri::State<AppState>, sources: Vec<String>) -> Result<(), AppError> {
+    for source in &sources {
+        let is_url = ["http://", "https://", "webcal://"].iter().any(|p| source.starts_with(p));
+        if !is_url {
+            state.access.check(std::path::Path::new(source))?;
+        }
+    }
+    let mut config = state.config.lock().unwrap();
+    config.calendars = sources;
+    Ok(save_config(&config)?)
+}
+
+/// Events on `date` (`YYYY-MM-DD`) across the configured calendars.
+/// Calendars that can't be loaded are skipped unless all of them fail.
+#[tauri::command]
+async fn get_day_events(app: tauri::AppHandle, date: String) -> Result<Vec<DayEvent>, AppError> {
+    blocking(move || {
+        let date = parse_date(&date)?;
+        let sources = app.state::<AppState>().config.lock().unwrap().calendars.clone();
+        Ok(day_events(&sources, date)?)
+    })
+    .await
+}
+
+fn day_events(sources: &[String], date: chrono::NaiveDate) -> Result<Vec<DayEvent>, String> {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    for source in sources {
+        match calendar::load(source) {
+            Ok(ics) => events.extend(calendar::events_on(&ics, date)),
+            Err(e) => errors.push(e),
+        }
+    }
+    if !sources.is_empty() && errors.len() == sources.len() {
+        return Err(errors.join("; "));
+    }
+    calendar::sort(&mut events);
+    Ok(events)
+}
+
+/// Opens the daily note for `date` (`YYYY-MM-DD`, today by default) in the
+/// active workspace, creating it with the day's events if there is none.
+#[tauri::command]
+async fn create_daily_note(app: tauri::AppHandle, date: Option<String>) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let date = match date {
+            Some(date) => parse_date(&date)?,
+            None => chrono::Local::now().date_naive(),
+        };
+        if let Some(existing) = journal_entry_for(&state, &watcher, date)? {
+            return Ok(existing.path);
+        }
+        let title = date.format("%Y-%m-%d").to_string();
+        let sources = state.config.lock().unwrap().calendars.clone();
+        let events = day_events(&sources, date).unwrap_or_default();
+        let mut content = format!("# {}\n\n", title);
+        if !events.is_empty() {
+            content.push_str(&format!("## Events\n\n{}\n", calendar::agenda_markdown(&events)));
+        }
+        let _guard = state.write_lock.lock().unwrap();
+        let path = add_note(&app, &state, &watcher, &content)?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// The active workspace's journal entry for `date` (`YYYY-MM-DD`), without
+/// creating one.
+#[tauri::command]
+async fn get_note_for_date(app: tauri::AppHandle, date: String) -> Result<Option<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        Ok(journal_entry_for(&state, &watcher, parse_date(&date)?)?)
+    })
+    .await
+}
+
+/// The closest journal entry before or after `date`, for stepping through
+/// the journal without landing on days nothing was written.
+#[tauri::command]
+async fn get_adjacent_entry(app: tauri::AppHandle, date: String, direction: Direction) -> Result<Option<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let entries = journal_entries(&state, &watcher)?;
+        Ok(journal::adjacent(&entries, parse_date(&date)?, direction).cloned())
+    })
+    .await
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct JournalMonth {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub entries: Vec<NoteEntry>,
+}
+
+/// The active workspace's journal entries filed by month, newest first, for
+/// showing as one folder per month.
+#[tauri::command]
+async fn get_journal_months(app: tauri::AppHandle) -> Result<Vec<JournalMonth>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        Ok(journal::by_month(journal_entries(&state, &watcher)?)
+            .into_iter()
+            .map(|(month, entries)| JournalMonth { month, entries })
+            .collect())
+    })
+    .await
+}
+
+/// Notes in the active workspace whose titles start with a date.
+fn journal_entries(state: &AppState, watcher: &WorkspaceWatcher) -> Result<Vec<(chrono::NaiveDate, NoteEntry)>, String> {
+    Ok(scan_notes(state, watcher, true)?
+        .into_iter()
+        .filter_map(|note| Some((journal::entry_date(&note.title)?, note)))
+        .collect())
+}
+
+/// The entry for `date`, preferring the plain daily note when a day has
+/// several.
+fn journal_entry_for(state: &AppState, watcher: &WorkspaceWatcher, date: chrono::NaiveDate) -> Result<Option<NoteEntry>, String> {
+    let mut entries: Vec<NoteEntry> = journal_entries(state, watcher)?
+        .into_iter()
+        .filter(|(day, _)| *day == date)
+        .map(|(_, note)| note)
+        .collect();
+    entries.sort_by_key(|note| note.title.len());
+    Ok(entries.into_iter().next())
+}
+
+fn parse_date(date: &str) -> Result<chrono::NaiveDate, String> {
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| format!("Invalid date {}: {}", date, e))
+}
+
+/// Sets the IMAP mailbox to file into the Inbox workspace, or stops
+/// checking mail when `settings` is none. A new `password` goes to the
+/// keychain.
+#[tauri::command]
+async fn set_email_settings(
+    app: tauri::AppHandle,
+    settings: Option<EmailSettings>,
+    password: Option<String>,
+) -> Result<(), AppError> {
+    blocking(move || {
+        match (&settings, password) {
+            (None, _) => secrets::delete(email::PASSWORD)?,
+            (Some(_), Some(password)) => secrets::set(email::PASSWORD, &password)?,
+            (Some(_), None) => {}
+        }
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap();
+        config.email = settings;
+        Ok(save_config(&config)?)
+    })
+    .await
+}
+
+/// Checks the mailbox now instead of waiting for the next poll. Returns how
+/// many messages became notes.
+#[tauri::command]
+async fn check_email(app: tauri::AppHandle) -> Result<usize, AppError> {
+    blocking(move || Ok(ingest_email(&app)?)).await
+}
+
+fn ingest_email(app: &tauri::AppHandle) -> Result<usize, String> {
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let settings = state.config.lock().unwrap().email.clone().ok_or("Email isn't set up")?;
+    let password = secrets::get(email::PASSWORD)?.ok_or("The mail password isn't in the keychain")?;
+    let workspace_id = {
+        let mut config = state.config.lock().unwrap();
+        let inbox_id = slugify(email::WORKSPACE_NAME);
+        if !config.workspaces.iter().any(|w| w.id == inbox_id) {
+            add_workspace(&mut config, email::WORKSPACE_NAME.to_string())?;
+            save_config(&config)?;
+        }
+        inbox_id
+    };
+    let assets_dir = get_workspace_dir(&workspace_id).join(ASSETS_DIR);
+
+    email::poll(&settings, &password, |message| {
+        let mut assets = Vec::new();
+        for attachment in &message.attachments {
+            let file_name = format!("{}-{}", message.uid, attachment.file_name);
+            fs::create_dir_all(&assets_dir).map_err(|e| e.to_string())?;
+            fs::write(assets_dir.join(&file_name), &attachment.data).map_err(|e| e.to_string())?;
+            assets.push(format!("{}/{}", ASSETS_DIR, file_name));
+        }
+        add_note_to(app, &state, &watcher, &workspace_id, &email::to_note(&message, &assets)).map(|_| ())
+    })
+}
+
+/// Sets the folder whose markdown and text files are imported into a
+/// workspace, or stops watching it when `settings` is none. Files already
+/// there are imported right away.
+#[tauri::command]
+async fn set_inbox_settings(app: tauri::AppHandle, settings: Option<InboxSettings>) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        if let Some(settings) = &settings {
+            let dir = PathBuf::from(&settings.dir);
+            if !dir.is_absolute() || !dir.is_dir() {
+                return Err(AppError::invalid_input(format!("{} isn't a folder", settings.dir)));
+            }
+            let root = get_notes_root();
+            if dir.starts_with(&root) || root.starts_with(&dir) {
+                return Err(AppError::invalid_input("The inbox has to be outside the notes folder"));
+            }
+            // A new inbox has to be a folder the user picked with `pick_folder`.
+            let current = state.config.lock().unwrap().inbox.as_ref().map(|inbox| inbox.dir.clone());
+            if current.as_ref() != Some(&settings.dir) {
+                state.access.check(&dir)?;
+            }
+            if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == settings.workspace_id) {
+                return Err(AppError::not_found("Workspace not found"));
+            }
+        }
+        {
+            let mut config = state.config.lock().unwrap();
+            config.inbox = settings.clone();
+            save_config(&config)?;
+        }
+        watch_inbox(&app)?;
+        if settings.is_some() {
+            ingest_inbox(&app)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Imports the inbox now rather than waiting for a file to be dropped in.
+/// Returns how many files became notes.
+#[tauri::command]
+async fn check_inbox(app: tauri::AppHandle) -> Result<usize, AppError> {
+    blocking(move || Ok(ingest_inbox(&app)?)).await
+}
+
+fn watch_inbox(app: &tauri::AppHandle) -> Result<(), String> {
+    let dir = app.state::<AppState>().config.lock().unwrap().inbox.as_ref().map(|i| PathBuf::from(&i.dir));
+    app.state::<InboxWatcher>().watch(app, dir.as_deref())
+}
+
+/// Turns the files waiting in the inbox into notes and moves them to its
+/// archive folder. A file that can't be imported stays for the next try.
+fn ingest_inbox(app: &tauri::AppHandle) -> Result<usize, String> {
+    let _importing = inbox::IMPORTING.lock().unwrap();
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let Some(settings) = state.config.lock().unwrap().inbox.clone() else {
+        return Ok(0);
+    };
+    if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == settings.workspace_id) {
+        return Err("The inbox's workspace doesn't exist anymore".to_string());
+    }
+    let dir = PathBuf::from(&settings.dir);
+    let mut imported = 0;
+    for file in inbox::pending(&dir) {
+        let content = match fs::read(&file).map(String::from_utf8) {
+            Ok(Ok(content)) => content,
+            Ok(Err(_)) => {
+                tracing::warn!("Inbox file {} isn't UTF-8 text", file.display());
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Reading inbox file {} failed: {}", file.display(), e);
+                continue;
+            }
+        };
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        add_note_to(app, &state, &watcher, &settings.workspace_id, &inbox::to_note(&name, &content))?;
+        inbox::archive(&dir, &file)?;
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+/// Saves the article at `url` as a note in the active workspace, tagged
+/// `#read-later`, with its images downloaded into the assets folder.
+/// Images stay remote in encrypted workspaces, which don't encrypt assets.
+#[tauri::command]
+async fn capture_url(app: tauri::AppHandle, url: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let article = clip::fetch_article(&url)?;
+        let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+        let body = if is_workspace_encrypted(&state, &workspace_id) {
+            article.body.clone()
+        } else {
+            let assets_dir = get_workspace_dir(&workspace_id).join(ASSETS_DIR);
+            let transliterate = state.config.lock().unwrap().transliterate_file_names;
+            let stem = match slug::slugify_with(&article.title, transliterate) {
+                slug if slug.is_empty() => "image".to_string(),
+                slug => slug,
+            };
+            clip::localize_images(&article.body, |src| {
+                let (data, extension) = clip::download_image(src)?;
+                fs::create_dir_all(&assets_dir).map_err(|e| e.to_string())?;
+                let file_name = (1..)
+                    .map(|n| format!("{}-{}.{}", stem, n, extension))
+                    .find(|name| !assets_dir.join(name).exists())
+                    .expect("unbounded range");
+                fs::write(assets_dir.join(&file_name), data).map_err(|e| e.to_string())?;
+                Ok(format!("{}/{}", ASSETS_DIR, file_name))
+            })
+        };
+        let clipped = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+        let content = clip::article_note(&article, &clipped, &["read-later"], &body);
+        let _guard = state.write_lock.lock().unwrap();
+        let path = add_note(&app, &state, &watcher, &content)?;
+        Ok(path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Sets where notes are published. A new S3 `secret` access key goes to
+/// the keychain; it's removed along with the S3 target.
+#[tauri::command]
+async fn set_publish_settings(app: tauri::AppHandle, settings: PublishSettings, secret: Option<String>) -> Result<(), AppError> {
+    blocking(move || {
+        match (&settings.s3, secret) {
+            (None, _) => secrets::delete(publish::S3_SECRET)?,
+            (Some(_), Some(secret)) => secrets::set(publish::S3_SECRET, &secret)?,
+            (Some(_), None) => {}
+        }
+        let state = app.state::<AppState>();
+        let mut config = state.config.lock().unwrap();
+        config.publish = settings;
+        Ok(save_config(&config)?)
+    })
+    .await
+}
+
+fn s3_target(state: &AppState) -> Result<(publish::S3Target, String), String> {
+    let target = state.config.lock().unwrap().publish.s3.clone().ok_or("Publishing to S3 isn't set up")?;
+    let secret = secrets::get(publish::S3_SECRET)?.ok_or("The S3 secret key isn't in the keychain")?;
+    Ok((target, secret))
+}
+
+/// Writes the `Q:`/`A:` and cloze cards in a workspace's notes, the active
+/// one by default, to `dest` for Anki to import. With `tag`, only notes
+/// tagged with it or a tag under it are included. Returns how many cards
+/// were written.
+#[tauri::command]
+async fn export_flashcards(
+    app: tauri::AppHandle,
+    workspace_id: Option<String>,
+    tag: Option<String>,
+    dest: String,
+) -> Result<usize, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_export_dest(&state, &dest)?;
+        let workspace_id = workspace_id.unwrap_or_else(|| state.config.lock().unwrap().active_workspace_id.clone());
+        if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == workspace_id) {
+            return Err(AppError::not_found("Workspace not found"));
+        }
+        let tag = tag.map(|tag| tag.trim().trim_start_matches('#').to_string());
+        let mut notes = workspace_notes(&state, &watcher, &get_workspace_dir(&workspace_id));
+        notes.retain(|n| n.stamp.size <= reader::MAX_READ_BYTES);
+        notes.sort_by(|a, b| a.path.cmp(&b.path));
+        let cards: Vec<(flashcards::Card, Vec<String>)> = notes
+            .par_iter()
+            .flat_map_iter(|note| {
+                let content = fs::read_to_string(&note.path)
+                    .ok()
+                    .and_then(|content| open_note_content(&state, &note.path, content).ok())
+                    .unwrap_or_default();
+                let note_tags = tags::parse_tags(&content);
+                let tagged = tag
+                    .as_ref()
+                    .is_none_or(|tag| note_tags.iter().any(|t| tags::is_within(t, tag)));
+                let cards = if tagged { flashcards::parse_cards(&content) } else { Vec::new() };
+                cards.into_iter().map(move |card| (card, note_tags.clone()))
+            })
+            .collect();
+        fs::write(&dest, flashcards::to_anki(&cards)).map_err(|e| format!("Couldn't write {}: {}", dest, e))?;
+        Ok(cards.len())
+    })
+    .await
+}
+
+/// One section or selection of a note as markdown, or as a standalone HTML
+/// page rendered like published notes.
+#[tauri::command]
+async fn export_fragment(
+    app: tauri::AppHandle,
+    path: String,
+    fragment: export::Fragment,
+    format: export::ExportFormat,
+) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let markdown = export::fragment(&content, &fragment)?;
+        match format {
+            export::ExportFormat::Markdown => Ok(markdown),
+            export::ExportFormat::Html => {
+                let title = match &fragment {
+                    export::Fragment::Heading(heading) => render::plain_text(heading),
+                    export::Fragment::Range(_) => note_title(&note_path, &content),
+                };
+                let dir = note_path.parent().ok_or("Invalid path")?;
+                let notes = workspace_notes(&state, &watcher, dir);
+                let markdown = bibtex::cite(&markdown, &workspace_bibliography(dir));
+                let body = render::render(
+                    &markdown,
+                    Some(dir),
+                    Some(highlight::DEFAULT_THEME),
+                    |target| resolve_wikilink(&notes, target),
+                    |source| render_diagram(&state, source),
+                );
+                let theme = export_theme_css(&state, &workspace_id_for_path(&note_path));
+                Ok(publish::page(&title, &body, theme.as_deref()))
+            }
+        }
+    })
+    .await
+}
+
+/// Converts the note at `path` to any format Pandoc writes, like `odt`,
+/// `rtf`, `rst` or `mediawiki`, saving it at `dest`, a path picked with
+/// `pick_save_file`. Citations use the workspace bibliography.
+#[tauri::command]
+async fn convert_note(
+    app: tauri::AppHandle,
+    path: String,
+    to_format: String,
+    dest: String,
+) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_export_dest(&state, &dest)?;
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let dir = note_path.parent().ok_or("Invalid path")?;
+        let bibliography = dir.join(bibtex::BIBLIOGRAPHY_FILE);
+        Ok(pandoc::convert(
+            &tool_command(&state, Tool::Pandoc)?,
+            &content,
+            &to_format,
+            std::path::Path::new(&dest),
+            dir,
+            bibliography.exists().then_some(bibliography.as_path()),
+        )?)
+    })
+    .await
+}
+
+/// Uploads the note at `path` as a web page and returns where it lives.
+/// Publishing again updates the same page. Notes of encrypted workspaces
+/// stay private.
+#[tauri::command]
+async fn publish_note(app: tauri::AppHandle, path: String, provider: PublishProvider) -> Result<Publication, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let note_path = std::path::Path::new(&path);
+        check_note_path(&state, note_path)?;
+        if is_workspace_encrypted(&state, &workspace_id_for_path(note_path)) {
+            return Err(AppError::conflict("Notes in encrypted workspaces can't be published"));
+        }
+        let content = reader::read_whole(note_path, note_size_limit(&state))?;
+        if crypto::is_encrypted(&content) {
+            return Err(AppError::conflict("Encrypted notes can't be published"));
+        }
+        let title = note_title(note_path, &content);
+        let dir = note_path.parent().ok_or("Invalid path")?;
+        let content = bibtex::cite(&content, &workspace_bibliography(dir));
+        let body = render::render(
+            &content,
+            None,
+            Some(highlight::DEFAULT_THEME),
+            |_| None,
+            |source| render_diagram(&state, source),
+        );
+        let theme = export_theme_css(&state, &workspace_id_for_path(note_path));
+        let page = publish::page(&title, &body, theme.as_deref());
+
+        let existing = state.store.publication(&path)?.filter(|p| p.provider == provider.as_str());
+        let (key, url) = match provider {
+            PublishProvider::S3 => {
+                let (target, secret) = s3_target(&state)?;
+                let key = match existing {
+                    Some(existing) => existing.key,
+                    None => publish::new_key(&target.prefix, &slugify(&title), &secrets::generate()?),
+                };
+                let url = publish::upload(&target, &secret, &key, &page)?;
+                (key, url)
+            }
+        };
+        let publication = Publication {
+            provider: provider.as_str().to_string(),
+            key,
+            url,
+            published_at: now_secs(),
+        };
+        state.store.set_publication(&path, Some(&publication))?;
+        Ok(publication)
+    })
+    .await
+}
+
+/// Takes the note's page down and forgets it was published.
+#[tauri::command]
+async fn unpublish_note(app: tauri::AppHandle, path: String) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        check_note_path(&state, std::path::Path::new(&path))?;
+        let publication = state.store.publication(&path)?.ok_or("Note isn't published")?;
+        match PublishProvider::parse(&publication.provider) {
+            Some(PublishProvider::S3) => {
+                let (target, secret) = s3_target(&state)?;
+                publish::delete(&target, &secret, &publication.key)?;
+            }
+            None => return Err(AppError::invalid_input(format!("Unknown provider \"{}\"", publication.provider))),
+        }
+        Ok(state.store.set_publication(&path, None)?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn get_publication(state: tauri::State<AppState>, path: String) -> Result<Option<Publication>, AppError> {
+    check_note_path(&state, std::path::Path::new(&path))?;
+    Ok(state.store.publication(&path)?)
+}
+
+/// Comments and review checkpoints copy note text into the metadata store,
+/// so they're kept out of encrypted workspaces. `feature` names them in the
+/// error.
+fn check_sidecar_allowed(state: &AppState, path: &std::path::Path, feature: &str) -> Result<(), String> {
+    check_note_path(state, path)?;
+    if is_workspace_encrypted(state, &workspace_id_for_path(path)) {
+        return Err(format!("{} aren't available in encrypted workspaces", feature));
+    }
+    Ok(())
+}
+
+/// Leaves a comment on `range` of the note without changing the note.
+#[tauri::command]
+fn add_comment(state: tauri::State<AppState>, path: String, range: TextRange, text: String) -> Result<Comment, AppError> {
+    let note_path = std::path::Path::new(&path);
+    check_sidecar_allowed(&state, note_path, "Comments")?;
+    if text.trim().is_empty() {
+        return Err(AppError::invalid_input("Comment is empty"));
+    }
+    let content = reader::read_whole(note_path, note_size_limit(&state))?;
+    let quote = comments::quote(&content, range).ok_or("Range is outside the note")?;
+    Ok(state.store.add_comment(&path, range, &quote, text.trim(), now_secs())?)
+}
+
+/// The note's comments, resolved ones included, with ranges moved to where
+/// their text is now.
+#[tauri::command]
+fn list_comments(state: tauri::State<AppState>, path: String) -> Result<Vec<Comment>, AppError> {
+    let note_path = std::path::Path::new(&path);
+    check_sidecar_allowed(&state, note_path, "Comments")?;
+    let content = reader::read_whole(note_path, note_size_limit(&state))?;
+    let mut comments = state.store.comments(&path)?;
+    for comment in &mut comments {
+        comments::reanchor(&content, comment);
+    }
+    Ok(comments)
+}
+
+#[tauri::command]
+fn resolve_comment(state: tauri::State<AppState>, id: i64) -> Result<(), AppError> {
+    Ok(state.store.resolve_comment(id)?)
+}
+
+/// Starts tracking changes to the note: edits from here on can be listed
+/// and accepted or rejected one by one. Starting again takes everything so
+/// far as reviewed.
+#[tauri::command]
+fn start_review(state: tauri::State<AppState>, path: String) -> Result<(), AppError> {
+    let note_path = std::path::Path::new(&path);
+    check_sidecar_allowed(&state, note_path, "Tracked changes")?;
+    let content = reader::read_whole(note_path, note_size_limit(&state))?;
+    Ok(state.store.set_review_checkpoint(&path, Some(&content), now_secs())?)
+}
+
+#[tauri::command]
+fn end_review(state: tauri::State<AppState>, path: String) -> Result<(), AppError> {
+    check_note_path(&state, std::path::Path::new(&path))?;
+    Ok(state.store.set_review_checkpoint(&path, None, now_secs())?)
+}
+
+/// Edits made since the review started.
+#[tauri::command]
+fn list_changes(state: tauri::State<AppState>, path: String) -> Result<Vec<Change>, AppError> {
+    let (checkpoint, content) = review_texts(&state, &path)?;
+    Ok(review::changes(&checkpoint, &content))
+}
+
+/// Keeps change `index` from `list_changes`. Returns the changes left.
+#[tauri::command]
+fn accept_change(state: tauri::State<AppState>, path: String, index: usize) -> Result<Vec<Change>, AppError> {
+    let (checkpoint, content) = review_texts(&state, &path)?;
+    let checkpoint = review::accept(&checkpoint, &content, index).ok_or("Change not found")?;
+    state.store.set_review_checkpoint(&path, Some(&checkpoint), now_secs())?;
+    Ok(review::changes(&checkpoint, &content))
+}
+
+/// Undoes change `index` from `list_changes` in the note. An open editor
+/// reloads through the external-change event. Returns the changes left.
+#[tauri::command]
+async fn reject_change(app: tauri::AppHandle, path: String, index: usize) -> Result<Vec<Change>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_unlocked(&state, &note_path)?;
+        let (checkpoint, content) = review_texts(&state, &path)?;
+        let reverted = review::reject(&checkpoint, &content, index).ok_or("Change not found")?;
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, &reverted).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let _ = app.emit(
+            NOTE_EXTERNALLY_CHANGED,
+            NoteFsEvent {
+                workspace_id: workspace_id_for_path(&note_path),
+                path,
+                content: Some(reverted.clone()),
+            },
+        );
+        Ok(review::changes(&checkpoint, &reverted))
+    })
+    .await
+}
+
+/// The note's review checkpoint and current text.
+fn review_texts(state: &AppState, path: &str) -> Result<(String, String), String> {
+    let note_path = std::path::Path::new(path);
+    check_sidecar_allowed(state, note_path, "Tracked changes")?;
+    let checkpoint = state.store.review_checkpoint(path)?.ok_or("The note isn't being reviewed")?;
+    Ok((checkpoint, reader::read_whole(note_path, note_size_limit(state))?))
+}
+
+/// Counts a save toward today's writing stats. Failing to record them never
+/// fails the save.
+fn record_writing(state: &AppState, path: &std::path::Path, words_before: usize, words_after: usize) {
+    let (added, removed) = stats::word_delta(words_before, words_after);
+    if added == 0 && removed == 0 {
+        return;
+    }
+    let today = stats::day_key(chrono::Local::now().date_naive());
+    let _ = state.store.record_words(&today, &workspace_id_for_path(path), added, removed);
+    if let Some(session) = state.session.lock().unwrap().as_mut() {
+        session.record(&path.to_string_lossy(), added, removed);
+    }
+}
+
+/// Words written per day in `range`, in one workspace or, without
+/// `workspace_id`, across all of them.
+#[tauri::command]
+fn get_writing_stats(
+    state: tauri::State<AppState>,
+    range: DateRange,
+    workspace_id: Option<String>,
+) -> Result<Vec<DayWords>, AppError> {
+    let days = state.store.words_by_day(
+        &stats::day_key(range.from),
+        &stats::day_key(range.to),
+        workspace_id.as_deref(),
+    )?;
+    Ok(days
+        .into_iter()
+        .filter_map(|(day, added, removed)| {
+            Some(DayWords {
+                day: stats::parse_day(&day)?,
+                added,
+                removed,
+            })
+        })
+        .collect())
+}
+
+/// Today's and this week's words against the workspace's goal (the active
+/// one by default), and the current streak.
+#[tauri::command]
+fn get_goal_progress(state: tauri::State<AppState>, workspace_id: Option<String>) -> Result<GoalProgress, AppError> {
+    let (workspace_id, goal) = {
+        let config = state.config.lock().unwrap();
+        let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+        let workspace = config
+            .workspaces
+            .iter()
+            .find(|w| w.id == workspace_id)
+            .ok_or("Workspace not found")?;
+        (workspace_id, workspace.word_goal)
+    };
+    let today = chrono::Local::now().date_naive();
+    // A streak longer than a year is still shown as a year.
+    let from = today - chrono::Duration::days(366);
+    let days = state
+        .store
+        .words_by_day(&stats::day_key(from), &stats::day_key(today), Some(&workspace_id))?
+        .into_iter()
+        .filter_map(|(day, added, removed)| {
+            Some(DayWords {
+                day: stats::parse_day(&day)?,
+                added,
+                removed,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(stats::goal_progress(goal, &days, today))
+}
+
+#[tauri::command]
+fn start_session(state: tauri::State<AppState>) -> Result<WritingSession, AppError> {
+    let mut session = state.session.lock().unwrap();
+    if session.is_some() {
+        return Err(AppError::conflict("A writing session is already running"));
+    }
+    Ok(session.insert(WritingSession::new(now_secs())).clone())
+}
+
+/// Stops the running session and stores it.
+#[tauri::command]
+fn end_session(state: tauri::State<AppState>) -> Result<WritingSession, AppError> {
+    let mut session = state
+        .session
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("No writing session is running")?;
+    session.end(now_secs());
+    session.id = state.store.add_session(&session)?;
+    Ok(session)
+}
+
+/// Sessions started on the days in `range`, oldest first.
+#[tauri::command]
+fn get_sessions(state: tauri::State<AppState>, range: DateRange) -> Result<Vec<WritingSession>, AppError> {
+    let (from, to) = stats::range_secs(range);
+    Ok(state.store.sessions(from, to)?)
+}
+
+/// Any note in the active workspace, for a "surprise me" button.
+#[tauri::command]
+async fn get_random_note(app: tauri::AppHandle) -> Result<Option<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let mut notes = scan_notes(&state, &watcher, true)?;
+        let picked = resurface::sample(&vec![1.0; notes.len()], 1, resurface::roll);
+        Ok(picked.first().map(|&i| notes.swap_remove(i)))
+    })
+    .await
+}
+
+/// A few notes in the active workspace neither opened nor edited in the
+/// last `months` (6 by default), picked at random with the longest
+/// forgotten most likely.
+#[tauri::command]
+async fn get_resurfacing_candidates(
+    app: tauri::AppHandle,
+    months: Option<u32>,
+    limit: Option<usize>,
+) -> Result<Vec<Resurfaced>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let months = months.unwrap_or(6);
+        let visits = state.store.visits()?;
+        let now = now_secs();
+        let (stale, weights): (Vec<Resurfaced>, Vec<f64>) = scan_notes(&state, &watcher, true)?
+            .into_iter()
+            .filter_map(|note| {
+                let visited = visits.get(&note.path).map_or(0, |(_, last)| *last);
+                let last_seen = note.modified.max(visited);
+                let weight = resurface::weight(last_seen, now, months)?;
+                let note = Resurfaced {
+                    path: note.path,
+                    title: note.title,
+                    last_seen,
+                };
+                Some((note, weight))
+            })
+            .unzip();
+        let mut picked: Vec<Option<Resurfaced>> = stale.into_iter().map(Some).collect();
+        Ok(resurface::sample(&weights, limit.unwrap_or(5), resurface::roll)
+            .into_iter()
+            .filter_map(|i| picked[i].take())
+            .collect())
+    })
+    .await
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Notes of the active workspace by how often and how recently they're
+/// opened and edited, for the quick switcher before anything is typed.
+/// Notes never used follow, newest first.
+#[tauri::command]
+async fn get_frecent_notes(app: tauri::AppHandle, limit: Option<usize>) -> Result<Vec<NoteEntry>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let visits = state.store.visits()?;
+        let edits = state.store.edits()?;
+        let now = now_secs();
+        let score = |path: &str| {
+            let visited = visits.get(path).map_or(0.0, |(count, last)| links::frecency(*count, *last, now));
+            let edited = edits.get(path).map_or(0.0, |(count, last)| links::frecency(*count, *last, now));
+            visited + links::EDIT_WEIGHT * edited
+        };
+        let mut entries: Vec<(f64, NoteEntry)> = listed_notes(&state, &watcher, true)?
+            .into_iter()
+            .map(|entry| (score(&entry.path), entry))
+            .collect();
+        entries.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.modified.cmp(&a.1.modified)));
+        Ok(entries.into_iter().take(limit.unwrap_or(20)).map(|(_, entry)| entry).collect())
+    })
+    .await
+}
+
+/// Completions for a wikilink being typed. `Note#Head` (or `#Head` for the
+/// note at `path`) lists that note's headings; anything else matches note
+/// titles and aliases, most-visited first.
+#[tauri::command]
+async fn get_link_candidates(
+    app: tauri::AppHandle,
+    prefix: String,
+    path: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LinkCandidate>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let limit = limit.unwrap_or(20);
+
+        if let Some((note, heading)) = prefix.split_once('#') {
+            let note_path = if note.is_empty() {
+                path.map(PathBuf::from).ok_or("No note to list headings for")?
+            } else {
+                let notes: Vec<IndexedNote> = watcher.with_index(|index| index.notes().cloned().collect());
+                resolve_wikilink(&notes, note).ok_or("Note not found")?
+            };
+            state.access.check(&note_path)?;
+            let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+            let candidates = links::parse_headings(&content)
+                .into_iter()
+                .map(|h| {
+                    let candidate = LinkCandidate {
+                        kind: "heading",
+                        target: format!("{}#{}", note, h),
+                        label: h,
+                        path: note_path.to_string_lossy().to_string(),
+                    };
+                    (candidate, 0.0)
+                })
+                .collect();
+            return Ok(links::rank(candidates, heading, limit));
+        }
+
+        let entries = scan_notes(&state, &watcher, true)?;
+        let stamps: HashMap<PathBuf, FileStamp> =
+            watcher.with_index(|index| index.notes().map(|n| (n.path.clone(), n.stamp)).collect());
+        let visits = state.store.visits()?;
+        let note_cache = state.note_cache.lock().unwrap();
+        let now = now_secs();
+        let mut candidates = Vec::new();
+        for entry in entries {
+            let score = visits
+                .get(&entry.path)
+                .map(|(count, last)| links::frecency(*count, *last, now))
+                .unwrap_or(0.0);
+            let path = std::path::Path::new(&entry.path);
+            let aliases = stamps
+                .get(path)
+                .and_then(|stamp| note_cache.get(path, *stamp))
+                .map(|c| c.aliases.clone())
+                .unwrap_or_default();
+            for alias in aliases {
+                let candidate = LinkCandidate {
+                    kind: "alias",
+                    label: alias,
+                    target: entry.title.clone(),
+                    path: entry.path.clone(),
+                };
+                candidates.push((candidate, score));
+            }
+            let candidate = LinkCandidate {
+                kind: "note",
+                label: entry.title.clone(),
+                target: entry.title,
+                path: entry.path,
+            };
+            candidates.push((candidate, score));
+        }
+        Ok(links::rank(candidates, &prefix, limit))
+    })
+    .await
+}
+
+/// Renders a note, or unsaved `content` from it, to sanitized HTML for the
+/// read-only preview.
+#[tauri::command]
+async fn render_markdown(
+    app: tauri::AppHandle,
+    content: Option<String>,
+    path: Option<String>,
+    code_theme: Option<String>,
+) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let path = path.map(PathBuf::from);
+        let content = match (content, &path) {
+            (Some(content), _) => content,
+            (None, Some(path)) => {
+                check_note_path(&state, path)?;
+                let raw = reader::read_whole(path, note_size_limit(&state))?;
+                open_note_content(&state, path, raw)?
+            }
+            (None, None) => return Err(AppError::invalid_input("Nothing to render")),
+        };
+        let (notes_dir, notes) = watcher.with_index(|index| (index.dir().to_path_buf(), index.notes().cloned().collect::<Vec<_>>()));
+        let base_dir = path
+            .as_deref()
+            .and_then(std::path::Path::parent)
+            .unwrap_or(&notes_dir);
+        let code_theme = code_theme.as_deref().unwrap_or(highlight::DEFAULT_THEME);
+        Ok(render::render(
+            &content,
+            Some(base_dir),
+            Some(code_theme),
+            |target| resolve_wikilink(&notes, target),
+            |source| render_diagram(&state, source),
+        ))
+    })
+    .await
+}
+
+/// The SVG for a mermaid diagram, drawn with mermaid-cli. None leaves the
+/// block as code, when mermaid-cli isn't installed or can't draw it.
+fn render_diagram(state: &AppState, source: &str) -> Option<String> {
+    let cache_dir = get_app_data_dir().join(diagram::CACHE_DIR);
+    tool_command(state, Tool::Mermaid)
+        .and_then(|command| diagram::mermaid(&command, source, &cache_dir))
+        .map_err(|e| tracing::debug!("Diagram left as code: {}", e))
+        .ok()
+}
+
+/// Syntax-highlighted HTML for a code block, as used in the preview.
+#[tauri::command]
+fn highlight_code(code: String, lang: String, theme: Option<String>) -> Result<String, AppError> {
+    Ok(highlight::highlight(&code, &lang, theme.as_deref().unwrap_or(highlight::DEFAULT_THEME))?)
+}
+
+#[tauri::command]
+fn list_code_themes() -> Vec<String> {
+    highlight::theme_names()
+}
+
+/// Word, sentence and readability statistics for a note, or for unsaved
+/// `content` from it.
+#[tauri::command]
+async fn analyze_text(app: tauri::AppHandle, path: String, content: Option<String>) -> Result<TextStats, AppError> {
+    blocking(move || {
+        let content = match content {
+            Some(content) => content,
+            None => {
+                let state = app.state::<AppState>();
+                let note_path = std::path::Path::new(&path);
+                check_note_path(&state, note_path)?;
+                open_note_content(&state, note_path, reader::read_whole(note_path, note_size_limit(&state))?)?
+            }
+        };
+        Ok(analytics::analyze(&content))
+    })
+    .await
+}
+
+/// Curly quotes, dashes and ellipses for `text`, leaving code untouched.
+#[tauri::command]
+fn smarten(text: String) -> String {
+    typography::smarten(&text)
+}
+
+/// Emoji for `:shortcode:` autocomplete.
+#[tauri::command]
+fn lookup_emoji(prefix: String, limit: Option<usize>) -> Vec<EmojiMatch> {
+    emoji::lookup(&prefix, limit.unwrap_or(20))
+}
+
+/// Finds the note `[[target]]` points at by title, file name, or the slug
+/// part of a numbered file name.
+fn resolve_wikilink(notes: &[IndexedNote], target: &str) -> Option<PathBuf> {
+    // Either spelling, since notes keep their names when transliteration is
+    // switched on or off.
+    let slugs = [slugify(target), slug::slugify_with(target, true)];
+    notes
+        .iter()
+        .find(|n| {
+            n.title.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(target))
+                || n.name.eq_ignore_ascii_case(target)
+                || (parse_file_number(&n.name).is_some()
+                    && n.name.split_once('-').is_some_and(|(_, s)| slugs.iter().any(|slug| slug == s)))
+        })
+        .map(|n| n.path.clone())
+}
+
+/// Re-aligns the markdown table in `text`, optionally inserting or deleting a
+/// row or column first.
+#[tauri::command]
+fn format_table(text: String, edit: Option<TableEdit>) -> Result<String, AppError> {
+    Ok(table::format_table(&text, edit)?)
+}
+
+/// Checkbox items across the workspace's notes, in file and line order.
+#[tauri::command]
+async fn list_tasks(app: tauri::AppHandle, filter: Option<TaskFilter>) -> Result<Vec<Task>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let filter = filter.unwrap_or_default();
+        let mut tasks = workspace_tasks(&state, &watcher);
+        tasks.retain(|task| filter.matches(task));
+        Ok(tasks)
+    })
+    .await
+}
+
+/// Open tasks with a due date across the workspace's notes: those due within
+/// `range`, and those due before it.
+#[tauri::command]
+async fn get_agenda(app: tauri::AppHandle, range: tasks::AgendaRange) -> Result<tasks::Agenda, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        Ok(tasks::agenda(workspace_tasks(&state, &watcher), range))
+    })
+    .await
+}
+
+/// A kanban board built from a note's headings and list items, or from
+/// tasks' nested tags.
+#[tauri::command]
+async fn get_board(app: tauri::AppHandle, source: board::BoardSource) -> Result<board::Board, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        match source {
+            board::BoardSource::Note(path) => {
+                let note_path = PathBuf::from(&path);
+                check_note_path(&state, &note_path)?;
+                let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+                Ok(board::note_board(&path, &content))
+            }
+            board::BoardSource::Tag(tag) => {
+                check_workspace_present(&watcher)?;
+                let tag = board_tag(&tag)?;
+                Ok(board::tag_board(workspace_tasks(&state, &watcher), &tag))
+            }
+        }
+    })
+    .await
+}
+
+/// Moves the card on 1-based `line` of the note at `path` to `column`: under
+/// that heading at `position` for a note board, or by retagging the task for
+/// a tag board. `text` is the card's text from `get_board`, so a stale board
+/// can't move the wrong line.
+#[tauri::command]
+async fn move_card(
+    app: tauri::AppHandle,
+    source: board::BoardSource,
+    path: String,
+    line: usize,
+    text: String,
+    column: String,
+    position: Option<usize>,
+) -> Result<(), AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let content = match &source {
+            board::BoardSource::Note(_) => board::move_in_note(&content, line, &text, &column, position)?,
+            board::BoardSource::Tag(tag) => board::move_tagged(&content, line, &text, &board_tag(tag)?, &column)?,
+        };
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, seal_note_content(&state, &note_path, content.clone())?).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let _ = app.emit(
+            NOTE_EXTERNALLY_CHANGED,
+            NoteFsEvent {
+                workspace_id: workspace_id_for_path(&note_path),
+                path,
+                content: Some(content),
+            },
+        );
+        Ok(())
+    })
+    .await
+}
+
+fn board_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().trim_start_matches('#').trim_end_matches(tags::SEPARATOR);
+    if !tags::is_tag(tag) {
+        return Err(format!("Invalid tag {}", tag));
+    }
+    Ok(tag.to_string())
+}
+
+/// Every task in the active workspace's notes, in file and line order.
+fn workspace_tasks(state: &AppState, watcher: &WorkspaceWatcher) -> Vec<Task> {
+    let mut notes: Vec<IndexedNote> = watcher.with_index(|index| index.notes().cloned().collect());
+    notes.retain(|n| n.stamp.size <= reader::MAX_READ_BYTES);
+    notes.sort_by(|a, b| a.path.cmp(&b.path));
+    notes
+        .par_iter()
+        .flat_map_iter(|note| {
+            let path = note.path.to_string_lossy().to_string();
+            let content = fs::read_to_string(&note.path)
+                .ok()
+                .and_then(|content| open_note_content(state, &note.path, content).ok())
+                .unwrap_or_default();
+            let note_title = note_title(&note.path, &content);
+            tasks::parse_tasks(&content)
+                .into_iter()
+                .map(move |(line, text, done, depth)| Task {
+                    path: path.clone(),
+                    note_title: note_title.clone(),
+                    line,
+                    due: tasks::parse_due(&text),
+                    text,
+                    done,
+                    depth,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Ticks or unticks the task on 1-based `line` and returns whether it's now
+/// done. Passing the `text` from `list_tasks` refuses the edit if the note
+/// changed since. An open editor reloads through the external-change event.
+#[tauri::command]
+async fn toggle_task(app: tauri::AppHandle, path: String, line: usize, text: Option<String>) -> Result<bool, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let (content, done) = tasks::toggle_line(&content, line, text.as_deref())?;
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, seal_note_content(&state, &note_path, content.clone())?).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let _ = app.emit(
+            NOTE_EXTERNALLY_CHANGED,
+            NoteFsEvent {
+                workspace_id: workspace_id_for_path(&note_path),
+                path,
+                content: Some(content),
+            },
+        );
+        Ok(done)
+    })
+    .await
+}
+
+/// Numbers the footnotes in the note at `path` in order of first reference
+/// and gathers their definitions at the end. An open editor reloads through
+/// the external-change event.
+#[tauri::command]
+async fn renumber_footnotes(app: tauri::AppHandle, path: String) -> Result<footnotes::FootnoteReport, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let (renumbered, report) = footnotes::renumber(&content);
+        if renumbered != content {
+            watcher.mark_self_write(&note_path);
+            fs::write(&note_path, seal_note_content(&state, &note_path, renumbered.clone())?).map_err(|e| e.to_string())?;
+            watcher.refresh(&[&note_path]);
+            let _ = app.emit(
+                NOTE_EXTERNALLY_CHANGED,
+                NoteFsEvent {
+                    workspace_id: workspace_id_for_path(&note_path),
+                    path,
+                    content: Some(renumbered),
+                },
+            );
+        }
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+async fn write_note(
+    app: tauri::AppHandle,
+    path: String,
+    content: String,
+    rename: Option<bool>,
+) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let (old_path, content) = save_note(&state, &watcher, &path, content)?;
+        if !rename.unwrap_or(true) {
+            return Ok(old_path.to_string_lossy().to_string());
+        }
+        let new_path = rename_to_title(&app, &watcher, &old_path, &content)?;
+        Ok(new_path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Writes `content` to the note `note`, a path or a note id, and returns
+/// the note's path with the content as written to disk. The caller holds
+/// the write lock.
+fn save_note(
+    state: &AppState,
+    watcher: &WorkspaceWatcher,
+    note: &str,
+    content: String,
+) -> Result<(PathBuf, String), AppError> {
+    let path = PathBuf::from(resolve_note_ref(state, note)?);
+    check_note_path(state, &path)?;
+    check_note_unlocked(state, &path)?;
+    // The open editor keeps what was typed; the formatted text shows up
+    // the next time the note is opened.
+    let rules = format_rules_for(state, &path);
+    let content = if rules.format_on_save && Markup::from_path(&path) == Some(Markup::Markdown) {
+        format::format(&content, &rules)
+    } else {
+        content
+    };
+    let previous = fs::read_to_string(&path)
+        .ok()
+        .and_then(|previous| open_note_content(state, &path, previous).ok());
+    let words = analytics::count_words(&content);
+    // In an encrypted workspace the title is taken from the ciphertext, so
+    // the file name stays opaque.
+    let content = seal_note_content(state, &path, content)?;
+    watcher.mark_self_write(&path);
+    fs::write(&path, &content)?;
+    watcher.refresh(&[&path]);
+    if let Some(previous) = previous {
+        record_writing(state, &path, analytics::count_words(&previous), words);
+    }
+    let _ = state.store.record_edit(&path.to_string_lossy(), now_secs());
+    Ok((path, content))
+}
+
+/// Formats a note with its workspace's rules and returns the new content. An
+/// open editor reloads through the external-change event.
+#[tauri::command]
+async fn format_note(app: tauri::AppHandle, path: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        if Markup::from_path(&note_path) != Some(Markup::Markdown) {
+            return Err(AppError::invalid_input("Only markdown notes can be formatted"));
+        }
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let formatted = format::format(&content, &format_rules_for(&state, &note_path));
+        if formatted == content {
+            return Ok(formatted);
+        }
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, seal_note_content(&state, &note_path, formatted.clone())?).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let _ = app.emit(
+            NOTE_EXTERNALLY_CHANGED,
+            NoteFsEvent {
+                workspace_id: workspace_id_for_path(&note_path),
+                path,
+                content: Some(formatted.clone()),
+            },
+        );
+        Ok(formatted)
+    })
+    .await
+}
+
+/// Transcribes the audio file at `asset_path` with whisper.cpp and appends
+/// the transcript to the note that links to it, where search picks it up.
+/// `model` is a ggml model file, by default `whisper/ggml-base.bin` in the
+/// app data dir. whisper.cpp itself is found through the settings. Returns
+/// the transcript.
+#[tauri::command]
+async fn transcribe(
+    app: tauri::AppHandle,
+    asset_path: String,
+    model: Option<String>,
+) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let asset = PathBuf::from(&asset_path);
+        state.access.check(&asset)?;
+        if !whisper::is_audio(&asset) {
+            return Err(AppError::invalid_input("Only audio files can be transcribed"));
+        }
+        let root = get_notes_root();
+        let workspace_dir = asset
+            .ancestors()
+            .find(|dir| dir.parent() == Some(root.as_path()))
+            .ok_or("Recording isn't in a workspace")?;
+        let relative = asset
+            .strip_prefix(workspace_dir)
+            .map_err(|e| e.to_string())?
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut notes: Vec<PathBuf> = fs::read_dir(workspace_dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| watcher::is_note_path(p))
+            .collect();
+        notes.sort();
+        let note_path = notes
+            .into_iter()
+            .find(|p| {
+                fs::read_to_string(p)
+                    .ok()
+                    .and_then(|content| open_note_content(&state, p, content).ok())
+                    .is_some_and(|content| whisper::references(&content, &relative))
+            })
+            .ok_or_else(|| format!("No note links to {}", relative))?;
+        check_note_unlocked(&state, &note_path)?;
+
+        let model = model
+            .map(PathBuf::from)
+            .unwrap_or_else(|| get_app_data_dir().join("whisper").join("ggml-base.bin"));
+        let transcript = whisper::transcribe(&tool_command(&state, Tool::Whisper)?, &model, &asset)?;
+
+        // Transcribing takes a while, so the note is only locked and re-read
+        // once the transcript is ready.
+        let _guard = state.write_lock.lock().unwrap();
+        let content = open_note_content(&state, &note_path, reader::read_whole(&note_path, note_size_limit(&state))?)?;
+        let file_name = asset.file_name().unwrap_or_default().to_string_lossy();
+        let updated = whisper::append_transcript(&content, &file_name, &transcript);
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, seal_note_content(&state, &note_path, updated.clone())?).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let _ = app.emit(
+            NOTE_EXTERNALLY_CHANGED,
+            NoteFsEvent {
+                workspace_id: workspace_id_for_path(&note_path),
+                path: note_path.to_string_lossy().to_string(),
+                content: Some(updated),
+            },
+        );
+        Ok(transcript)
+    })
+    .await
+}
+
+/// Replaces a note's content with a passphrase-encrypted copy. The note stays
+/// listed, under a generic title and file name, and can't be read or searched
+/// until it's decrypted. Returns the note's new path.
+#[tauri::command]
+async fn encrypt_note(app: tauri::AppHandle, path: String, passphrase: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        let content = reader::read_whole(&note_path, note_size_limit(&state))?;
+        if crypto::is_encrypted(&content) {
+            return Err(AppError::conflict("Note is already encrypted"));
+        }
+        let ciphertext = crypto::encrypt_with_passphrase(&content, &passphrase)?;
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, &ciphertext).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let new_path = rename_to_title(&app, &watcher, &note_path, &ciphertext)?;
+        Ok(new_path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Turns an encrypted note back into plain markdown. Returns the note's new
+/// path, named for its title again.
+#[tauri::command]
+async fn decrypt_note(app: tauri::AppHandle, path: String, passphrase: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let note_path = PathBuf::from(&path);
+        check_note_path(&state, &note_path)?;
+        check_note_unlocked(&state, &note_path)?;
+        let ciphertext = reader::read_whole(&note_path, note_size_limit(&state))?;
+        if !crypto::is_encrypted(&ciphertext) {
+            return Err(AppError::conflict("Note isn't encrypted"));
+        }
+        let content = crypto::decrypt_with_passphrase(&ciphertext, &passphrase)?;
+        watcher.mark_self_write(&note_path);
+        fs::write(&note_path, &content).map_err(|e| e.to_string())?;
+        watcher.refresh(&[&note_path]);
+        let new_path = rename_to_title(&app, &watcher, &note_path, &content)?;
+        Ok(new_path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Renames a note to match its current title. Used when saves are made with
+/// `rename: false`, e.g. once the note is closed.
+#[tauri::command]
+async fn sync_filename(app: tauri::AppHandle, path: String) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        let old_path = PathBuf::from(resolve_note_ref(&state, &path)?);
+        check_note_path(&state, &old_path)?;
+        let content = reader::read_whole(&old_path, note_size_limit(&state))?;
+        let new_path = rename_to_title(&app, &watcher, &old_path, &content)?;
+        Ok(new_path.to_string_lossy().to_string())
+    })
+    .await
+}
+
+/// Renames a numbered note to `{number}-{slug}.md` for the title in `content`.
+/// Returns the path the note ends up at.
+fn rename_to_title(
+    app: &tauri::AppHandle,
+    watcher: &WorkspaceWatcher,
+    old_path: &std::path::Path,
+    content: &str,
+) -> Result<PathBuf, String> {
+    let parent = old_path.parent().ok_or("Invalid path")?;
+    let old_name = old_path
+        .file_stem()
+        .ok_or("Invalid filename")?
+        .to_string_lossy()
+        .to_string();
+
+    let Some(number) = parse_file_number(&old_name) else {
+        return Ok(old_path.to_path_buf());
+    };
+
+    let title = note_title(old_path, content);
+    let transliterate = app.state::<AppState>().config.lock().unwrap().transliterate_file_names;
+    let slug = match slug::slugify_with(&title, transliterate) {
+        slug if title == "Untitled" || slug.is_empty() => untitled_slug(&note_names(parent), &old_name),
+        slug => slug,
+    };
+
+    let extension = Markup::from_path(old_path).unwrap_or(Markup::Markdown).extension();
+    let new_name = unique_name(&format!("{}-{}", number, slug), &old_name, |name| {
+        parent.join(format!("{}.{}", name, extension)).exists()
+    });
+    if new_name == old_name {
+        return Ok(old_path.to_path_buf());
+    }
+
+    let new_path = parent.join(format!("{}.{}", new_name, extension));
+    watcher.mark_self_write(&new_path);
+    fs::rename(old_path, &new_path).map_err(|e| e.to_string())?;
+    watcher.refresh(&[old_path, &new_path]);
+    move_note_metadata(&app.state::<AppState>(), old_path, &new_path);
+    emit_note_event(app, NOTE_RENAMED, Some(old_path), Some(new_path.as_path()));
+    Ok(new_path)
+}
+
+/// File names, without `.md`, of the notes in `dir`.
+fn note_names(dir: &std::path::Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| Markup::from_path(p).is_some())
+        .filter_map(|p| Some(p.file_stem()?.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// The slug for an untitled note named `old_name` among notes `names`:
+/// `untitled`, or the first of `untitled-2`, `untitled-3`… no other note
+/// uses, so untitled notes can be told apart. A note that already has one
+/// keeps it.
+fn untitled_slug(names: &[String], old_name: &str) -> String {
+    let slug_of = |name: &str| parse_file_number(name).and(name.split_once('-')).map(|(_, slug)| slug.to_string());
+    let is_untitled = |slug: &String| {
+        slug == "untitled" || slug.strip_prefix("untitled-").is_some_and(|n| n.parse::<u32>().is_ok())
+    };
+    if let Some(own) = slug_of(old_name).filter(is_untitled) {
+        return own;
+    }
+    let taken: std::collections::HashSet<String> = names
+        .iter()
+        .filter(|name| *name != old_name)
+        .filter_map(|name| slug_of(name.as_str()))
+        .filter(is_untitled)
+        .collect();
+    unique_name("untitled", "", |slug| taken.contains(slug))
+}
+
+/// `name`, with `-2`, `-3`… appended while it's `taken` by another note. A
+/// note already under one of those names keeps it.
+fn unique_name(name: &str, old_name: &str, taken: impl Fn(&str) -> bool) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => name.to_string(),
+            n => format!("{}-{}", name, n),
+        })
+        .find(|name| name == old_name || !taken(name))
+        .expect("unbounded range")
+}
+
+/// Creates a note in the active workspace, empty or from the workspace's
+/// template `template_id` with its placeholders filled in. `answers` holds
+/// what the user entered for the template's prompts, by question.
+#[tauri::command]
+fn create_note(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    watcher: tauri::State<WorkspaceWatcher>,
+    template_id: Option<String>,
+    answers: Option<HashMap<String, String>>,
+) -> Result<String, AppError> {
+    let content = match template_id {
+        Some(id) => {
+            let template = read_template(&state, &id)?;
+            templates::expand(&template, chrono::Local::now().naive_local(), &answers.unwrap_or_default())
+        }
+        None => "\n".to_string(),
+    };
+    let _guard = state.write_lock.lock().unwrap();
+    let path = add_note(&app, &state, &watcher, &content)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// The questions template `template_id` asks with `{{prompt:…}}`, to be
+/// answered before `create_note` fills it in.
+#[tauri::command]
+fn get_template_prompts(state: tauri::State<AppState>, template_id: String) -> Result<Vec<String>, AppError> {
+    Ok(templates::prompts(&read_template(&state, &template_id)?))
+}
+
+fn read_template(state: &AppState, id: &str) -> Result<String, String> {
+    let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+    let path = templates::path(&get_workspace_dir(&workspace_id), id)?;
+    fs::read_to_string(&path).map_err(|e| format!("Couldn't read template {}: {}", id, e))
+}
+
+/// Templates `create_note` can start from, kept as markdown files in the
+/// workspace's `templates` folder.
+#[tauri::command]
+fn list_templates(state: tauri::State<AppState>, workspace_id: Option<String>) -> Result<Vec<Template>, AppError> {
+    let config = state.config.lock().unwrap();
+    let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+    if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+        return Err(AppError::not_found("Workspace not found"));
+    }
+    Ok(templates::list(&get_workspace_dir(&workspace_id)))
+}
+
+/// Snippets that can be inserted by abbreviation, shared ones and those of
+/// the workspace.
+#[tauri::command]
+fn list_snippets(state: tauri::State<AppState>, workspace_id: Option<String>) -> Result<Vec<snippets::Snippet>, AppError> {
+    let config = state.config.lock().unwrap();
+    let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+    if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+        return Err(AppError::not_found("Workspace not found"));
+    }
+    Ok(snippets::list(&get_app_data_dir(), &get_workspace_dir(&workspace_id)))
+}
+
+/// The text snippet `key` inserts, with its placeholders filled in and where
+/// the cursor goes.
+#[tauri::command]
+fn expand_snippet(
+    state: tauri::State<AppState>,
+    key: String,
+    workspace_id: Option<String>,
+) -> Result<snippets::Expansion, AppError> {
+    let snippet = list_snippets(state, workspace_id)?
+        .into_iter()
+        .find(|s| s.key == key)
+        .ok_or_else(|| AppError::not_found(format!("No snippet {}", key)))?;
+    Ok(snippets::expand(&snippet.text, chrono::Local::now().naive_local()))
+}
+
+/// Export themes for the workspace, shared ones and its own, with any
+/// problems that keep them from being used.
+#[tauri::command]
+fn list_export_themes(state: tauri::State<AppState>, workspace_id: Option<String>) -> Result<Vec<themes::ExportTheme>, AppError> {
+    let config = state.config.lock().unwrap();
+    let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+    if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+        return Err(AppError::not_found("Workspace not found"));
+    }
+    Ok(themes::list(&get_app_data_dir(), &get_workspace_dir(&workspace_id)))
+}
+
+/// CSS of the export theme chosen for `workspace_id`, if it has a usable
+/// one.
+fn export_theme_css(state: &AppState, workspace_id: &str) -> Option<String> {
+    let name = state
+        .config
+        .lock()
+        .unwrap()
+        .workspaces
+        .iter()
+        .find(|w| w.id == workspace_id)?
+        .export_theme
+        .clone()?;
+    let theme = themes::list(&get_app_data_dir(), &get_workspace_dir(workspace_id))
+        .into_iter()
+        .find(|t| t.name == name);
+    match theme {
+        Some(themes::ExportTheme { error: None, css, .. }) => Some(css),
+        Some(themes::ExportTheme { error: Some(error), .. }) => {
+            tracing::warn!("Export theme {} skipped: {}", name, error);
+            None
+        }
+        None => {
+            tracing::warn!("Export theme {} not found", name);
+            None
+        }
+    }
+}
+
+/// Entries of the bibliography in workspace folder `dir`, if it has one.
+fn workspace_bibliography(dir: &std::path::Path) -> Vec<bibtex::Entry> {
+    fs::read_to_string(dir.join(bibtex::BIBLIOGRAPHY_FILE))
+        .map(|text| bibtex::parse(&text))
+        .unwrap_or_default()
+}
+
+/// Bibliography entries matching `query`, for completing `[@key]`
+/// citations.
+#[tauri::command]
+fn cite_lookup(
+    state: tauri::State<AppState>,
+    query: String,
+    workspace_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<bibtex::Entry>, AppError> {
+    let config = state.config.lock().unwrap();
+    let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+    if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+        return Err(AppError::not_found("Workspace not found"));
+    }
+    let entries = workspace_bibliography(&get_workspace_dir(&workspace_id));
+    Ok(bibtex::lookup(&entries, &query, limit.unwrap_or(20)).into_iter().cloned().collect())
+}
+
+/// Creates a note holding `content` in the active workspace, named for its
+/// title.
+fn add_note(app: &tauri::AppHandle, state: &AppState, watcher: &WorkspaceWatcher, content: &str) -> Result<PathBuf, String> {
+    let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
+    add_note_to(app, state, watcher, &workspace_id, content)
+}
+
+/// Like `add_note`, for any workspace. Notes for one that isn't open are
+/// numbered from its folder instead of the watcher's index.
+fn add_note_to(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    watcher: &WorkspaceWatcher,
+    workspace_id: &str,
+    content: &str,
+) -> Result<PathBuf, String> {
+    let notes_dir = get_workspace_dir(workspace_id);
+    let watched = watcher.with_index(|index| index.dir() == notes_dir);
+    if watched {
+        check_workspace_present(watcher)?;
+    }
+
+    if !notes_dir.exists() {
+        fs::create_dir_all(&notes_dir).map_err(|e| e.to_string())?;
+        if watched {
+            watch_workspace(app, workspace_id)?;
+        }
+    }
+
+    let _numbering = state.numbering.lock().unwrap();
+    let mut number = if watched {
+        watcher.with_index(|index| index.next_number())
+    } else {
+        get_next_number(&notes_dir)
+    };
+    let slug = untitled_slug(&note_names(&notes_dir), "");
+    let sealed = seal_note_content(state, &notes_dir.join(format!("{}-{}.md", number, slug)), content.to_string())?;
+    // The index can lag behind a file another process just created, so the
+    // number is only taken once the file is.
+    let path = loop {
+        let path = notes_dir.join(format!("{}-{}.md", number, slug));
+        watcher.mark_self_write(&path);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(sealed.as_bytes()).map_err(|e| e.to_string())?;
+                break path;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => number += 1,
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+    watcher.refresh(&[&path]);
+    emit_note_event(app, NOTE_CREATED, None, Some(path.as_path()));
+    rename_to_title(app, watcher, &path, &sealed)
+}
+
+/// Adds Simplenote's `notes.json` export to a workspace, the active one by
+/// default, keeping modification dates and pinning the notes pinned there.
+/// Returns how many notes were added.
+#[tauri::command]
+async fn import_simplenote(app: tauri::AppHandle, path: String, workspace_id: Option<String>) -> Result<usize, AppError> {
+    blocking(move || {
+        app.state::<AppState>().access.check(std::path::Path::new(&path))?;
+        let workspace_id = import_workspace(&app, workspace_id)?;
+        let json = fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+        let notes = import::simplenote(&json)?;
+        Ok(add_imported_notes(&app, &workspace_id, notes)?)
+    })
+    .await
+}
+
+/// Adds the notes in a Google Takeout `Keep` folder to a workspace, the
+/// active one by default. Checklists become tasks, labels tags, and attached
+/// files are copied into the assets folder. Returns how many notes were
+/// added.
+#[tauri::command]
+async fn import_keep(app: tauri::AppHandle, path: String, workspace_id: Option<String>) -> Result<usize, AppError> {
+    blocking(move || {
+        app.state::<AppState>().access.check(std::path::Path::new(&path))?;
+        let workspace_id = import_workspace(&app, workspace_id)?;
+        let assets_dir = get_workspace_dir(&workspace_id).join(ASSETS_DIR);
+        let notes = import::keep(std::path::Path::new(&path), |file, name| copy_asset(&assets_dir, file, name))?;
+        Ok(add_imported_notes(&app, &workspace_id, notes)?)
+    })
+    .await
+}
+
+/// Adds a folder exported from Joplin as RAW to `workspace_id`, or with
+/// each notebook in a workspace of its own, created when needed. Resources
+/// are copied into the assets folder and links between notes become
+/// wikilinks. Returns how many notes were added.
+#[tauri::command]
+async fn import_joplin(app: tauri::AppHandle, path: String, workspace_id: Option<String>) -> Result<usize, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        state.access.check(std::path::Path::new(&path))?;
+        let fallback = import_workspace(&app, workspace_id.clone())?;
+        let workspace_for = |notebook: Option<&str>| match (&workspace_id, notebook.map(slugify)) {
+            (None, Some(id)) if !id.is_empty() => id,
+            _ => fallback.clone(),
+        };
+        let notes = import::joplin(std::path::Path::new(&path), |notebook, file, name| {
+            copy_asset(&get_workspace_dir(&workspace_for(notebook)).join(ASSETS_DIR), file, name)
+        })?;
+
+        let mut by_workspace: Vec<(String, Vec<import::ImportedNote>)> = Vec::new();
+        for note in notes {
+            let target = workspace_for(note.notebook.as_deref());
+            if let Some(notebook) = note.notebook.filter(|_| target != fallback) {
+                let mut config = state.config.lock().unwrap();
+                if !config.workspaces.iter().any(|w| w.id == target) {
+                    add_workspace(&mut config, notebook)?;
+                    save_config(&config)?;
+                }
+            }
+            match by_workspace.iter_mut().find(|(id, _)| *id == target) {
+                Some((_, notes)) => notes.push(note.note),
+                None => by_workspace.push((target, vec![note.note])),
+            }
+        }
+        let mut added = 0;
+        for (workspace_id, notes) in by_workspace {
+            added += add_imported_notes(&app, &workspace_id, notes)?;
+        }
+        Ok(added)
+    })
+    .await
+}
+
+/// The workspace to import into, the active one by default.
+fn import_workspace(app: &tauri::AppHandle, workspace_id: Option<String>) -> Result<String, String> {
+    let state = app.state::<AppState>();
+    let config = state.config.lock().unwrap();
+    let workspace_id = workspace_id.unwrap_or_else(|| config.active_workspace_id.clone());
+    if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+        return Err("Workspace not found".to_string());
+    }
+    Ok(workspace_id)
+}
+
+/// Copies `file` into `assets_dir` as `name`, numbered if that's taken, and
+/// returns the path notes link it by.
+fn copy_asset(assets_dir: &std::path::Path, file: &std::path::Path, name: &str) -> Result<String, String> {
+    let name = std::path::Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    fs::create_dir_all(assets_dir).map_err(|e| e.to_string())?;
+    let file_name = (1..)
+        .map(|n| match n {
+            1 => format!("{}{}", stem, extension),
+            n => format!("{}-{}{}", stem, n, extension),
+        })
+        .find(|name| !assets_dir.join(name).exists())
+        .expect("unbounded range");
+    fs::copy(file, assets_dir.join(&file_name)).map_err(|e| format!("Couldn't copy {}: {}", file.display(), e))?;
+    Ok(format!("{}/{}", ASSETS_DIR, file_name))
+}
+
+/// Creates `notes` in workspace `workspace_id`, dated as in the app they
+/// came from.
+fn add_imported_notes(app: &tauri::AppHandle, workspace_id: &str, notes: Vec<import::ImportedNote>) -> Result<usize, String> {
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let mut pinned = Vec::new();
+    for note in &notes {
+        let path = add_note_to(app, &state, &watcher, workspace_id, &note.content)?;
+        if let Some(modified) = note.modified {
+            watcher.mark_self_write(&path);
+            if let Err(e) = File::options().write(true).open(&path).and_then(|f| f.set_modified(modified)) {
+                tracing::warn!("Couldn't date imported note {}: {}", path.display(), e);
+            }
+            watcher.refresh(&[&path]);
+        }
+        if note.pinned {
+            pinned.push(path.to_string_lossy().to_string());
+        }
+    }
+    state.store.add_to_list(store::PINNED_LIST, &pinned)?;
+    Ok(notes.len())
+}
+
+/// Pinned notes, in the order they were pinned.
+#[tauri::command]
+fn get_pinned_notes(state: tauri::State<AppState>) -> Result<Vec<String>, AppError> {
+    Ok(state.store.list_paths(store::PINNED_LIST)?)
+}
+
+/// Notes that share a title, or titles that only differ in case and
+/// punctuation.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct DuplicateTitles {
+    pub title: String,
+    pub notes: Vec<NoteEntry>,
+}
+
+/// Groups of notes in a workspace whose titles give the same slug, most
+/// duplicated first, so near-duplicates can be found and merged.
+#[tauri::command]
+async fn find_duplicate_titles(app: tauri::AppHandle, workspace_id: String) -> Result<Vec<DuplicateTitles>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let index = {
+            let config = state.config.lock().unwrap();
+            if !config.workspaces.iter().any(|w| w.id == workspace_id) {
+                return Err(AppError::not_found("Workspace not found"));
+            }
+            workspace_index(&config, &workspace_id)
+        };
+        let mut notes: Vec<NoteEntry> = index
+            .notes()
+            .map(|note| NoteEntry {
+                name: note.name.clone(),
+                path: note.path.to_string_lossy().to_string(),
+                modified: note.stamp.modified_secs(),
+                created: note.created,
+                id: None,
+                title: read_header_from_file(&note.path).title,
+            })
+            .collect();
+        reveal_titles(
+            &state,
+            &workspace_id,
+            notes.par_iter_mut().map(|n| (n.path.as_str(), &mut n.title)),
+        );
+        Ok(duplicate_titles(notes))
+    })
+    .await
+}
+
+fn duplicate_titles(notes: Vec<NoteEntry>) -> Vec<DuplicateTitles> {
+    let mut groups: HashMap<String, Vec<NoteEntry>> = HashMap::new();
+    for note in notes {
+        if note.title == "Untitled" || note.title == crypto::ENCRYPTED_TITLE {
+            continue;
+        }
+        let key = slugify(&note.title);
+        if !key.is_empty() {
+            groups.entry(key).or_default().push(note);
+        }
+    }
+    let mut duplicates: Vec<DuplicateTitles> = groups
+        .into_values()
+        .filter(|notes| notes.len() > 1)
+        .map(|mut notes| {
+            notes.sort_by(|a, b| a.path.cmp(&b.path));
+            DuplicateTitles {
+                title: notes[0].title.clone(),
+                notes,
+            }
+        })
+        .collect();
+    duplicates.sort_by(|a, b| b.notes.len().cmp(&a.notes.len()).then_with(|| a.title.cmp(&b.title)));
+    duplicates
+}
+
+/// Moves a note to the trash, or deletes it for good with `permanent`.
+#[tauri::command]
+fn delete_note(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    watcher: tauri::State<WorkspaceWatcher>,
+    path: String,
+    permanent: Option<bool>,
+) -> Result<(), AppError> {
+    let path = PathBuf::from(resolve_note_ref(&state, &path)?);
+    state.access.check(&path).map_err(AppError::permission_denied)?;
+    check_note_unlocked(&state, &path)?;
+    watcher.mark_self_write(&path);
+    discard(&path, permanent.unwrap_or(false))?;
+    watcher.refresh(&[&path]);
+    drop_note_metadata(&state, &path);
+    emit_note_event(&app, NOTE_DELETED, Some(path.as_path()), None);
+    Ok(())
+}
+
+/// What `rename_note` does when a note already has the new name.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OnCollision {
+    #[default]
+    Fail,
+    /// Replace the other note.
+    Overwrite,
+    /// Append `-2`, `-3`… to the new name.
+    Suffix,
+}
+
+/// What a batch command did with one note.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct BatchResult {
+    pub path: String,
+    /// Where the note is now, after a move.
+    pub new_path: Option<String>,
+    /// Whether the note was changed; tagging a note that has the tag doesn't.
+    pub changed: bool,
+    pub error: Option<String>,
+}
+
+impl BatchResult {
+    fn done(path: &str, new_path: Option<&std::path::Path>, changed: bool) -> Self {
+        BatchResult {
+            path: path.to_string(),
+            new_path: new_path.map(|p| p.to_string_lossy().to_string()),
+            changed,
+            error: None,
+        }
+    }
+
+    fn failed(path: &str, error: String) -> Self {
+        BatchResult {
+            path: path.to_string(),
+            new_path: None,
+            changed: false,
+            error: Some(error),
+        }
+    }
+}
+
+/// Checks every note of a batch before any is touched.
+fn check_batch(state: &AppState, paths: &[String]) -> Result<(), Vec<BatchResult>> {
+    let checks = paths
+        .iter()
+        .map(|path| {
+            let path = std::path::Path::new(path);
+            check_note_path(state, path)?;
+            check_note_unlocked(state, path)?;
+            if !path.is_file() {
+                return Err("Note not found".to_string());
+            }
+            Ok(())
+        })
+        .collect();
+    whole_batch(paths, checks).map(|_| ())
+}
+
+/// What preparing each note of a batch gave, if all of them could be
+/// prepared. Otherwise each note's result, so a batch goes through whole or
+/// not at all.
+fn whole_batch<T>(paths: &[String], items: Vec<Result<T, String>>) -> Result<Vec<T>, Vec<BatchResult>> {
+    if items.iter().all(|item| item.is_ok()) {
+        return Ok(items.into_iter().flatten().collect());
+    }
+    Err(paths
+        .iter()
+        .zip(items)
+        .map(|(path, item)| match item {
+            Ok(_) => BatchResult::failed(path, "Skipped because other notes can't be changed".to_string()),
+            Err(e) => BatchResult::failed(path, e),
+        })
+        .collect())
+}
+
+/// Moves several notes to the trash, or deletes them for good with
+/// `permanent`.
+#[tauri::command]
+async fn batch_delete(app: tauri::AppHandle, paths: Vec<String>, permanent: Option<bool>) -> Result<Vec<BatchResult>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        if let Err(results) = check_batch(&state, &paths) {
+            return Ok(results);
+        }
+        Ok(paths
+            .iter()
+            .map(|path| {
+                let note_path = PathBuf::from(path);
+                watcher.mark_self_write(&note_path);
+                if let Err(e) = discard(&note_path, permanent.unwrap_or(false)) {
+                    return BatchResult::failed(path, e);
+                }
+                watcher.refresh(&[&note_path]);
+                drop_note_metadata(&state, &note_path);
+                emit_note_event(&app, NOTE_DELETED, Some(note_path.as_path()), None);
+                BatchResult::done(path, None, true)
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Moves several notes into another workspace. Numbered notes are numbered
+/// after the ones already there, and notes are re-encrypted or decrypted to
+/// match the workspace they land in.
+#[tauri::command]
+async fn batch_move(app: tauri::AppHandle, paths: Vec<String>, workspace_id: String) -> Result<Vec<BatchResult>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let _guard = state.write_lock.lock().unwrap();
+        if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == workspace_id) {
+            return Err(AppError::not_found("Workspace not found"));
+        }
+        if let Err(results) = check_batch(&state, &paths) {
+            return Ok(results);
+        }
+        let target_dir = get_workspace_dir(&workspace_id);
+        fs::create_dir_all(&target_dir).map_err(|e| e.to_string())?;
+        // Encrypted notes have to be readable, and writable where they go,
+        // so contents are sealed as if already in the target workspace.
+        let sealed = paths
+            .iter()
+            .map(|path| {
+                let path = std::path::Path::new(path);
+                let content = open_note_content(&state, path, reader::read_whole(path, note_size_limit(&state))?)?;
+                seal_note_content(&state, &target_dir.join(path.file_name().unwrap_or_default()), content)
+            })
+            .collect();
+        let contents = match whole_batch(&paths, sealed) {
+            Ok(contents) => contents,
+            Err(results) => return Ok(results),
+        };
+
+        let mut next_number = get_next_number(&target_dir);
+        Ok(paths
+            .iter()
+            .zip(contents)
+            .map(|(path, content)| {
+                let old_path = PathBuf::from(path);
+                if old_path.parent() == Some(target_dir.as_path()) {
+                    return BatchResult::done(path, Some(&old_path), false);
+                }
+                let stem = old_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+                let name = match parse_file_number(&stem) {
+                    Some(_) => {
+                        let slug = stem.split_once('-').map_or("untitled", |(_, slug)| slug);
+                        next_number += 1;
+                        format!("{}-{}", next_number - 1, slug)
+                    }
+                    None => stem.clone(),
+                };
+                let extension = Markup::from_path(&old_path).unwrap_or(Markup::Markdown).extension();
+                let name = unique_name(&name, "", |name| target_dir.join(format!("{}.{}", name, extension)).exists());
+                let new_path = target_dir.join(format!("{}.{}", name, extension));
+                watcher.mark_self_write(&old_path);
+                watcher.mark_self_write(&new_path);
+                let moved = fs::write(&new_path, content)
+                    .and_then(|_| fs::remove_file(&old_path))
+                    .map_err(|e| e.to_string());
+                if let Err(e) = moved {
+                    return BatchResult::failed(path, e);
+                }
+                watcher.refresh(&[&old_path, &new_path]);
+                move_note_metadata(&state, &old_path, &new_path);
+                emit_note_event(&app, NOTE_DELETED, Some(old_path.as_path()), None);
+                emit_note_event(&app, NOTE_CREATED, None, Some(new_path.as_path()));
+                BatchResult::done(path, Some(&new_path), true)
+            })
+            .collect())
+    })
+    .await
+}
+
+/// Adds `tag` to several notes, in their frontmatter `tags` when they have
+/// one and as an inline `#tag` otherwise.
+#[tauri::command]
+async fn batch_tag(app: tauri::AppHandle, paths: Vec<String>, tag: String) -> Result<Vec<BatchResult>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let tag = tag.trim().trim_start_matches('#').to_string();
+        if !tags::is_tag(&tag) {
+            return Err(AppError::invalid_input(format!("Invalid tag {}", tag)));
+        }
+        let _guard = state.write_lock.lock().unwrap();
+        if let Err(results) = check_batch(&state, &paths) {
+            return Ok(results);
+        }
+        let opened = paths
+            .iter()
+            .map(|path| {
+                let path = std::path::Path::new(path);
+                open_note_content(&state, path, reader::read_whole(path, note_size_limit(&state))?)
+            })
+            .collect();
+        let contents = match whole_batch(&paths, opened) {
+            Ok(contents) => contents,
+            Err(results) => return Ok(results),
+        };
+        Ok(paths
+            .iter()
+            .zip(contents)
+            .map(|(path, content)| {
+                let note_path = PathBuf::from(path);
+                let Some(tagged) = tags::add_tag(&content, &tag) else {
+                    return BatchResult::done(path, None, false);
+                };
+                let written = seal_note_content(&state, &note_path, tagged.clone()).and_then(|sealed| {
+                    watcher.mark_self_write(&note_path);
+                    fs::write(&note_path, sealed).map_err(|e| e.to_string())
+                });
+                if let Err(e) = written {
+                    return BatchResult::failed(path, e);
+                }
+                watcher.refresh(&[&note_path]);
+                let _ = app.emit(
+                    NOTE_EXTERNALLY_CHANGED,
+                    NoteFsEvent {
+                        workspace_id: workspace_id_for_path(&note_path),
+                        path: path.clone(),
+                        content: Some(tagged),
+                    },
+                );
+                BatchResult::done(path, None, true)
+            })
+            .collect())
+    })
+    .await
+}
+
+/// The tags used in a workspace, the active one by default, as a tree of
+/// nested tags. Refreshes the workspace's notes in the tag index.
+#[tauri::command]
+async fn list_tags(app: tauri::AppHandle, workspace_id: Option<String>) -> Result<Vec<tags::TagNode>, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let workspace_id = workspace_id.unwrap_or_else(|| state.config.lock().unwrap().active_workspace_id.clone());
+        let dir = get_workspace_dir(&workspace_id);
+        let notes = workspace_notes(&state, &watcher, &dir);
+        let tagged: Vec<(String, Vec<String>)> = notes
+            .par_iter()
+            .filter(|note| note.stamp.size <= reader::MAX_READ_BYTES)
+            .filter_map(|note| {
+                let content = fs::read_to_string(&note.path).ok()?;
+                let content = open_note_content(&state, &note.path, content).ok()?;
+                Some((note.path.to_string_lossy().to_string(), tags::parse_tags(&content)))
+            })
+            .collect();
+        state.store.set_tags(&tagged)?;
+        Ok(tags::tag_tree(tagged.iter().map(|(_, tags)| tags.as_slice())))
+    })
+    .await
+}
+
+/// Renames tag `old` to `new` in every note of a workspace, the active one
+/// by default, and in the tag index. Every note is read first, so nothing
+/// changes if one can't be. Returns how many notes changed.
+#[tauri::command]
+async fn rename_tag(app: tauri::AppHandle, old: String, new: String, workspace_id: Option<String>) -> Result<usize, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        let old = old.trim().trim_start_matches('#').to_string();
+        let new = new.trim().trim_start_matches('#').to_string();
+        if !tags::is_tag(&old) || !tags::is_tag(&new) {
+            return Err(AppError::invalid_input(format!("Invalid tag {}", if tags::is_tag(&old) { &new } else { &old })));
+        }
+        let workspace_id = workspace_id.unwrap_or_else(|| state.config.lock().unwrap().active_workspace_id.clone());
+        let dir = get_workspace_dir(&workspace_id);
+        let _guard = state.write_lock.lock().unwrap();
+
+        let mut renamed = Vec::new();
+        let notes = workspace_notes(&state, &watcher, &dir);
+        for note in notes.into_iter().filter(|note| note.stamp.size <= reader::MAX_READ_BYTES) {
+            let content = open_note_content(&state, &note.path, reader::read_whole(&note.path, note_size_limit(&state))?)?;
+            if let Some(content) = tags::rename_tag(&content, &old, &new) {
+                check_note_unlocked(&state, &note.path)?;
+                let sealed = seal_note_content(&state, &note.path, content.clone())?;
+                renamed.push((note.path, content, sealed));
+            }
+        }
+
+        for (path, content, sealed) in &renamed {
+            watcher.mark_self_write(path);
+            fs::write(path, sealed).map_err(|e| e.to_string())?;
+            watcher.refresh(&[path]);
+            let _ = app.emit(
+                NOTE_EXTERNALLY_CHANGED,
+                NoteFsEvent {
+                    workspace_id: workspace_id.clone(),
+                    path: path.to_string_lossy().to_string(),
+                    content: Some(content.clone()),
+                },
+            );
+        }
+        let index: Vec<(String, Vec<String>)> = renamed
+            .iter()
+            .map(|(path, content, _)| (path.to_string_lossy().to_string(), tags::parse_tags(content)))
+            .collect();
+        state.store.set_tags(&index)?;
+        Ok(renamed.len())
+    })
+    .await
+}
+
+/// Untitled notes with nothing in them, left for longer than the grace
+/// period, in one workspace or all of them. These are what
+/// `clean_up_empty_notes` trashes.
+#[tauri::command]
+async fn list_empty_notes(app: tauri::AppHandle, workspace_id: Option<String>) -> Result<Vec<cleanup::EmptyNote>, AppError> {
+    blocking(move || Ok(empty_notes(&app.state::<AppState>(), workspace_id.as_deref())?)).await
+}
+
+/// Moves the notes `list_empty_notes` finds to the trash and returns their
+/// paths.
+#[tauri::command]
+async fn clean_up_empty_notes(app: tauri::AppHandle, workspace_id: Option<String>) -> Result<Vec<String>, AppError> {
+    blocking(move || Ok(trash_empty_notes(&app, workspace_id.as_deref())?)).await
+}
+
+fn empty_notes(state: &AppState, only: Option<&str>) -> Result<Vec<cleanup::EmptyNote>, String> {
+    let workspaces: Vec<(String, IgnoreRules)> = {
+        let config = state.config.lock().unwrap();
+        if let Some(id) = only {
+            if !config.workspaces.iter().any(|w| w.id == id) {
+                return Err("Workspace not found".to_string());
+            }
+        }
+        config
+            .workspaces
+            .iter()
+            .filter(|w| only.is_none_or(|id| w.id == id))
+            .map(|w| (w.id.clone(), get_ignore_rules(&config, &w.id)))
+            .collect()
+    };
+    let now = now_secs();
+    let mut empty = Vec::new();
+    for (workspace_id, ignore) in workspaces {
+        let index = WorkspaceIndex::build(&get_workspace_dir(&workspace_id), ignore);
+        for note in index.notes() {
+            let stem = note.path.file_stem().unwrap_or_default().to_string_lossy();
+            if !cleanup::is_untitled(&stem) || note.stamp.size > cleanup::MAX_EMPTY_BYTES {
+                continue;
+            }
+            let path = note.path.to_string_lossy().to_string();
+            if state.store.is_locked(&path)? {
+                continue;
+            }
+            // Notes of a locked encrypted workspace can't be read, so stay.
+            let Some(content) = fs::read_to_string(&note.path)
+                .ok()
+                .and_then(|raw| open_note_content(state, &note.path, raw).ok())
+            else {
+                continue;
+            };
+            let modified = note.stamp.modified_secs();
+            if cleanup::is_abandoned(&stem, &content, modified, now) {
+                empty.push(cleanup::EmptyNote {
+                    path,
+                    workspace_id: workspace_id.clone(),
+                    modified,
+                });
+            }
+        }
+    }
+    empty.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(empty)
+}
+
+fn trash_empty_notes(app: &tauri::AppHandle, only: Option<&str>) -> Result<Vec<String>, String> {
+    let state = app.state::<AppState>();
+    let watcher = app.state::<WorkspaceWatcher>();
+    let _guard = state.write_lock.lock().unwrap();
+    let mut trashed = Vec::new();
+    for note in empty_notes(&state, only)? {
+        let path = PathBuf::from(&note.path);
+        watcher.mark_self_write(&path);
+        if let Err(e) = discard(&path, false) {
+            tracing::warn!("Cleaning up {} failed: {}", note.path, e);
+            continue;
+        }
+        watcher.refresh(&[&path]);
+        drop_note_metadata(&state, &path);
+        emit_note_event(app, NOTE_DELETED, Some(path.as_path()), None);
+        trashed.push(note.path);
+    }
+    if !trashed.is_empty() {
+        tracing::info!("Moved {} empty untitled notes to the trash", trashed.len());
+    }
+    Ok(trashed)
+}
+
+/// Moves a file or folder to the system trash, where Finder, Explorer or the
+/// desktop's file manager can restore it, unless it's `permanent`.
+fn discard(path: &std::path::Path, permanent: bool) -> Result<(), String> {
+    if !permanent {
+        return trash::delete(path).map_err(|e| format!("Couldn't move {} to the trash: {}", path.display(), e));
+    }
+    if path.is_dir() {
+        fs::remove_dir_all(path).map_err(|e| e.to_string())
+    } else {
+        fs::remove_file(path).map_err(|e| e.to_string())
+    }
+}
+
+/// Renames a note to `new_name`, slugified and kept in the same workspace
+/// folder. Numbered notes keep their number.
+#[tauri::command]
+fn rename_note(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    watcher: tauri::State<WorkspaceWatcher>,
+    old_path: String,
+    new_name: String,
+    on_collision: Option<OnCollision>,
+) -> Result<String, AppError> {
+    let old_path = PathBuf::from(resolve_note_ref(&state, &old_path)?);
+    let new_path = rename_note_file(&state, &watcher, &old_path, &new_name, on_collision)?;
+    if new_path != old_path {
+        emit_note_event(&app, NOTE_RENAMED, Some(old_path.as_path()), Some(new_path.as_path()));
+    }
+    Ok(new_path.to_string_lossy().to_string())
+}
+
+/// Renames the note at `old_path` for `rename_note`, keeping its extension,
+/// and returns where it ends up.
+fn rename_note_file(
+    state: &AppState,
+    watcher: &WorkspaceWatcher,
+    old_path: &std::path::Path,
+    new_name: &str,
+    on_collision: Option<OnCollision>,
+) -> Result<PathBuf, AppError> {
+    check_note_path(state, old_path)?;
+    check_note_unlocked(state, old_path)?;
+    let parent = old_path.parent().ok_or("Invalid path")?;
+    let old_name = old_path.file_stem().ok_or("Invalid filename")?.to_string_lossy().to_string();
+    let extension = Markup::from_path(old_path).unwrap_or(Markup::Markdown).extension();
+    let transliterate = state.config.lock().unwrap().transliterate_file_names;
+    let mut name = renamed_file_name(&old_name, new_name, transliterate).map_err(AppError::invalid_input)?;
+    let taken = |name: &str| {
+        let path = parent.join(format!("{}.{}", name, extension));
+        path != old_path && path.exists()
+    };
+    if taken(&name) {
+        match on_collision.unwrap_or_default() {
+            OnCollision::Fail => return Err(AppError::conflict("A note with this name already exists")),
+            OnCollision::Overwrite => check_note_unlocked(state, &parent.join(format!("{}.{}", name, extension)))?,
+            OnCollision::Suffix => name = unique_name(&name, &old_name, taken),
+        }
+    }
+    let new_path = parent.join(format!("{}.{}", name, extension));
+    state.access.check(&new_path).map_err(AppError::permission_denied)?;
+    if new_path == old_path {
+        return Ok(new_path);
+    }
+    if new_path.exists() {
+        drop_note_metadata(state, &new_path);
+    }
+
+    watcher.mark_self_write(old_path);
+    watcher.mark_self_write(&new_path);
+    fs::rename(old_path, &new_path)?;
+    watcher.refresh(&[old_path, &new_path]);
+    move_note_metadata(state, old_path, &new_path);
+    Ok(new_path)
+}
+
+/// The file name, without its extension, that `requested` gives a note now
+/// named `old_name`: slugified so it can't leave the folder or trip up other
+/// file systems, and numbered like before.
+fn renamed_file_name(old_name: &str, requested: &str, transliterate: bool) -> Result<String, String> {
+    let requested = requested.trim();
+    let requested = Markup::ALL
+        .iter()
+        .find_map(|markup| requested.strip_suffix(&format!(".{}", markup.extension())))
+        .unwrap_or(requested);
+    let slug = slug::slugify_with(requested, transliterate);
+    let name = match parse_file_number(old_name) {
+        Some(number) => {
+            let rest = slug.strip_prefix(&format!("{}-", number)).unwrap_or(&slug);
+            (!rest.is_empty()).then(|| format!("{}-{}", number, rest))
+        }
+        None => (!slug.is_empty()).then_some(slug),
+    };
+    name.ok_or_else(|| "Invalid note name".to_string())
+}
+
+/// Makes a note read-only: saves, edits, renames and deletes are refused
+/// until it's unlocked.
+#[tauri::command]
+fn lock_note(state: tauri::State<AppState>, path: String) -> Result<(), AppError> {
+    check_note_path(&state, std::path::Path::new(&path))?;
+    Ok(state.store.set_locked(&path, true)?)
+}
+
+#[tauri::command]
+fn unlock_note(state: tauri::State<AppState>, path: String) -> Result<(), AppError> {
+    check_note_path(&state, std::path::Path::new(&path))?;
+    Ok(state.store.set_locked(&path, false)?)
+}
+
+#[tauri::command]
+fn list_locked_notes(state: tauri::State<AppState>) -> Result<Vec<String>, AppError> {
+    Ok(state.store.locked_paths()?)
+}
+
+/// Asks the user for a file outside the notes folder, e.g. to import, and
+/// allows access to it for the rest of the session.
+#[tauri::command]
+async fn pick_file(app: tauri::AppHandle, extensions: Option<Vec<String>>) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+    blocking(move || {
+        let mut dialog = app.dialog().file();
+        if let Some(extensions) = &extensions {
+            let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+            dialog = dialog.add_filter("Files", &extensions);
+        }
+        let Some(picked) = dialog.blocking_pick_file() else {
+            return Ok(None);
+        };
+        let path = picked.into_path().map_err(|e| e.to_string())?;
+        app.state::<AppState>().access.grant(&path)?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    })
+    .await
+}
+
+/// Asks the user for a folder outside the notes folder, e.g. an export to
+/// import, and allows reading it for the rest of the session.
+#[tauri::command]
+async fn pick_folder(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+    blocking(move || {
+        let Some(picked) = app.dialog().file().blocking_pick_folder() else {
+            return Ok(None);
+        };
+        let path = picked.into_path().map_err(|e| e.to_string())?;
+        app.state::<AppState>().access.grant(&path)?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    })
+    .await
+}
+
+/// Asks the user where to save a file outside the notes folder, e.g. an
+/// export, and allows writing it for the rest of the session.
+#[tauri::command]
+async fn pick_save_file(
+    app: tauri::AppHandle,
+    file_name: Option<String>,
+    extensions: Option<Vec<String>>,
+) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+    blocking(move || {
+        let mut dialog = app.dialog().file();
+        if let Some(file_name) = &file_name {
+            dialog = dialog.set_file_name(file_name);
+        }
+        if let Some(extensions) = &extensions {
+            let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+            dialog = dialog.add_filter("Files", &extensions);
+        }
+        let Some(picked) = dialog.blocking_save_file() else {
+            return Ok(None);
+        };
+        let path = picked.into_path().map_err(|e| e.to_string())?;
+        app.state::<AppState>().access.grant(&path)?;
+        Ok(Some(path.to_string_lossy().to_string()))
+    })
+    .await
+}
+
+/// Starts the local HTTP API on `port` of 127.0.0.1, for scripts and
+/// extensions. Returns the address and the token requests must carry.
+#[tauri::command]
+async fn start_api_server(app: tauri::AppHandle, port: Option<u16>) -> Result<ApiServerInfo, AppError> {
+    blocking(move || {
+        let server = app.state::<ApiServer>();
+        Ok(server.start(app.clone(), port.unwrap_or(api::DEFAULT_PORT))?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn stop_api_server(server: tauri::State<ApiServer>) {
+    server.stop();
+}
+
+#[tauri::command]
+fn api_server_status(server: tauri::State<ApiServer>) -> Option<ApiServerInfo> {
+    server.info()
+}
+
+/// Replaces the API token. A running server stops accepting the old one
+/// right away.
+#[tauri::command]
+async fn regenerate_api_token(app: tauri::AppHandle) -> Result<String, AppError> {
+    blocking(move || {
+        let token = api::regenerate_token()?;
+        app.state::<ApiServer>().set_token(&token);
+        Ok(token)
+    })
+    .await
+}
+
+/// Serves a workspace over WebDAV for editors on other devices, on this
+/// computer only unless `lan` is set. Returns the login to use.
+#[tauri::command]
+async fn start_webdav_server(
+    app: tauri::AppHandle,
+    workspace_id: String,
+    port: Option<u16>,
+    lan: Option<bool>,
+) -> Result<WebDavInfo, AppError> {
+    blocking(move || {
+        let server = app.state::<WebDavServer>();
+        Ok(server.start(
+            app.clone(),
+            &workspace_id,
+            port.unwrap_or(webdav::DEFAULT_PORT),
+            lan.unwrap_or(false),
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn stop_webdav_server(server: tauri::State<WebDavServer>) {
+    server.stop();
+}
+
+#[tauri::command]
+fn webdav_server_status(server: tauri::State<WebDavServer>) -> Option<WebDavInfo> {
+    server.info()
+}
+
+/// Opens a workspace's notes to live collaborative editing from other
+/// devices, on this computer only unless `lan` is set. Returns the token
+/// clients connect with.
+#[tauri::command]
+async fn start_collab_server(
+    app: tauri::AppHandle,
+    workspace_id: String,
+    port: Option<u16>,
+    lan: Option<bool>,
+) -> Result<CollabInfo, AppError> {
+    blocking(move || {
+        let server = app.state::<CollabServer>();
+        Ok(server.start(
+            app.clone(),
+            &workspace_id,
+            port.unwrap_or(collab::DEFAULT_PORT),
+            lan.unwrap_or(false),
+        )?)
+    })
+    .await
+}
+
+#[tauri::command]
+fn stop_collab_server(server: tauri::State<CollabServer>) {
+    server.stop();
+}
+
+#[tauri::command]
+fn collab_server_status(server: tauri::State<CollabServer>) -> Option<CollabInfo> {
+    server.info()
+}
+
+#[tauri::command]
+fn reveal_in_finder(state: tauri::State<AppState>, path: String) -> Result<(), AppError> {
+    state.access.check(std::path::Path::new(&path))?;
     std::process::Command::new("open")
         .args(["-R", &path])
         .spawn()
@@ -475,69 +5091,103 @@ fn reveal_in_finder(path: String) -> Result<(), String> {
     Ok(())
 }
 
+/// Moves a note to `new_index` in the listing by updating its order key.
+/// Files are left untouched, so links, mtimes and sync state survive; the
+/// returned path is always `path`.
 #[tauri::command]
-fn reorder_note(state: tauri::State<AppState>, path: String, new_index: usize) -> Result<String, String> {
-    let config = state.config.lock().unwrap();
-    let notes_dir = get_workspace_dir(&config.active_workspace_id);
-    drop(config);
+async fn reorder_note(app: tauri::AppHandle, path: String, new_index: usize) -> Result<String, AppError> {
+    blocking(move || {
+        let state = app.state::<AppState>();
+        let watcher = app.state::<WorkspaceWatcher>();
+        check_workspace_present(&watcher)?;
+        let workspace_id = state.config.lock().unwrap().active_workspace_id.clone();
 
-    let mut entries: Vec<(PathBuf, String)> = fs::read_dir(&notes_dir)
-        .map_err(|e| e.to_string())?
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let p = entry.path();
-            if p.extension()?.to_str()? != "md" {
-                return None;
-            }
-            let name = p.file_stem()?.to_string_lossy().to_string();
-            parse_file_number(&name)?;
-            Some((p, name))
-        })
-        .collect();
+        let mut entries: Vec<NoteEntry> = watcher.with_index(|index| {
+            index
+                .notes()
+                .map(|n| NoteEntry {
+                    path: n.path.to_string_lossy().to_string(),
+                    name: n.name.clone(),
+                    title: String::new(),
+                    modified: n.stamp.modified_secs(),
+                    created: n.created,
+                    id: None,
+                })
+                .collect()
+        });
 
-    entries.sort_by(|a, b| {
-        let num_a = parse_file_number(&a.1).unwrap_or(0);
-        let num_b = parse_file_number(&b.1).unwrap_or(0);
-        num_b.cmp(&num_a)
-    });
+        let mut order = state.order.lock().unwrap();
+        sort_entries(&mut entries, &order, &workspace_id);
 
-    let source_path = PathBuf::from(&path);
-    let source_idx = entries.iter().position(|(p, _)| p == &source_path).ok_or("Note not found")?;
+        let source = entries.iter().position(|e| e.path == path).ok_or("Note not found")?;
+        if source == new_index || entries.len() <= 1 {
+            return Ok(path);
+        }
 
-    if source_idx == new_index || entries.len() <= 1 {
-        return Ok(path);
-    }
+        let names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        order.move_to(&workspace_id, &names, &entries[source].name, new_index);
+        order.save()?;
+        drop(order);
 
-    let item = entries.remove(source_idx);
-    let insert_idx = new_index.min(entries.len());
-    entries.insert(insert_idx, item);
+        let source_path = PathBuf::from(&path);
+        emit_note_event(&app, NOTE_REORDERED, Some(source_path.as_path()), Some(source_path.as_path()));
+        Ok(path)
+    })
+    .await
+}
 
-    let mut new_path_result = path.clone();
-    for (i, (old_path, name)) in entries.iter().enumerate() {
-        let slug = name.splitn(2, '-').nth(1).unwrap_or("untitled");
-        let new_num = (entries.len() - i) as u64;
-        let new_p = notes_dir.join(format!("{}-{}.md", new_num, slug));
-        if old_path != &new_p {
-            fs::rename(old_path, &new_p).map_err(|e| e.to_string())?;
-            if *old_path == source_path {
-                new_path_result = new_p.to_string_lossy().to_string();
-            }
-        }
-    }
+const OPEN_LOGS_MENU_ID: &str = "open_logs";
+const CHECK_FOR_UPDATES_MENU_ID: &str = "check_for_updates";
 
-    Ok(new_path_result)
+/// Serves the Model Context Protocol on stdin and stdout, for assistants
+/// that launch the app with `--mcp`. No window is opened; only workspaces
+/// the user shared in the app are exposed.
+pub fn run_mcp() {
+    let server = mcp::Server::new(get_notes_root(), read_config);
+    if let Err(e) = server.serve(std::io::stdin().lock(), std::io::stdout().lock()) {
+        eprintln!("MCP server stopped: {}", e);
+        std::process::exit(1);
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let logging = Logging::init(&get_app_data_dir().join(logging::LOG_DIR), LogLevel::default());
     let config = init_workspaces();
+    let _ = logging.set_level(config.log_level);
+    tracing::info!("Starting Write {}", env!("CARGO_PKG_VERSION"));
+    let store = match open_store() {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("Can't open the metadata store: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     tauri::Builder::default()
         .manage(AppState {
             config: Mutex::new(config),
+            store: store.clone(),
+            note_cache: Mutex::new(NoteCache::load(store.clone())),
+            listing: Mutex::new(ListingSnapshot::default()),
+            order: Mutex::new(OrderStore::load(store)),
+            write_lock: Mutex::new(()),
+            numbering: Mutex::new(()),
+            spell: SpellChecker::default(),
+            keys: WorkspaceKeys::default(),
+            access: AccessControl::new(vec![get_notes_root()]),
+            session: Mutex::new(None),
+            pending_open: Mutex::new(None),
         })
+        .manage(WorkspaceWatcher::new())
+        .manage(ConfigWatcher::new())
+        .manage(InboxWatcher::new())
+        .manage(ApiServer::default())
+        .manage(WebDavServer::default())
+        .manage(CollabServer::default())
+        .manage(logging)
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .setup(|app| {
             #[cfg(desktop)]
@@ -579,32 +5229,264 @@ pub fn run() {
                     .close_window()
                     .build()?;
 
+                let help_submenu = SubmenuBuilder::new(app, "Help")
+                    .item(&MenuItemBuilder::with_id(CHECK_FOR_UPDATES_MENU_ID, "Check for Updates…").build(app)?)
+                    .separator()
+                    .item(&MenuItemBuilder::with_id(OPEN_LOGS_MENU_ID, "Open Logs").build(app)?)
+                    .build()?;
+
                 let menu = MenuBuilder::new(app)
-                    .items(&[&app_submenu, &edit_submenu, &window_submenu])
+                    .items(&[&app_submenu, &edit_submenu, &window_submenu, &help_submenu])
                     .build()?;
 
                 app.set_menu(menu)?;
+                app.on_menu_event(|app, event| {
+                    if event.id().as_ref() == CHECK_FOR_UPDATES_MENU_ID {
+                        let _ = app.emit(updates::UPDATE_CHECK_REQUESTED, ());
+                    }
+                    if event.id().as_ref() == OPEN_LOGS_MENU_ID {
+                        use tauri_plugin_opener::OpenerExt;
+                        let dir = app.state::<Logging>().dir().to_path_buf();
+                        if let Err(e) = app.opener().open_path(dir.to_string_lossy(), None::<&str>) {
+                            tracing::warn!("Opening the log folder failed: {}", e);
+                        }
+                    }
+                });
+            }
+
+            let active_workspace_id = app
+                .state::<AppState>()
+                .config
+                .lock()
+                .unwrap()
+                .active_workspace_id
+                .clone();
+            sync_extra_roots(&app.state::<AppState>());
+            watch_workspace(app.handle(), &active_workspace_id)?;
+            app.state::<ConfigWatcher>().watch(app.handle(), &get_config_path())?;
+            if let Err(e) = watch_inbox(app.handle()) {
+                tracing::warn!("Can't watch the inbox: {}", e);
             }
+
+            let args: Vec<String> = std::env::args().skip(1).collect();
+            let cwd = std::env::current_dir().unwrap_or_default();
+            if let Some(target) = launch::target_from_args(&args, &cwd) {
+                if let Err(e) = request_open(app.handle(), target) {
+                    tracing::warn!("Can't open the note from the command line: {}", e);
+                }
+            }
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                if let Err(e) = ingest_inbox(&handle) {
+                    tracing::warn!("Importing the inbox failed: {}", e);
+                }
+            });
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                if let Err(e) = auto_archive(&handle, None) {
+                    tracing::warn!("Auto-archiving failed: {}", e);
+                }
+                std::thread::sleep(archive::CHECK_INTERVAL);
+            });
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                if let Err(e) = trash_empty_notes(&handle, None) {
+                    tracing::warn!("Cleaning up empty notes failed: {}", e);
+                }
+                std::thread::sleep(cleanup::CHECK_INTERVAL);
+            });
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(email::POLL_INTERVAL);
+                if handle.state::<AppState>().config.lock().unwrap().email.is_some() {
+                    if let Err(e) = ingest_email(&handle) {
+                        tracing::warn!("Checking mail failed: {}", e);
+                    }
+                }
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             ensure_notes_dir,
+            recreate_workspace_dir,
             list_notes,
+            list_notes_delta,
+            get_titles,
+            list_notes_page,
+            search_notes,
+            semantic_search,
             read_note,
+            read_note_chunk,
+            render_markdown,
+            highlight_code,
+            list_code_themes,
+            lookup_emoji,
+            analyze_text,
+            smarten,
+            list_dictionaries,
+            check_text,
+            add_to_dictionary,
+            check_grammar,
+            summarize_note,
+            get_link_candidates,
+            format_table,
+            list_tasks,
+            toggle_task,
             write_note,
+            sync_filename,
+            format_note,
+            transcribe,
+            encrypt_note,
+            decrypt_note,
+            encrypt_workspace,
+            unlock_workspace,
+            lock_workspace,
+            biometric_available,
+            enable_biometric_unlock,
+            disable_biometric_unlock,
+            unlock_workspace_biometric,
+            set_secret,
+            has_secret,
+            delete_secret,
             create_note,
             delete_note,
             rename_note,
             reveal_in_finder,
+            pick_file,
+            pick_folder,
+            pick_save_file,
+            start_api_server,
+            stop_api_server,
+            api_server_status,
+            regenerate_api_token,
+            start_webdav_server,
+            stop_webdav_server,
+            webdav_server_status,
+            start_collab_server,
+            stop_collab_server,
+            collab_server_status,
+            lock_note,
+            unlock_note,
+            list_locked_notes,
             reorder_note,
             get_workspaces,
             set_active_workspace,
             create_workspace,
             delete_workspace,
-            rename_workspace
+            rename_workspace,
+            set_workspace_ignore_patterns,
+            set_workspace_hidden_patterns,
+            set_workspace_format_rules,
+            set_workspace_mcp_access,
+            set_calendar_sources,
+            get_day_events,
+            create_daily_note,
+            set_email_settings,
+            check_email,
+            capture_url,
+            set_publish_settings,
+            publish_note,
+            unpublish_note,
+            get_publication,
+            add_comment,
+            list_comments,
+            resolve_comment,
+            start_review,
+            end_review,
+            list_changes,
+            accept_change,
+            reject_change,
+            get_writing_stats,
+            set_workspace_word_goal,
+            set_workspace_export_theme,
+            set_workspace_auto_archive,
+            set_workspace_roots,
+            get_goal_progress,
+            start_session,
+            end_session,
+            get_sessions,
+            get_note_for_date,
+            get_adjacent_entry,
+            get_journal_months,
+            get_random_note,
+            get_resurfacing_candidates,
+            set_transliterate_file_names,
+            set_tool_paths,
+            list_templates,
+            get_template_prompts,
+            list_snippets,
+            expand_snippet,
+            get_agenda,
+            get_board,
+            move_card,
+            get_note_link,
+            import_simplenote,
+            import_keep,
+            import_joplin,
+            export_flashcards,
+            renumber_footnotes,
+            cite_lookup,
+            convert_note,
+            list_export_themes,
+            set_inbox_settings,
+            check_inbox,
+            get_frecent_notes,
+            run_auto_archive,
+            list_empty_notes,
+            clean_up_empty_notes,
+            create_workspace_from_git,
+            get_pinned_notes,
+            find_duplicate_titles,
+            get_note_info,
+            batch_delete,
+            batch_move,
+            batch_tag,
+            get_note_id,
+            resolve_note,
+            get_log_path,
+            set_log_level,
+            set_update_channel,
+            check_for_updates,
+            take_open_request,
+            set_max_note_size,
+            rename_tag,
+            list_tags,
+            export_fragment
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(handle_run_event);
+}
+
+fn handle_run_event(app: &tauri::AppHandle, event: tauri::RunEvent) {
+    #[cfg(target_os = "macos")]
+    match event {
+        // Files opened with the app in Finder, and `write://` links.
+        tauri::RunEvent::Opened { urls } => {
+            for target in urls.iter().filter_map(launch::target_from_url) {
+                if let Err(e) = request_open(app, target) {
+                    tracing::warn!("Can't open the note: {}", e);
+                }
+            }
+        }
+        // Clicking the dock icon with the window closed brings it back.
+        tauri::RunEvent::Reopen {
+            has_visible_windows: false,
+            ..
+        } => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }
+        _ => {}
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app, event);
 }
 
 #[cfg(test)]
@@ -628,11 +5510,82 @@ mod tests {
         assert_eq!(parse_title(""), "Untitled");
     }
 
+    #[test]
+    fn test_parse_title_skips_frontmatter() {
+        let content = "---\n# yaml comment\ntags: [a]\n---\n\n# Real Title\n";
+        assert_eq!(parse_title(content), "Real Title");
+        assert_eq!(parse_title("---\n\n# Unclosed\n"), "Untitled");
+    }
+
+    #[test]
+    fn test_read_header_collects_aliases() {
+        let inline = read_header("---\naliases: [Groceries, \"Shopping list\"]\n---\n# Food\n".as_bytes());
+        assert_eq!(inline.aliases, vec!["Groceries", "Shopping list"]);
+        let listed = read_header("---\ntags: [a]\naliases:\n  - One\n  - 'Two'\ndate: 2024\n---\n# T\n".as_bytes());
+        assert_eq!(listed.title, "T");
+        assert_eq!(listed.aliases, vec!["One", "Two"]);
+    }
+
+    #[test]
+    fn test_parse_title_hides_encrypted_notes() {
+        let content = format!("{}\nYWdlLWVuY3J5cHRpb24\n-----END AGE ENCRYPTED FILE-----\n", crypto::ARMOR_BEGIN);
+        assert_eq!(parse_title(&content), crypto::ENCRYPTED_TITLE);
+    }
+
+    #[test]
+    fn test_read_title_from_file_after_long_frontmatter() {
+        let path = std::env::temp_dir().join(format!("write-title-{}.md", std::process::id()));
+        let frontmatter = "summary: ".to_string() + &"x".repeat(5000);
+        fs::write(&path, format!("---\n{}\n---\n# Ünïcödé título\n", frontmatter)).unwrap();
+        assert_eq!(read_header_from_file(&path).title, "Ünïcödé título");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_title_keeps_whole_chars_when_cut() {
+        let title = "é".repeat(300);
+        let parsed = read_header(format!("# {}", title).as_bytes().take(101)).title;
+        assert_eq!(parsed, "é".repeat(49));
+        assert_eq!(parse_title(&format!("# {}", title)).chars().count(), MAX_TITLE_CHARS);
+    }
+
     #[test]
     fn test_parse_title_h2_not_matched() {
         assert_eq!(parse_title("## Not a title"), "Untitled");
     }
 
+    #[test]
+    fn test_note_title_follows_markup() {
+        let org = "#+TITLE: Reading list\n# not a heading in Org\n";
+        assert_eq!(note_title(std::path::Path::new("/n/1-a.org"), org), "Reading list");
+        assert_eq!(note_title(std::path::Path::new("/n/1-a.md"), org), "not a heading in Org");
+        assert_eq!(note_title(std::path::Path::new("/n/2-b.txt"), "\nShopping\nmilk\n"), "Shopping");
+        assert_eq!(note_title(std::path::Path::new("/n/2-b.txt"), ""), "Untitled");
+    }
+
+    #[test]
+    fn test_parse_title_setext() {
+        assert_eq!(parse_title("My Title\n========\n\nBody"), "My Title");
+        assert_eq!(parse_title("A long\ntitle\n===\n"), "A long title");
+        assert_eq!(parse_title("Section\n-------\n# Real\n"), "Real");
+        assert_eq!(parse_title("===\nText\n"), "Untitled");
+    }
+
+    #[test]
+    fn test_parse_title_skips_code_fences() {
+        let content = "Setup:\n\n```sh\n# install deps\nnpm i\n```\n\n# Actual Title\n";
+        assert_eq!(parse_title(content), "Actual Title");
+        assert_eq!(parse_title("~~~~\n# in code\n~~~\n# still code\n~~~~\n# Out\n"), "Out");
+        assert_eq!(parse_title("    # indented code\n# Title\n"), "Title");
+    }
+
+    #[test]
+    fn test_parse_title_strips_inline_markdown() {
+        assert_eq!(parse_title("# **Bold** title"), "Bold title");
+        assert_eq!(parse_title("# Notes on [Rust](https://rust-lang.org) and `cargo` #"), "Notes on Rust and cargo");
+        assert_eq!(parse_title("#\n# Second\n"), "Second");
+    }
+
     #[test]
     fn test_slugify_basic() {
         assert_eq!(slugify("Hello World"), "hello-world");
@@ -663,6 +5616,57 @@ mod tests {
         assert_eq!(slugify("日本語"), "日本語");
     }
 
+    #[test]
+    fn test_unique_name_adds_suffix_on_collision() {
+        let taken = |name: &str| ["12-meeting-notes", "12-meeting-notes-2"].contains(&name);
+        assert_eq!(unique_name("12-meeting-notes", "12-untitled", taken), "12-meeting-notes-3");
+        assert_eq!(unique_name("12-meeting-notes", "12-meeting-notes-2", taken), "12-meeting-notes-2");
+        assert_eq!(unique_name("12-agenda", "12-untitled", taken), "12-agenda");
+    }
+
+    #[test]
+    fn test_untitled_slug_numbers_untitled_notes() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(untitled_slug(&names(&["1-plan"]), ""), "untitled");
+        assert_eq!(untitled_slug(&names(&["1-untitled", "2-untitled-2"]), ""), "untitled-3");
+        assert_eq!(untitled_slug(&names(&["1-untitled", "3-untitled-3"]), ""), "untitled-2");
+        // A note keeps its own suffix, and a titled note takes a free one.
+        assert_eq!(untitled_slug(&names(&["1-untitled", "2-untitled-2"]), "2-untitled-2"), "untitled-2");
+        assert_eq!(untitled_slug(&names(&["1-untitled", "2-plan"]), "2-plan"), "untitled-2");
+    }
+
+    #[test]
+    fn test_duplicate_titles() {
+        let note = |name: &str, title: &str| NoteEntry {
+            name: name.to_string(),
+            path: format!("/notes/{}.md", name),
+            modified: 0,
+            created: None,
+            id: None,
+            title: title.to_string(),
+        };
+        let groups = duplicate_titles(vec![
+            note("1-meeting-notes", "Meeting notes"),
+            note("2-groceries", "Groceries"),
+            note("3-meeting-notes", "Meeting Notes!"),
+            note("4-untitled", "Untitled"),
+            note("5-untitled", "Untitled"),
+        ]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].title, "Meeting notes");
+        let names: Vec<&str> = groups[0].notes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, vec!["1-meeting-notes", "3-meeting-notes"]);
+    }
+
+    #[test]
+    fn test_renamed_file_name() {
+        assert_eq!(renamed_file_name("12-old", "My Plans", false).as_deref(), Ok("12-my-plans"));
+        assert_eq!(renamed_file_name("12-old", "12-my-plans.md", false).as_deref(), Ok("12-my-plans"));
+        assert_eq!(renamed_file_name("12-old", "../../etc/passwd", false).as_deref(), Ok("12-etc-passwd"));
+        assert_eq!(renamed_file_name("readme", "Read Me", false).as_deref(), Ok("read-me"));
+        assert!(renamed_file_name("12-old", "???", false).is_err());
+    }
+
     #[test]
     fn test_parse_file_number_valid() {
         assert_eq!(parse_file_number("1-hello"), Some(1));
@@ -683,10 +5687,101 @@ mod tests {
         assert!(is_old_timestamp_format("1234567890"));
     }
 
+    #[test]
+    fn test_check_note_dir_present() {
+        let existing = std::env::temp_dir().join("note.md");
+        assert!(check_note_dir_present(&existing).is_ok());
+        let missing = std::env::temp_dir().join("write-missing-workspace").join("note.md");
+        assert_eq!(check_note_dir_present(&missing), Err(AppError::not_found(WORKSPACE_MISSING_ERROR)));
+    }
+
+    #[test]
+    fn test_sort_entries_puts_unordered_notes_first() {
+        let entry = |name: &str| NoteEntry {
+            name: name.to_string(),
+            path: format!("/Notes/w/{}.md", name),
+            modified: 0,
+            created: None,
+            id: None,
+            title: String::new(),
+        };
+        let mut entries = vec![entry("1-a"), entry("2-b"), entry("3-c"), entry("4-d")];
+        let mut order = OrderStore::load(MetaStore::open_in_memory().unwrap());
+        let names: Vec<String> = ["3-c", "2-b", "1-a"].iter().map(|s| s.to_string()).collect();
+        order.move_to("w", &names, "1-a", 0);
+
+        sort_entries(&mut entries, &order, "w");
+        let sorted: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(sorted, vec!["4-d", "1-a", "3-c", "2-b"]);
+    }
+
     #[test]
     fn test_is_old_timestamp_format_invalid() {
         assert!(!is_old_timestamp_format("123"));
         assert!(!is_old_timestamp_format("abc1234567"));
         assert!(!is_old_timestamp_format("12-hello"));
     }
+
+    fn app_state(root: &std::path::Path) -> AppState {
+        let store = MetaStore::open_in_memory().unwrap();
+        AppState {
+            config: Mutex::new(WorkspaceConfig {
+                version: config::CONFIG_VERSION,
+                workspaces: vec![],
+                active_workspace_id: String::new(),
+                calendars: vec![],
+                email: None,
+                publish: Default::default(),
+                transliterate_file_names: false,
+                log_level: Default::default(),
+                update_channel: Default::default(),
+                max_note_bytes: None,
+                inbox: None,
+                tools: Default::default(),
+            }),
+            store: store.clone(),
+            note_cache: Mutex::new(NoteCache::load(store.clone())),
+            listing: Mutex::new(ListingSnapshot::default()),
+            order: Mutex::new(OrderStore::load(store)),
+            write_lock: Mutex::new(()),
+            numbering: Mutex::new(()),
+            spell: SpellChecker::default(),
+            keys: WorkspaceKeys::default(),
+            access: AccessControl::new(vec![root.to_path_buf()]),
+            session: Mutex::new(None),
+            pending_open: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn test_save_note_by_id() {
+        let root = std::env::temp_dir().join(format!("write-save-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("1-plan.md");
+        fs::write(&path, "# Plan").unwrap();
+        let state = app_state(&root);
+        let key = path.to_string_lossy().to_string();
+        let id = state.store.note_ids(std::slice::from_ref(&key)).unwrap()[&key].clone();
+
+        let (saved, _) = save_note(&state, &WorkspaceWatcher::new(), &id, "# Plan\n\nShip it".to_string()).unwrap();
+        assert_eq!(saved, path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Plan\n\nShip it");
+        assert!(!PathBuf::from(&id).exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rename_note_keeps_extension() {
+        let root = std::env::temp_dir().join(format!("write-rename-org-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("4-reading.org");
+        fs::write(&path, "#+TITLE: Reading").unwrap();
+        let state = app_state(&root);
+
+        let renamed = rename_note_file(&state, &WorkspaceWatcher::new(), &path, "Books.org", None).unwrap();
+        assert_eq!(renamed, root.join("4-books.org"));
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "#+TITLE: Reading");
+        assert!(!path.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,228 @@
+use crate::render;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+/// Something `[[` autocomplete can insert. Aliases link to the note's title
+/// and display the alias; headings link as `target`, i.e. `Note#Heading`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LinkCandidate {
+    pub kind: &'static str,
+    pub label: String,
+    pub target: String,
+    pub path: String,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStyle {
+    /// `[[Title]]`
+    #[default]
+    Wikilink,
+    /// `[Title](relative/path.md)`
+    Markdown,
+}
+
+/// A `[[wikilink]]` to the note titled `title`. Titles that can't be written
+/// inside one link by file `name` instead and show the title.
+pub fn wikilink(title: &str, name: &str) -> String {
+    if title.is_empty() || title.contains(['[', ']', '|', '#']) {
+        let label = title.replace(['[', ']', '|'], "");
+        if label.trim().is_empty() {
+            return format!("[[{}]]", name);
+        }
+        return format!("[[{}|{}]]", name, label.trim());
+    }
+    format!("[[{}]]", title)
+}
+
+/// A markdown link to the note at `target`, relative to folder `from`.
+pub fn markdown_link(title: &str, target: &Path, from: &Path) -> String {
+    let label = title.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]");
+    let url: Vec<String> = relative_path(target, from).iter().map(|part| render::percent_encode(part)).collect();
+    format!("[{}]({})", label, url.join("/"))
+}
+
+/// The segments leading from folder `from` to `target`, going up with `..`
+/// past the folders they share.
+fn relative_path(target: &Path, from: &Path) -> Vec<String> {
+    let target: Vec<Component> = target.components().collect();
+    let from: Vec<Component> = from.components().collect();
+    let shared = target.iter().zip(&from).take_while(|(a, b)| a == b).count();
+    let up = from[shared..].iter().map(|_| "..".to_string());
+    let down = target[shared..].iter().map(|c| c.as_os_str().to_string_lossy().to_string());
+    up.chain(down).collect()
+}
+
+/// Recency-weighted visit count, in the spirit of browser frecency: recent
+/// visits are worth more than old ones.
+pub fn frecency(count: u32, last_visited: u64, now: u64) -> f64 {
+    let age_days = now.saturating_sub(last_visited) / 86_400;
+    let weight = match age_days {
+        0..=3 => 100.0,
+        4..=14 => 70.0,
+        15..=31 => 50.0,
+        32..=90 => 30.0,
+        _ => 10.0,
+    };
+    count as f64 * weight
+}
+
+/// Editing a note says more about using it than opening it does.
+pub const EDIT_WEIGHT: f64 = 2.0;
+
+/// How well `label` matches `prefix`: 0 for a prefix match, 1 when a later
+/// word starts with it, 2 for any substring. `None` if it doesn't match.
+pub fn match_tier(label: &str, prefix: &str) -> Option<u8> {
+    let label = label.to_lowercase();
+    let prefix = prefix.to_lowercase();
+    if label.starts_with(&prefix) {
+        Some(0)
+    } else if label
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(&prefix))
+    {
+        Some(1)
+    } else if label.contains(&prefix) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Keeps candidates matching `prefix`, best match first, then by frecency,
+/// then shortest label.
+pub fn rank(candidates: Vec<(LinkCandidate, f64)>, prefix: &str, limit: usize) -> Vec<LinkCandidate> {
+    let mut matched: Vec<(u8, f64, LinkCandidate)> = candidates
+        .into_iter()
+        .filter_map(|(candidate, score)| Some((match_tier(&candidate.label, prefix)?, score, candidate)))
+        .collect();
+    matched.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.total_cmp(&a.1))
+            .then(a.2.label.len().cmp(&b.2.label.len()))
+    });
+    matched.into_iter().take(limit).map(|(_, _, c)| c).collect()
+}
+
+/// ATX headings in `content`, outside fenced code blocks.
+pub fn parse_headings(content: &str) -> Vec<String> {
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let text = trimmed[level..].trim().trim_end_matches('#').trim();
+            if !text.is_empty() {
+                headings.push(text.to_string());
+            }
+        }
+    }
+    headings
+}
+
+/// Targets of the `[[wikilinks]]` in `content` outside fenced code, without
+/// any `#Heading` or `|label`.
+pub fn parse_wikilinks(content: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let Some(len) = rest[start + 2..].find("]]") else {
+                break;
+            };
+            let inner = &rest[start + 2..start + 2 + len];
+            let target = inner.split(['|', '#']).next().unwrap_or_default().trim();
+            if !target.is_empty() {
+                targets.push(target.to_string());
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wikilinks() {
+        let content = "See [[Plans#Q3|the plan]] and [[ Budget ]].\n```\n[[Not a link]]\n```\n[[Plans]] [[#Local]]";
+        assert_eq!(parse_wikilinks(content), vec!["Plans", "Budget", "Plans"]);
+    }
+
+    #[test]
+    fn test_wikilink() {
+        assert_eq!(wikilink("Plans", "3-plans"), "[[Plans]]");
+        assert_eq!(wikilink("C# tips", "4-c-tips"), "[[4-c-tips|C# tips]]");
+        assert_eq!(wikilink("", "5-untitled"), "[[5-untitled]]");
+    }
+
+    #[test]
+    fn test_markdown_link() {
+        let from = Path::new("/Notes/work/projects");
+        assert_eq!(
+            markdown_link("Q3 [draft]", Path::new("/Notes/work/1-q3 plan (v2).md"), from),
+            "[Q3 \\[draft\\]](../1-q3%20plan%20%28v2%29.md)"
+        );
+        assert_eq!(
+            markdown_link("Here", Path::new("/Notes/work/projects/2-here.md"), from),
+            "[Here](2-here.md)"
+        );
+    }
+
+    fn note(label: &str) -> LinkCandidate {
+        LinkCandidate {
+            kind: "note",
+            label: label.to_string(),
+            target: label.to_string(),
+            path: format!("/Notes/w/{}.md", label),
+        }
+    }
+
+    #[test]
+    fn test_rank_prefers_prefix_then_frecency() {
+        let ranked = rank(
+            vec![
+                (note("Weekly review"), 500.0),
+                (note("Reading list"), 10.0),
+                (note("Recipes"), 100.0),
+                (note("Groceries"), 1000.0),
+            ],
+            "re",
+            10,
+        );
+        let labels: Vec<&str> = ranked.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["Recipes", "Reading list", "Weekly review"]);
+    }
+
+    #[test]
+    fn test_frecency_decays_with_age() {
+        let now = 300 * 86_400;
+        assert!(frecency(1, now, now) > frecency(1, now - 20 * 86_400, now));
+        assert!(frecency(5, now - 200 * 86_400, now) < frecency(1, now, now));
+    }
+
+    #[test]
+    fn test_parse_headings_skips_code() {
+        let content = "# Title\n\n## Plan ##\n```\n# not a heading\n```\n#hashtag\n### Done";
+        assert_eq!(parse_headings(content), vec!["Title", "Plan", "Done"]);
+    }
+}
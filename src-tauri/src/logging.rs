@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+/// Folder in the app data dir holding the log files, one per day.
+pub const LOG_DIR: &str = "logs";
+
+/// Days of logs kept before the oldest file is deleted.
+const KEPT_FILES: usize = 7;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// The app's log file writer. Dropping it flushes what's still buffered.
+pub struct Logging {
+    dir: PathBuf,
+    filter: Option<reload::Handle<LevelFilter, Registry>>,
+    _guard: Mutex<Option<WorkerGuard>>,
+}
+
+impl Logging {
+    /// Starts writing `tracing` events at `level` and up to daily files in
+    /// `dir`. Logging is off, but the app runs, when the folder can't be
+    /// created or a subscriber is already installed.
+    pub fn init(dir: &Path, level: LogLevel) -> Self {
+        let disabled = || Self {
+            dir: dir.to_path_buf(),
+            filter: None,
+            _guard: Mutex::new(None),
+        };
+        let appender = match RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("write")
+            .filename_suffix("log")
+            .max_log_files(KEPT_FILES)
+            .build(dir)
+        {
+            Ok(appender) => appender,
+            Err(e) => {
+                eprintln!("Logging disabled: {}", e);
+                return disabled();
+            }
+        };
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        let (filter, handle) = reload::Layer::new(LevelFilter::from(level));
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false));
+        if tracing::subscriber::set_global_default(subscriber).is_err() {
+            return disabled();
+        }
+        Self {
+            dir: dir.to_path_buf(),
+            filter: Some(handle),
+            _guard: Mutex::new(Some(guard)),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_level(&self, level: LogLevel) -> Result<(), String> {
+        match &self.filter {
+            Some(handle) => handle.reload(LevelFilter::from(level)).map_err(|e| e.to_string()),
+            None => Err("Logging isn't available".to_string()),
+        }
+    }
+}
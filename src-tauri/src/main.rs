@@ -2,5 +2,8 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--mcp") {
+        return write_lib::run_mcp();
+    }
     write_lib::run()
 }
@@ -0,0 +1,105 @@
+use std::path::Path;
+
+/// The markup a note is written in, told by its file extension. Org and text
+/// notes are listed, titled and searched like markdown ones but saved as
+/// they are; nothing converts them to markdown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Markup {
+    Markdown,
+    Org,
+    Text,
+}
+
+impl Markup {
+    pub const ALL: [Markup; 3] = [Markup::Markdown, Markup::Org, Markup::Text];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?;
+        Self::ALL.into_iter().find(|markup| extension == markup.extension())
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Markup::Markdown => "md",
+            Markup::Org => "org",
+            Markup::Text => "txt",
+        }
+    }
+
+    /// The title of a non-markdown note from its `content`. Markdown titles
+    /// come from `read_header`, which also picks up aliases.
+    pub fn title(self, content: &str) -> Option<String> {
+        match self {
+            Markup::Markdown => None,
+            Markup::Org => org_title(content),
+            Markup::Text => text_title(content),
+        }
+    }
+}
+
+/// The `#+TITLE:` keyword, or else the first heading without its stars,
+/// `TODO` keyword and tags.
+fn org_title(content: &str) -> Option<String> {
+    let keyword = content.lines().find_map(|line| {
+        let line = line.trim();
+        let (key, value) = line.strip_prefix("#+")?.split_once(':')?;
+        key.eq_ignore_ascii_case("title").then(|| value.trim().to_string())
+    });
+    if let Some(title) = keyword.filter(|t| !t.is_empty()) {
+        return Some(title);
+    }
+    content.lines().find_map(|line| {
+        let rest = line.trim_start_matches('*');
+        if rest.len() == line.len() || !rest.starts_with(' ') {
+            return None;
+        }
+        let mut words: Vec<&str> = rest.split_whitespace().collect();
+        if words.first().is_some_and(|w| matches!(*w, "TODO" | "DONE")) {
+            words.remove(0);
+        }
+        if words.last().is_some_and(|w| w.len() > 1 && w.starts_with(':') && w.ends_with(':')) {
+            words.pop();
+        }
+        Some(words.join(" ")).filter(|t| !t.is_empty())
+    })
+}
+
+/// The first line with text on it.
+fn text_title(content: &str) -> Option<String> {
+    content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Markup::from_path(Path::new("/n/1-a.md")), Some(Markup::Markdown));
+        assert_eq!(Markup::from_path(Path::new("/n/2-b.org")), Some(Markup::Org));
+        assert_eq!(Markup::from_path(Path::new("/n/3-c.txt")), Some(Markup::Text));
+        assert_eq!(Markup::from_path(Path::new("/n/scan.pdf")), None);
+        assert_eq!(Markup::from_path(Path::new("/n/org")), None);
+    }
+
+    #[test]
+    fn test_org_title() {
+        assert_eq!(
+            org_title("#+author: Me\n#+TITLE:  Reading list\n* Books").as_deref(),
+            Some("Reading list")
+        );
+        assert_eq!(org_title("Intro\n** TODO Call the bank :home:\n").as_deref(), Some("Call the bank"));
+        assert_eq!(org_title("*bold* text\n#+TITLE:\n"), None);
+    }
+
+    #[test]
+    fn test_text_title() {
+        assert_eq!(text_title("\u{feff}\n  Groceries \nmilk").as_deref(), Some("Groceries"));
+        assert_eq!(text_title(" \n"), None);
+    }
+}
@@ -0,0 +1,341 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::index::WorkspaceIndex;
+use crate::search::{self, SearchIndex};
+use crate::{crypto, get_ignore_rules, get_next_number, parse_title, read_header_from_file, slug, WorkspaceConfig};
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// What assistants connected over MCP may do in a workspace. Nothing until
+/// the user allows it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum McpAccess {
+    #[default]
+    None,
+    Read,
+    ReadWrite,
+}
+
+/// A Model Context Protocol server over newline-delimited JSON-RPC, exposing
+/// the notes of workspaces the user opened up to assistants. The config is
+/// read on every call so access changes in the app apply right away.
+pub struct Server {
+    notes_root: PathBuf,
+    config: Box<dyn Fn() -> Option<WorkspaceConfig>>,
+}
+
+impl Server {
+    pub fn new(notes_root: PathBuf, config: impl Fn() -> Option<WorkspaceConfig> + 'static) -> Self {
+        Self {
+            notes_root,
+            config: Box::new(config),
+        }
+    }
+
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> Result<(), String> {
+        for line in input.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(output, "{}", response).map_err(|e| e.to_string())?;
+                output.flush().map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to one message; notifications get none.
+    fn handle(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(Value::Null, -32700, &format!("Parse error: {}", e))),
+        };
+        let id = message.get("id")?.clone();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match message.get("method").and_then(Value::as_str).unwrap_or("") {
+            "initialize" => json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "write", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "ping" => json!({}),
+            "tools/list" => json!({ "tools": tools() }),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+                match self.call_tool(name, &arguments) {
+                    Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+                    Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+                }
+            }
+            method => return Some(error_response(id, -32601, &format!("Method not found: {}", method))),
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    fn call_tool(&self, name: &str, arguments: &Value) -> Result<String, String> {
+        let arg = |key: &str| -> Result<&str, String> {
+            arguments
+                .get(key)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("Missing argument \"{}\"", key))
+        };
+        match name {
+            "list_workspaces" => {
+                let config = self.config()?;
+                let workspaces: Vec<Value> = config
+                    .workspaces
+                    .iter()
+                    .filter(|w| w.mcp_access != McpAccess::None && !w.encrypted)
+                    .map(|w| json!({ "id": w.id, "name": w.name, "access": w.mcp_access }))
+                    .collect();
+                to_text(&workspaces)
+            }
+            "list_notes" => {
+                let index = self.index(arg("workspace")?, McpAccess::Read)?;
+                let mut notes: Vec<Value> = index
+                    .notes()
+                    .map(|n| json!({ "note": n.name, "title": read_header_from_file(&n.path).title }))
+                    .collect();
+                notes.sort_by(|a, b| a["note"].as_str().cmp(&b["note"].as_str()));
+                to_text(&notes)
+            }
+            "search_notes" => {
+                let index = self.index(arg("workspace")?, McpAccess::Read)?;
+                let mut search = SearchIndex::default();
+                for note in index.notes() {
+                    if let Ok(content) = fs::read_to_string(&note.path) {
+                        if !crypto::is_encrypted(&content) {
+                            search.insert(&note.path, note.stamp, search::tokenize(&content));
+                        }
+                    }
+                }
+                let mut hits: Vec<Value> = search
+                    .query(arg("query")?)
+                    .iter()
+                    .map(|path| {
+                        json!({
+                            "note": path.file_name().unwrap_or_default().to_string_lossy(),
+                            "title": read_header_from_file(path).title,
+                        })
+                    })
+                    .collect();
+                hits.sort_by(|a, b| a["note"].as_str().cmp(&b["note"].as_str()));
+                to_text(&hits)
+            }
+            "read_note" => {
+                let path = self.note_path(arg("workspace")?, arg("note")?, McpAccess::Read)?;
+                let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                if crypto::is_encrypted(&content) {
+                    return Err("Note is encrypted".to_string());
+                }
+                Ok(content)
+            }
+            "create_note" => {
+                let dir = self.workspace_dir(arg("workspace")?, McpAccess::ReadWrite)?;
+                let content = arg("content")?;
+                let transliterate = (self.config)().is_some_and(|c| c.transliterate_file_names);
+                let slug = slug::slugify_with(&parse_title(content), transliterate);
+                let name = format!(
+                    "{}-{}.md",
+                    get_next_number(&dir),
+                    if slug.is_empty() { "untitled" } else { &slug }
+                );
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(dir.join(&name))
+                    .map_err(|e| e.to_string())?;
+                file.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+                Ok(name)
+            }
+            _ => Err(format!("Unknown tool \"{}\"", name)),
+        }
+    }
+
+    fn config(&self) -> Result<WorkspaceConfig, String> {
+        (self.config)().ok_or_else(|| "Workspaces aren't set up yet".to_string())
+    }
+
+    /// The workspace's folder, if the user gave assistants at least `needed`.
+    fn workspace_dir(&self, workspace_id: &str, needed: McpAccess) -> Result<PathBuf, String> {
+        let config = self.config()?;
+        let workspace = config
+            .workspaces
+            .iter()
+            .find(|w| w.id == workspace_id && w.mcp_access != McpAccess::None)
+            .ok_or_else(|| format!("Workspace \"{}\" isn't shared with assistants", workspace_id))?;
+        if workspace.encrypted {
+            return Err("Encrypted workspaces can't be shared with assistants".to_string());
+        }
+        if needed == McpAccess::ReadWrite && workspace.mcp_access != McpAccess::ReadWrite {
+            return Err(format!("Workspace \"{}\" is shared read-only", workspace_id));
+        }
+        Ok(self.notes_root.join(&workspace.id))
+    }
+
+    fn index(&self, workspace_id: &str, needed: McpAccess) -> Result<WorkspaceIndex, String> {
+        let dir = self.workspace_dir(workspace_id, needed)?;
+        let ignore = get_ignore_rules(&self.config()?, workspace_id);
+        Ok(WorkspaceIndex::build(&dir, ignore))
+    }
+
+    /// A listed note of the workspace by file name. Anything else, including
+    /// paths out of the folder and ignored files, is refused.
+    fn note_path(&self, workspace_id: &str, note: &str, needed: McpAccess) -> Result<PathBuf, String> {
+        let index = self.index(workspace_id, needed)?;
+        let path = index.dir().join(note);
+        if Path::new(note).file_name().is_none_or(|n| n != note) || !index.contains(&path) {
+            return Err(format!("No note \"{}\" in workspace \"{}\"", note, workspace_id));
+        }
+        Ok(path)
+    }
+}
+
+fn tools() -> Value {
+    let workspace = json!({ "type": "string", "description": "Workspace id from list_workspaces" });
+    json!([
+        {
+            "name": "list_workspaces",
+            "description": "Lists the note workspaces shared with assistants and whether they can be written to.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "list_notes",
+            "description": "Lists the notes in a workspace with their titles.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "workspace": workspace },
+                "required": ["workspace"],
+            },
+        },
+        {
+            "name": "search_notes",
+            "description": "Finds notes containing a word starting with each term of the query.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "workspace": workspace, "query": { "type": "string" } },
+                "required": ["workspace", "query"],
+            },
+        },
+        {
+            "name": "read_note",
+            "description": "Returns the markdown content of a note.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "workspace": workspace,
+                    "note": { "type": "string", "description": "Note file name from list_notes" },
+                },
+                "required": ["workspace", "note"],
+            },
+        },
+        {
+            "name": "create_note",
+            "description": "Creates a markdown note. Its first \"# \" heading becomes the title.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "workspace": workspace, "content": { "type": "string" } },
+                "required": ["workspace", "content"],
+            },
+        },
+    ])
+}
+
+fn to_text(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Workspace;
+
+    fn workspace(id: &str, access: McpAccess) -> Workspace {
+        Workspace {
+            id: id.to_string(),
+            name: id.to_string(),
+            shortcut: None,
+            ignore_patterns: vec![],
+            hidden_patterns: vec![],
+            format_rules: Default::default(),
+            encrypted: false,
+            biometric_unlock: false,
+            mcp_access: access,
+            word_goal: Default::default(),
+            export_theme: None,
+            auto_archive: None,
+            extra_roots: vec![],
+        }
+    }
+
+    fn call(server: &Server, name: &str, arguments: Value) -> Value {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments },
+        });
+        server.handle(&request.to_string()).unwrap()["result"].clone()
+    }
+
+    #[test]
+    fn test_tools_are_scoped_to_shared_workspaces() {
+        let root = std::env::temp_dir().join(format!("write-mcp-{}", std::process::id()));
+        for id in ["Shared", "Private"] {
+            fs::create_dir_all(root.join(id)).unwrap();
+            fs::write(root.join(id).join("1-plan.md"), "# Plan\n\nMigration notes").unwrap();
+        }
+        let server = Server::new(root.clone(), || {
+            Some(WorkspaceConfig {
+                version: crate::config::CONFIG_VERSION,
+                workspaces: vec![workspace("Shared", McpAccess::Read), workspace("Private", McpAccess::None)],
+                active_workspace_id: "Shared".to_string(),
+                calendars: vec![],
+                email: None,
+                publish: Default::default(),
+                transliterate_file_names: false,
+                log_level: Default::default(),
+                update_channel: Default::default(),
+                max_note_bytes: None,
+                inbox: None,
+                tools: Default::default(),
+            })
+        });
+
+        let read = call(&server, "read_note", json!({ "workspace": "Shared", "note": "1-plan.md" }));
+        assert_eq!(read["content"][0]["text"], "# Plan\n\nMigration notes");
+        let search = call(&server, "search_notes", json!({ "workspace": "Shared", "query": "migr" }));
+        assert!(search["content"][0]["text"].as_str().unwrap().contains("1-plan.md"));
+
+        for (name, arguments) in [
+            ("read_note", json!({ "workspace": "Private", "note": "1-plan.md" })),
+            ("read_note", json!({ "workspace": "Shared", "note": "../Private/1-plan.md" })),
+            ("create_note", json!({ "workspace": "Shared", "content": "# New" })),
+        ] {
+            assert_eq!(call(&server, name, arguments)["isError"], true, "{}", name);
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_notifications_get_no_response() {
+        let server = Server::new(PathBuf::from("/nonexistent"), || None);
+        assert!(server
+            .handle(r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#)
+            .is_none());
+        let response = server.handle(r#"{"jsonrpc": "2.0", "id": 7, "method": "nope"}"#).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use crate::store::MetaStore;
+
+/// Smallest gap left between neighbouring keys before a workspace's keys are
+/// spread out again.
+const MIN_GAP: f64 = 1e-9;
+
+/// Manual note order, stored as fractional keys per workspace and file name so
+/// moving a note only writes one key instead of renaming files.
+pub struct OrderStore {
+    store: MetaStore,
+    workspaces: HashMap<String, HashMap<String, f64>>,
+    /// Keys changed since the last save; `None` marks a removal.
+    pending: HashMap<(String, String), Option<f64>>,
+}
+
+impl OrderStore {
+    pub fn load(store: MetaStore) -> Self {
+        let workspaces = load_keys(&store).unwrap_or_default();
+        Self {
+            store,
+            workspaces,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.store.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for ((workspace_id, name), key) in &self.pending {
+            match key {
+                Some(key) => tx.execute(
+                    "INSERT OR REPLACE INTO note_order (workspace_id, name, key) VALUES (?1, ?2, ?3)",
+                    (workspace_id, name, key),
+                ),
+                None => tx.execute(
+                    "DELETE FROM note_order WHERE workspace_id = ?1 AND name = ?2",
+                    (workspace_id, name),
+                ),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn set(&mut self, workspace_id: &str, file_name: &str, key: Option<f64>) {
+        let keys = self.workspaces.entry(workspace_id.to_string()).or_default();
+        match key {
+            Some(key) => keys.insert(file_name.to_string(), key),
+            None => keys.remove(file_name),
+        };
+        self.pending
+            .insert((workspace_id.to_string(), file_name.to_string()), key);
+    }
+
+    pub fn key(&self, workspace_id: &str, file_name: &str) -> Option<f64> {
+        self.workspaces.get(workspace_id)?.get(file_name).copied()
+    }
+
+    /// Moves `file_name` to `new_index` within `order`, the file names in their
+    /// current display order. Only the moved note's key changes unless the
+    /// neighbouring keys are missing or too close together.
+    pub fn move_to(&mut self, workspace_id: &str, order: &[String], file_name: &str, new_index: usize) {
+        let mut order: Vec<String> = order.iter().filter(|n| *n != file_name).cloned().collect();
+        let new_index = new_index.min(order.len());
+        order.insert(new_index, file_name.to_string());
+
+        let keys = self.workspaces.get(workspace_id);
+        let key_of = |name: &String| keys.and_then(|k| k.get(name)).copied();
+        let before = new_index.checked_sub(1).and_then(|i| key_of(&order[i]));
+        let after = order.get(new_index + 1).and_then(key_of);
+        let has_neighbours = (new_index == 0 || before.is_some()) && (new_index + 1 == order.len() || after.is_some());
+
+        match (has_neighbours, before, after) {
+            (true, Some(b), Some(a)) if a - b > MIN_GAP => self.set(workspace_id, file_name, Some((a + b) / 2.0)),
+            (true, Some(b), None) => self.set(workspace_id, file_name, Some(b + 1.0)),
+            (true, None, Some(a)) => self.set(workspace_id, file_name, Some(a - 1.0)),
+            _ => {
+                // First manual move in this workspace, or keys ran out of room.
+                for (i, name) in order.iter().enumerate() {
+                    self.set(workspace_id, name, Some(i as f64));
+                }
+            }
+        }
+    }
+
+    /// Carries a note's position over when its file is renamed.
+    pub fn rename(&mut self, workspace_id: &str, old_name: &str, new_name: &str) {
+        if let Some(key) = self.key(workspace_id, old_name) {
+            self.set(workspace_id, old_name, None);
+            self.set(workspace_id, new_name, Some(key));
+        }
+    }
+
+    pub fn remove(&mut self, workspace_id: &str, file_name: &str) {
+        if self.key(workspace_id, file_name).is_some() {
+            self.set(workspace_id, file_name, None);
+        }
+    }
+}
+
+fn load_keys(store: &MetaStore) -> rusqlite::Result<HashMap<String, HashMap<String, f64>>> {
+    let conn = store.conn();
+    let mut stmt = conn.prepare("SELECT workspace_id, name, key FROM note_order")?;
+    let mut rows = stmt.query([])?;
+    let mut workspaces: HashMap<String, HashMap<String, f64>> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        workspaces.entry(row.get(0)?).or_default().insert(row.get(1)?, row.get(2)?);
+    }
+    Ok(workspaces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn sorted(store: &OrderStore, order: &[String]) -> Vec<String> {
+        let mut order = order.to_vec();
+        order.sort_by(|a, b| {
+            let ka = store.key("w", a).unwrap();
+            let kb = store.key("w", b).unwrap();
+            ka.partial_cmp(&kb).unwrap()
+        });
+        order
+    }
+
+    #[test]
+    fn test_first_move_assigns_keys_in_display_order() {
+        let mut store = OrderStore::load(MetaStore::open_in_memory().unwrap());
+        let order = names(&["c.md", "b.md", "a.md"]);
+        store.move_to("w", &order, "a.md", 0);
+        assert_eq!(sorted(&store, &order), names(&["a.md", "c.md", "b.md"]));
+    }
+
+    #[test]
+    fn test_later_moves_only_touch_one_key() {
+        let mut store = OrderStore::load(MetaStore::open_in_memory().unwrap());
+        let order = names(&["c.md", "b.md", "a.md"]);
+        store.move_to("w", &order, "c.md", 0);
+        let b_key = store.key("w", "b.md");
+
+        store.move_to("w", &order, "c.md", 2);
+        assert_eq!(store.key("w", "b.md"), b_key);
+        assert_eq!(sorted(&store, &order), names(&["b.md", "a.md", "c.md"]));
+    }
+
+    #[test]
+    fn test_rename_keeps_position() {
+        let mut store = OrderStore::load(MetaStore::open_in_memory().unwrap());
+        store.move_to("w", &names(&["b.md", "a.md"]), "a.md", 0);
+        let key = store.key("w", "a.md");
+        store.rename("w", "a.md", "z.md");
+        assert_eq!(store.key("w", "z.md"), key);
+        assert_eq!(store.key("w", "a.md"), None);
+    }
+
+    #[test]
+    fn test_keys_persist_across_loads() {
+        let meta = MetaStore::open_in_memory().unwrap();
+        let mut store = OrderStore::load(meta.clone());
+        store.move_to("w", &names(&["b.md", "a.md"]), "a.md", 0);
+        store.remove("w", "b.md");
+        store.save().unwrap();
+
+        let reloaded = OrderStore::load(meta);
+        assert_eq!(reloaded.key("w", "a.md"), Some(0.0));
+        assert_eq!(reloaded.key("w", "b.md"), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Pandoc's command line tool, looked up on `PATH` unless configured.
+pub const DEFAULT_COMMAND: &str = "pandoc";
+
+/// Whether `format` looks like a Pandoc output format, like `odt`, `rst` or
+/// `markdown_strict+footnotes`.
+pub fn is_format(format: &str) -> bool {
+    format.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && format.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'))
+}
+
+/// Converts `markdown` to `format` with the Pandoc `command`, writing it to
+/// `dest`. Relative images resolve against `resource_dir`, and citations
+/// against `bibliography` when there is one.
+pub fn convert(
+    command: &str,
+    markdown: &str,
+    format: &str,
+    dest: &Path,
+    resource_dir: &Path,
+    bibliography: Option<&Path>,
+) -> Result<(), String> {
+    if !is_format(format) {
+        return Err(format!("{} isn't a Pandoc format", format));
+    }
+    let mut pandoc = Command::new(command);
+    pandoc
+        .args(["--from=markdown", "--standalone"])
+        .arg(format!("--to={}", format))
+        .arg("--resource-path")
+        .arg(resource_dir)
+        .arg("--output")
+        .arg(dest);
+    if let Some(bibliography) = bibliography {
+        pandoc.arg("--citeproc").arg("--bibliography").arg(bibliography);
+    }
+    let mut child = pandoc
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("Pandoc isn't installed ({} wasn't found)", command),
+            _ => e.to_string(),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(markdown.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("Conversion failed: {}", reason.trim()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_format() {
+        assert!(is_format("odt"));
+        assert!(is_format("markdown_strict+footnotes-smart"));
+        assert!(!is_format("--lua-filter=x.lua"));
+        assert!(!is_format("html5 --self-contained"));
+        assert!(!is_format(""));
+    }
+}
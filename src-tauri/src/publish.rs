@@ -0,0 +1,244 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use url::Url;
+
+use crate::render::percent_encode;
+
+/// Keychain name of the S3 secret access key.
+pub const S3_SECRET: &str = "publish-s3-secret";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where published notes go. Only S3-compatible storage (AWS, Cloudflare
+/// R2, Backblaze B2, MinIO) for now.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishProvider {
+    S3,
+}
+
+impl PublishProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PublishProvider::S3 => "s3",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "s3" => Some(PublishProvider::S3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct PublishSettings {
+    #[serde(default)]
+    pub s3: Option<S3Target>,
+}
+
+/// A bucket the pages are uploaded to, readable by anyone through
+/// `public_url` or the endpoint itself.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct S3Target {
+    /// Like `https://s3.eu-west-1.amazonaws.com`, or the R2/MinIO endpoint.
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key_id: String,
+    /// Folder in the bucket, like `notes/`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Base URL the bucket is served from, when not the endpoint.
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Where a note is published, kept in the metadata store so later
+/// publishes update the same page.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Publication {
+    pub provider: String,
+    pub key: String,
+    pub url: String,
+    /// Unix seconds of the last upload.
+    pub published_at: u64,
+}
+
+/// An object key for a newly published note: its slug and a random part,
+/// so links can be shared without the bucket's notes being guessable.
+pub fn new_key(prefix: &str, slug: &str, random: &str) -> String {
+    let slug = if slug.is_empty() { "note" } else { slug };
+    format!("{}{}-{}.html", prefix, slug, &random[..random.len().min(12)])
+}
+
+/// A standalone page for a note's rendered `body`, styled by `theme` CSS
+/// on top of the default look.
+pub fn page(title: &str, body: &str, theme: Option<&str>) -> String {
+    let theme = theme.map(|css| format!("\n<style>{}</style>", css)).unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>{}\n</head>\n<body>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape(title),
+        PAGE_STYLE,
+        theme,
+        body
+    )
+}
+
+const PAGE_STYLE: &str = "body{margin:0;font:17px/1.6 -apple-system,BlinkMacSystemFont,\"Segoe UI\",sans-serif;color:#222;background:#fff}\
+main{max-width:42rem;margin:0 auto;padding:3rem 1.25rem}\
+img{max-width:100%}pre{overflow-x:auto;padding:1rem;border-radius:6px}\
+table{border-collapse:collapse}td,th{border:1px solid #ddd;padding:.3rem .6rem}\
+@media (prefers-color-scheme:dark){body{color:#ddd;background:#1a1a1a}}";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Uploads `html` as `key`, replacing any earlier version, and returns its
+/// public URL.
+pub fn upload(target: &S3Target, secret: &str, key: &str, html: &str) -> Result<String, String> {
+    let request = signed_request(target, secret, "PUT", key, html.as_bytes(), Utc::now())?;
+    request
+        .set("Content-Type", "text/html; charset=utf-8")
+        .send_bytes(html.as_bytes())
+        .map_err(|e| format!("Upload failed: {}", e))?;
+    Ok(public_url(target, key))
+}
+
+pub fn delete(target: &S3Target, secret: &str, key: &str) -> Result<(), String> {
+    match signed_request(target, secret, "DELETE", key, b"", Utc::now())?.call() {
+        Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+        Err(e) => Err(format!("Unpublishing failed: {}", e)),
+    }
+}
+
+pub fn public_url(target: &S3Target, key: &str) -> String {
+    let encoded = encode_key(key);
+    match &target.public_url {
+        Some(base) => format!("{}/{}", base.trim_end_matches('/'), encoded),
+        None => format!("{}/{}/{}", target.endpoint.trim_end_matches('/'), target.bucket, encoded),
+    }
+}
+
+fn encode_key(key: &str) -> String {
+    key.split('/').map(percent_encode).collect::<Vec<_>>().join("/")
+}
+
+/// A path-style request for `key` signed with AWS Signature Version 4.
+fn signed_request(
+    target: &S3Target,
+    secret: &str,
+    method: &str,
+    key: &str,
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Result<ureq::Request, String> {
+    let endpoint = Url::parse(&target.endpoint).map_err(|e| format!("Invalid endpoint {}: {}", target.endpoint, e))?;
+    let host = match (endpoint.host_str(), endpoint.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(format!("Invalid endpoint {}", target.endpoint)),
+    };
+    let path = format!("/{}/{}", percent_encode(&target.bucket), encode_key(key));
+    let payload_hash = hex(&Sha256::digest(payload));
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let canonical = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, target.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical.as_bytes()))
+    );
+    let signature = hex(&hmac(&signing_key(secret, &date, &target.region, "s3"), string_to_sign.as_bytes()));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        target.access_key_id, scope, SIGNED_HEADERS, signature
+    );
+
+    let url = format!("{}://{}{}", endpoint.scheme(), host, path);
+    Ok(ureq::request(method, &url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("x-amz-date", &amz_date)
+        .set("x-amz-content-sha256", &payload_hash)
+        .set("Authorization", &authorization))
+}
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac(&key, region.as_bytes());
+    let key = hmac(&key, service.as_bytes());
+    hmac(&key, b"aws4_request")
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(public_url: Option<&str>) -> S3Target {
+        S3Target {
+            endpoint: "https://s3.eu-west-1.amazonaws.com/".to_string(),
+            bucket: "notes".to_string(),
+            region: "eu-west-1".to_string(),
+            access_key_id: "AKID".to_string(),
+            prefix: "p/".to_string(),
+            public_url: public_url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_signing_key() {
+        // The example from AWS's Signature Version 4 documentation.
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex(&key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn test_public_url() {
+        assert_eq!(
+            public_url(&target(None), "p/my note-1a.html"),
+            "https://s3.eu-west-1.amazonaws.com/notes/p/my%20note-1a.html"
+        );
+        assert_eq!(
+            public_url(&target(Some("https://pub.example.com/")), "p/a.html"),
+            "https://pub.example.com/p/a.html"
+        );
+    }
+
+    #[test]
+    fn test_new_key_and_page() {
+        assert_eq!(new_key("p/", "trip", "0123456789abcdef"), "p/trip-0123456789ab.html");
+        assert_eq!(new_key("", "", "abc"), "note-abc.html");
+        assert!(page("A <b>", "<p>x</p>", None).contains("<title>A &lt;b&gt;</title>"));
+        assert!(page("A", "<p>x</p>", Some("main{color:navy}")).contains("</style>\n<style>main{color:navy}</style>\n</head>"));
+    }
+}
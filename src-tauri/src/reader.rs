@@ -0,0 +1,185 @@
+use crate::error::AppError;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use memmap2::Mmap;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Largest note `read_note` returns in one piece unless the config sets
+/// another limit. Bigger files have to be read with `read_note_chunk` so a
+/// single IPC message can't freeze the window.
+pub const MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
+
+const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NoteChunk {
+    pub content: String,
+    pub offset: u64,
+    pub next_offset: u64,
+    pub total_size: u64,
+    pub eof: bool,
+}
+
+pub fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// The note at `path` as text, refused when it's over `limit` bytes.
+pub fn read_whole(path: &Path, limit: u64) -> Result<String, String> {
+    Ok(read_whole_decoded(path, limit)?.0)
+}
+
+/// Like `read_whole`, also naming the encoding the file
+/// was converted from when it wasn't UTF-8.
+pub fn read_whole_decoded(path: &Path, limit: u64) -> Result<(String, Option<&'static str>), AppError> {
+    let size = fs::metadata(path)?.len();
+    if size > limit {
+        return Err(AppError::TooLarge {
+            message: format!(
+                "Note is too large to open in one piece ({}, limit {})",
+                format_size(size),
+                format_size(limit)
+            ),
+            size,
+            limit,
+        });
+    }
+    Ok(decode(fs::read(path)?))
+}
+
+/// `bytes` as text. UTF-8 is taken as is; UTF-16 with a byte order mark and
+/// legacy encodings like Windows-1252 are detected and converted, and their
+/// name returned. Saving writes the text back as UTF-8.
+pub fn decode(bytes: Vec<u8>) -> (String, Option<&'static str>) {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(&bytes).filter(|(encoding, _)| *encoding != UTF_8) {
+        let text = encoding.decode_without_bom_handling(&bytes[bom_len..]).0.into_owned();
+        return (text, Some(encoding.name()));
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        Err(e) => {
+            let bytes = e.into_bytes();
+            let mut detector = EncodingDetector::new();
+            detector.feed(&bytes, true);
+            let encoding = detector.guess(None, false);
+            let text = encoding.decode_without_bom_handling(&bytes).0.into_owned();
+            (text, Some(encoding.name()))
+        }
+    }
+}
+
+/// Reads up to `length` bytes starting at `offset`. The chunk ends on a UTF-8
+/// character boundary, so `next_offset` may be a few bytes short of
+/// `offset + length`. The file is memory-mapped, so only the pages of the
+/// chunk are read however big it is.
+pub fn read_chunk(path: &Path, offset: u64, length: usize) -> Result<NoteChunk, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    if file.metadata().map_err(|e| e.to_string())?.len() == 0 {
+        return Ok(chunk_of(&[], offset, length));
+    }
+    // SAFETY: the map is only read here and dropped before returning. A
+    // file truncated by another process meanwhile can fault the read, the
+    // same risk every mmap reader accepts.
+    let map = unsafe { Mmap::map(&file) }.map_err(|e| e.to_string())?;
+    Ok(chunk_of(&map, offset, length))
+}
+
+/// The chunk of `bytes` at `offset`, cut the way `read_chunk` cuts a file.
+pub fn chunk_of(bytes: &[u8], offset: u64, length: usize) -> NoteChunk {
+    let total_size = bytes.len() as u64;
+    let offset = offset.min(total_size);
+    let start = offset as usize;
+    let end = (start + length.min(MAX_CHUNK_BYTES)).min(bytes.len());
+    let mut buf = &bytes[start..end];
+
+    if (end as u64) < total_size {
+        // A chunk shorter than one character is returned as-is so callers
+        // always make progress.
+        let complete = complete_utf8_len(buf);
+        if complete > 0 {
+            buf = &buf[..complete];
+        }
+    }
+    let next_offset = offset + buf.len() as u64;
+
+    NoteChunk {
+        content: String::from_utf8_lossy(buf).to_string(),
+        offset,
+        next_offset,
+        total_size,
+        eof: next_offset >= total_size,
+    }
+}
+
+/// Length of `buf` without a trailing, incomplete multibyte sequence.
+pub fn complete_utf8_len(buf: &[u8]) -> usize {
+    // A character is at most 4 bytes, so its start is within the last 4.
+    for back in 1..=buf.len().min(4) {
+        let i = buf.len() - back;
+        let width = match buf[i] {
+            0x80..=0xBF => continue,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if width > back { i } else { buf.len() };
+    }
+    buf.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_utf8_len_trims_split_char() {
+        let text = "aé".as_bytes();
+        assert_eq!(complete_utf8_len(text), 3);
+        assert_eq!(complete_utf8_len(&text[..2]), 1);
+    }
+
+    #[test]
+    fn test_decode_converts_legacy_encodings() {
+        assert_eq!(decode("# Café".as_bytes().to_vec()), ("# Café".to_string(), None));
+        let latin1 = b"Le caf\xe9 \xe9tait d\xe9j\xe0 ferm\xe9 quand nous sommes arriv\xe9s.".to_vec();
+        assert_eq!(
+            decode(latin1),
+            ("Le café était déjà fermé quand nous sommes arrivés.".to_string(), Some("windows-1252"))
+        );
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter().chain("# Hé".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!(decode(utf16), ("# Hé".to_string(), Some("UTF-16LE")));
+    }
+
+    #[test]
+    fn test_read_chunk_walks_file_on_char_boundaries() {
+        let path = std::env::temp_dir().join(format!("write-chunk-{}.md", std::process::id()));
+        fs::write(&path, "日本語テキスト").unwrap();
+
+        let mut content = String::new();
+        let mut offset = 0;
+        loop {
+            let chunk = read_chunk(&path, offset, 4).unwrap();
+            assert!(!chunk.content.contains('\u{FFFD}'));
+            content.push_str(&chunk.content);
+            offset = chunk.next_offset;
+            if chunk.eof {
+                break;
+            }
+        }
+        assert_eq!(content, "日本語テキスト");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_chunk_of_matches_file_chunks() {
+        let text = "日本語テキスト";
+        let chunk = chunk_of(text.as_bytes(), 0, 4);
+        assert_eq!((chunk.content.as_str(), chunk.next_offset, chunk.eof), ("日", 3, false));
+        let last = chunk_of(text.as_bytes(), 18, 64);
+        assert_eq!((last.content.as_str(), last.total_size, last.eof), ("ト", 21, true));
+        assert!(chunk_of(&[], 5, 4).eof);
+    }
+}
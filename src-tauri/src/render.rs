@@ -0,0 +1,378 @@
+use crate::{emoji, highlight};
+use base64::Engine;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::path::{Path, PathBuf};
+
+/// Scheme the frontend intercepts to open another note from rendered HTML.
+pub const NOTE_LINK_SCHEME: &str = "note";
+
+/// Renders `content` to sanitized HTML. `[[Target]]` and `[[Target|Label]]`
+/// wikilinks go through `resolve`, and relative link and image URLs are made
+/// absolute against `base_dir` so they load outside the editor. `:shortcode:`
+/// emoji are expanded outside code, and fenced code in a known language is
+/// highlighted with `code_theme` when one is given. ```` ```mermaid ````
+/// blocks become the SVG `diagram` draws for them, or stay code without one.
+pub fn render(
+    content: &str,
+    base_dir: Option<&Path>,
+    code_theme: Option<&str>,
+    resolve: impl Fn(&str) -> Option<PathBuf>,
+    diagram: impl Fn(&str) -> Option<String>,
+) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    let parser = Parser::new_ext(content, options);
+    let mut in_code = false;
+    // Language and buffered text of a fenced block being highlighted.
+    let mut highlighted: Option<(String, String)> = None;
+    // Source of a diagram block, and the SVGs drawn so far.
+    let mut diagram_source: Option<String> = None;
+    let mut diagrams: Vec<String> = Vec::new();
+    let events = merge_text(parser)
+        .into_iter()
+        .flat_map(|event| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if fence_language(&lang) == "mermaid" => {
+                in_code = true;
+                diagram_source = Some(String::new());
+                vec![]
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                if code_theme.is_some() && highlight::supports(fence_language(&lang)) =>
+            {
+                in_code = true;
+                highlighted = Some((fence_language(&lang).to_string(), String::new()));
+                vec![]
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code = true;
+                vec![Event::Start(Tag::CodeBlock(kind))]
+            }
+            Event::Text(text) if diagram_source.is_some() => {
+                if let Some(source) = diagram_source.as_mut() {
+                    source.push_str(&text);
+                }
+                vec![]
+            }
+            Event::Text(text) if highlighted.is_some() => {
+                if let Some((_, code)) = highlighted.as_mut() {
+                    code.push_str(&text);
+                }
+                vec![]
+            }
+            Event::End(TagEnd::CodeBlock) if diagram_source.is_some() => {
+                in_code = false;
+                let source = diagram_source.take().unwrap_or_default();
+                match diagram(&source) {
+                    // SVG can't get past the sanitizer, so it goes in once
+                    // the rest of the page is clean.
+                    Some(svg) => {
+                        diagrams.push(svg);
+                        let html = format!("<div>{}</div>", diagram_placeholder(diagrams.len() - 1));
+                        vec![Event::Html(CowStr::from(html))]
+                    }
+                    None => vec![
+                        Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::from("mermaid")))),
+                        Event::Text(CowStr::from(source)),
+                        Event::End(TagEnd::CodeBlock),
+                    ],
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code = false;
+                match highlighted.take() {
+                    Some((lang, code)) => {
+                        let theme = code_theme.unwrap_or(highlight::DEFAULT_THEME);
+                        let html = highlight::highlight(&code, &lang, theme)
+                            .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape_html(&code)));
+                        vec![Event::Html(CowStr::from(html))]
+                    }
+                    None => vec![Event::End(TagEnd::CodeBlock)],
+                }
+            }
+            Event::Text(text) if !in_code => expand_wikilinks(&emoji::expand(&text), &resolve),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => vec![Event::Start(Tag::Link {
+                link_type,
+                dest_url: resolve_url(dest_url, base_dir),
+                title,
+                id,
+            })],
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => vec![Event::Start(Tag::Image {
+                link_type,
+                dest_url: resolve_url(dest_url, base_dir),
+                title,
+                id,
+            })],
+            other => vec![other],
+        });
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    let mut html = sanitize(&unsafe_html);
+    for (i, svg) in diagrams.iter().enumerate() {
+        // As an image, so scripts in the SVG don't run.
+        let data = base64::engine::general_purpose::STANDARD.encode(svg);
+        let image = format!("<img class=\"diagram\" alt=\"Diagram\" src=\"data:image/svg+xml;base64,{}\">", data);
+        html = html.replace(&diagram_placeholder(i), &image);
+    }
+    html
+}
+
+/// Stands in for diagram `i` while the page is sanitized.
+fn diagram_placeholder(i: usize) -> String {
+    format!("\u{E000}diagram-{}\u{E000}", i)
+}
+
+/// The language is the first word of the info string, e.g. `rust` in
+/// `rust,ignore` or `python title="x"`.
+fn fence_language(info: &str) -> &str {
+    info.split(|c: char| c.is_whitespace() || c == ',').next().unwrap_or("")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// A heading's inline markdown as plain text: `**Bold** [link](url)`
+/// becomes `Bold link`, and wikilinks show their label.
+pub fn plain_text(heading: &str) -> String {
+    // Parsed as a heading so text like `1. Intro` isn't taken for a list.
+    let source = format!("# {}", heading);
+    let parser = Parser::new_ext(&source, Options::ENABLE_STRIKETHROUGH);
+    let mut text = String::new();
+    for event in parser {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    let mut plain = String::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        plain.push_str(&rest[..start]);
+        plain.push_str(inner.split_once('|').map_or(inner, |(_, label)| label).trim());
+        rest = &rest[start + 2 + len + 2..];
+    }
+    plain.push_str(rest);
+    plain.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The parser splits text at every bracket, so adjacent text events are
+/// joined before looking for wikilinks.
+fn merge_text<'a>(parser: Parser<'a>) -> Vec<Event<'a>> {
+    let mut events: Vec<Event<'a>> = Vec::new();
+    for event in parser {
+        match (events.last_mut(), event) {
+            (Some(Event::Text(prev)), Event::Text(next)) => {
+                *prev = CowStr::from(format!("{}{}", prev, next));
+            }
+            (_, event) => events.push(event),
+        }
+    }
+    events
+}
+
+fn expand_wikilinks<'a>(text: &str, resolve: &impl Fn(&str) -> Option<PathBuf>) -> Vec<Event<'a>> {
+    let mut events = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+        let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+        let (target, label) = (target.trim(), label.trim());
+
+        if start > 0 {
+            events.push(Event::Text(CowStr::from(rest[..start].to_string())));
+        }
+        let link = match resolve(target) {
+            Some(path) => Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url: CowStr::from(note_url(&path)),
+                title: CowStr::from(target.to_string()),
+                id: CowStr::from(""),
+            },
+            // Unresolved links point at a bare `note:` URL so they can be styled.
+            None => Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url: CowStr::from(format!("{}:", NOTE_LINK_SCHEME)),
+                title: CowStr::from(format!("No note named \"{}\"", target)),
+                id: CowStr::from(""),
+            },
+        };
+        let end = link.to_end();
+        events.push(Event::Start(link));
+        events.push(Event::Text(CowStr::from(label.to_string())));
+        events.push(Event::End(end));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    if !rest.is_empty() {
+        events.push(Event::Text(CowStr::from(rest.to_string())));
+    }
+    events
+}
+
+fn note_url(path: &Path) -> String {
+    format!("{}:{}", NOTE_LINK_SCHEME, percent_encode(&path.to_string_lossy()))
+}
+
+/// Relative paths become asset-protocol URLs for files next to the note.
+/// Anything with a scheme or an anchor is left alone.
+fn resolve_url<'a>(url: CowStr<'a>, base_dir: Option<&Path>) -> CowStr<'a> {
+    let Some(base_dir) = base_dir else {
+        return url;
+    };
+    if url.is_empty() || url.starts_with('#') || url.contains("://") || url.starts_with("mailto:") {
+        return url;
+    }
+    let path = if url.starts_with('/') {
+        PathBuf::from(&*url)
+    } else {
+        base_dir.join(&*url)
+    };
+    CowStr::from(format!("asset://localhost/{}", percent_encode(&path.to_string_lossy())))
+}
+
+pub fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// CSS properties highlighted code uses. Other `style` declarations are
+/// dropped so notes can't restyle or overlay the preview.
+const ALLOWED_STYLES: &[&str] = &["color", "background-color", "font-weight", "font-style", "text-decoration"];
+
+fn sanitize(unsafe_html: &str) -> String {
+    ammonia::Builder::default()
+        .add_url_schemes(&["asset", NOTE_LINK_SCHEME])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .add_tag_attributes("pre", &["style"])
+        .add_tag_attributes("span", &["style"])
+        .add_tags(&["input"])
+        .attribute_filter(|_, attribute, value| {
+            if attribute != "style" {
+                return Some(value.into());
+            }
+            let kept: Vec<&str> = value
+                .split(';')
+                .filter(|declaration| {
+                    declaration.split_once(':').is_some_and(|(property, value)| {
+                        ALLOWED_STYLES.contains(&property.trim()) && !value.contains('(')
+                    })
+                })
+                .collect();
+            Some(kept.join(";").into())
+        })
+        .clean(unsafe_html)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(plain_text("**Bold** _and_ `code` title"), "Bold and code title");
+        assert_eq!(plain_text("See [the docs](https://example.com) ~~now~~"), "See the docs now");
+        assert_eq!(plain_text("About [[Project X|X]] and [[Y]]"), "About X and Y");
+        assert_eq!(plain_text("1. Intro"), "1. Intro");
+        assert_eq!(plain_text("snake_case and 5 * 3"), "snake_case and 5 * 3");
+        assert_eq!(plain_text("Closed heading ##"), "Closed heading");
+    }
+
+    fn no_notes(_: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn no_diagrams(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_render_strips_scripts() {
+        let html = render("# Hi\n\n<script>alert(1)</script><b onclick=\"x()\">bold</b>", None, None, no_notes, no_diagrams);
+        assert!(html.contains("<h1>Hi</h1>"));
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onclick"));
+    }
+
+    #[test]
+    fn test_render_resolves_wikilinks() {
+        let html = render(
+            "See [[Other Note|the other one]] and [[Missing]].",
+            None,
+            None,
+            |target| (target == "Other Note").then(|| PathBuf::from("/Notes/w/2-other-note.md")),
+            no_diagrams,
+        );
+        assert!(html.contains("href=\"note:%2FNotes%2Fw%2F2-other-note.md\""));
+        assert!(html.contains(">the other one</a>"));
+        assert!(html.contains(">Missing</a>"));
+    }
+
+    #[test]
+    fn test_render_leaves_wikilinks_in_code_alone() {
+        let html = render("```\n[[Other]]\n```", None, None, |_| Some(PathBuf::from("/Notes/w/1-other.md")), no_diagrams);
+        assert!(html.contains("[[Other]]"));
+        assert!(!html.contains("href"));
+    }
+
+    #[test]
+    fn test_render_makes_local_images_absolute() {
+        let html = render("![cat](img/cat.png) [site](https://example.com)", Some(Path::new("/Notes/w")), None, no_notes, no_diagrams);
+        assert!(html.contains("src=\"asset://localhost/%2FNotes%2Fw%2Fimg%2Fcat.png\""));
+        assert!(html.contains("href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn test_render_expands_emoji_outside_code() {
+        let html = render("Done :tada: `:tada:`", None, None, no_notes, no_diagrams);
+        assert!(html.contains("Done 🎉 <code>:tada:</code>"));
+    }
+
+    #[test]
+    fn test_render_highlights_fenced_code() {
+        let html = render("```rust,ignore\nlet x = 1;\n```\n\n<span style=\"position:fixed;color:red\">hi</span>", None, Some(highlight::DEFAULT_THEME), no_notes, no_diagrams);
+        assert!(html.contains("<pre style=\"background-color:"));
+        assert!(html.contains("<span style=\"color:"));
+        assert!(html.contains("<span style=\"color:red\">hi</span>"));
+        assert!(!html.contains("position"));
+    }
+
+    #[test]
+    fn test_render_draws_diagrams() {
+        let source = "```mermaid\ngraph TD; A-->B\n```\n\n```mermaid\nbroken\n```";
+        let html = render(source, None, Some(highlight::DEFAULT_THEME), no_notes, |source| {
+            source.starts_with("graph").then(|| "<svg><script>x()</script></svg>".to_string())
+        });
+        let data = base64::engine::general_purpose::STANDARD.encode("<svg><script>x()</script></svg>");
+        assert!(html.contains(&format!("<div><img class=\"diagram\" alt=\"Diagram\" src=\"data:image/svg+xml;base64,{}\"></div>", data)));
+        assert!(html.contains("broken\n</code></pre>"));
+        assert!(!html.contains('\u{E000}'));
+    }
+}
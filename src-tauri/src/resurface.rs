@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+const MONTH_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// A note that hasn't been opened or edited in a while.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Resurfaced {
+    pub path: String,
+    pub title: String,
+    /// Unix seconds of the last open or edit, whichever is later.
+    pub last_seen: u64,
+}
+
+/// How strongly a note unseen since `last_seen` should come back, or none
+/// if it was seen within `months`. Grows with the months forgotten, so the
+/// oldest notes come up most but not only.
+pub fn weight(last_seen: u64, now_secs: u64, months: u32) -> Option<f64> {
+    let unseen = now_secs.saturating_sub(last_seen);
+    (unseen >= months as u64 * MONTH_SECS).then(|| (unseen as f64 / MONTH_SECS as f64).max(1.0))
+}
+
+/// Up to `count` distinct indexes into `weights`, each drawn with chance
+/// proportional to its weight. `roll` gives numbers in `[0, 1)`.
+pub fn sample(weights: &[f64], count: usize, mut roll: impl FnMut() -> f64) -> Vec<usize> {
+    let mut remaining: Vec<(usize, f64)> = weights.iter().copied().enumerate().filter(|(_, w)| *w > 0.0).collect();
+    let mut picked = Vec::new();
+    while picked.len() < count && !remaining.is_empty() {
+        let total: f64 = remaining.iter().map(|(_, w)| w).sum();
+        let mut target = roll() * total;
+        let mut chosen = remaining.len() - 1;
+        for (i, (_, w)) in remaining.iter().enumerate() {
+            if target < *w {
+                chosen = i;
+                break;
+            }
+            target -= w;
+        }
+        picked.push(remaining.remove(chosen).0);
+    }
+    picked
+}
+
+/// A random number in `[0, 1)` from the OS generator.
+pub fn roll() -> f64 {
+    let mut bytes = [0u8; 8];
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return 0.0;
+    }
+    (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weight() {
+        let now = 100 * MONTH_SECS;
+        assert_eq!(weight(now - MONTH_SECS, now, 6), None);
+        assert_eq!(weight(now - 6 * MONTH_SECS, now, 6), Some(6.0));
+        assert_eq!(weight(0, now, 6), Some(100.0));
+        assert_eq!(weight(now, now, 0), Some(1.0));
+    }
+
+    #[test]
+    fn test_sample_is_weighted_and_distinct() {
+        let mut rolls = [0.95, 0.0, 0.5].into_iter();
+        let picked = sample(&[1.0, 0.0, 9.0, 5.0], 3, || rolls.next().unwrap());
+        // 0.95 of 15 lands in the last note, then 0.0 takes the first left.
+        assert_eq!(picked, vec![3, 0, 2]);
+        assert_eq!(sample(&[1.0, 2.0], 5, || 0.3).len(), 2);
+        assert!(sample(&[], 1, || 0.3).is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use serde::Serialize;
+use similar::{Algorithm, DiffTag};
+
+/// Lines changed since the review checkpoint. Line numbers start at 0 and
+/// each line keeps its newline.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Change {
+    /// Position in the list, which is how changes are accepted or rejected.
+    pub index: usize,
+    pub old_start: usize,
+    pub removed: Vec<String>,
+    pub new_start: usize,
+    pub added: Vec<String>,
+}
+
+/// The changes from `base` (the checkpoint) to `current`, adjacent edits
+/// grouped into one.
+pub fn changes(base: &str, current: &str) -> Vec<Change> {
+    let old = lines(base);
+    let new = lines(current);
+    hunks(&old, &new)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (old_range, new_range))| Change {
+            index,
+            old_start: old_range.start,
+            removed: old[old_range].iter().map(|l| l.to_string()).collect(),
+            new_start: new_range.start,
+            added: new[new_range].iter().map(|l| l.to_string()).collect(),
+        })
+        .collect()
+}
+
+/// The checkpoint with change `index` taken in, so it no longer shows as a
+/// change.
+pub fn accept(base: &str, current: &str, index: usize) -> Option<String> {
+    let old = lines(base);
+    let new = lines(current);
+    let (old_range, new_range) = hunks(&old, &new).into_iter().nth(index)?;
+    Some([&old[..old_range.start], &new[new_range], &old[old_range.end..]].concat().concat())
+}
+
+/// The current text with change `index` undone.
+pub fn reject(base: &str, current: &str, index: usize) -> Option<String> {
+    let old = lines(base);
+    let new = lines(current);
+    let (old_range, new_range) = hunks(&old, &new).into_iter().nth(index)?;
+    Some([&new[..new_range.start], &old[old_range], &new[new_range.end..]].concat().concat())
+}
+
+type Hunk = (std::ops::Range<usize>, std::ops::Range<usize>);
+
+fn hunks(old: &[&str], new: &[&str]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in similar::capture_diff_slices(Algorithm::Myers, old, new) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some(last) if last.0.end == old_range.start && last.1.end == new_range.start => {
+                last.0.end = old_range.end;
+                last.1.end = new_range.end;
+            }
+            _ => hunks.push((old_range, new_range)),
+        }
+    }
+    hunks
+}
+
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# Draft\n\nFirst idea.\nSecond idea.\nThe end.\n";
+    const EDITED: &str = "# Draft\n\nFirst, better idea.\nSecond idea.\nA new point.\nThe end.\n";
+
+    #[test]
+    fn test_changes() {
+        assert_eq!(
+            changes(BASE, EDITED),
+            vec![
+                Change {
+                    index: 0,
+                    old_start: 2,
+                    removed: vec!["First idea.\n".to_string()],
+                    new_start: 2,
+                    added: vec!["First, better idea.\n".to_string()],
+                },
+                Change {
+                    index: 1,
+                    old_start: 4,
+                    removed: vec![],
+                    new_start: 4,
+                    added: vec!["A new point.\n".to_string()],
+                },
+            ]
+        );
+        assert!(changes(BASE, BASE).is_empty());
+    }
+
+    #[test]
+    fn test_accept_and_reject_one_change() {
+        let accepted = accept(BASE, EDITED, 1).unwrap();
+        assert_eq!(accepted, "# Draft\n\nFirst idea.\nSecond idea.\nA new point.\nThe end.\n");
+        assert_eq!(changes(&accepted, EDITED).len(), 1);
+
+        let rejected = reject(BASE, EDITED, 0).unwrap();
+        assert_eq!(rejected, "# Draft\n\nFirst idea.\nSecond idea.\nA new point.\nThe end.\n");
+        assert_eq!(accept(BASE, EDITED, 2), None);
+    }
+}
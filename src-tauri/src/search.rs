@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::cache::FileStamp;
+
+/// Word index over note contents, so searching a large workspace doesn't read
+/// every file per keystroke. Files are (re)tokenized lazily, only when their
+/// stamp no longer matches the indexed one.
+#[derive(Default)]
+pub struct SearchIndex {
+    terms: BTreeMap<String, HashSet<PathBuf>>,
+    docs: HashMap<PathBuf, (FileStamp, Vec<String>)>,
+}
+
+impl SearchIndex {
+    pub fn is_current(&self, path: &Path, stamp: FileStamp) -> bool {
+        self.docs.get(path).is_some_and(|(s, _)| *s == stamp)
+    }
+
+    pub fn insert(&mut self, path: &Path, stamp: FileStamp, words: Vec<String>) {
+        self.remove(path);
+        for word in &words {
+            self.terms.entry(word.clone()).or_default().insert(path.to_path_buf());
+        }
+        self.docs.insert(path.to_path_buf(), (stamp, words));
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let Some((_, words)) = self.docs.remove(path) else {
+            return;
+        };
+        for word in words {
+            if let Some(paths) = self.terms.get_mut(&word) {
+                paths.remove(path);
+                if paths.is_empty() {
+                    self.terms.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Notes containing a word starting with each term of `query`.
+    pub fn query(&self, query: &str) -> HashSet<PathBuf> {
+        let mut result: Option<HashSet<PathBuf>> = None;
+        for term in tokenize(query) {
+            let matches: HashSet<PathBuf> = self
+                .terms
+                .range(term.clone()..)
+                .take_while(|(word, _)| word.starts_with(&term))
+                .flat_map(|(_, paths)| paths.iter().cloned())
+                .collect();
+            result = Some(match result {
+                Some(prev) => prev.intersection(&matches).cloned().collect(),
+                None => matches,
+            });
+        }
+        result.unwrap_or_default()
+    }
+}
+
+/// Distinct lowercase words in `text`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let words: HashSet<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    words.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_matches_prefixes_of_all_terms() {
+        let mut index = SearchIndex::default();
+        let a = PathBuf::from("/Notes/w/1-a.md");
+        let b = PathBuf::from("/Notes/w/2-b.md");
+        index.insert(&a, FileStamp::default(), tokenize("# Groceries\nBuy apples and bread"));
+        index.insert(&b, FileStamp::default(), tokenize("# Trip\nApples in Normandy"));
+
+        assert_eq!(index.query("appl"), HashSet::from([a.clone(), b.clone()]));
+        assert_eq!(index.query("apple bre"), HashSet::from([a.clone()]));
+        assert!(index.query("").is_empty());
+
+        index.remove(&a);
+        assert_eq!(index.query("apples"), HashSet::from([b]));
+        assert!(index.query("bread").is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+/// Keychain service every secret is filed under.
+const SERVICE: &str = "write";
+
+/// Names under this prefix hold workspace encryption keys and are only
+/// written by the app itself.
+const WORKSPACE_KEY_PREFIX: &str = "workspace-key:";
+
+/// The local API's bearer token, the WebDAV server's password and the
+/// collaboration server's token, managed by the app like workspace keys.
+pub const API_TOKEN: &str = "api-token";
+pub const WEBDAV_PASSWORD: &str = "webdav-password";
+pub const COLLAB_TOKEN: &str = "collab-token";
+
+/// Secrets like sync credentials, API tokens and encryption keys live in the
+/// macOS Keychain, Windows Credential Manager or the Secret Service on Linux,
+/// never in files under the app data dir.
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| e.to_string())
+}
+
+pub fn get(name: &str) -> Result<Option<String>, String> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn set(name: &str, secret: &str) -> Result<(), String> {
+    entry(name)?.set_password(secret).map_err(|e| e.to_string())
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// A random 256-bit secret as hex, for tokens and passwords the app hands
+/// out.
+pub fn generate() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn workspace_key_name(workspace_id: &str) -> String {
+    format!("{}{}", WORKSPACE_KEY_PREFIX, workspace_id)
+}
+
+/// Checks a name passed in from the frontend: short, plain, and not one of
+/// the app's own workspace keys.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '.' | '_' | '-'));
+    if !valid {
+        return Err(format!("Invalid secret name \"{}\"", name));
+    }
+    if name.starts_with(WORKSPACE_KEY_PREFIX) {
+        return Err("Workspace keys can't be changed directly".to_string());
+    }
+    if [API_TOKEN, WEBDAV_PASSWORD, COLLAB_TOKEN].contains(&name) {
+        return Err(format!("\"{}\" can only be regenerated", name));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("sync:webdav").is_ok());
+        assert!(validate_name("gist-token").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("has space").is_err());
+        assert!(validate_name(&workspace_key_name("Personal")).is_err());
+        assert!(validate_name(API_TOKEN).is_err());
+        assert!(validate_name(WEBDAV_PASSWORD).is_err());
+        assert!(validate_name(COLLAB_TOKEN).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+/// Slugs are cut to this many bytes, so a numbered note name stays well
+/// inside the 255-byte file name limit and Windows' 260-character paths.
+pub const MAX_SLUG_BYTES: usize = 80;
+
+/// Names Windows won't create a file or folder under, whatever the extension.
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+pub fn slugify(text: &str) -> String {
+    slugify_with(text, false)
+}
+
+/// A lowercase, dash-separated name for `text` that's safe on every
+/// platform. With `transliterate`, accents and other scripts are spelled
+/// in ASCII (`Café` is `cafe`, `Привет` is `privet`).
+pub fn slugify_with(text: &str, transliterate: bool) -> String {
+    let text = if transliterate {
+        deunicode::deunicode(text)
+    } else {
+        text.to_string()
+    };
+    let mut result = String::new();
+    let mut prev_dash = false;
+    for c in text.to_lowercase().chars() {
+        let c = match c {
+            c if c.is_alphanumeric() && (c.is_ascii() || !transliterate) => c,
+            _ => '-',
+        };
+        if c == '-' {
+            if !prev_dash && !result.is_empty() {
+                result.push(c);
+            }
+            prev_dash = true;
+        } else {
+            if result.len() + c.len_utf8() > MAX_SLUG_BYTES {
+                // Drop the cut-off word if there's a whole one before it.
+                if let Some(dash) = result.rfind('-') {
+                    result.truncate(dash);
+                }
+                break;
+            }
+            result.push(c);
+            prev_dash = false;
+        }
+    }
+    let slug = result.trim_end_matches('-');
+    if RESERVED.contains(&slug) {
+        format!("{}_", slug)
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(slugify_with("Café Crème", true), "cafe-creme");
+        assert_eq!(slugify_with("Привет мир", true), "privet-mir");
+        assert_eq!(slugify_with("Café Crème", false), "café-crème");
+    }
+
+    #[test]
+    fn test_length_is_capped_on_char_boundaries() {
+        let slug = slugify(&"word ".repeat(40));
+        assert_eq!(slug, ["word"; 16].join("-"));
+        let slug = slugify(&"é".repeat(100));
+        assert_eq!(slug, "é".repeat(MAX_SLUG_BYTES / 2));
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        assert_eq!(slugify("CON"), "con_");
+        assert_eq!(slugify("lpt1."), "lpt1_");
+        assert_eq!(slugify("Console"), "console");
+    }
+}
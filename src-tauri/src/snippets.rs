@@ -0,0 +1,92 @@
+use crate::templates;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Folder holding snippets, one `.md` file each named for its abbreviation.
+/// There's one in the app data dir for snippets shared by every workspace
+/// and one in each workspace.
+pub const SNIPPETS_DIR: &str = "snippets";
+
+/// Where the cursor goes after the snippet is inserted.
+const CURSOR: &str = "{{cursor}}";
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Snippet {
+    /// The abbreviation, the file name without `.md`.
+    pub key: String,
+    pub text: String,
+    /// Whether it comes from the workspace rather than the shared folder.
+    pub workspace: bool,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Expansion {
+    pub text: String,
+    /// The `{{cursor}}` position in `text`, in UTF-16 offsets like the
+    /// editor's.
+    pub cursor: Option<usize>,
+}
+
+/// Snippets in the shared `global_dir` and `workspace_dir`, by key. A
+/// workspace snippet replaces a shared one with the same key.
+pub fn list(global_dir: &Path, workspace_dir: &Path) -> Vec<Snippet> {
+    let mut snippets: HashMap<String, Snippet> = HashMap::new();
+    for (dir, workspace) in [(global_dir, false), (workspace_dir, true)] {
+        let Ok(entries) = fs::read_dir(dir.join(SNIPPETS_DIR)) else {
+            continue;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let (Some(key), Ok(text)) = (path.file_stem(), fs::read_to_string(&path)) else {
+                continue;
+            };
+            let key = key.to_string_lossy().to_string();
+            snippets.insert(
+                key.clone(),
+                Snippet {
+                    key,
+                    text: text.trim_end_matches('\n').to_string(),
+                    workspace,
+                },
+            );
+        }
+    }
+    let mut snippets: Vec<Snippet> = snippets.into_values().collect();
+    snippets.sort_by_key(|s| s.key.to_lowercase());
+    snippets
+}
+
+/// `text` with its date placeholders filled in like a template's, and
+/// `{{cursor}}` taken out. Later cursor markers are dropped.
+pub fn expand(text: &str, now: NaiveDateTime) -> Expansion {
+    let expanded = templates::expand(text, now, &HashMap::new());
+    let cursor = expanded.find(CURSOR).map(|at| expanded[..at].encode_utf16().count());
+    Expansion {
+        text: expanded.replace(CURSOR, ""),
+        cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_expand() {
+        let now = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap().and_hms_opt(14, 5, 0).unwrap();
+        assert_eq!(
+            expand("Best, — {{date}}\n{{cursor}} ✓", now),
+            Expansion {
+                text: "Best, — 2026-03-09\n ✓".to_string(),
+                cursor: Some(19),
+            }
+        );
+        assert_eq!(expand("```\n\n```", now).cursor, None);
+    }
+}
@@ -0,0 +1,164 @@
+use serde::Serialize;
+use spellbook::Dictionary;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const MAX_SUGGESTIONS: usize = 5;
+
+/// A misspelled word. `from` and `to` are UTF-16 offsets into the checked
+/// text, matching the editor's positions.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Misspelling {
+    pub word: String,
+    pub from: usize,
+    pub to: usize,
+    pub suggestions: Vec<String>,
+}
+
+/// Hunspell dictionaries loaded on first use, one language at a time.
+#[derive(Default)]
+pub struct SpellChecker {
+    loaded: Mutex<Option<(String, Arc<Dictionary>)>>,
+}
+
+impl SpellChecker {
+    pub fn dictionary(&self, language: &str, search_dirs: &[PathBuf]) -> Result<Arc<Dictionary>, String> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some((lang, dict)) = loaded.as_ref() {
+            if lang == language {
+                return Ok(Arc::clone(dict));
+            }
+        }
+        let dict = Arc::new(load_dictionary(language, search_dirs)?);
+        *loaded = Some((language.to_string(), Arc::clone(&dict)));
+        Ok(dict)
+    }
+}
+
+/// Languages with both an `.aff` and a `.dic` file in `search_dirs`.
+pub fn available_languages(search_dirs: &[PathBuf]) -> Vec<String> {
+    let mut languages: Vec<String> = search_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "dic") && path.with_extension("aff").exists())
+        .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    languages.sort();
+    languages
+}
+
+fn load_dictionary(language: &str, search_dirs: &[PathBuf]) -> Result<Dictionary, String> {
+    if language.contains(['/', '\\']) || language.starts_with('.') {
+        return Err(format!("Invalid dictionary language \"{}\"", language));
+    }
+    let dir = search_dirs
+        .iter()
+        .find(|dir| dictionary_files_exist(dir, language))
+        .ok_or_else(|| format!("No dictionary installed for \"{}\"", language))?;
+    let aff = fs::read_to_string(dir.join(format!("{}.aff", language))).map_err(|e| e.to_string())?;
+    let dic = fs::read_to_string(dir.join(format!("{}.dic", language))).map_err(|e| e.to_string())?;
+    Dictionary::new(&aff, &dic).map_err(|e| format!("Failed to load dictionary \"{}\": {}", language, e))
+}
+
+fn dictionary_files_exist(dir: &Path, language: &str) -> bool {
+    dir.join(format!("{}.aff", language)).exists() && dir.join(format!("{}.dic", language)).exists()
+}
+
+/// Checks every word in `text`, skipping words in `personal` (compared
+/// case-insensitively) and tokens that aren't prose, like numbers and URLs.
+pub fn check(dict: &Dictionary, personal: &HashSet<String>, text: &str) -> Vec<Misspelling> {
+    words(text)
+        .into_iter()
+        .filter(|(word, _, _)| !personal.contains(&word.to_lowercase()) && !dict.check(word))
+        .map(|(word, from, to)| {
+            let mut suggestions = Vec::new();
+            dict.suggest(word, &mut suggestions);
+            suggestions.truncate(MAX_SUGGESTIONS);
+            Misspelling {
+                word: word.to_string(),
+                from,
+                to,
+                suggestions,
+            }
+        })
+        .collect()
+}
+
+/// Words in `text` with their UTF-16 start and end offsets. Apostrophes inside
+/// a word are kept so contractions are checked whole.
+fn words(text: &str) -> Vec<(&str, usize, usize)> {
+    let mut words = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    let mut utf16 = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_letter = chars.peek().is_some_and(|(_, n)| n.is_alphabetic());
+        let in_word = c.is_alphabetic() || (start.is_some() && (c == '\'' || c == '’') && next_is_letter);
+        match (in_word, start) {
+            (true, None) => start = Some((i, utf16)),
+            (false, Some((byte_start, utf16_start))) => {
+                words.push((&text[byte_start..i], utf16_start, utf16));
+                start = None;
+            }
+            _ => {}
+        }
+        utf16 += c.len_utf16();
+    }
+    if let Some((byte_start, utf16_start)) = start {
+        words.push((&text[byte_start..], utf16_start, utf16));
+    }
+    words.retain(|(word, _, _)| !is_code_like(text, word));
+    words
+}
+
+/// Skips tokens glued to digits, slashes or dots (URLs, paths, versions) and
+/// single letters.
+fn is_code_like(text: &str, word: &str) -> bool {
+    if word.chars().count() < 2 {
+        return true;
+    }
+    let start = word.as_ptr() as usize - text.as_ptr() as usize;
+    let end = start + word.len();
+    let glued = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit() || matches!(c, '/' | '.' | '_' | ':' | '@'));
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    glued(before) || (glued(after) && text[end..].chars().nth(1).is_some_and(|c| !c.is_whitespace()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_reports_utf16_offsets() {
+        assert_eq!(words("Café 🙂 isn't ok"), vec![("Café", 0, 4), ("isn't", 8, 13), ("ok", 14, 16)]);
+    }
+
+    #[test]
+    fn test_words_skips_urls_and_numbers() {
+        let found: Vec<&str> = words("see https://exmple.com/pth and v2 or 3rd. Done.")
+            .into_iter()
+            .map(|(w, _, _)| w)
+            .collect();
+        assert_eq!(found, vec!["see", "and", "or", "Done"]);
+    }
+
+    #[test]
+    fn test_check_uses_personal_dictionary() {
+        let aff = "SET UTF-8\n";
+        let dic = "2\nhello\nworld\n";
+        let dict = Dictionary::new(aff, dic).unwrap();
+        let personal = HashSet::from(["tauri".to_string()]);
+        let found = check(&dict, &personal, "hello Tauri wrld");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].word, "wrld");
+        assert_eq!((found[0].from, found[0].to), (12, 16));
+    }
+}
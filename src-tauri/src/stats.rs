@@ -0,0 +1,194 @@
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Days from `from` to `to`, both included.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Words written on a day. Rewrites count both ways, so `added - removed`
+/// is how much the notes grew.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct DayWords {
+    pub day: NaiveDate,
+    pub added: u64,
+    pub removed: u64,
+}
+
+/// Words a workspace aims for. No goal means any writing at all counts
+/// toward the streak.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct WordGoal {
+    #[serde(default)]
+    pub daily: Option<u64>,
+    #[serde(default)]
+    pub weekly: Option<u64>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct GoalProgress {
+    pub goal: WordGoal,
+    pub today: u64,
+    /// Words since Monday, today included.
+    pub this_week: u64,
+    /// Days in a row the daily goal was met. Today only extends it once
+    /// met, so the streak holds until the day is over.
+    pub streak: u32,
+}
+
+/// Progress toward `goal` from `days` of writing history ending `today`.
+pub fn goal_progress(goal: WordGoal, days: &[DayWords], today: NaiveDate) -> GoalProgress {
+    let added_on = |day: NaiveDate| days.iter().find(|d| d.day == day).map_or(0, |d| d.added);
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let met = |day: NaiveDate| {
+        let added = added_on(day);
+        added > 0 && added >= goal.daily.unwrap_or(0)
+    };
+    let mut day = if met(today) { today } else { today - Duration::days(1) };
+    let mut streak = 0;
+    while met(day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    GoalProgress {
+        goal,
+        today: added_on(today),
+        this_week: days.iter().filter(|d| d.day >= monday && d.day <= today).map(|d| d.added).sum(),
+        streak,
+    }
+}
+
+/// A timed writing sprint. Saves made while one runs are counted toward it.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct WritingSession {
+    /// 0 until the session has ended and been stored.
+    pub id: i64,
+    /// Unix seconds.
+    pub started: u64,
+    pub ended: Option<u64>,
+    /// Seconds the session ran, 0 while it's running.
+    pub duration: u64,
+    pub words_added: u64,
+    pub words_removed: u64,
+    /// Paths of the notes saved during the session, in the order first saved.
+    pub notes: Vec<String>,
+}
+
+impl WritingSession {
+    pub fn new(started: u64) -> Self {
+        WritingSession {
+            id: 0,
+            started,
+            ended: None,
+            duration: 0,
+            words_added: 0,
+            words_removed: 0,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, path: &str, added: u64, removed: u64) {
+        self.words_added += added;
+        self.words_removed += removed;
+        if !self.notes.iter().any(|p| p == path) {
+            self.notes.push(path.to_string());
+        }
+    }
+
+    pub fn end(&mut self, now_secs: u64) {
+        self.ended = Some(now_secs);
+        self.duration = now_secs.saturating_sub(self.started);
+    }
+}
+
+/// `range` as unix seconds from the start of its first local day to the
+/// start of the day after its last.
+pub fn range_secs(range: DateRange) -> (u64, u64) {
+    let start_of = |day: NaiveDate| {
+        day.and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(Local).earliest())
+            .map_or(0, |t| t.timestamp().max(0) as u64)
+    };
+    (start_of(range.from), start_of(range.to + Duration::days(1)))
+}
+
+/// How a save changed a note's word count, as `(added, removed)`.
+pub fn word_delta(before: usize, after: usize) -> (u64, u64) {
+    if after >= before {
+        ((after - before) as u64, 0)
+    } else {
+        (0, (before - after) as u64)
+    }
+}
+
+pub fn day_key(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+pub fn parse_day(key: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(key, "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_delta() {
+        assert_eq!(word_delta(10, 25), (15, 0));
+        assert_eq!(word_delta(25, 10), (0, 15));
+        assert_eq!(word_delta(3, 3), (0, 0));
+    }
+
+    fn day(d: u32, added: u64) -> DayWords {
+        DayWords {
+            day: NaiveDate::from_ymd_opt(2026, 3, d).unwrap(),
+            added,
+            removed: 0,
+        }
+    }
+
+    #[test]
+    fn test_goal_progress() {
+        // Thursday 2026-03-12; the week started on Monday the 9th.
+        let today = NaiveDate::from_ymd_opt(2026, 3, 12).unwrap();
+        let goal = WordGoal {
+            daily: Some(500),
+            weekly: Some(2000),
+        };
+        let history = [day(7, 900), day(8, 100), day(9, 600), day(10, 500), day(11, 700), day(12, 200)];
+        let progress = goal_progress(goal, &history, today);
+        assert_eq!(progress.today, 200);
+        assert_eq!(progress.this_week, 2000);
+        assert_eq!(progress.streak, 3);
+
+        let progress = goal_progress(goal, &[day(11, 700), day(12, 800)], today);
+        assert_eq!(progress.streak, 2);
+        assert_eq!(goal_progress(WordGoal::default(), &[day(12, 1)], today).streak, 1);
+        assert_eq!(goal_progress(goal, &[day(10, 700)], today).streak, 0);
+    }
+
+    #[test]
+    fn test_session_counts_each_note_once() {
+        let mut session = WritingSession::new(1_000);
+        session.record("/notes/a.md", 120, 5);
+        session.record("/notes/b.md", 30, 0);
+        session.record("/notes/a.md", 50, 10);
+        assert_eq!(session.words_added, 200);
+        assert_eq!(session.words_removed, 15);
+        assert_eq!(session.notes, vec!["/notes/a.md", "/notes/b.md"]);
+        session.end(1_600);
+        assert_eq!(session.ended, Some(1_600));
+        assert_eq!(session.duration, 600);
+    }
+
+    #[test]
+    fn test_day_key_round_trips() {
+        let day = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+        assert_eq!(day_key(day), "2026-03-09");
+        assert_eq!(parse_day("2026-03-09"), Some(day));
+        assert_eq!(parse_day("March 9"), None);
+    }
+}
@@ -0,0 +1,845 @@
+use crate::cache::FileStamp;
+use crate::comments::{Comment, TextRange};
+use crate::embeddings;
+use crate::publish::Publication;
+use crate::stats::WritingSession;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// have run, so new entries are only ever appended.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE notes (
+        path TEXT PRIMARY KEY,
+        mtime_ns INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        created INTEGER
+    );
+    CREATE TABLE note_order (
+        workspace_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        key REAL NOT NULL,
+        PRIMARY KEY (workspace_id, name)
+    );
+    CREATE TABLE tags (
+        path TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (path, tag)
+    );
+    CREATE INDEX tags_by_tag ON tags (tag);
+    CREATE TABLE links (
+        path TEXT NOT NULL,
+        target TEXT NOT NULL,
+        PRIMARY KEY (path, target)
+    );
+    CREATE INDEX links_by_target ON links (target);
+    CREATE TABLE note_lists (
+        list TEXT NOT NULL,
+        path TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (list, path)
+    );",
+    "CREATE TABLE dictionary_words (
+        workspace_id TEXT NOT NULL,
+        word TEXT NOT NULL,
+        PRIMARY KEY (workspace_id, word)
+    );",
+    "ALTER TABLE notes ADD COLUMN aliases TEXT;
+    CREATE TABLE note_visits (
+        path TEXT PRIMARY KEY,
+        count INTEGER NOT NULL,
+        last_visited INTEGER NOT NULL
+    );",
+    "CREATE TABLE note_locks (
+        path TEXT PRIMARY KEY
+    );",
+    "CREATE TABLE embeddings (
+        path TEXT PRIMARY KEY,
+        mtime_ns INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        model TEXT NOT NULL,
+        vector BLOB NOT NULL
+    );",
+    "CREATE TABLE publications (
+        path TEXT PRIMARY KEY,
+        provider TEXT NOT NULL,
+        key TEXT NOT NULL,
+        url TEXT NOT NULL,
+        published_at INTEGER NOT NULL
+    );",
+    "CREATE TABLE comments (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        path TEXT NOT NULL,
+        range_from INTEGER NOT NULL,
+        range_to INTEGER NOT NULL,
+        quote TEXT NOT NULL,
+        text TEXT NOT NULL,
+        created INTEGER NOT NULL,
+        resolved INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX comments_by_path ON comments (path);",
+    "CREATE TABLE review_checkpoints (
+        path TEXT PRIMARY KEY,
+        content TEXT NOT NULL,
+        created INTEGER NOT NULL
+    );",
+    "CREATE TABLE writing_stats (
+        day TEXT NOT NULL,
+        workspace_id TEXT NOT NULL,
+        words_added INTEGER NOT NULL,
+        words_removed INTEGER NOT NULL,
+        PRIMARY KEY (day, workspace_id)
+    );",
+    "CREATE TABLE writing_sessions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        started INTEGER NOT NULL,
+        ended INTEGER NOT NULL,
+        words_added INTEGER NOT NULL,
+        words_removed INTEGER NOT NULL,
+        notes TEXT NOT NULL
+    );
+    CREATE INDEX writing_sessions_by_start ON writing_sessions (started);",
+    "CREATE TABLE note_ids (
+        path TEXT PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE
+    );",
+    "ALTER TABLE note_visits ADD COLUMN edits INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE note_visits ADD COLUMN last_edited INTEGER NOT NULL DEFAULT 0;",
+];
+
+/// Saves less than this far apart belong to one editing session, which
+/// counts as one edit however often it autosaves.
+const EDIT_SESSION_SECS: u64 = 60 * 60;
+
+/// Tables keyed by note path, updated together when a note moves or goes away.
+const PATH_TABLES: &[(&str, &str)] = &[
+    ("notes", "path"),
+    ("tags", "path"),
+    ("links", "path"),
+    ("note_lists", "path"),
+    ("note_visits", "path"),
+    ("note_locks", "path"),
+    ("embeddings", "path"),
+    ("publications", "path"),
+    ("comments", "path"),
+    ("review_checkpoints", "path"),
+    ("note_ids", "path"),
+];
+
+/// The note list of pinned notes.
+pub const PINNED_LIST: &str = "pinned";
+
+/// Embedded SQLite database in the app data dir holding note metadata, so
+/// caches and sidecar features share one store. Cheap to clone.
+#[derive(Clone)]
+pub struct MetaStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl MetaStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| e.to_string())?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, String> {
+        Self::init(Connection::open_in_memory().map_err(|e| e.to_string())?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        migrate(&conn).map_err(|e| format!("Failed to migrate metadata store: {}", e))?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap()
+    }
+
+    /// Points every row for `old_path` at `new_path`.
+    pub fn rename_path(&self, old_path: &str, new_path: &str) -> Result<(), String> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (table, column) in PATH_TABLES {
+            tx.execute(
+                &format!("UPDATE OR REPLACE {table} SET {column} = ?2 WHERE {column} = ?1"),
+                (old_path, new_path),
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    pub fn remove_path(&self, path: &str) -> Result<(), String> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (table, column) in PATH_TABLES {
+            tx.execute(&format!("DELETE FROM {table} WHERE {column} = ?1"), [path])
+                .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Blanks the title and aliases the note cache keeps for `path`, for a
+    /// note whose content was just encrypted.
+    pub fn clear_title(&self, path: &str) -> Result<(), String> {
+        self.conn()
+            .execute("UPDATE notes SET title = '', aliases = '[]' WHERE path = ?1", [path])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Stable ids of the notes at `paths`, giving new ones to notes that
+    /// have none yet. Ids follow notes through renames.
+    pub fn note_ids(&self, paths: &[String]) -> Result<HashMap<String, String>, String> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let mut ids = HashMap::new();
+        {
+            let mut select = tx.prepare("SELECT id FROM note_ids WHERE path = ?1").map_err(|e| e.to_string())?;
+            let mut insert = tx
+                .prepare("INSERT INTO note_ids (path, id) VALUES (?1, ?2)")
+                .map_err(|e| e.to_string())?;
+            for path in paths {
+                let existing: Option<String> = select
+                    .query_map([path], |row| row.get(0))
+                    .map_err(|e| e.to_string())?
+                    .next()
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+                let id = match existing {
+                    Some(id) => id,
+                    None => {
+                        let id = new_note_id()?;
+                        insert.execute((path, &id)).map_err(|e| e.to_string())?;
+                        id
+                    }
+                };
+                ids.insert(path.clone(), id);
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok(ids)
+    }
+
+    pub fn path_for_note_id(&self, id: &str) -> Result<Option<String>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT path FROM note_ids WHERE id = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query_map([id], |row| row.get(0)).map_err(|e| e.to_string())?;
+        rows.next().transpose().map_err(|e| e.to_string())
+    }
+
+    pub fn record_visit(&self, path: &str, now_secs: u64) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "INSERT INTO note_visits (path, count, last_visited) VALUES (?1, 1, ?2)
+                 ON CONFLICT (path) DO UPDATE SET count = count + 1, last_visited = ?2",
+                (path, now_secs as i64),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn record_edit(&self, path: &str, now_secs: u64) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "INSERT INTO note_visits (path, count, last_visited, edits, last_edited) VALUES (?1, 0, 0, 1, ?2)
+                 ON CONFLICT (path) DO UPDATE SET edits = edits + (last_edited <= ?2 - ?3), last_edited = ?2",
+                (path, now_secs as i64, EDIT_SESSION_SECS as i64),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Visit count and last visit time (unix seconds) by note path.
+    pub fn visits(&self) -> Result<HashMap<String, (u32, u64)>, String> {
+        self.usage("count", "last_visited")
+    }
+
+    /// Editing sessions and last edit time (unix seconds) by note path.
+    pub fn edits(&self) -> Result<HashMap<String, (u32, u64)>, String> {
+        self.usage("edits", "last_edited")
+    }
+
+    fn usage(&self, count: &str, last: &str) -> Result<HashMap<String, (u32, u64)>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(&format!("SELECT path, {count}, {last} FROM note_visits WHERE {count} > 0"))
+            .map_err(|e| e.to_string())?;
+        let visits = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, (row.get(1)?, row.get::<_, i64>(2)? as u64)))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<HashMap<String, (u32, u64)>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(visits)
+    }
+
+    pub fn is_locked(&self, path: &str) -> Result<bool, String> {
+        self.conn()
+            .query_row("SELECT EXISTS (SELECT 1 FROM note_locks WHERE path = ?1)", [path], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn set_locked(&self, path: &str, locked: bool) -> Result<(), String> {
+        let sql = if locked {
+            "INSERT OR IGNORE INTO note_locks (path) VALUES (?1)"
+        } else {
+            "DELETE FROM note_locks WHERE path = ?1"
+        };
+        self.conn().execute(sql, [path]).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn locked_paths(&self) -> Result<Vec<String>, String> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT path FROM note_locks").map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(paths)
+    }
+
+    /// Appends `paths` to the end of note list `list`, such as
+    /// [`PINNED_LIST`], skipping those already in it.
+    pub fn add_to_list(&self, list: &str, paths: &[String]) -> Result<(), String> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut insert = tx
+                .prepare(
+                    "INSERT OR IGNORE INTO note_lists (list, path, position)
+                     SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM note_lists WHERE list = ?1",
+                )
+                .map_err(|e| e.to_string())?;
+            for path in paths {
+                insert.execute((list, path)).map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// The notes in list `list`, in order.
+    pub fn list_paths(&self, list: &str) -> Result<Vec<String>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT path FROM note_lists WHERE list = ?1 ORDER BY position")
+            .map_err(|e| e.to_string())?;
+        let paths = stmt
+            .query_map([list], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(paths)
+    }
+
+    /// Stored note vectors made with `model`, with the file version each was
+    /// made from.
+    pub fn embeddings(&self, model: &str) -> Result<HashMap<String, (FileStamp, Vec<f32>)>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT path, mtime_ns, size, vector FROM embeddings WHERE model = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([model], |row| {
+                let stamp = FileStamp {
+                    mtime_ns: row.get::<_, i64>(1)? as u64,
+                    size: row.get::<_, i64>(2)? as u64,
+                };
+                Ok((row.get(0)?, (stamp, embeddings::from_bytes(&row.get::<_, Vec<u8>>(3)?))))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<HashMap<String, (FileStamp, Vec<f32>)>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(rows)
+    }
+
+    pub fn set_embedding(&self, path: &str, stamp: FileStamp, model: &str, vector: &[f32]) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO embeddings (path, mtime_ns, size, model, vector) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (path, stamp.mtime_ns as i64, stamp.size as i64, model, embeddings::to_bytes(vector)),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn publication(&self, path: &str) -> Result<Option<Publication>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT provider, key, url, published_at FROM publications WHERE path = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query_map([path], |row| {
+                Ok(Publication {
+                    provider: row.get(0)?,
+                    key: row.get(1)?,
+                    url: row.get(2)?,
+                    published_at: row.get::<_, i64>(3)? as u64,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.next().transpose().map_err(|e| e.to_string())
+    }
+
+    pub fn set_publication(&self, path: &str, publication: Option<&Publication>) -> Result<(), String> {
+        let conn = self.conn();
+        match publication {
+            Some(p) => conn.execute(
+                "INSERT OR REPLACE INTO publications (path, provider, key, url, published_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (path, &p.provider, &p.key, &p.url, p.published_at as i64),
+            ),
+            None => conn.execute("DELETE FROM publications WHERE path = ?1", [path]),
+        }
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    pub fn add_comment(&self, path: &str, range: TextRange, quote: &str, text: &str, created: u64) -> Result<Comment, String> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO comments (path, range_from, range_to, quote, text, created) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (path, range.from as i64, range.to as i64, quote, text, created as i64),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(Comment {
+            id: conn.last_insert_rowid(),
+            range,
+            quote: quote.to_string(),
+            text: text.to_string(),
+            created,
+            resolved: false,
+            anchored: true,
+        })
+    }
+
+    /// The note's comments, oldest first.
+    pub fn comments(&self, path: &str) -> Result<Vec<Comment>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, range_from, range_to, quote, text, created, resolved FROM comments
+                 WHERE path = ?1 ORDER BY id",
+            )
+            .map_err(|e| e.to_string())?;
+        let comments = stmt
+            .query_map([path], |row| {
+                Ok(Comment {
+                    id: row.get(0)?,
+                    range: TextRange {
+                        from: row.get::<_, i64>(1)? as usize,
+                        to: row.get::<_, i64>(2)? as usize,
+                    },
+                    quote: row.get(3)?,
+                    text: row.get(4)?,
+                    created: row.get::<_, i64>(5)? as u64,
+                    resolved: row.get(6)?,
+                    anchored: true,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<Comment>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(comments)
+    }
+
+    pub fn resolve_comment(&self, id: i64) -> Result<(), String> {
+        let changed = self
+            .conn()
+            .execute("UPDATE comments SET resolved = 1 WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+        if changed == 0 {
+            return Err(format!("No comment {}", id));
+        }
+        Ok(())
+    }
+
+    /// The note's text at its review checkpoint.
+    pub fn review_checkpoint(&self, path: &str) -> Result<Option<String>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT content FROM review_checkpoints WHERE path = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query_map([path], |row| row.get(0)).map_err(|e| e.to_string())?;
+        rows.next().transpose().map_err(|e| e.to_string())
+    }
+
+    pub fn set_review_checkpoint(&self, path: &str, content: Option<&str>, now_secs: u64) -> Result<(), String> {
+        let conn = self.conn();
+        match content {
+            Some(content) => conn.execute(
+                "INSERT OR REPLACE INTO review_checkpoints (path, content, created) VALUES (?1, ?2, ?3)",
+                (path, content, now_secs as i64),
+            ),
+            None => conn.execute("DELETE FROM review_checkpoints WHERE path = ?1", [path]),
+        }
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Creation times the note cache recorded, for notes whose file system
+    /// has no birth time.
+    pub fn created_times(&self) -> Result<HashMap<String, u64>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT path, created FROM notes WHERE created IS NOT NULL")
+            .map_err(|e| e.to_string())?;
+        let times = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(times)
+    }
+
+    /// Replaces the indexed tags of each note in `notes`, all in one
+    /// transaction.
+    pub fn set_tags(&self, notes: &[(String, Vec<String>)]) -> Result<(), String> {
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut clear = tx.prepare("DELETE FROM tags WHERE path = ?1").map_err(|e| e.to_string())?;
+            let mut insert = tx
+                .prepare("INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)")
+                .map_err(|e| e.to_string())?;
+            for (path, tags) in notes {
+                clear.execute([path]).map_err(|e| e.to_string())?;
+                for tag in tags {
+                    insert.execute((path, tag)).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Adds to the words written in the workspace on `day` (`YYYY-MM-DD`).
+    pub fn record_words(&self, day: &str, workspace_id: &str, added: u64, removed: u64) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "INSERT INTO writing_stats (day, workspace_id, words_added, words_removed) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (day, workspace_id) DO UPDATE SET
+                    words_added = words_added + ?3, words_removed = words_removed + ?4",
+                (day, workspace_id, added as i64, removed as i64),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Words added and removed per day from `from` to `to` (both
+    /// `YYYY-MM-DD`, included), in one workspace or all of them, oldest
+    /// first. Days without writing are left out.
+    pub fn words_by_day(&self, from: &str, to: &str, workspace_id: Option<&str>) -> Result<Vec<(String, u64, u64)>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT day, SUM(words_added), SUM(words_removed) FROM writing_stats
+                 WHERE day BETWEEN ?1 AND ?2 AND (?3 IS NULL OR workspace_id = ?3)
+                 GROUP BY day ORDER BY day",
+            )
+            .map_err(|e| e.to_string())?;
+        let days = stmt
+            .query_map((from, to, workspace_id), |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(days)
+    }
+
+    /// Stores an ended session and returns its id.
+    pub fn add_session(&self, session: &WritingSession) -> Result<i64, String> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO writing_sessions (started, ended, words_added, words_removed, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                session.started as i64,
+                session.ended.unwrap_or(session.started) as i64,
+                session.words_added as i64,
+                session.words_removed as i64,
+                serde_json::to_string(&session.notes).unwrap_or_default(),
+            ),
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Sessions started from `from` up to (not including) `to`, both unix
+    /// seconds, oldest first.
+    pub fn sessions(&self, from: u64, to: u64) -> Result<Vec<WritingSession>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, started, ended, words_added, words_removed, notes FROM writing_sessions
+                 WHERE started >= ?1 AND started < ?2 ORDER BY started",
+            )
+            .map_err(|e| e.to_string())?;
+        let sessions = stmt
+            .query_map((from as i64, to as i64), |row| {
+                let notes: String = row.get(5)?;
+                let started = row.get::<_, i64>(1)? as u64;
+                let ended = row.get::<_, i64>(2)? as u64;
+                Ok(WritingSession {
+                    id: row.get(0)?,
+                    started,
+                    ended: Some(ended),
+                    duration: ended.saturating_sub(started),
+                    words_added: row.get::<_, i64>(3)? as u64,
+                    words_removed: row.get::<_, i64>(4)? as u64,
+                    notes: serde_json::from_str(&notes).unwrap_or_default(),
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(sessions)
+    }
+
+    /// Words the user added to the workspace's personal dictionary.
+    pub fn dictionary_words(&self, workspace_id: &str) -> Result<Vec<String>, String> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare("SELECT word FROM dictionary_words WHERE workspace_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let words = stmt
+            .query_map([workspace_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(words)
+    }
+
+    pub fn add_dictionary_word(&self, workspace_id: &str, word: &str) -> Result<(), String> {
+        self.conn()
+            .execute(
+                "INSERT OR IGNORE INTO dictionary_words (workspace_id, word) VALUES (?1, ?2)",
+                (workspace_id, word),
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Imports manual order from the JSON sidecar used before this store
+    /// existed, then removes the file.
+    pub fn import_legacy_order(&self, path: &Path) -> Result<(), String> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(());
+        };
+        let workspaces: HashMap<String, HashMap<String, f64>> = serde_json::from_str(&content).unwrap_or_default();
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        for (workspace_id, keys) in &workspaces {
+            for (name, key) in keys {
+                tx.execute(
+                    "INSERT OR IGNORE INTO note_order (workspace_id, name, key) VALUES (?1, ?2, ?3)",
+                    (workspace_id, name, key),
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        fs::remove_file(path).map_err(|e| e.to_string())
+    }
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(sql)?;
+        conn.pragma_update(None, "user_version", i + 1)?;
+    }
+    Ok(())
+}
+
+/// Whether `text` has the shape of a note id, a hyphenated UUID.
+pub fn is_note_id(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// A random (version 4) UUID.
+fn new_note_id() -> Result<String, String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let path = std::env::temp_dir().join(format!("write-store-{}.db", std::process::id()));
+        MetaStore::open(&path).unwrap();
+        let store = MetaStore::open(&path).unwrap();
+        let version: usize = store
+            .conn()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[test]
+    fn test_note_lists_keep_order() {
+        let store = MetaStore::open_in_memory().unwrap();
+        let paths = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        store.add_to_list(PINNED_LIST, &paths(&["/a.md", "/b.md"])).unwrap();
+        store.add_to_list(PINNED_LIST, &paths(&["/c.md", "/a.md"])).unwrap();
+        assert_eq!(store.list_paths(PINNED_LIST).unwrap(), paths(&["/a.md", "/b.md", "/c.md"]));
+        assert!(store.list_paths("other").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rename_and_remove_path_cover_all_tables() {
+        let store = MetaStore::open_in_memory().unwrap();
+        store
+            .conn()
+            .execute_batch(
+                "INSERT INTO notes VALUES ('/w/1-a.md', 1, 1, 'A', NULL, '[]');
+                 INSERT INTO tags VALUES ('/w/1-a.md', 'work');
+                 INSERT INTO links VALUES ('/w/1-a.md', '2-b');",
+            )
+            .unwrap();
+
+        store.rename_path("/w/1-a.md", "/w/1-b.md").unwrap();
+        let count = |sql: &str| -> i64 { store.conn().query_row(sql, [], |row| row.get(0)).unwrap() };
+        assert_eq!(count("SELECT COUNT(*) FROM tags WHERE path = '/w/1-b.md'"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM notes WHERE path = '/w/1-a.md'"), 0);
+
+        store.clear_title("/w/1-b.md").unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM notes WHERE path = '/w/1-b.md' AND title = ''"), 1);
+
+        store.remove_path("/w/1-b.md").unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM notes"), 0);
+        assert_eq!(count("SELECT COUNT(*) FROM links"), 0);
+    }
+
+    #[test]
+    fn test_note_ids_are_stable_across_renames() {
+        let store = MetaStore::open_in_memory().unwrap();
+        let paths = vec!["/w/1-a.md".to_string(), "/w/2-b.md".to_string()];
+        let ids = store.note_ids(&paths).unwrap();
+        let id = ids["/w/1-a.md"].clone();
+        assert!(is_note_id(&id));
+        assert!(!is_note_id("/w/1-a.md"));
+        assert_ne!(id, ids["/w/2-b.md"]);
+        assert_eq!(store.note_ids(&paths[..1]).unwrap()["/w/1-a.md"], id);
+
+        store.rename_path("/w/1-a.md", "/w/1-renamed.md").unwrap();
+        assert_eq!(store.path_for_note_id(&id).unwrap().as_deref(), Some("/w/1-renamed.md"));
+        store.remove_path("/w/1-renamed.md").unwrap();
+        assert_eq!(store.path_for_note_id(&id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_edits_count_sessions() {
+        let store = MetaStore::open_in_memory().unwrap();
+        store.record_edit("/w/1-a.md", 1_000).unwrap();
+        store.record_edit("/w/1-a.md", 1_000 + 60).unwrap();
+        store.record_edit("/w/1-a.md", 1_000 + 60 + EDIT_SESSION_SECS).unwrap();
+        store.record_visit("/w/2-b.md", 5_000).unwrap();
+        assert_eq!(store.edits().unwrap()["/w/1-a.md"], (2, 1_000 + 60 + EDIT_SESSION_SECS));
+        assert!(!store.edits().unwrap().contains_key("/w/2-b.md"));
+        assert!(!store.visits().unwrap().contains_key("/w/1-a.md"));
+
+        store.record_visit("/w/1-a.md", 9_000).unwrap();
+        assert_eq!(store.visits().unwrap()["/w/1-a.md"], (1, 9_000));
+        assert_eq!(store.edits().unwrap()["/w/1-a.md"].0, 2);
+    }
+
+    #[test]
+    fn test_locks_follow_renames() {
+        let store = MetaStore::open_in_memory().unwrap();
+        store.set_locked("/w/1-a.md", true).unwrap();
+        store.rename_path("/w/1-a.md", "/w/1-b.md").unwrap();
+        assert!(!store.is_locked("/w/1-a.md").unwrap());
+        assert!(store.is_locked("/w/1-b.md").unwrap());
+        store.set_locked("/w/1-b.md", false).unwrap();
+        assert!(store.locked_paths().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_embeddings_round_trip_per_model() {
+        let store = MetaStore::open_in_memory().unwrap();
+        let stamp = FileStamp { mtime_ns: 5, size: 10 };
+        store.set_embedding("/w/1-a.md", stamp, "small", &[0.5, 1.0]).unwrap();
+        let stored = store.embeddings("small").unwrap();
+        assert_eq!(stored["/w/1-a.md"], (stamp, vec![0.5, 1.0]));
+        assert!(store.embeddings("large").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_comments_follow_renames_and_resolve() {
+        let store = MetaStore::open_in_memory().unwrap();
+        let range = TextRange { from: 0, to: 4 };
+        let added = store.add_comment("/w/1-a.md", range, "Plan", "Too vague", 9).unwrap();
+        store.rename_path("/w/1-a.md", "/w/1-b.md").unwrap();
+        assert!(store.comments("/w/1-a.md").unwrap().is_empty());
+        assert_eq!(store.comments("/w/1-b.md").unwrap(), vec![added.clone()]);
+
+        store.resolve_comment(added.id).unwrap();
+        assert!(store.comments("/w/1-b.md").unwrap()[0].resolved);
+        assert!(store.resolve_comment(added.id + 1).is_err());
+    }
+
+    #[test]
+    fn test_words_by_day_per_workspace_and_overall() {
+        let store = MetaStore::open_in_memory().unwrap();
+        store.record_words("2026-03-01", "personal", 100, 5).unwrap();
+        store.record_words("2026-03-01", "personal", 20, 0).unwrap();
+        store.record_words("2026-03-01", "work", 30, 10).unwrap();
+        store.record_words("2026-03-04", "work", 7, 0).unwrap();
+        assert_eq!(
+            store.words_by_day("2026-03-01", "2026-03-31", None).unwrap(),
+            vec![("2026-03-01".to_string(), 150, 15), ("2026-03-04".to_string(), 7, 0)]
+        );
+        assert_eq!(
+            store.words_by_day("2026-03-01", "2026-03-02", Some("personal")).unwrap(),
+            vec![("2026-03-01".to_string(), 120, 5)]
+        );
+    }
+
+    #[test]
+    fn test_publications_follow_renames() {
+        let store = MetaStore::open_in_memory().unwrap();
+        let publication = Publication {
+            provider: "s3".to_string(),
+            key: "a-1.html".to_string(),
+            url: "https://x/a-1.html".to_string(),
+            published_at: 7,
+        };
+        store.set_publication("/w/1-a.md", Some(&publication)).unwrap();
+        store.rename_path("/w/1-a.md", "/w/1-b.md").unwrap();
+        assert_eq!(store.publication("/w/1-a.md").unwrap(), None);
+        assert_eq!(store.publication("/w/1-b.md").unwrap(), Some(publication));
+        store.set_publication("/w/1-b.md", None).unwrap();
+        assert_eq!(store.publication("/w/1-b.md").unwrap(), None);
+    }
+}
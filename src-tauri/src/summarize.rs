@@ -0,0 +1,150 @@
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Ollama's default address. A llama.cpp server works too, since both speak
+/// the OpenAI chat completions API.
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+pub const DEFAULT_MODEL: &str = "llama3.2";
+
+/// Local models are slow on long notes, so this is far longer than the
+/// grammar check's timeout.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Notes are cut to this many characters so they fit a small model's context.
+const MAX_INPUT_CHARS: usize = 24_000;
+
+const PROMPT: &str = "Summarize the following markdown note in a few sentences. \
+Reply with the summary only, in the note's language.";
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+/// Summarizes `content` with the model at `endpoint`. Endpoints on another
+/// machine are refused unless `allow_remote` is set, so notes don't leave the
+/// computer without the user asking for it.
+pub fn summarize(endpoint: &str, model: &str, content: &str, allow_remote: bool) -> Result<String, String> {
+    check_endpoint(endpoint, allow_remote)?;
+    let text = match content.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((end, _)) => &content[..end],
+        None => content,
+    };
+    if text.trim().is_empty() {
+        return Err("Note is empty".to_string());
+    }
+    let url = format!("{}/v1/chat/completions", endpoint.trim_end_matches('/'));
+    let body = ureq::post(&url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(
+            &json!({
+                "model": model,
+                "stream": false,
+                "messages": [
+                    { "role": "system", "content": PROMPT },
+                    { "role": "user", "content": text },
+                ],
+            })
+            .to_string(),
+        )
+        .map_err(|e| match e {
+            ureq::Error::Status(code, _) => format!("Model server returned {}", code),
+            ureq::Error::Transport(_) => format!("Model server isn't reachable at {}", endpoint),
+        })?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    parse_response(&body)
+}
+
+fn parse_response(body: &str) -> Result<String, String> {
+    let response: ChatResponse =
+        serde_json::from_str(body).map_err(|e| format!("Unexpected model server response: {}", e))?;
+    let summary = response
+        .choices
+        .into_iter()
+        .next()
+        .map(|c| c.message.content.trim().to_string())
+        .unwrap_or_default();
+    if summary.is_empty() {
+        return Err("Model returned an empty summary".to_string());
+    }
+    Ok(summary)
+}
+
+/// Refuses endpoints that aren't HTTP, and ones on another machine unless
+/// `allow_remote` is set.
+pub fn check_endpoint(endpoint: &str, allow_remote: bool) -> Result<(), String> {
+    if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+        return Err(format!("Invalid model endpoint URL \"{}\"", endpoint));
+    }
+    if !allow_remote && !is_local(endpoint) {
+        return Err(format!(
+            "{} isn't on this computer. Allow remote providers to send notes to it",
+            endpoint
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `endpoint` points at this machine.
+fn is_local(endpoint: &str) -> bool {
+    let authority = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest)
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or("");
+    // Credentials before an `@` would otherwise hide the real host.
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    let host = if let Some(rest) = host_port.strip_prefix('[') {
+        rest.split(']').next().unwrap_or("")
+    } else {
+        host_port.split(':').next().unwrap_or("")
+    };
+    let host = host.to_ascii_lowercase();
+    host == "localhost"
+        || host == "::1"
+        || host.parse::<std::net::Ipv4Addr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local() {
+        assert!(is_local("http://localhost:11434"));
+        assert!(is_local("http://127.0.0.1:8080/"));
+        assert!(is_local("http://[::1]:8080"));
+        assert!(!is_local("https://api.example.com"));
+        assert!(!is_local("http://localhost@example.com"));
+        assert!(!is_local("http://localhost.example.com"));
+    }
+
+    #[test]
+    fn test_summarize_refuses_remote_endpoint_by_default() {
+        let err = summarize("https://api.example.com", DEFAULT_MODEL, "# Note", false).unwrap_err();
+        assert!(err.contains("isn't on this computer"));
+        assert!(summarize("file:///tmp/model", DEFAULT_MODEL, "# Note", true).is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let body = r#"{"choices": [{"index": 0, "message": {"role": "assistant", "content": " A short summary.\n"}}]}"#;
+        assert_eq!(parse_response(body).unwrap(), "A short summary.");
+        assert!(parse_response(r#"{"choices": []}"#).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use serde::Deserialize;
+
+/// A structural change applied before re-aligning. Row indices count body
+/// rows only, so 0 is the first row under the delimiter.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TableEdit {
+    InsertColumn { index: usize },
+    DeleteColumn { index: usize },
+    InsertRow { index: usize },
+    DeleteRow { index: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+struct Table {
+    indent: String,
+    header: Vec<String>,
+    aligns: Vec<Align>,
+    rows: Vec<Vec<String>>,
+}
+
+/// Re-aligns the GFM table in `text` so every column is padded to its widest
+/// cell, applying `edit` first. Leading indentation and a trailing newline are
+/// kept; cells are never rewritten beyond trimming.
+pub fn format_table(text: &str, edit: Option<TableEdit>) -> Result<String, String> {
+    let mut table = parse(text).ok_or("Not a markdown table")?;
+    if let Some(edit) = edit {
+        apply(&mut table, edit)?;
+    }
+    let mut out = render(&table);
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn parse(text: &str) -> Option<Table> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 2 {
+        return None;
+    }
+    let indent = lines[0][..lines[0].len() - lines[0].trim_start().len()].to_string();
+    let header = split_row(lines[0]);
+    let aligns = split_row(lines[1])
+        .iter()
+        .map(|cell| parse_align(cell))
+        .collect::<Option<Vec<Align>>>()?;
+    if aligns.len() != header.len() || !lines[0].contains('|') {
+        return None;
+    }
+    let mut table = Table {
+        indent,
+        header,
+        aligns,
+        rows: lines[2..].iter().map(|line| split_row(line)).collect(),
+    };
+    // Cells past the header would be dropped by a renderer; widen the table
+    // rather than lose them.
+    let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0).max(table.header.len());
+    table.header.resize(columns, String::new());
+    table.aligns.resize(columns, Align::None);
+    for row in &mut table.rows {
+        row.resize(columns, String::new());
+    }
+    Some(table)
+}
+
+/// Splits a row on unescaped pipes, dropping the optional outer ones.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in line.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn parse_align(cell: &str) -> Option<Align> {
+    let left = cell.starts_with(':');
+    let right = cell.ends_with(':');
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (left, right) {
+        (true, true) => Align::Center,
+        (true, false) => Align::Left,
+        (false, true) => Align::Right,
+        (false, false) => Align::None,
+    })
+}
+
+fn apply(table: &mut Table, edit: TableEdit) -> Result<(), String> {
+    let columns = table.header.len();
+    match edit {
+        TableEdit::InsertColumn { index } => {
+            let index = index.min(columns);
+            table.header.insert(index, String::new());
+            table.aligns.insert(index, Align::None);
+            for row in &mut table.rows {
+                row.insert(index, String::new());
+            }
+        }
+        TableEdit::DeleteColumn { index } => {
+            if index >= columns {
+                return Err("Column is out of range".to_string());
+            }
+            if columns == 1 {
+                return Err("Can't delete a table's only column".to_string());
+            }
+            table.header.remove(index);
+            table.aligns.remove(index);
+            for row in &mut table.rows {
+                row.remove(index);
+            }
+        }
+        TableEdit::InsertRow { index } => {
+            let index = index.min(table.rows.len());
+            table.rows.insert(index, vec![String::new(); columns]);
+        }
+        TableEdit::DeleteRow { index } => {
+            if index >= table.rows.len() {
+                return Err("Row is out of range".to_string());
+            }
+            table.rows.remove(index);
+        }
+    }
+    Ok(())
+}
+
+fn render(table: &Table) -> String {
+    let width = |s: &str| s.chars().count();
+    let widths: Vec<usize> = (0..table.header.len())
+        .map(|i| {
+            std::iter::once(&table.header)
+                .chain(&table.rows)
+                .map(|row| width(&row[i]))
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+
+    let row_line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .zip(&table.aligns)
+            .map(|((cell, &w), align)| {
+                let fill = w - width(cell);
+                match align {
+                    Align::Right => format!("{}{}", " ".repeat(fill), cell),
+                    Align::Center => format!("{}{}{}", " ".repeat(fill / 2), cell, " ".repeat(fill - fill / 2)),
+                    Align::None | Align::Left => format!("{}{}", cell, " ".repeat(fill)),
+                }
+            })
+            .collect();
+        format!("{}| {} |", table.indent, padded.join(" | "))
+    };
+    let delimiter: Vec<String> = widths
+        .iter()
+        .zip(&table.aligns)
+        .map(|(&w, align)| match align {
+            Align::None => "-".repeat(w),
+            Align::Left => format!(":{}", "-".repeat(w - 1)),
+            Align::Right => format!("{}:", "-".repeat(w - 1)),
+            Align::Center => format!(":{}:", "-".repeat(w - 2)),
+        })
+        .collect();
+
+    let mut lines = vec![row_line(&table.header), format!("{}| {} |", table.indent, delimiter.join(" | "))];
+    lines.extend(table.rows.iter().map(|row| row_line(row)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_table_aligns_columns() {
+        let text = "| Name | Qty |Note|\n|:--|--:|:-:|\n| Apples | 3 | ripe |\n|Kiwi|12|\n";
+        assert_eq!(
+            format_table(text, None).unwrap(),
+            "| Name   | Qty | Note |\n\
+             | :----- | --: | :--: |\n\
+             | Apples |   3 | ripe |\n\
+             | Kiwi   |  12 |      |\n"
+        );
+    }
+
+    #[test]
+    fn test_format_table_keeps_escaped_pipes_and_indent() {
+        let text = "  a | b\n  --- | ---\n  x \\| y | z";
+        assert_eq!(
+            format_table(text, None).unwrap(),
+            "  | a      | b   |\n  | ------ | --- |\n  | x \\| y | z   |"
+        );
+    }
+
+    #[test]
+    fn test_format_table_edits() {
+        let text = "| a | b |\n| - | - |\n| 1 | 2 |";
+        let inserted = format_table(text, Some(TableEdit::InsertColumn { index: 1 })).unwrap();
+        assert_eq!(inserted, "| a   |     | b   |\n| --- | --- | --- |\n| 1   |     | 2   |");
+        let deleted = format_table(text, Some(TableEdit::DeleteRow { index: 0 })).unwrap();
+        assert_eq!(deleted, "| a   | b   |\n| --- | --- |");
+        assert!(format_table(text, Some(TableEdit::DeleteRow { index: 1 })).is_err());
+        assert!(format_table("just text\nmore", None).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// Separates the levels of a nested tag like `#project/alpha`.
+pub const SEPARATOR: char = '/';
+
+/// A tag in the tag tree, counting the notes tagged with it or anything
+/// nested under it.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct TagNode {
+    /// The last level, `alpha` in `project/alpha`.
+    pub name: String,
+    /// The whole tag.
+    pub tag: String,
+    pub count: usize,
+    pub children: Vec<TagNode>,
+}
+
+/// Tags in a note: the frontmatter `tags` and inline `#tags` outside code,
+/// in the order they first appear, each once regardless of case.
+pub fn parse_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for span in tag_spans(content) {
+        let tag = &content[span];
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Byte ranges of every tag in `content`, without the `#` or quotes, in
+/// order.
+fn tag_spans(content: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut add = |tag: &str| {
+        let tag = tag.trim().trim_start_matches('#').trim_end_matches(SEPARATOR);
+        if is_tag(tag) {
+            let start = tag.as_ptr() as usize - content.as_ptr() as usize;
+            spans.push(start..start + tag.len());
+        }
+    };
+
+    let mut lines = content.lines().peekable();
+    if lines.peek().map(|l| l.trim_end()) == Some("---") {
+        lines.next();
+        let mut in_tag_list = false;
+        for line in lines.by_ref() {
+            let line = line.trim_end();
+            if line == "---" || line == "..." {
+                break;
+            }
+            if in_tag_list {
+                if let Some(item) = line.trim_start().strip_prefix("- ") {
+                    add(unquote(item));
+                    continue;
+                }
+                in_tag_list = false;
+            }
+            if let Some(value) = line.strip_prefix("tags:") {
+                let value = value.trim();
+                let value = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(value);
+                in_tag_list = value.is_empty();
+                value.split(',').map(unquote).for_each(&mut add);
+            }
+        }
+    }
+
+    let mut in_fence = false;
+    for line in lines {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let mut in_code = false;
+        let mut prev = ' ';
+        for (i, c) in line.char_indices() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '#' && !in_code && (prev.is_whitespace() || prev == '(') {
+                let rest = &line[i + 1..];
+                let len = rest.find(|c: char| !is_tag_char(c) && c != SEPARATOR).unwrap_or(rest.len());
+                add(&rest[..len]);
+            }
+            prev = c;
+        }
+    }
+    spans
+}
+
+/// `content` with every use of tag `old` renamed to `new`, inline and in
+/// the frontmatter, matching `old` regardless of case. Tags nested under
+/// `old` move with it. None when the note doesn't use `old`.
+pub fn rename_tag(content: &str, old: &str, new: &str) -> Option<String> {
+    let spans: Vec<Range<usize>> = tag_spans(content)
+        .into_iter()
+        .filter(|span| is_within(&content[span.clone()], old))
+        .map(|span| span.start..span.start + old.len())
+        .collect();
+    if spans.is_empty() {
+        return None;
+    }
+    let mut renamed = content.to_string();
+    for span in spans.into_iter().rev() {
+        renamed.replace_range(span, new);
+    }
+    Some(renamed)
+}
+
+/// `content` with `tag` added: to the frontmatter's `tags` when it has
+/// them, otherwise as an inline `#tag` on a last line of tags. None when the
+/// note already has the tag.
+pub fn add_tag(content: &str, tag: &str) -> Option<String> {
+    if parse_tags(content).iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+        return None;
+    }
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+    if lines.first().map(|l| l.trim_end()) == Some("---") {
+        let end = lines.iter().skip(1).position(|l| matches!(l.trim_end(), "---" | "...")).map(|i| i + 1);
+        let tags_line = end.and_then(|end| (1..end).find(|&i| lines[i].starts_with("tags:")));
+        if let (Some(end), Some(i)) = (end, tags_line) {
+            let value = lines[i]["tags:".len()..].trim().to_string();
+            if value.is_empty() {
+                let items = (i + 1..end).take_while(|&j| lines[j].trim_start().starts_with("- ")).count();
+                let indent = lines
+                    .get(i + 1)
+                    .filter(|_| items > 0)
+                    .map_or("  ".to_string(), |l| l[..l.len() - l.trim_start().len()].to_string());
+                lines.insert(i + 1 + items, format!("{}- {}\n", indent, tag));
+            } else {
+                let list = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(&value).trim();
+                let list = if list.is_empty() {
+                    tag.to_string()
+                } else {
+                    format!("{}, {}", list, tag)
+                };
+                lines[i] = format!("tags: [{}]\n", list);
+            }
+            return Some(lines.concat());
+        }
+    }
+    let body = content.trim_end();
+    let last = body.lines().last().unwrap_or_default();
+    let last_is_tags = !last.is_empty() && last.split_whitespace().all(|word| word.strip_prefix('#').is_some_and(is_tag));
+    let separator = match (body.is_empty(), last_is_tags) {
+        (true, _) => "",
+        (false, true) => " ",
+        (false, false) => "\n\n",
+    };
+    Some(format!("{}{}#{}\n", body, separator, tag))
+}
+
+/// Whether `tag` is `parent` or nested under it, regardless of case.
+pub fn is_within(tag: &str, parent: &str) -> bool {
+    tag.get(..parent.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(parent))
+        && matches!(tag[parent.len()..].chars().next(), None | Some(SEPARATOR))
+}
+
+/// The tags of every note as a tree, each level sorted by name. Tags that
+/// only differ in case are one tag, spelled as first seen.
+pub fn tag_tree<'a>(notes: impl IntoIterator<Item = &'a [String]>) -> Vec<TagNode> {
+    let mut roots: Vec<TagNode> = Vec::new();
+    for tags in notes {
+        // A note counts once for each tag, however many of its tags are
+        // nested under it.
+        let mut counted = HashSet::new();
+        for tag in tags {
+            let mut level = &mut roots;
+            for (end, _) in tag.match_indices(SEPARATOR).chain([(tag.len(), "")]) {
+                let path = &tag[..end];
+                let name = path.rsplit(SEPARATOR).next().unwrap_or(path);
+                let i = match level.iter().position(|node| node.name.eq_ignore_ascii_case(name)) {
+                    Some(i) => i,
+                    None => {
+                        level.push(TagNode {
+                            name: name.to_string(),
+                            tag: path.to_string(),
+                            count: 0,
+                            children: Vec::new(),
+                        });
+                        level.len() - 1
+                    }
+                };
+                if counted.insert(path.to_lowercase()) {
+                    level[i].count += 1;
+                }
+                level = &mut level[i].children;
+            }
+        }
+    }
+    sort_tree(&mut roots);
+    roots
+}
+
+fn sort_tree(nodes: &mut [TagNode]) {
+    nodes.sort_by_key(|node| node.name.to_lowercase());
+    for node in nodes {
+        sort_tree(&mut node.children);
+    }
+}
+
+/// Whether `tag` (without `#`) is a valid tag: tag characters only, in
+/// levels separated by `/`, and not just a number like `#1`.
+pub fn is_tag(tag: &str) -> bool {
+    tag.split(SEPARATOR).all(|level| !level.is_empty() && level.chars().all(is_tag_char))
+        && tag.chars().any(|c| !c.is_ascii_digit() && c != '-' && c != '_' && c != SEPARATOR)
+}
+
+/// Characters a level of a tag can have.
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_tags() {
+        let content = "# Title\n\nPlanning #work and #Ideas (#later), not #1 or a#b.\n`#code` ## heading\n```\n#fenced\n```\n#work again #ideas";
+        assert_eq!(parse_tags(content), vec!["work", "Ideas", "later"]);
+    }
+
+    #[test]
+    fn test_add_tag() {
+        assert_eq!(add_tag("# T\n\nBody\n", "work").as_deref(), Some("# T\n\nBody\n\n#work\n"));
+        assert_eq!(add_tag("# T\n\n#a #b\n", "c").as_deref(), Some("# T\n\n#a #b #c\n"));
+        assert_eq!(add_tag("# T #Work\n", "work"), None);
+        assert_eq!(
+            add_tag("---\ntags: [a]\n---\n# T\n", "b").as_deref(),
+            Some("---\ntags: [a, b]\n---\n# T\n")
+        );
+        assert_eq!(
+            add_tag("---\ntags:\n    - a\ndate: x\n---\n", "b").as_deref(),
+            Some("---\ntags:\n    - a\n    - b\ndate: x\n---\n")
+        );
+        assert_eq!(add_tag("---\ntitle: x\n---\nBody", "b").as_deref(), Some("---\ntitle: x\n---\nBody\n\n#b\n"));
+    }
+
+    #[test]
+    fn test_rename_tag() {
+        let content = "---\ntags: [Draft, blog]\n---\n# T\n\n#draft notes, not #drafts or `#draft`\n";
+        assert_eq!(
+            rename_tag(content, "draft", "wip").as_deref(),
+            Some("---\ntags: [wip, blog]\n---\n# T\n\n#wip notes, not #drafts or `#draft`\n")
+        );
+        assert_eq!(
+            rename_tag("---\ntags:\n  - \"draft\"\n---\n", "draft", "wip").as_deref(),
+            Some("---\ntags:\n  - \"wip\"\n---\n")
+        );
+        assert_eq!(rename_tag(content, "other", "wip"), None);
+    }
+
+    #[test]
+    fn test_nested_tags() {
+        assert_eq!(
+            parse_tags("#project/alpha and #project/ and #a//b #2024/q1"),
+            vec!["project/alpha", "project", "2024/q1"]
+        );
+        assert_eq!(
+            rename_tag("#project #project/alpha #projects", "project", "work").as_deref(),
+            Some("#work #work/alpha #projects")
+        );
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let notes = [
+            vec!["project/alpha".to_string(), "project".to_string()],
+            vec!["Project/beta".to_string(), "ideas".to_string()],
+        ];
+        let tree = tag_tree(notes.iter().map(Vec::as_slice));
+        let leaf = |name: &str, tag: &str| TagNode {
+            name: name.to_string(),
+            tag: tag.to_string(),
+            count: 1,
+            children: vec![],
+        };
+        assert_eq!(
+            tree,
+            vec![
+                leaf("ideas", "ideas"),
+                TagNode {
+                    name: "project".to_string(),
+                    tag: "project".to_string(),
+                    count: 2,
+                    children: vec![leaf("alpha", "project/alpha"), leaf("beta", "Project/beta")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frontmatter_tags() {
+        assert_eq!(parse_tags("---\ntags: [draft, \"blog\"]\n---\n# T\n#extra"), vec!["draft", "blog", "extra"]);
+        assert_eq!(parse_tags("---\ntitle: x\ntags:\n  - one\n  - '#two'\n---\n"), vec!["one", "two"]);
+        assert_eq!(parse_tags("---\ntags: solo\n---\n"), vec!["solo"]);
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// A checkbox item. `line` is 1-based.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Task {
+    pub path: String,
+    pub note_title: String,
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+    pub depth: usize,
+    /// From `📅 2024-06-01` or `@due(2024-06-01)` in the text.
+    pub due: Option<NaiveDate>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct TaskFilter {
+    pub done: Option<bool>,
+    /// Case-insensitive substring of the task text.
+    pub query: Option<String>,
+    pub path: Option<String>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &Task) -> bool {
+        self.done.is_none_or(|done| task.done == done)
+            && self.path.as_ref().is_none_or(|path| &task.path == path)
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|q| task.text.to_lowercase().contains(&q.to_lowercase()))
+    }
+}
+
+/// Open tasks due within the agenda's dates, and those already past them.
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Agenda {
+    pub overdue: Vec<Task>,
+    pub upcoming: Vec<Task>,
+}
+
+/// The days an agenda covers, both included; `from` is usually today.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AgendaRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Sorts the open tasks with a due date into `range`'s agenda, soonest first.
+pub fn agenda(tasks: impl IntoIterator<Item = Task>, range: AgendaRange) -> Agenda {
+    let mut agenda = Agenda::default();
+    for task in tasks.into_iter().filter(|task| !task.done) {
+        match task.due {
+            Some(due) if due < range.from => agenda.overdue.push(task),
+            Some(due) if due <= range.to => agenda.upcoming.push(task),
+            _ => {}
+        }
+    }
+    for tasks in [&mut agenda.overdue, &mut agenda.upcoming] {
+        tasks.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.path.cmp(&b.path)).then(a.line.cmp(&b.line)));
+    }
+    agenda
+}
+
+/// The due date in a task's text, written `📅 2024-06-01` or
+/// `@due(2024-06-01)`. The first valid one wins.
+pub fn parse_due(text: &str) -> Option<NaiveDate> {
+    let date = |s: &str| NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok();
+    let emoji = text
+        .match_indices('📅')
+        .find_map(|(i, marker)| date(text[i + marker.len()..].trim_start()));
+    emoji.or_else(|| {
+        text.match_indices("@due(").find_map(|(i, marker)| {
+            let rest = &text[i + marker.len()..];
+            NaiveDate::parse_from_str(rest[..rest.find(')')?].trim(), "%Y-%m-%d").ok()
+        })
+    })
+}
+
+/// A parsed checkbox line: where the `[ ]` box starts, whether it's ticked,
+/// and the text after it.
+struct Checkbox<'a> {
+    box_start: usize,
+    indent: usize,
+    done: bool,
+    text: &'a str,
+}
+
+fn parse_checkbox(line: &str) -> Option<Checkbox<'_>> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker_len = if rest.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let after_marker = rest[marker_len..].strip_prefix(' ')?;
+    let done = match after_marker.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &after_marker[3..];
+    if !text.is_empty() && !text.starts_with(' ') {
+        return None;
+    }
+    Some(Checkbox {
+        box_start: indent + marker_len + 1,
+        indent,
+        done,
+        text: text.trim(),
+    })
+}
+
+/// Tasks in `content` outside fenced code blocks, as (line, text, done, depth).
+/// Depth counts two spaces or a tab per level.
+pub fn parse_tasks(content: &str) -> Vec<(usize, String, bool, usize)> {
+    let mut tasks = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(checkbox) = parse_checkbox(line) {
+            let indent = &line[..checkbox.indent];
+            let depth = indent.matches('\t').count() + indent.matches(' ').count() / 2;
+            tasks.push((i + 1, checkbox.text.to_string(), checkbox.done, depth));
+        }
+    }
+    tasks
+}
+
+/// Flips the checkbox on 1-based `line`. Fails if that line is no longer a
+/// task, or no longer has `expected_text` when given, so a stale list can't
+/// tick the wrong item.
+pub fn toggle_line(content: &str, line: usize, expected_text: Option<&str>) -> Result<(String, bool), String> {
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let target = line
+        .checked_sub(1)
+        .and_then(|i| lines.get(i).copied())
+        .ok_or("Task line is out of range")?;
+    let checkbox = parse_checkbox(target.trim_end_matches(['\n', '\r'])).ok_or("Line is no longer a task")?;
+    if expected_text.is_some_and(|text| text.trim() != checkbox.text) {
+        return Err("Task has changed since it was listed".to_string());
+    }
+    let done = !checkbox.done;
+    let mark = if done { "[x]" } else { "[ ]" };
+    let replaced = format!(
+        "{}{}{}",
+        &target[..checkbox.box_start],
+        mark,
+        &target[checkbox.box_start + 3..]
+    );
+    lines[line - 1] = &replaced;
+    Ok((lines.concat(), done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tasks() {
+        let content = "# Todo\n- [ ] Buy milk\n  - [x] Call Bob\n1. [ ] Numbered\n```\n- [ ] in code\n```\n- [link](x)\n-[ ] no space";
+        assert_eq!(
+            parse_tasks(content),
+            vec![
+                (2, "Buy milk".to_string(), false, 0),
+                (3, "Call Bob".to_string(), true, 1),
+                (4, "Numbered".to_string(), false, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_due() {
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+        assert_eq!(parse_due("pay rent 📅 2024-06-01"), date("2024-06-01"));
+        assert_eq!(parse_due("pay rent 📅2024-06-01 #home"), date("2024-06-01"));
+        assert_eq!(parse_due("call @due( 2024-06-02 ) soon"), date("2024-06-02"));
+        assert_eq!(parse_due("@due(2024-06-02T10:00)"), None);
+        assert_eq!(parse_due("📅 2024-13-01"), None);
+        assert_eq!(parse_due("no date 2024-06-01"), None);
+    }
+
+    #[test]
+    fn test_agenda() {
+        let task = |line, done, due: &str| Task {
+            path: "/notes/a.md".to_string(),
+            note_title: "A".to_string(),
+            line,
+            text: String::new(),
+            done,
+            depth: 0,
+            due: NaiveDate::parse_from_str(due, "%Y-%m-%d").ok(),
+        };
+        let range = AgendaRange {
+            from: NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 6, 7).unwrap(),
+        };
+        let tasks = vec![
+            task(1, false, "2024-06-05"),
+            task(2, false, "2024-05-20"),
+            task(3, true, "2024-05-21"),
+            task(4, false, "2024-06-01"),
+            task(5, false, "2024-06-08"),
+            task(6, false, ""),
+        ];
+        let agenda = agenda(tasks, range);
+        assert_eq!(agenda.overdue.iter().map(|t| t.line).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(agenda.upcoming.iter().map(|t| t.line).collect::<Vec<_>>(), vec![4, 1]);
+    }
+
+    #[test]
+    fn test_toggle_line_preserves_rest_of_file() {
+        let content = "# Todo\r\n- [ ] Buy milk\r\n* [X] Done\r\n";
+        let (toggled, done) = toggle_line(content, 2, Some("Buy milk")).unwrap();
+        assert!(done);
+        assert_eq!(toggled, "# Todo\r\n- [x] Buy milk\r\n* [X] Done\r\n");
+        let (toggled, done) = toggle_line(&toggled, 3, None).unwrap();
+        assert!(!done);
+        assert_eq!(toggled, "# Todo\r\n- [x] Buy milk\r\n* [ ] Done\r\n");
+    }
+
+    #[test]
+    fn test_toggle_line_rejects_stale_requests() {
+        let content = "- [ ] Buy milk\nplain line\n";
+        assert!(toggle_line(content, 2, None).is_err());
+        assert!(toggle_line(content, 1, Some("Buy bread")).is_err());
+        assert!(toggle_line(content, 9, None).is_err());
+    }
+}
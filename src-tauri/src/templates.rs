@@ -0,0 +1,145 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder in a workspace holding its note templates, one `.md` file each.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Prefix of placeholders the user fills in, like `{{prompt:Client name}}`.
+const PROMPT: &str = "prompt:";
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Template {
+    /// The file name without `.md`.
+    pub id: String,
+    pub name: String,
+}
+
+/// Templates in `workspace_dir`, by name.
+pub fn list(workspace_dir: &Path) -> Vec<Template> {
+    let Ok(entries) = fs::read_dir(workspace_dir.join(TEMPLATES_DIR)) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<Template> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|p| {
+            let id = p.file_stem()?.to_string_lossy().to_string();
+            Some(Template {
+                name: id.replace(['-', '_'], " "),
+                id,
+            })
+        })
+        .collect();
+    templates.sort_by_key(|t| t.name.to_lowercase());
+    templates
+}
+
+/// The file of template `id`, which must name a file directly in the
+/// templates folder.
+pub fn path(workspace_dir: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(format!("Invalid template {}", id));
+    }
+    Ok(workspace_dir.join(TEMPLATES_DIR).join(format!("{}.md", id)))
+}
+
+/// The questions `{{prompt:…}}` placeholders in `template` ask, each once,
+/// in the order they first appear.
+pub fn prompts(template: &str) -> Vec<String> {
+    let mut prompts: Vec<String> = Vec::new();
+    for name in placeholders(template).map(|(_, name)| name) {
+        if let Some(prompt) = prompt(name) {
+            if !prompts.iter().any(|p| p == prompt) {
+                prompts.push(prompt.to_string());
+            }
+        }
+    }
+    prompts
+}
+
+/// Fills in `{{date}}`, `{{time}}`, `{{datetime}}` and `{{weekday}}` with
+/// `now`, and `{{prompt:…}}` with the user's answer in `answers`. Other
+/// placeholders, and prompts without an answer, are left as they are.
+pub fn expand(template: &str, now: NaiveDateTime, answers: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut end = 0;
+    for (range, name) in placeholders(template) {
+        out.push_str(&template[end..range.start]);
+        let value = match prompt(name) {
+            Some(prompt) => answers.get(prompt).cloned(),
+            None => value(name, now),
+        };
+        out.push_str(value.as_deref().unwrap_or(&template[range.clone()]));
+        end = range.end;
+    }
+    out.push_str(&template[end..]);
+    out
+}
+
+/// Each `{{…}}` in `template`, with its span and trimmed name.
+fn placeholders(template: &str) -> impl Iterator<Item = (std::ops::Range<usize>, &str)> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let rest = &template[offset..];
+        let start = rest.find("{{")?;
+        let len = rest[start + 2..].find("}}")?;
+        let range = offset + start..offset + start + 2 + len + 2;
+        offset = range.end;
+        Some((range, rest[start + 2..start + 2 + len].trim()))
+    })
+}
+
+fn prompt(name: &str) -> Option<&str> {
+    name.strip_prefix(PROMPT).map(str::trim).filter(|p| !p.is_empty())
+}
+
+fn value(name: &str, now: NaiveDateTime) -> Option<String> {
+    let format = match name {
+        "date" => "%Y-%m-%d",
+        "time" => "%H:%M",
+        "datetime" => "%Y-%m-%d %H:%M",
+        "weekday" => "%A",
+        _ => return None,
+    };
+    Some(now.format(format).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_expand() {
+        let now = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap().and_hms_opt(14, 5, 0).unwrap();
+        assert_eq!(
+            expand("# Standup {{date}}\n\n{{ weekday }} at {{time}}, {{unknown}} {{", now, &HashMap::new()),
+            "# Standup 2026-03-09\n\nMonday at 14:05, {{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn test_prompts() {
+        let now = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap().and_hms_opt(14, 5, 0).unwrap();
+        let template = "# {{prompt:Client name}} {{date}}\n\nWith {{ prompt: Client name }} about {{prompt:Topic}}{{prompt:}}";
+        assert_eq!(prompts(template), vec!["Client name", "Topic"]);
+        let answers = HashMap::from([("Client name".to_string(), "Acme".to_string())]);
+        assert_eq!(
+            expand(template, now, &answers),
+            "# Acme 2026-03-09\n\nWith Acme about {{prompt:Topic}}{{prompt:}}"
+        );
+    }
+
+    #[test]
+    fn test_path_stays_in_templates_folder() {
+        let dir = Path::new("/notes/work");
+        assert_eq!(path(dir, "meeting").unwrap(), Path::new("/notes/work/templates/meeting.md"));
+        assert!(path(dir, "../secrets").is_err());
+        assert!(path(dir, "..").is_err());
+        assert!(path(dir, "").is_err());
+    }
+}
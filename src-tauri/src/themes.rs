@@ -0,0 +1,141 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Folder of `.css` export themes, in the app data dir for every workspace
+/// or in a workspace folder for just that one.
+pub const THEMES_DIR: &str = "themes";
+
+/// Largest theme accepted; anything bigger is probably not a stylesheet.
+const MAX_THEME_BYTES: usize = 256 * 1024;
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ExportTheme {
+    /// The file name without `.css`.
+    pub name: String,
+    pub css: String,
+    /// Whether it comes from the workspace rather than the shared folder.
+    pub workspace: bool,
+    /// Why the theme can't be used, if it can't.
+    pub error: Option<String>,
+}
+
+/// Themes in the shared `global_dir` and `workspace_dir`, by name. A
+/// workspace theme replaces a shared one with the same name.
+pub fn list(global_dir: &Path, workspace_dir: &Path) -> Vec<ExportTheme> {
+    let mut themes: HashMap<String, ExportTheme> = HashMap::new();
+    for (dir, workspace) in [(global_dir, false), (workspace_dir, true)] {
+        let Ok(entries) = fs::read_dir(dir.join(THEMES_DIR)) else {
+            continue;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_none_or(|ext| ext != "css") {
+                continue;
+            }
+            let (Some(name), Ok(css)) = (path.file_stem(), fs::read_to_string(&path)) else {
+                continue;
+            };
+            let name = name.to_string_lossy().to_string();
+            themes.insert(
+                name.clone(),
+                ExportTheme {
+                    name,
+                    error: validate(&css).err(),
+                    css,
+                    workspace,
+                },
+            );
+        }
+    }
+    let mut themes: Vec<ExportTheme> = themes.into_values().collect();
+    themes.sort_by_key(|t| t.name.to_lowercase());
+    themes
+}
+
+/// Checks `css` can go in a page's `<style>` element as is: that it can't
+/// end the element early and its comments, strings and blocks are closed,
+/// so a typo can't swallow the page's own styles.
+pub fn validate(css: &str) -> Result<(), String> {
+    if css.len() > MAX_THEME_BYTES {
+        return Err(format!("Themes can be at most {} KB", MAX_THEME_BYTES / 1024));
+    }
+    if css.to_lowercase().contains("</style") {
+        return Err("Themes can't contain </style>".to_string());
+    }
+    let mut depth: i32 = 0;
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => previous = c,
+                        None => return Err("Unclosed comment".to_string()),
+                    }
+                }
+            }
+            '"' | '\'' => loop {
+                match chars.next() {
+                    Some('\\') => {
+                        chars.next();
+                    }
+                    Some(q) if q == c => break,
+                    Some('\n') | None => return Err("Unclosed string".to_string()),
+                    Some(_) => {}
+                }
+            },
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return Err("Unexpected }".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err("Unclosed {".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list() {
+        let dir = std::env::temp_dir().join(format!("write-themes-{}", std::process::id()));
+        let global = dir.join("global");
+        let workspace = dir.join("workspace");
+        fs::create_dir_all(global.join(THEMES_DIR)).unwrap();
+        fs::create_dir_all(workspace.join(THEMES_DIR)).unwrap();
+        fs::write(global.join(THEMES_DIR).join("Brand.css"), "body{color:red}").unwrap();
+        fs::write(global.join(THEMES_DIR).join("serif.css"), "body{font-family:serif}").unwrap();
+        fs::write(global.join(THEMES_DIR).join("notes.txt"), "skipped").unwrap();
+        fs::write(workspace.join(THEMES_DIR).join("serif.css"), "body{").unwrap();
+
+        let themes = list(&global, &workspace);
+        let summary: Vec<(&str, bool, Option<&str>)> = themes
+            .iter()
+            .map(|t| (t.name.as_str(), t.workspace, t.error.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![("Brand", false, None), ("serif", true, Some("Unclosed {"))]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate("/* { */ main { content: \"}\"; } @media print { a { color: #000 } }"), Ok(()));
+        assert!(validate("main { color: red; ").is_err());
+        assert!(validate("main { color: red; } }").is_err());
+        assert!(validate("/* open").is_err());
+        assert!(validate("a::after { content: 'x }").is_err());
+        assert!(validate("</STYLE><script>alert(1)</script>").is_err());
+    }
+}
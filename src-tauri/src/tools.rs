@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{diagram, git, pandoc, whisper};
+
+/// External programs commands run, by file name. Which one runs is only
+/// ever read from the settings, never taken from a command's arguments.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tool {
+    Pandoc,
+    Whisper,
+    Git,
+    Mermaid,
+}
+
+impl Tool {
+    fn label(self) -> &'static str {
+        match self {
+            Tool::Pandoc => "Pandoc",
+            Tool::Whisper => "Whisper",
+            Tool::Git => "Git",
+            Tool::Mermaid => "mermaid-cli",
+        }
+    }
+
+    /// File names the tool's program may have. The first is looked up on
+    /// `PATH` when no path is set.
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            Tool::Pandoc => &[pandoc::DEFAULT_COMMAND],
+            Tool::Whisper => &[whisper::DEFAULT_COMMAND, "whisper-cpp"],
+            Tool::Git => &[git::DEFAULT_COMMAND],
+            Tool::Mermaid => &[diagram::DEFAULT_COMMAND],
+        }
+    }
+}
+
+/// Where the external programs are installed, for when they aren't on
+/// `PATH`.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct ToolPaths {
+    pub pandoc: Option<String>,
+    pub whisper: Option<String>,
+    pub git: Option<String>,
+    pub mermaid: Option<String>,
+}
+
+impl ToolPaths {
+    /// The program to run for `tool`.
+    pub fn command(&self, tool: Tool) -> Result<String, String> {
+        match self.configured(tool) {
+            Some(path) => check(tool, path).map(|_| path.to_string()),
+            None => Ok(tool.names()[0].to_string()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for tool in [Tool::Pandoc, Tool::Whisper, Tool::Git, Tool::Mermaid] {
+            self.command(tool)?;
+        }
+        Ok(())
+    }
+
+    fn configured(&self, tool: Tool) -> Option<&str> {
+        let path = match tool {
+            Tool::Pandoc => &self.pandoc,
+            Tool::Whisper => &self.whisper,
+            Tool::Git => &self.git,
+            Tool::Mermaid => &self.mermaid,
+        };
+        path.as_deref().map(str::trim).filter(|p| !p.is_empty())
+    }
+}
+
+/// Only an absolute path to a program named like `tool`'s, such as
+/// `/opt/homebrew/bin/pandoc` or `C:\Pandoc\pandoc.exe`, is run.
+fn check(tool: Tool, path: &str) -> Result<(), String> {
+    let path = Path::new(path);
+    let allowed = path.is_absolute()
+        && path.extension().is_none_or(|e| e.eq_ignore_ascii_case("exe"))
+        && path.file_stem().and_then(|s| s.to_str()).is_some_and(|s| tool.names().contains(&s));
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!("{} isn't a {} program", path.display(), tool.label()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let paths = ToolPaths {
+            pandoc: Some("/opt/homebrew/bin/pandoc".to_string()),
+            whisper: Some(" ".to_string()),
+            git: Some("/bin/sh".to_string()),
+            mermaid: None,
+        };
+        assert_eq!(paths.command(Tool::Pandoc).as_deref(), Ok("/opt/homebrew/bin/pandoc"));
+        assert_eq!(paths.command(Tool::Whisper).as_deref(), Ok("whisper-cli"));
+        assert!(paths.command(Tool::Git).is_err());
+        assert_eq!(paths.command(Tool::Mermaid).as_deref(), Ok("mmdc"));
+        assert!(paths.validate().is_err());
+
+        for path in ["pandoc", "/tmp/pandoc.sh", "/tmp/not-pandoc"] {
+            let paths = ToolPaths {
+                pandoc: Some(path.to_string()),
+                ..Default::default()
+            };
+            assert!(paths.command(Tool::Pandoc).is_err(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+/// Curly quotes, dashes and ellipses for `text`, leaving frontmatter, fenced
+/// code, inline code, HTML tags and link destinations as typed.
+pub fn smarten(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_frontmatter = text.starts_with("---");
+    let mut fence = false;
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        if in_frontmatter {
+            in_frontmatter = i == 0 || !matches!(trimmed, "---" | "...");
+            out.push_str(line);
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = !fence;
+            out.push_str(line);
+        } else if fence {
+            out.push_str(line);
+        } else {
+            out.push_str(&smarten_line(line));
+        }
+    }
+    out
+}
+
+/// Smartens one line of prose outside fenced code.
+pub fn smarten_line(line: &str) -> String {
+    // Thematic breaks and table delimiter rows are all dashes.
+    if line.trim().chars().all(|c| matches!(c, '-' | '|' | ':' | ' ' | '*' | '_')) {
+        return line.to_string();
+    }
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = out.chars().next_back();
+        match c {
+            '`' => {
+                let ticks = chars[i..].iter().take_while(|&&c| c == '`').count();
+                let end = find_run(&chars, i + ticks, '`', ticks).unwrap_or(chars.len());
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '<' if chars.get(i + 1).is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!')) => {
+                let end = chars[i..].iter().position(|&c| c == '>').map_or(chars.len(), |p| i + p + 1);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '(' if prev == Some(']') => {
+                let end = chars[i..].iter().position(|&c| c == ')').map_or(chars.len(), |p| i + p + 1);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '.' if chars[i..].starts_with(&['.', '.', '.']) && chars.get(i + 3) != Some(&'.') => {
+                out.push('…');
+                i += 3;
+                continue;
+            }
+            '-' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '-').count();
+                let next = chars.get(i + run).copied();
+                let joins = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == ' ');
+                if (run == 2 || run == 3) && joins(prev) && joins(next) {
+                    out.push(if run == 3 { '—' } else { '–' });
+                    i += run;
+                    continue;
+                }
+                out.extend(&chars[i..i + run]);
+                i += run;
+                continue;
+            }
+            '"' => out.push(if opens(prev) { '“' } else { '”' }),
+            '\'' => out.push(if opens(prev) && !is_elision(&chars[i + 1..]) { '‘' } else { '’' }),
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// A quote opens at the start of a line or after whitespace or an opening
+/// bracket or dash.
+fn opens(prev: Option<char>) -> bool {
+    prev.is_none_or(|p| p.is_whitespace() || matches!(p, '(' | '[' | '{' | '—' | '–' | '“' | '‘'))
+}
+
+/// Words that start with an apostrophe standing in for dropped letters, like
+/// 'twas or '90s, which takes a closing quote even where a quote would open.
+fn is_elision(rest: &[char]) -> bool {
+    const WORDS: [&str; 7] = ["tis", "twas", "til", "em", "cause", "bout", "n"];
+    let word: String = rest.iter().take_while(|c| c.is_alphanumeric()).collect();
+    let word = word.to_lowercase();
+    let decade = word
+        .strip_suffix('s')
+        .is_some_and(|d| d.len() == 2 && d.chars().all(|c| c.is_ascii_digit()));
+    decade || WORDS.contains(&word.as_str())
+}
+
+/// Index just past the next run of exactly `len` `c`s at or after `from`.
+fn find_run(chars: &[char], from: usize, c: char, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        let run = chars[i..].iter().take_while(|&&x| x == c).count();
+        if run == len {
+            return Some(i + run);
+        }
+        i += run.max(1);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smarten_quotes_dashes_and_ellipses() {
+        assert_eq!(
+            smarten_line("\"Don't,\" she said -- 'twas 1990--2000 --- wait..."),
+            "“Don’t,” she said – ’twas 1990–2000 — wait…"
+        );
+        assert_eq!(smarten_line("'Tis the '90s, not 'tissue'"), "’Tis the ’90s, not ‘tissue’");
+    }
+
+    #[test]
+    fn test_smarten_skips_code_html_and_links() {
+        assert_eq!(
+            smarten_line("Run `echo \"hi\" -- x` or <a href=\"x\">it's</a> [\"a\"](http://x.com/a--b)"),
+            "Run `echo \"hi\" -- x` or <a href=\"x\">it’s</a> [“a”](http://x.com/a--b)"
+        );
+        assert_eq!(smarten_line("| --- | :-: |\n"), "| --- | :-: |\n");
+    }
+
+    #[test]
+    fn test_smarten_skips_frontmatter_and_fences() {
+        let text = "---\ntitle: \"x\"\n---\nIt's\n```\nlet s = \"a\";\n```\n";
+        assert_eq!(smarten(text), "---\ntitle: \"x\"\n---\nIt’s\n```\nlet s = \"a\";\n```\n");
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_updater::UpdaterExt;
+
+/// Bytes downloaded so far, as `UpdateProgress`.
+pub const UPDATE_PROGRESS: &str = "update:progress";
+/// The update is installed and applies on restart, as `UpdateInfo`.
+pub const UPDATE_READY: &str = "update:ready";
+/// Help ▸ Check for Updates… was picked; the frontend runs the check so it
+/// can show the result.
+pub const UPDATE_CHECK_REQUESTED: &str = "update:check-requested";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    /// Where the channel's signed update manifest is published.
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "https://github.com/elitan/write/releases/latest/download/latest.json",
+            UpdateChannel::Beta => "https://github.com/elitan/write/releases/download/beta/latest.json",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UpdateProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// The newer release on `channel`, if there is one. With `install` it's
+/// also downloaded and installed, reporting progress through events.
+pub async fn check(app: &AppHandle, channel: UpdateChannel, install: bool) -> Result<Option<UpdateInfo>, String> {
+    let endpoint = channel.endpoint().parse().map_err(|e: url::ParseError| e.to_string())?;
+    let updater = app
+        .updater_builder()
+        .endpoints(vec![endpoint])
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?;
+    let Some(update) = updater.check().await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let info = UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|d| d.to_string()),
+    };
+    if install {
+        tracing::info!("Installing update {} from the {:?} channel", info.version, channel);
+        let mut downloaded = 0;
+        update
+            .download_and_install(
+                |chunk, total| {
+                    downloaded += chunk as u64;
+                    let _ = app.emit(UPDATE_PROGRESS, UpdateProgress { downloaded, total });
+                },
+                || {},
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        let _ = app.emit(UPDATE_READY, info.clone());
+    }
+    Ok(Some(info))
+}
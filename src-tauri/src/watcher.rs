@@ -0,0 +1,636 @@
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::events::NoteLifecycleEvent;
+use crate::ignore::IgnoreRules;
+use crate::index::WorkspaceIndex;
+use crate::markup::Markup;
+
+pub const NOTE_EXTERNALLY_CHANGED: &str = "note:externally-changed";
+pub const NOTE_ADDED: &str = "note:added";
+pub const NOTE_REMOVED: &str = "note:removed";
+pub const NOTE_EXTERNALLY_RENAMED: &str = "note:externally-renamed";
+pub const WORKSPACES_CHANGED: &str = "workspaces:changed";
+pub const WORKSPACE_MISSING: &str = "workspace:missing";
+
+// Writes made by the app itself show up as filesystem events too. Anything
+// touched by a command within this window is not reported as external.
+const SELF_WRITE_WINDOW: Duration = Duration::from_millis(1500);
+
+// Sync clients write temp files and partial chunks in quick succession. Events
+// are held until the folder has been quiet this long, but never longer than
+// MAX_BURST so a constant trickle still gets through.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const MAX_BURST: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Clone)]
+pub struct NoteFsEvent {
+    pub path: String,
+    pub workspace_id: String,
+    /// Current file content, included for external modifications so an open
+    /// editor can reload without a separate read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct WorkspaceMissingEvent {
+    pub workspace_id: String,
+    pub path: String,
+}
+
+pub struct WorkspaceWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    self_writes: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+    missing: Arc<AtomicBool>,
+    index: Arc<Mutex<WorkspaceIndex>>,
+}
+
+impl WorkspaceWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Mutex::new(None),
+            self_writes: Arc::new(Mutex::new(HashMap::new())),
+            missing: Arc::new(AtomicBool::new(false)),
+            index: Arc::new(Mutex::new(WorkspaceIndex::default())),
+        }
+    }
+
+    /// Replaces any existing watch with one on `dir` and `extra_roots`.
+    pub fn watch(
+        &self,
+        app: &AppHandle,
+        workspace_id: &str,
+        dir: &Path,
+        extra_roots: &[PathBuf],
+        ignore: IgnoreRules,
+    ) -> Result<(), String> {
+        let mut slot = self.watcher.lock().unwrap();
+        *slot = None;
+        self.missing.store(false, Ordering::SeqCst);
+        *self.index.lock().unwrap() = WorkspaceIndex::build_with_roots(dir, extra_roots, ignore.clone());
+
+        if !dir.exists() {
+            return Ok(());
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let emit_app = app.clone();
+        let emit_workspace_id = workspace_id.to_string();
+        thread::spawn(move || run_burst_loop(rx, emit_app, emit_workspace_id));
+
+        let app = app.clone();
+        let workspace_id = workspace_id.to_string();
+        let watched_dir = dir.to_path_buf();
+        let self_writes = Arc::clone(&self.self_writes);
+        let missing = Arc::clone(&self.missing);
+        let index = Arc::clone(&self.index);
+        let mut file_ids = scan_file_ids(dir);
+        for root in extra_roots {
+            file_ids.ids.extend(scan_file_ids(root).ids);
+        }
+        // The sender lives in this closure, so dropping the watcher ends the
+        // burst loop thread.
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            // Deleting the folder or unmounting its volume may surface as an
+            // event on the folder itself or as a watcher error, so check on both.
+            if !watched_dir.is_dir() {
+                if !missing.swap(true, Ordering::SeqCst) {
+                    let payload = WorkspaceMissingEvent {
+                        workspace_id: workspace_id.clone(),
+                        path: watched_dir.to_string_lossy().to_string(),
+                    };
+                    let _ = app.emit(WORKSPACE_MISSING, payload);
+                }
+                return;
+            }
+            let Ok(event) = res else {
+                return;
+            };
+            for (name, path) in classify_event(&event) {
+                let root = path.parent().unwrap_or(&watched_dir).to_path_buf();
+                if ignore.is_ignored(&root, &path) {
+                    continue;
+                }
+                index.lock().unwrap().refresh(&path);
+                // The id map is kept current even for our own writes, otherwise
+                // a later external rename of an app-renamed note goes unnoticed.
+                let self_write = is_self_write(&self_writes, &path);
+                match name {
+                    NOTE_REMOVED => {
+                        if let Some(new_path) = file_ids.resolve_rename(&root, &path) {
+                            index.lock().unwrap().refresh(&new_path);
+                            if !self_write {
+                                crate::handle_external_rename(&app, &path, &new_path);
+                                let _ = tx.send(WatchEvent::Renamed(path, new_path));
+                            }
+                            continue;
+                        }
+                    }
+                    // Already reported as the target of a rename.
+                    NOTE_ADDED if !file_ids.track(&path) => continue,
+                    _ => {}
+                }
+                if !self_write {
+                    let _ = tx.send(WatchEvent::Fs(name, path));
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| e.to_string())?;
+        for root in extra_roots {
+            // A root that's gone, like an unmounted drive, leaves the rest
+            // of the workspace working.
+            if let Err(e) = watcher.watch(root, RecursiveMode::NonRecursive) {
+                tracing::warn!("Can't watch {}: {}", root.display(), e);
+            }
+        }
+        *slot = Some(watcher);
+        Ok(())
+    }
+
+    /// Runs `f` against the index of the watched workspace.
+    pub fn with_index<R>(&self, f: impl FnOnce(&mut WorkspaceIndex) -> R) -> R {
+        f(&mut self.index.lock().unwrap())
+    }
+
+    /// Brings the index up to date after a command changed `paths` on disk,
+    /// without waiting for the watcher to catch up.
+    pub fn refresh(&self, paths: &[&Path]) {
+        let mut index = self.index.lock().unwrap();
+        for path in paths {
+            index.refresh(path);
+        }
+    }
+
+    /// Whether the watched workspace folder has disappeared since it was watched.
+    pub fn is_missing(&self) -> bool {
+        self.missing.load(Ordering::SeqCst)
+    }
+
+    /// Records that the app is about to write `path` so the resulting
+    /// filesystem events are not reported back as external changes.
+    pub fn mark_self_write(&self, path: &Path) {
+        let mut writes = self.self_writes.lock().unwrap();
+        let now = Instant::now();
+        writes.retain(|_, at| now.duration_since(*at) < SELF_WRITE_WINDOW);
+        writes.insert(path.to_path_buf(), now);
+    }
+}
+
+/// Maps note paths to filesystem ids so a path that disappears can be matched
+/// to the path the same file now lives at.
+#[derive(Default)]
+struct FileIds {
+    ids: HashMap<PathBuf, u64>,
+}
+
+impl FileIds {
+    /// Starts tracking `path`. Returns false if it was already tracked under
+    /// the same id.
+    fn track(&mut self, path: &Path) -> bool {
+        let Some(id) = file_id(path) else {
+            return true;
+        };
+        self.ids.insert(path.to_path_buf(), id) != Some(id)
+    }
+
+    /// Stops tracking `old_path` and, if a note with the same id now exists
+    /// elsewhere in `dir`, returns its path.
+    fn resolve_rename(&mut self, dir: &Path, old_path: &Path) -> Option<PathBuf> {
+        let id = self.ids.remove(old_path)?;
+        // Renames are atomic, so the new name exists by the time the event for
+        // the old one is delivered.
+        let new_path = fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .find(|p| is_note_path(p) && p != old_path && file_id(p) == Some(id))?;
+        self.ids.insert(new_path.clone(), id);
+        Some(new_path)
+    }
+}
+
+fn scan_file_ids(dir: &Path) -> FileIds {
+    let mut file_ids = FileIds::default();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if is_note_path(&path) {
+                file_ids.track(&path);
+            }
+        }
+    }
+    file_ids
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| m.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+impl Default for WorkspaceWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Watches `workspaces.json` so edits from other instances are loaded into
+/// memory rather than overwritten by our next save.
+pub struct ConfigWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Mutex::new(None),
+        }
+    }
+
+    pub fn watch(&self, app: &AppHandle, config_path: &Path) -> Result<(), String> {
+        // Sync tools usually replace the file rather than write in place, so
+        // watch the directory and match on the file name.
+        let dir = config_path.parent().ok_or("Invalid config path")?;
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        let file_name = config_path.file_name().map(|n| n.to_os_string());
+
+        let app = app.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                return;
+            }
+            if event.paths.iter().any(|p| p.file_name() == file_name.as_deref()) {
+                crate::reload_config(&app);
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| e.to_string())?;
+        *self.watcher.lock().unwrap() = Some(watcher);
+        Ok(())
+    }
+}
+
+impl Default for ConfigWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Scanners and scripts may take a while to write a file. The inbox is only
+// imported once it has been quiet this long.
+const INBOX_SETTLE: Duration = Duration::from_secs(1);
+
+/// Watches the inbox folder and imports files dropped into it.
+pub struct InboxWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl InboxWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Mutex::new(None),
+        }
+    }
+
+    /// Replaces any existing watch with one on `dir`, or stops watching
+    /// when it's `None`.
+    pub fn watch(&self, app: &AppHandle, dir: Option<&Path>) -> Result<(), String> {
+        let mut slot = self.watcher.lock().unwrap();
+        *slot = None;
+        let Some(dir) = dir else {
+            return Ok(());
+        };
+
+        let (tx, rx) = mpsc::channel::<()>();
+        let import_app = app.clone();
+        // Ends once the watcher, which owns the sender, is dropped.
+        thread::spawn(move || {
+            while rx.recv().is_ok() {
+                loop {
+                    match rx.recv_timeout(INBOX_SETTLE) {
+                        Ok(()) => continue,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                if let Err(e) = crate::ingest_inbox(&import_app) {
+                    tracing::warn!("Importing the inbox failed: {}", e);
+                }
+            }
+        });
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    let _ = tx.send(());
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| e.to_string())?;
+        *slot = Some(watcher);
+        Ok(())
+    }
+}
+
+impl Default for InboxWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_self_write(self_writes: &Mutex<HashMap<PathBuf, Instant>>, path: &Path) -> bool {
+    let writes = self_writes.lock().unwrap();
+    writes
+        .get(path)
+        .is_some_and(|at| at.elapsed() < SELF_WRITE_WINDOW)
+}
+
+enum WatchEvent {
+    Fs(&'static str, PathBuf),
+    Renamed(PathBuf, PathBuf),
+}
+
+/// A note moved from the first path to the second.
+type Rename = (PathBuf, PathBuf);
+
+/// Events collected during one burst, coalesced to at most one per note.
+#[derive(Default)]
+struct Burst {
+    pending: HashMap<PathBuf, &'static str>,
+    order: Vec<PathBuf>,
+    renames: Vec<Rename>,
+}
+
+impl Burst {
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.renames.is_empty()
+    }
+
+    fn push(&mut self, event: WatchEvent) {
+        match event {
+            WatchEvent::Fs(name, path) => {
+                let merged = match self.pending.get(&path) {
+                    None => Some(name),
+                    Some(prev) => coalesce(prev, name),
+                };
+                match merged {
+                    Some(name) => {
+                        if self.pending.insert(path.clone(), name).is_none() {
+                            self.order.push(path);
+                        }
+                    }
+                    None => {
+                        self.pending.remove(&path);
+                        self.order.retain(|p| p != &path);
+                    }
+                }
+            }
+            WatchEvent::Renamed(old_path, new_path) => {
+                // Anything already pending for the old name now applies to the new one.
+                if let Some(name) = self.pending.remove(&old_path) {
+                    self.order.retain(|p| p != &old_path);
+                    self.push(WatchEvent::Fs(name, new_path.clone()));
+                }
+                self.renames.push((old_path, new_path));
+            }
+        }
+    }
+
+    fn take(&mut self) -> (Vec<Rename>, Vec<(&'static str, PathBuf)>) {
+        let renames = std::mem::take(&mut self.renames);
+        let mut pending = std::mem::take(&mut self.pending);
+        let events = std::mem::take(&mut self.order)
+            .into_iter()
+            .filter_map(|p| Some((pending.remove(&p)?, p)))
+            .collect();
+        (renames, events)
+    }
+
+    fn flush(&mut self, app: &AppHandle, workspace_id: &str) {
+        let (renames, events) = self.take();
+        for (old_path, new_path) in renames {
+            let payload = NoteLifecycleEvent::new(Some(old_path.as_path()), Some(new_path.as_path()));
+            let _ = app.emit(NOTE_EXTERNALLY_RENAMED, payload);
+        }
+        for (name, path) in events {
+            // Read at flush time so the payload has the settled content.
+            let content = if name == NOTE_EXTERNALLY_CHANGED {
+                match fs::read_to_string(&path) {
+                    Ok(content) => Some(content),
+                    Err(_) => continue,
+                }
+            } else {
+                None
+            };
+            let payload = NoteFsEvent {
+                path: path.to_string_lossy().to_string(),
+                workspace_id: workspace_id.to_string(),
+                content,
+            };
+            let _ = app.emit(name, payload);
+        }
+    }
+}
+
+/// Merges two events for the same path. `None` means they cancel out, like a
+/// temp file that was created and removed within the burst.
+fn coalesce(prev: &'static str, next: &'static str) -> Option<&'static str> {
+    match (prev, next) {
+        (NOTE_ADDED, NOTE_REMOVED) => None,
+        (NOTE_ADDED, _) => Some(NOTE_ADDED),
+        // Atomic saves replace the file, which looks like a remove then add.
+        (NOTE_REMOVED, NOTE_REMOVED) => Some(NOTE_REMOVED),
+        (NOTE_REMOVED, _) => Some(NOTE_EXTERNALLY_CHANGED),
+        (_, NOTE_REMOVED) => Some(NOTE_REMOVED),
+        _ => Some(NOTE_EXTERNALLY_CHANGED),
+    }
+}
+
+fn run_burst_loop(rx: Receiver<WatchEvent>, app: AppHandle, workspace_id: String) {
+    let mut burst = Burst::default();
+    let mut started: Option<Instant> = None;
+    loop {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(event) => {
+                burst.push(event);
+                let started = *started.get_or_insert_with(Instant::now);
+                if started.elapsed() < MAX_BURST {
+                    continue;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                burst.flush(&app, &workspace_id);
+                return;
+            }
+        }
+        if !burst.is_empty() {
+            burst.flush(&app, &workspace_id);
+        }
+        started = None;
+    }
+}
+
+/// Editor swap files, sync-tool partials and Finder metadata.
+fn is_temporary_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+        return true;
+    };
+    name.starts_with('.') || name.starts_with("~$") || name.ends_with('~') || name.ends_with(".tmp")
+}
+
+/// Whether `path` is a note, in any `Markup`.
+pub fn is_note_path(path: &Path) -> bool {
+    !is_temporary_file(path) && Markup::from_path(path).is_some()
+}
+
+fn classify_event(event: &Event) -> Vec<(&'static str, PathBuf)> {
+    event
+        .paths
+        .iter()
+        .filter(|p| is_note_path(p))
+        .filter_map(|p| {
+            let name = match event.kind {
+                EventKind::Create(_) => NOTE_ADDED,
+                EventKind::Remove(_) => NOTE_REMOVED,
+                // Renames arrive as separate events for each side; whether the
+                // path still exists tells us which side this one is.
+                EventKind::Modify(ModifyKind::Name(_)) => {
+                    if p.exists() {
+                        NOTE_ADDED
+                    } else {
+                        NOTE_REMOVED
+                    }
+                }
+                EventKind::Modify(_) => NOTE_EXTERNALLY_CHANGED,
+                _ => return None,
+            };
+            Some((name, p.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    #[test]
+    fn test_classify_event_ignores_non_markdown() {
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path(PathBuf::from("/n/.DS_Store"));
+        assert!(classify_event(&event).is_empty());
+    }
+
+    #[test]
+    fn test_classify_event_kinds() {
+        let path = PathBuf::from("/n/1-hello.md");
+        let created = Event::new(EventKind::Create(CreateKind::File)).add_path(path.clone());
+        let removed = Event::new(EventKind::Remove(RemoveKind::File)).add_path(path.clone());
+        let modified = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(path.clone());
+        assert_eq!(classify_event(&created), vec![(NOTE_ADDED, path.clone())]);
+        assert_eq!(classify_event(&removed), vec![(NOTE_REMOVED, path.clone())]);
+        assert_eq!(classify_event(&modified), vec![(NOTE_EXTERNALLY_CHANGED, path)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_ids_resolve_rename() {
+        let dir = std::env::temp_dir().join(format!("write-file-ids-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old_path = dir.join("1-old.md");
+        let new_path = dir.join("1-new.md");
+        fs::write(&old_path, "# Old").unwrap();
+
+        let mut file_ids = scan_file_ids(&dir);
+        fs::rename(&old_path, &new_path).unwrap();
+
+        assert_eq!(file_ids.resolve_rename(&dir, &old_path), Some(new_path.clone()));
+        assert!(!file_ids.track(&new_path));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_is_note_path_skips_temporary_files() {
+        assert!(is_note_path(Path::new("/n/1-hello.md")));
+        assert!(!is_note_path(Path::new("/n/.1-hello.md")));
+        assert!(!is_note_path(Path::new("/n/1-hello.md~")));
+        assert!(!is_note_path(Path::new("/n/1-hello.md.tmp")));
+        assert!(!is_note_path(Path::new("/n/.DS_Store")));
+        assert!(is_note_path(Path::new("/n/2-reading.org")));
+        assert!(!is_note_path(Path::new("/n/.#2-reading.org")));
+    }
+
+    #[test]
+    fn test_burst_coalesces_per_note() {
+        let path = PathBuf::from("/n/1-hello.md");
+        let mut burst = Burst::default();
+        burst.push(WatchEvent::Fs(NOTE_EXTERNALLY_CHANGED, path.clone()));
+        burst.push(WatchEvent::Fs(NOTE_EXTERNALLY_CHANGED, path.clone()));
+        burst.push(WatchEvent::Fs(NOTE_REMOVED, path.clone()));
+        burst.push(WatchEvent::Fs(NOTE_ADDED, path.clone()));
+        let (renames, events) = burst.take();
+        assert!(renames.is_empty());
+        assert_eq!(events, vec![(NOTE_EXTERNALLY_CHANGED, path)]);
+        assert!(burst.is_empty());
+    }
+
+    #[test]
+    fn test_burst_drops_short_lived_files() {
+        let path = PathBuf::from("/n/2-draft.md");
+        let mut burst = Burst::default();
+        burst.push(WatchEvent::Fs(NOTE_ADDED, path.clone()));
+        burst.push(WatchEvent::Fs(NOTE_EXTERNALLY_CHANGED, path.clone()));
+        burst.push(WatchEvent::Fs(NOTE_REMOVED, path));
+        assert!(burst.is_empty());
+    }
+
+    #[test]
+    fn test_burst_moves_pending_event_on_rename() {
+        let old_path = PathBuf::from("/n/1-old.md");
+        let new_path = PathBuf::from("/n/1-new.md");
+        let mut burst = Burst::default();
+        burst.push(WatchEvent::Fs(NOTE_EXTERNALLY_CHANGED, old_path.clone()));
+        burst.push(WatchEvent::Renamed(old_path.clone(), new_path.clone()));
+        let (renames, events) = burst.take();
+        assert_eq!(renames, vec![(old_path, new_path.clone())]);
+        assert_eq!(events, vec![(NOTE_EXTERNALLY_CHANGED, new_path)]);
+    }
+
+    #[test]
+    fn test_self_write_suppression() {
+        let watcher = WorkspaceWatcher::new();
+        let path = PathBuf::from("/n/1-hello.md");
+        assert!(!is_self_write(&watcher.self_writes, &path));
+        watcher.mark_self_write(&path);
+        assert!(is_self_write(&watcher.self_writes, &path));
+    }
+}
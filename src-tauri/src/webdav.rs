@@ -0,0 +1,430 @@
+use base64::Engine;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::api::percent_decode;
+use crate::render::percent_encode;
+use crate::watcher::is_note_path;
+use crate::{check_note_unlocked, get_workspace_dir, is_workspace_encrypted, secrets, AppState};
+
+pub const DEFAULT_PORT: u16 = 27184;
+
+/// The user name editors log in with; the password is generated.
+pub const USERNAME: &str = "write";
+
+/// Uploads past this are refused.
+const MAX_UPLOAD_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WebDavInfo {
+    pub workspace_id: String,
+    pub port: u16,
+    /// Reachable from other devices on the network, not just this one.
+    pub lan: bool,
+    pub username: String,
+    pub password: String,
+}
+
+struct Running {
+    server: Arc<Server>,
+    info: WebDavInfo,
+}
+
+/// Serves one workspace's folder over WebDAV so editors on other devices
+/// can change notes in place. Changes land on disk like any outside edit,
+/// so the watcher picks them up and the app stays the source of truth.
+#[derive(Default)]
+pub struct WebDavServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl WebDavServer {
+    /// Serves `workspace_id` on `port`, on every interface when `lan` is set.
+    /// Basic auth travels unencrypted, so LAN mode is for trusted networks.
+    pub fn start(&self, app: AppHandle, workspace_id: &str, port: u16, lan: bool) -> Result<WebDavInfo, String> {
+        let state = app.state::<AppState>();
+        if !state.config.lock().unwrap().workspaces.iter().any(|w| w.id == workspace_id) {
+            return Err("Workspace not found".to_string());
+        }
+        if is_workspace_encrypted(&state, workspace_id) {
+            return Err("Encrypted workspaces can't be served over WebDAV".to_string());
+        }
+        self.stop();
+        let password = password()?;
+        let host = if lan { "0.0.0.0" } else { "127.0.0.1" };
+        let server =
+            Arc::new(Server::http((host, port)).map_err(|e| format!("Couldn't listen on port {}: {}", port, e))?);
+        let info = WebDavInfo {
+            workspace_id: workspace_id.to_string(),
+            port,
+            lan,
+            username: USERNAME.to_string(),
+            password,
+        };
+        let listener = Arc::clone(&server);
+        let root = get_workspace_dir(workspace_id);
+        let served = workspace_id.to_string();
+        let expected = info.password.clone();
+        std::thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                handle(&app, &served, &root, &expected, request);
+            }
+        });
+        *self.running.lock().unwrap() = Some(Running {
+            server,
+            info: info.clone(),
+        });
+        Ok(info)
+    }
+
+    pub fn stop(&self) {
+        if let Some(running) = self.running.lock().unwrap().take() {
+            running.server.unblock();
+        }
+    }
+
+    pub fn info(&self) -> Option<WebDavInfo> {
+        self.running.lock().unwrap().as_ref().map(|r| r.info.clone())
+    }
+}
+
+fn password() -> Result<String, String> {
+    if let Some(password) = secrets::get(secrets::WEBDAV_PASSWORD)? {
+        return Ok(password);
+    }
+    let password = secrets::generate()?;
+    secrets::set(secrets::WEBDAV_PASSWORD, &password)?;
+    Ok(password)
+}
+
+struct Reply {
+    status: u16,
+    body: Vec<u8>,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl Reply {
+    fn status(status: u16) -> Self {
+        Self {
+            status,
+            body: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: message.into().into_bytes(),
+            headers: vec![("Content-Type", "text/plain; charset=utf-8".to_string())],
+        }
+    }
+}
+
+fn handle(app: &AppHandle, workspace_id: &str, root: &Path, password: &str, mut request: Request) {
+    let reply = if !is_authorized(&request, password) {
+        let mut reply = Reply::error(401, "Unauthorized");
+        reply.headers.push(("WWW-Authenticate", "Basic realm=\"Write\"".to_string()));
+        reply
+    } else {
+        route(app, workspace_id, root, &mut request).unwrap_or_else(|(status, message)| Reply::error(status, message))
+    };
+    let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+    for (name, value) in reply.headers {
+        if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            response.add_header(header);
+        }
+    }
+    let _ = request.respond(response);
+}
+
+fn route(app: &AppHandle, workspace_id: &str, root: &Path, request: &mut Request) -> Result<Reply, (u16, String)> {
+    let state = app.state::<AppState>();
+    let method = request.method().as_str().to_string();
+    let href = request.url().split('?').next().unwrap_or("/").to_string();
+    let path = resolve(root, &href)?;
+    let io = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::NotFound => (404, "Not found".to_string()),
+        _ => (500, e.to_string()),
+    };
+    // Locked notes stay read-only from other devices too, and a workspace
+    // encrypted while it's served takes no more plaintext.
+    let check_writable = |path: &Path| -> Result<(), (u16, String)> {
+        if is_workspace_encrypted(&state, workspace_id) {
+            return Err((403, "Workspace is encrypted".to_string()));
+        }
+        if is_note_path(path) {
+            check_note_unlocked(&state, path).map_err(|e| (423, e.to_string()))?;
+        }
+        Ok(())
+    };
+
+    match method.as_str() {
+        "OPTIONS" => {
+            let mut reply = Reply::status(200);
+            reply.headers.push(("DAV", "1".to_string()));
+            reply.headers.push((
+                "Allow",
+                "OPTIONS, PROPFIND, GET, HEAD, PUT, DELETE, MKCOL, MOVE".to_string(),
+            ));
+            Ok(reply)
+        }
+        "PROPFIND" => {
+            let depth = header(request, "Depth").unwrap_or_else(|| "1".to_string());
+            let metadata = fs::metadata(&path).map_err(io)?;
+            let mut entries = vec![(href_for(root, &path), metadata.clone())];
+            if metadata.is_dir() && depth != "0" {
+                let mut children: Vec<(String, fs::Metadata)> = fs::read_dir(&path)
+                    .map_err(io)?
+                    .filter_map(|e| e.ok())
+                    .filter(|e| !is_hidden(&e.file_name().to_string_lossy()))
+                    .filter_map(|e| Some((href_for(root, &e.path()), e.metadata().ok()?)))
+                    .collect();
+                children.sort_by(|a, b| a.0.cmp(&b.0));
+                entries.extend(children);
+            }
+            Ok(Reply {
+                status: 207,
+                body: multistatus(&entries).into_bytes(),
+                headers: vec![("Content-Type", "application/xml; charset=utf-8".to_string())],
+            })
+        }
+        method @ ("GET" | "HEAD") => {
+            if path.is_dir() {
+                return Err((405, "Can't download a folder".to_string()));
+            }
+            let body = fs::read(&path).map_err(io)?;
+            let mut reply = Reply::status(200);
+            reply.headers.push(("Content-Type", content_type(&path).to_string()));
+            if method == "GET" {
+                reply.body = body;
+            }
+            Ok(reply)
+        }
+        "PUT" => {
+            check_writable(&path)?;
+            if path.is_dir() {
+                return Err((405, "Can't overwrite a folder".to_string()));
+            }
+            let mut body = Vec::new();
+            request
+                .as_reader()
+                .take(MAX_UPLOAD_BYTES + 1)
+                .read_to_end(&mut body)
+                .map_err(|e| (400, e.to_string()))?;
+            if body.len() as u64 > MAX_UPLOAD_BYTES {
+                return Err((413, "File is too large".to_string()));
+            }
+            let existed = path.exists();
+            let _guard = state.write_lock.lock().unwrap();
+            fs::write(&path, body).map_err(io)?;
+            Ok(Reply::status(if existed { 204 } else { 201 }))
+        }
+        "DELETE" => {
+            check_writable(&path)?;
+            if path == root {
+                return Err((403, "Can't delete the workspace".to_string()));
+            }
+            let _guard = state.write_lock.lock().unwrap();
+            if path.is_dir() {
+                fs::remove_dir(&path).map_err(|_| (409, "Only empty folders can be deleted".to_string()))?;
+            } else {
+                fs::remove_file(&path).map_err(io)?;
+            }
+            Ok(Reply::status(204))
+        }
+        "MKCOL" => {
+            check_writable(&path)?;
+            let _guard = state.write_lock.lock().unwrap();
+            fs::create_dir(&path).map_err(|e| (405, e.to_string()))?;
+            Ok(Reply::status(201))
+        }
+        "MOVE" => {
+            let destination = header(request, "Destination").ok_or((400, "Missing Destination".to_string()))?;
+            let target = resolve(root, &destination_path(&destination))?;
+            check_writable(&path)?;
+            check_writable(&target)?;
+            if path == root || target == root {
+                return Err((403, "Can't move the workspace".to_string()));
+            }
+            let existed = target.exists();
+            if existed && header(request, "Overwrite").is_some_and(|o| o.eq_ignore_ascii_case("F")) {
+                return Err((412, "Destination exists".to_string()));
+            }
+            let _guard = state.write_lock.lock().unwrap();
+            fs::rename(&path, &target).map_err(io)?;
+            Ok(Reply::status(if existed { 204 } else { 201 }))
+        }
+        _ => Err((405, "Method not allowed".to_string())),
+    }
+}
+
+fn is_authorized(request: &Request, password: &str) -> bool {
+    let Some(encoded) = header(request, "Authorization").and_then(|v| v.strip_prefix("Basic ").map(str::to_string))
+    else {
+        return false;
+    };
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    let expected = format!("{}:{}", USERNAME, password);
+    decoded.len() == expected.len()
+        && decoded.iter().zip(expected.as_bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn header(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+/// The file `href` names inside `root`. Parent references and hidden files,
+/// like an encrypted workspace's key file, are refused.
+fn resolve(root: &Path, href: &str) -> Result<PathBuf, (u16, String)> {
+    let decoded = percent_decode(href);
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    let mut path = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) if !is_hidden(&name.to_string_lossy()) => path.push(name),
+            Component::CurDir => {}
+            _ => return Err((403, format!("Access to {} isn't allowed", href))),
+        }
+    }
+    Ok(path)
+}
+
+/// The path part of a `Destination` header, which is usually a full URL.
+fn destination_path(destination: &str) -> String {
+    match destination.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/".to_string(), |i| rest[i..].to_string()),
+        None => destination.to_string(),
+    }
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+fn href_for(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(Path::new(""));
+    let mut href = String::from("/");
+    let segments: Vec<String> = relative
+        .components()
+        .map(|c| percent_encode(&c.as_os_str().to_string_lossy()))
+        .collect();
+    href.push_str(&segments.join("/"));
+    if path.is_dir() && !href.ends_with('/') {
+        href.push('/');
+    }
+    href
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("org") => "text/org; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn multistatus(entries: &[(String, fs::Metadata)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n");
+    for (href, metadata) in entries {
+        let name = href.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        xml.push_str("<D:response>");
+        xml.push_str(&format!("<D:href>{}</D:href>", escape_xml(href)));
+        xml.push_str("<D:propstat><D:prop>");
+        xml.push_str(&format!("<D:displayname>{}</D:displayname>", escape_xml(&percent_decode(name))));
+        xml.push_str(&format!("<D:getlastmodified>{}</D:getlastmodified>", http_date(modified)));
+        if metadata.is_dir() {
+            xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+        } else {
+            xml.push_str("<D:resourcetype/>");
+            xml.push_str(&format!("<D:getcontentlength>{}</D:getcontentlength>", metadata.len()));
+            xml.push_str(&format!(
+                "<D:getcontenttype>{}</D:getcontenttype>",
+                content_type(Path::new(name))
+            ));
+        }
+        xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n");
+    }
+    xml.push_str("</D:multistatus>\n");
+    xml
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// RFC 1123 date, as WebDAV uses for `getlastmodified`.
+fn http_date(secs: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+    // Civil date from days since the epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_stays_in_root() {
+        let root = Path::new("/Notes/Personal");
+        assert_eq!(resolve(root, "/1-a%20b.md").unwrap(), root.join("1-a b.md"));
+        assert_eq!(resolve(root, "/").unwrap(), root);
+        assert!(resolve(root, "/../Work/1-a.md").is_err());
+        assert!(resolve(root, "/%2E%2E/Work").is_err());
+        assert!(resolve(root, "/.write-key.age").is_err());
+    }
+
+    #[test]
+    fn test_destination_path() {
+        assert_eq!(destination_path("http://127.0.0.1:27184/2-b.md"), "/2-b.md");
+        assert_eq!(destination_path("/2-b.md"), "/2-b.md");
+    }
+
+    #[test]
+    fn test_http_date() {
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(http_date(1_709_210_096), "Thu, 29 Feb 2024 12:34:56 GMT");
+    }
+}
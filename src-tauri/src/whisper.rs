@@ -0,0 +1,107 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// whisper.cpp's command line tool, looked up on `PATH` unless configured.
+pub const DEFAULT_COMMAND: &str = "whisper-cli";
+
+/// Formats whisper.cpp can decode.
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "mp3", "m4a", "ogg", "flac", "webm"];
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+/// Transcribes `audio` with the whisper.cpp `command` and ggml `model`. Runs
+/// entirely on this machine.
+pub fn transcribe(command: &str, model: &Path, audio: &Path) -> Result<String, String> {
+    if !model.exists() {
+        return Err(format!(
+            "No Whisper model at {}. Download a ggml model from whisper.cpp",
+            model.display()
+        ));
+    }
+    let output = Command::new(command)
+        .arg("--model")
+        .arg(model)
+        .arg("--file")
+        .arg(audio)
+        .args(["--no-timestamps", "--no-prints"])
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => format!("whisper.cpp isn't installed ({} wasn't found)", command),
+            _ => e.to_string(),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("Transcription failed: {}", reason.trim()));
+    }
+    let transcript = clean_output(&String::from_utf8_lossy(&output.stdout));
+    if transcript.is_empty() {
+        return Err("No speech found in the recording".to_string());
+    }
+    Ok(transcript)
+}
+
+/// whisper.cpp prints one segment per line, each with a leading space.
+fn clean_output(stdout: &str) -> String {
+    stdout
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && *l != "[BLANK_AUDIO]")
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `content` links to the file at `relative`, a path relative to the
+/// note's folder, either as typed or percent-encoded.
+pub fn references(content: &str, relative: &str) -> bool {
+    content.contains(&format!("({})", relative))
+        || content.contains(&format!("(<{}>)", relative))
+        || content.contains(&format!("({})", relative.replace(' ', "%20")))
+}
+
+/// `content` with the transcript of `file_name` added as a section at the end.
+pub fn append_transcript(content: &str, file_name: &str, transcript: &str) -> String {
+    let mut out = content.trim_end().to_string();
+    if !out.is_empty() {
+        out.push_str("\n\n");
+    }
+    out.push_str(&format!("## Transcript of {}\n\n{}\n", file_name, transcript));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_audio() {
+        assert!(is_audio(Path::new("/w/memo.M4A")));
+        assert!(!is_audio(Path::new("/w/cat.png")));
+        assert!(!is_audio(Path::new("/w/noext")));
+    }
+
+    #[test]
+    fn test_clean_output() {
+        assert_eq!(clean_output(" Hello there.\n [BLANK_AUDIO]\n General Kenobi.\n"), "Hello there. General Kenobi.");
+    }
+
+    #[test]
+    fn test_references() {
+        assert!(references("Listen: ![memo](audio/memo 1.m4a)", "audio/memo 1.m4a"));
+        assert!(references("[memo](audio/memo%201.m4a)", "audio/memo 1.m4a"));
+        assert!(!references("audio/memo 1.m4a mentioned in passing", "audio/memo 1.m4a"));
+    }
+
+    #[test]
+    fn test_append_transcript() {
+        assert_eq!(
+            append_transcript("# Call\n\n[memo](memo.m4a)\n\n", "memo.m4a", "Hi."),
+            "# Call\n\n[memo](memo.m4a)\n\n## Transcript of memo.m4a\n\nHi.\n"
+        );
+    }
+}
@@ -7,13 +7,16 @@ import { DebugPanel, debugLog } from "./components/debug-panel";
 import { Editor } from "./components/editor";
 import { EmptyState } from "./components/empty-state";
 import { Modal } from "./components/modal";
-import { SettingsPopover } from "./components/settings-popover";
+import {
+  type ApiServerInfo,
+  SettingsPopover,
+} from "./components/settings-popover";
 import { Sidebar } from "./components/sidebar";
 import { UpdatePrompt } from "./components/update-prompt";
 import { WorkspaceSwitcher } from "./components/workspace-switcher";
 import { useSettings } from "./hooks/use-settings";
 import { useUpdater } from "./hooks/use-updater";
-import { useNotesStore } from "./stores/notes-store";
+import { errorMessage, useNotesStore } from "./stores/notes-store";
 
 function App() {
   const notes = useNotesStore((s) => s.notes);
@@ -32,6 +35,12 @@ function App() {
   const createNote = useNotesStore((s) => s.createNote);
   const deleteNote = useNotesStore((s) => s.deleteNote);
   const reorderNote = useNotesStore((s) => s.reorderNote);
+  const conflict = useNotesStore((s) => s.conflict);
+  const applyExternalChange = useNotesStore((s) => s.applyExternalChange);
+  const resolveConflict = useNotesStore((s) => s.resolveConflict);
+  const applyRename = useNotesStore((s) => s.applyRename);
+  const applyDeletion = useNotesStore((s) => s.applyDeletion);
+  const setRenameOnSave = useNotesStore((s) => s.setRenameOnSave);
   const switchWorkspace = useNotesStore((s) => s.switchWorkspace);
   const createWorkspace = useNotesStore((s) => s.createWorkspace);
   const deleteWorkspace = useNotesStore((s) => s.deleteWorkspace);
@@ -52,6 +61,9 @@ function App() {
     title: string;
   } | null>(null);
   const [sidebarFocused, setSidebarFocused] = useState(false);
+  const [workspaceMissing, setWorkspaceMissing] = useState(false);
+  const [transcodedFrom, setTranscodedFrom] = useState<string | null>(null);
+  const [apiServer, setApiServer] = useState<ApiServerInfo | null>(null);
 
   const handleCloseEditor = useCallback(() => {
     deselectNote();
@@ -85,6 +97,21 @@ function App() {
     loadWorkspaces();
   }, [loadWorkspaces]);
 
+  useEffect(() => {
+    setRenameOnSave(settings.renameOnSave);
+  }, [settings.renameOnSave, setRenameOnSave]);
+
+  useEffect(() => {
+    if (!settings.localApi) {
+      invoke("stop_api_server").catch(() => {});
+      setApiServer(null);
+      return;
+    }
+    invoke<ApiServerInfo>("start_api_server")
+      .then(setApiServer)
+      .catch((error) => debugLog("app:api", { error: errorMessage(error) }));
+  }, [settings.localApi]);
+
   useEffect(() => {
     function handleKeyDown(e: KeyboardEvent) {
       const target = e.target as HTMLElement;
@@ -153,6 +180,20 @@ function App() {
     sidebarFocused,
   ]);
 
+  // Notes opened from the command line, Finder or a write:// link.
+  useEffect(() => {
+    function openRequested() {
+      invoke<{ path: string } | null>("take_open_request")
+        .then((request) => request && selectNote(request.path))
+        .catch((error) => console.error("Failed to open note:", errorMessage(error)));
+    }
+    openRequested();
+    const unlisten = listen("note:open-requested", openRequested);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [selectNote]);
+
   useEffect(() => {
     const unlisten = listen("tauri://focus", () => {
       loadNotes();
@@ -162,6 +203,59 @@ function App() {
     };
   }, [loadNotes]);
 
+  useEffect(() => {
+    const unlisteners = [
+      listen("note:added", () => {
+        loadNotes();
+      }),
+      listen("note:removed", () => {
+        loadNotes();
+      }),
+      listen<{ old_path: string; new_path: string }>("note:renamed", (event) => {
+        applyRename(event.payload.old_path, event.payload.new_path);
+      }),
+      listen<{ old_path: string; new_path: string }>(
+        "note:externally-renamed",
+        (event) => {
+          applyRename(event.payload.old_path, event.payload.new_path);
+          loadNotes();
+        },
+      ),
+      listen<{ old_path: string }>("note:deleted", (event) => {
+        applyDeletion(event.payload.old_path);
+      }),
+      listen("workspace:missing", () => {
+        setWorkspaceMissing(true);
+      }),
+      listen<{ path: string; encoding: string }>("note:transcoded", (event) => {
+        setTranscodedFrom(event.payload.encoding);
+      }),
+      listen("workspaces:changed", () => {
+        loadWorkspaces();
+      }),
+      listen<{ path: string; content?: string }>(
+        "note:externally-changed",
+        (event) => {
+          if (event.payload.content !== undefined) {
+            applyExternalChange(event.payload.path, event.payload.content);
+          }
+          loadNotes();
+        },
+      ),
+    ];
+    return () => {
+      for (const unlisten of unlisteners) {
+        unlisten.then((fn) => fn());
+      }
+    };
+  }, [
+    loadNotes,
+    loadWorkspaces,
+    applyExternalChange,
+    applyRename,
+    applyDeletion,
+  ]);
+
   const prevWorkspaceRef = useRef(activeWorkspaceId);
 
   useEffect(() => {
@@ -237,6 +331,7 @@ function App() {
           <Editor
             key={`${selectedPath}-${settings.vimMode}`}
             vimMode={settings.vimMode}
+            grammarCheck={settings.grammarCheck}
             onClose={handleCloseEditor}
           />
         ) : (
@@ -272,6 +367,18 @@ function App() {
         onClose={() => setOpenModal(null)}
         vimMode={settings.vimMode}
         onVimModeChange={(v) => setSetting("vimMode", v)}
+        renameOnSave={settings.renameOnSave}
+        onRenameOnSaveChange={(v) => setSetting("renameOnSave", v)}
+        grammarCheck={settings.grammarCheck}
+        onGrammarCheckChange={(v) => setSetting("grammarCheck", v)}
+        localApi={settings.localApi}
+        onLocalApiChange={(v) => setSetting("localApi", v)}
+        apiServer={apiServer}
+        onRegenerateApiToken={() =>
+          invoke<string>("regenerate_api_token").then((token) =>
+            setApiServer((prev) => (prev ? { ...prev, token } : prev)),
+          )
+        }
       />
 
       <WorkspaceSwitcher
@@ -322,6 +429,81 @@ function App() {
         </div>
       </Modal>
 
+      <Modal
+        isOpen={conflict !== null}
+        onClose={() => resolveConflict("mine")}
+        title="Page changed on disk"
+      >
+        <p className="text-sm text-[var(--color-muted)] mb-4">
+          This page was modified outside Write while you had unsaved changes.
+        </p>
+        <div className="flex gap-2 justify-end">
+          <button
+            onClick={() => resolveConflict("disk")}
+            className="px-3 py-1.5 text-sm rounded-[var(--radius-sm)] hover:bg-[var(--color-sidebar)] transition-colors"
+          >
+            Use disk version
+          </button>
+          <button
+            autoFocus
+            onClick={() => resolveConflict("mine")}
+            className="px-3 py-1.5 text-sm rounded-[var(--radius-sm)] bg-[var(--color-accent)] text-white transition-opacity hover:opacity-90"
+          >
+            Keep mine
+          </button>
+        </div>
+      </Modal>
+
+      <Modal
+        isOpen={transcodedFrom !== null}
+        onClose={() => setTranscodedFrom(null)}
+        title="Note converted to UTF-8"
+      >
+        <p className="text-sm text-[var(--color-muted)] mb-4">
+          This note was saved as {transcodedFrom} and has been converted for
+          editing. It will be written back as UTF-8 the next time it's saved.
+        </p>
+        <div className="flex gap-2 justify-end">
+          <button
+            autoFocus
+            onClick={() => setTranscodedFrom(null)}
+            className="px-3 py-1.5 text-sm rounded-[var(--radius-sm)] bg-[var(--color-accent)] text-white transition-opacity hover:opacity-90"
+          >
+            OK
+          </button>
+        </div>
+      </Modal>
+
+      <Modal
+        isOpen={workspaceMissing}
+        onClose={() => setWorkspaceMissing(false)}
+        title="Workspace folder missing"
+      >
+        <p className="text-sm text-[var(--color-muted)] mb-4">
+          The folder for "{activeWorkspace?.name}" was deleted or its volume is
+          no longer available.
+        </p>
+        <div className="flex gap-2 justify-end">
+          <button
+            onClick={() => setWorkspaceMissing(false)}
+            className="px-3 py-1.5 text-sm rounded-[var(--radius-sm)] hover:bg-[var(--color-sidebar)] transition-colors"
+          >
+            Dismiss
+          </button>
+          <button
+            autoFocus
+            onClick={async () => {
+              await invoke("recreate_workspace_dir");
+              setWorkspaceMissing(false);
+              loadNotes();
+            }}
+            className="px-3 py-1.5 text-sm rounded-[var(--radius-sm)] bg-[var(--color-accent)] text-white transition-opacity hover:opacity-90"
+          >
+            Recreate folder
+          </button>
+        </div>
+      </Modal>
+
       <DebugPanel
         isOpen={openModal === "debug"}
         onClose={() => setOpenModal(null)}
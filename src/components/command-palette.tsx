@@ -97,27 +97,70 @@ export function CommandPalette({
       : []),
   ];
 
-  const noteItems: NoteItem[] = notes.map((n) => ({
-    type: "note",
-    path: n.path,
-    title: n.title,
-  }));
-  const allItems: PaletteItem[] = [...commands, ...noteItems];
+  const noteItems: NoteItem[] = useMemo(
+    () =>
+      notes.map((n) => ({
+        type: "note",
+        path: n.path,
+        title: n.title,
+      })),
+    [notes],
+  );
 
-  const fuse = useMemo(
+  // Indexing thousands of notes is the expensive part, so the note index is
+  // only rebuilt when the notes change, not on every render.
+  const noteFuse = useMemo(
     () =>
-      new Fuse(allItems, {
+      new Fuse(noteItems, {
         keys: ["title"],
         threshold: 0.4,
-        includeScore: true,
       }),
-    [allItems],
+    [noteItems],
   );
 
-  const results = useMemo(() => {
-    if (!query.trim()) return allItems;
-    return fuse.search(query).map((r) => r.item);
-  }, [query, fuse, allItems]);
+  const [contentHits, setContentHits] = useState<NoteItem[]>([]);
+
+  useEffect(() => {
+    const q = query.trim();
+    if (!isOpen || !q) {
+      setContentHits([]);
+      return;
+    }
+    let cancelled = false;
+    const timer = setTimeout(() => {
+      invoke<NoteEntry[]>("search_notes", { query: q, limit: 20 })
+        .then((hits) => {
+          if (cancelled) return;
+          setContentHits(
+            hits.map((n) => ({ type: "note", path: n.path, title: n.title })),
+          );
+        })
+        .catch(() => {
+          if (!cancelled) setContentHits([]);
+        });
+    }, 150);
+    return () => {
+      cancelled = true;
+      clearTimeout(timer);
+    };
+  }, [query, isOpen]);
+
+  const results: PaletteItem[] = useMemo(() => {
+    if (!query.trim()) return [...commands, ...noteItems];
+    const commandHits = new Fuse(commands, {
+      keys: ["title"],
+      threshold: 0.4,
+    })
+      .search(query)
+      .map((r) => r.item);
+    const titleHits = noteFuse.search(query).map((r) => r.item);
+    const seen = new Set(titleHits.map((n) => n.path));
+    return [
+      ...commandHits,
+      ...titleHits,
+      ...contentHits.filter((n) => !seen.has(n.path)),
+    ];
+  }, [query, commands, noteItems, noteFuse, contentHits]);
 
   useEffect(() => {
     if (isOpen) {
@@ -10,9 +10,16 @@ import {
   syntaxHighlighting,
   syntaxTree,
 } from "@codemirror/language";
-import { Compartment, EditorState, RangeSetBuilder } from "@codemirror/state";
+import {
+  Compartment,
+  EditorState,
+  RangeSetBuilder,
+  StateEffect,
+  StateField,
+} from "@codemirror/state";
 import {
   Decoration,
+  type DecorationSet,
   EditorView,
   ViewPlugin,
   ViewUpdate,
@@ -22,9 +29,10 @@ import {
 } from "@codemirror/view";
 import { tags } from "@lezer/highlight";
 import { Vim, vim } from "@replit/codemirror-vim";
+import { invoke } from "@tauri-apps/api/core";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { useEffect, useRef } from "react";
-import { useNotesStore } from "../stores/notes-store";
+import { errorMessage, useNotesStore } from "../stores/notes-store";
 
 const markdownHighlight = HighlightStyle.define([
   {
@@ -154,12 +162,56 @@ const clickableLinks = EditorView.domEventHandlers({
   },
 });
 
+interface GrammarIssue {
+  message: string;
+  from: number;
+  to: number;
+  replacements: string[];
+}
+
+const GRAMMAR_CHECK_DELAY = 1500;
+
+const setGrammarIssues = StateEffect.define<GrammarIssue[]>();
+
+const grammarIssues = StateField.define<DecorationSet>({
+  create: () => Decoration.none,
+  update(decorations, tr) {
+    let next = decorations.map(tr.changes);
+    for (const effect of tr.effects) {
+      if (!effect.is(setGrammarIssues)) continue;
+      const length = tr.state.doc.length;
+      next = Decoration.set(
+        effect.value
+          .filter((issue) => issue.from < issue.to && issue.to <= length)
+          .map((issue) =>
+            Decoration.mark({
+              class: "cm-grammar-issue",
+              attributes: {
+                title: issue.replacements.length
+                  ? `${issue.message} (${issue.replacements.slice(0, 3).join(", ")})`
+                  : issue.message,
+              },
+            }).range(issue.from, issue.to),
+          ),
+        true,
+      );
+    }
+    return next;
+  },
+  provide: (field) => EditorView.decorations.from(field),
+});
+
+function formatMegabytes(bytes: number): string {
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
 interface EditorProps {
   vimMode: boolean;
+  grammarCheck: boolean;
   onClose: () => void;
 }
 
-export function Editor({ vimMode, onClose }: EditorProps) {
+export function Editor({ vimMode, grammarCheck, onClose }: EditorProps) {
   const containerRef = useRef<HTMLDivElement>(null);
   const viewRef = useRef<EditorView | null>(null);
   const titleInputRef = useRef<HTMLInputElement>(null);
@@ -167,6 +219,7 @@ export function Editor({ vimMode, onClose }: EditorProps) {
 
   const selectedPath = useNotesStore((s) => s.selectedPath);
   const noteContent = useNotesStore((s) => s.noteContent);
+  const contentRevision = useNotesStore((s) => s.contentRevision);
   const isCreating = useNotesStore((s) => s.isCreating);
   const setTitle = useNotesStore((s) => s.setTitle);
   const setBody = useNotesStore((s) => s.setBody);
@@ -219,12 +272,15 @@ export function Editor({ vimMode, onClose }: EditorProps) {
         markdown(),
         syntaxHighlighting(markdownHighlight),
         bulletPlugin,
+        grammarIssues,
         clickableLinks,
         history(),
         keymap.of([indentWithTab, ...defaultKeymap, ...historyKeymap]),
         placeholder("Start writing..."),
         updateListener,
         EditorView.lineWrapping,
+        EditorState.readOnly.of(!!noteContent.readOnly),
+        EditorView.editable.of(!noteContent.readOnly),
       ],
     });
 
@@ -241,7 +297,27 @@ export function Editor({ vimMode, onClose }: EditorProps) {
       }
       view.destroy();
     };
-  }, [selectedPath, vimMode]);
+  }, [selectedPath, vimMode, contentRevision]);
+
+  useEffect(() => {
+    const view = viewRef.current;
+    if (!view) return;
+    if (!grammarCheck) {
+      view.dispatch({ effects: setGrammarIssues.of([]) });
+      return;
+    }
+    const text = view.state.doc.toString();
+    const timer = setTimeout(() => {
+      invoke<GrammarIssue[]>("check_grammar", { text })
+        .then((issues) => {
+          // Offsets are only valid for the text that was checked.
+          if (viewRef.current?.state.doc.toString() !== text) return;
+          viewRef.current.dispatch({ effects: setGrammarIssues.of(issues) });
+        })
+        .catch((err) => console.error("Grammar check failed:", errorMessage(err)));
+    }, GRAMMAR_CHECK_DELAY);
+    return () => clearTimeout(timer);
+  }, [grammarCheck, noteContent?.body, selectedPath, contentRevision]);
 
   function handleTitleChange(newTitle: string) {
     setTitle(newTitle);
@@ -269,7 +345,16 @@ export function Editor({ vimMode, onClose }: EditorProps) {
               onKeyDown={handleTitleKeyDown}
               placeholder="New Page"
               className="editor-title"
+              readOnly={!!noteContent.readOnly}
             />
+            {noteContent.readOnly && (
+              <p className="text-sm text-[var(--color-muted)] mt-2">
+                Read-only preview of the first{" "}
+                {formatMegabytes(noteContent.readOnly.shown)} of{" "}
+                {formatMegabytes(noteContent.readOnly.size)}. This note is
+                over the size limit for editing.
+              </p>
+            )}
           </div>
           <div ref={containerRef} className="px-12 pb-12" />
         </div>
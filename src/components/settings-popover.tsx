@@ -1,10 +1,23 @@
 import { Modal } from "./modal";
 
+export interface ApiServerInfo {
+  url: string;
+  token: string;
+}
+
 interface SettingsPopoverProps {
   isOpen: boolean;
   onClose: () => void;
   vimMode: boolean;
   onVimModeChange: (enabled: boolean) => void;
+  renameOnSave: boolean;
+  onRenameOnSaveChange: (enabled: boolean) => void;
+  grammarCheck: boolean;
+  onGrammarCheckChange: (enabled: boolean) => void;
+  localApi: boolean;
+  onLocalApiChange: (enabled: boolean) => void;
+  apiServer: ApiServerInfo | null;
+  onRegenerateApiToken: () => void;
 }
 
 export function SettingsPopover({
@@ -12,6 +25,14 @@ export function SettingsPopover({
   onClose,
   vimMode,
   onVimModeChange,
+  renameOnSave,
+  onRenameOnSaveChange,
+  grammarCheck,
+  onGrammarCheckChange,
+  localApi,
+  onLocalApiChange,
+  apiServer,
+  onRegenerateApiToken,
 }: SettingsPopoverProps) {
   return (
     <Modal isOpen={isOpen} onClose={onClose} title="Settings">
@@ -30,6 +51,69 @@ export function SettingsPopover({
           />
         </button>
       </label>
+      <label className="flex items-center justify-between cursor-pointer mt-3">
+        <span className="text-sm">Rename files while typing titles</span>
+        <button
+          onClick={() => onRenameOnSaveChange(!renameOnSave)}
+          className={`relative w-10 h-6 rounded-full transition-colors ${
+            renameOnSave
+              ? "bg-[var(--color-accent)]"
+              : "bg-[var(--color-border)]"
+          }`}
+        >
+          <span
+            className={`absolute top-1 left-1 w-4 h-4 bg-white rounded-full shadow transition-transform ${
+              renameOnSave ? "translate-x-4" : "translate-x-0"
+            }`}
+          />
+        </button>
+      </label>
+      <label className="flex items-center justify-between cursor-pointer mt-3">
+        <span className="text-sm">Grammar check (local LanguageTool)</span>
+        <button
+          onClick={() => onGrammarCheckChange(!grammarCheck)}
+          className={`relative w-10 h-6 rounded-full transition-colors ${
+            grammarCheck
+              ? "bg-[var(--color-accent)]"
+              : "bg-[var(--color-border)]"
+          }`}
+        >
+          <span
+            className={`absolute top-1 left-1 w-4 h-4 bg-white rounded-full shadow transition-transform ${
+              grammarCheck ? "translate-x-4" : "translate-x-0"
+            }`}
+          />
+        </button>
+      </label>
+      <label className="flex items-center justify-between cursor-pointer mt-3">
+        <span className="text-sm">Local API (localhost only)</span>
+        <button
+          onClick={() => onLocalApiChange(!localApi)}
+          className={`relative w-10 h-6 rounded-full transition-colors ${
+            localApi ? "bg-[var(--color-accent)]" : "bg-[var(--color-border)]"
+          }`}
+        >
+          <span
+            className={`absolute top-1 left-1 w-4 h-4 bg-white rounded-full shadow transition-transform ${
+              localApi ? "translate-x-4" : "translate-x-0"
+            }`}
+          />
+        </button>
+      </label>
+      {localApi && apiServer && (
+        <div className="mt-2 text-xs text-[var(--color-muted)] space-y-1">
+          <div className="select-text">{apiServer.url}</div>
+          <div className="select-text font-mono break-all">
+            {apiServer.token}
+          </div>
+          <button
+            onClick={onRegenerateApiToken}
+            className="underline hover:text-[var(--color-text)]"
+          >
+            Regenerate token
+          </button>
+        </div>
+      )}
     </Modal>
   );
 }
@@ -2,10 +2,18 @@ import { useEffect, useState } from "react";
 
 interface Settings {
   vimMode: boolean;
+  renameOnSave: boolean;
+  grammarCheck: boolean;
+  localApi: boolean;
 }
 
 const STORAGE_KEY = "write-settings";
-const DEFAULT_SETTINGS: Settings = { vimMode: false };
+const DEFAULT_SETTINGS: Settings = {
+  vimMode: false,
+  renameOnSave: true,
+  grammarCheck: false,
+  localApi: false,
+};
 
 function loadSettings(): Settings {
   try {
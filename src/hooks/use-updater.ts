@@ -1,40 +1,59 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { relaunch } from "@tauri-apps/plugin-process";
-import { check, type Update } from "@tauri-apps/plugin-updater";
-import { useEffect, useState } from "react";
+import { useEffect, useRef, useState } from "react";
+import { errorMessage } from "../stores/notes-store";
+
+export interface UpdateInfo {
+  version: string;
+  current_version: string;
+  notes: string | null;
+  date: string | null;
+}
+
+interface UpdateProgress {
+  downloaded: number;
+  total: number | null;
+}
 
 export function useUpdater() {
-  const [updateAvailable, setUpdateAvailable] = useState<Update | null>(null);
+  const [updateAvailable, setUpdateAvailable] = useState<UpdateInfo | null>(
+    null,
+  );
   const [readyToInstall, setReadyToInstall] = useState(false);
   const [isDownloading, setIsDownloading] = useState(false);
+  const [progress, setProgress] = useState<UpdateProgress | null>(null);
+  const [upToDate, setUpToDate] = useState(false);
+  const checking = useRef(false);
+  const installed = useRef(false);
 
-  async function checkAndDownload() {
+  // Checks the configured channel and installs what it finds. `manual`
+  // checks also report when there's nothing new.
+  async function checkAndDownload(manual = false) {
+    if (checking.current || installed.current) return;
+    checking.current = true;
+    setUpToDate(false);
     try {
       console.log("[updater] checking for updates...");
-      const update = await check();
+      const update = await invoke<UpdateInfo | null>("check_for_updates", {
+        install: false,
+      });
 
       if (!update) {
         console.log("[updater] no update available");
+        if (manual) setUpToDate(true);
         return;
       }
 
       console.log("[updater] update available:", update.version);
       setUpdateAvailable(update);
       setIsDownloading(true);
-
-      await update.downloadAndInstall((event) => {
-        if (event.event === "Started") {
-          console.log("[updater] download started");
-        } else if (event.event === "Progress") {
-          console.log("[updater] downloading...", event.data.chunkLength);
-        } else if (event.event === "Finished") {
-          console.log("[updater] download finished, ready to install");
-          setIsDownloading(false);
-          setReadyToInstall(true);
-        }
-      });
+      await invoke("check_for_updates", { install: true });
     } catch (error) {
-      console.error("[updater] error:", error);
+      console.error("[updater] error:", errorMessage(error));
       setIsDownloading(false);
+    } finally {
+      checking.current = false;
     }
   }
 
@@ -46,15 +65,32 @@ export function useUpdater() {
   useEffect(() => {
     checkAndDownload();
 
+    const unlisten = [
+      listen<UpdateProgress>("update:progress", (event) =>
+        setProgress(event.payload),
+      ),
+      listen<UpdateInfo>("update:ready", () => {
+        console.log("[updater] update installed, ready to restart");
+        installed.current = true;
+        setIsDownloading(false);
+        setReadyToInstall(true);
+      }),
+      listen("update:check-requested", () => checkAndDownload(true)),
+    ];
     const interval = setInterval(checkAndDownload, 15 * 60 * 1000);
-    return () => clearInterval(interval);
+    return () => {
+      clearInterval(interval);
+      for (const promise of unlisten) promise.then((fn) => fn());
+    };
   }, []);
 
   return {
     updateAvailable,
     readyToInstall,
     isDownloading,
+    progress,
+    upToDate,
     restartAndInstall,
-    checkForUpdates: checkAndDownload,
+    checkForUpdates: () => checkAndDownload(true),
   };
 }
@@ -242,6 +242,46 @@ describe("notes store", () => {
     });
   });
 
+  describe("applyExternalChange", () => {
+    it("reloads content when the open note is clean", async () => {
+      await store.getState().loadNotes();
+      await store.getState().selectNote("/notes/001-hello.md");
+
+      store
+        .getState()
+        .applyExternalChange("/notes/001-hello.md", "# Hello\nEdited elsewhere");
+
+      expect(store.getState().noteContent?.body).toBe("Edited elsewhere");
+      expect(store.getState().contentRevision).toBe(1);
+      expect(store.getState().conflict).toBeNull();
+    });
+
+    it("raises a conflict when the open note has unsaved changes", async () => {
+      await store.getState().loadNotes();
+      await store.getState().selectNote("/notes/001-hello.md");
+      store.getState().setBody("Local edit");
+
+      store
+        .getState()
+        .applyExternalChange("/notes/001-hello.md", "# Hello\nEdited elsewhere");
+
+      expect(store.getState().noteContent?.body).toBe("Local edit");
+      expect(store.getState().conflict?.diskContent).toBe(
+        "# Hello\nEdited elsewhere",
+      );
+    });
+
+    it("ignores changes to other notes", async () => {
+      await store.getState().loadNotes();
+      await store.getState().selectNote("/notes/001-hello.md");
+
+      store.getState().applyExternalChange("/notes/002-world.md", "# World\nx");
+
+      expect(store.getState().noteContent?.body).toBe("Hello body");
+      expect(store.getState().contentRevision).toBe(0);
+    });
+  });
+
   describe("workspaces", () => {
     it("loads workspaces", async () => {
       await store.getState().loadWorkspaces();
@@ -6,24 +6,109 @@ export interface NoteEntry {
   name: string;
   path: string;
   modified: number;
+  created?: number | null;
+  id?: string | null;
   title: string;
 }
 
+/** Errors from commands that report a kind; the rest reject with a string. */
+export interface AppError {
+  kind:
+    | "not_found"
+    | "conflict"
+    | "permission_denied"
+    | "invalid_input"
+    | "too_large"
+    | "io"
+    | "other";
+  message: string;
+  io_kind?: string;
+  size?: number;
+  limit?: number;
+}
+
+export function errorMessage(err: unknown): string {
+  if (typeof err === "object" && err !== null && "message" in err) {
+    return String((err as AppError).message);
+  }
+  return String(err);
+}
+
 export interface NoteContent {
   title: string;
   body: string;
   isDirty: boolean;
+  /** Set for notes over the size limit, shown as a preview of their start. */
+  readOnly?: { size: number; shown: number };
+}
+
+/** How much of an oversized note is shown. */
+const PREVIEW_BYTES = 1024 * 1024;
+
+export interface FormatRules {
+  format_on_save: boolean;
+  heading_spacing: boolean;
+  list_marker: string | null;
+  trim_trailing_whitespace: boolean;
+  tidy_reference_links: boolean;
+  smart_typography: boolean;
 }
 
 export interface Workspace {
   id: string;
   name: string;
   shortcut: string | null;
+  ignore_patterns?: string[];
+  hidden_patterns?: string[];
+  format_rules?: FormatRules;
+  encrypted?: boolean;
+  biometric_unlock?: boolean;
+  mcp_access?: "none" | "read" | "read_write";
+  word_goal?: { daily?: number | null; weekly?: number | null };
+  export_theme?: string | null;
+  auto_archive?: { months: number } | null;
+  extra_roots?: string[];
+}
+
+export interface NoteConflict {
+  path: string;
+  diskContent: string;
+}
+
+export interface EmailSettings {
+  host: string;
+  port: number;
+  username: string;
+  mailbox: string;
+}
+
+export interface S3Target {
+  endpoint: string;
+  bucket: string;
+  region: string;
+  access_key_id: string;
+  prefix: string;
+  public_url: string | null;
 }
 
 interface WorkspaceConfig {
+  version?: number;
   workspaces: Workspace[];
   active_workspace_id: string;
+  calendars?: string[];
+  email?: EmailSettings | null;
+  publish?: { s3?: S3Target | null };
+  transliterate_file_names?: boolean;
+  log_level?: "off" | "error" | "warn" | "info" | "debug" | "trace";
+  update_channel?: "stable" | "beta";
+  max_note_bytes?: number | null;
+  inbox?: { dir: string; workspace_id: string } | null;
+  tools?: {
+    pandoc?: string | null;
+    whisper?: string | null;
+    git?: string | null;
+    mermaid?: string | null;
+  };
 }
 
 export function parseContent(content: string): { title: string; body: string } {
@@ -54,6 +139,9 @@ interface NotesState {
 
   selectedPath: string | null;
   noteContent: NoteContent | null;
+  contentRevision: number;
+  conflict: NoteConflict | null;
+  renameOnSave: boolean;
 }
 
 type Invoker = typeof tauriInvoke;
@@ -75,6 +163,12 @@ interface NotesActions {
   setTitle: (title: string) => void;
   setBody: (body: string) => void;
   flush: () => Promise<void>;
+  setRenameOnSave: (enabled: boolean) => void;
+
+  applyExternalChange: (path: string, content: string) => void;
+  applyRename: (oldPath: string, newPath: string) => void;
+  applyDeletion: (path: string) => void;
+  resolveConflict: (keep: "mine" | "disk") => Promise<void>;
 }
 
 export type NotesStore = NotesState &
@@ -100,245 +194,358 @@ const initialState: NotesState = {
   notesLoading: true,
   selectedPath: null,
   noteContent: null,
+  contentRevision: 0,
+  conflict: null,
+  renameOnSave: true,
 };
 
 export function createNotesStore(invoker: Invoker = tauriInvoke) {
   const store = create<NotesStore>()(
-    immer((set, get) => ({
-      ...initialState,
-
-      get isCreating() {
-        return get().selectedPath?.startsWith("temp-") ?? false;
-      },
-
-      get activeWorkspace() {
-        const { workspaces, activeWorkspaceId } = get();
-        return workspaces.find((w) => w.id === activeWorkspaceId) ?? null;
-      },
-
-      loadWorkspaces: async () => {
+    immer((set, get) => {
+      // Saves the open note and, when renaming on save is off, gives it the
+      // filename for its title now that editing is done.
+      async function leaveNote() {
+        const { selectedPath, renameOnSave, isCreating } = get();
+        await get().flush();
+        if (renameOnSave || !selectedPath || isCreating) return;
         try {
-          const config = await invoker<WorkspaceConfig>("get_workspaces");
-          set((state) => {
-            state.workspaces = config.workspaces;
-            state.activeWorkspaceId = config.active_workspace_id;
-            state.workspacesLoading = false;
+          const newPath = await invoker<string>("sync_filename", {
+            path: selectedPath,
           });
+          if (newPath !== selectedPath) get().applyRename(selectedPath, newPath);
         } catch (err) {
-          console.error("Failed to load workspaces:", err);
-          set((state) => {
-            state.workspacesLoading = false;
-          });
+          console.error("Failed to sync filename:", errorMessage(err));
         }
-      },
-
-      switchWorkspace: async (workspaceId: string) => {
-        await get().flush();
-        await invoker("set_active_workspace", { workspaceId });
-        set((state) => {
-          state.activeWorkspaceId = workspaceId;
-          state.selectedPath = null;
-          state.noteContent = null;
-        });
-      },
-
-      createWorkspace: async (name: string) => {
-        const workspace = await invoker<Workspace>("create_workspace", {
-          name,
-        });
-        set((state) => {
-          state.workspaces.push(workspace);
-        });
-        return workspace;
-      },
-
-      deleteWorkspace: async (workspaceId: string) => {
-        await invoker("delete_workspace", { workspaceId });
-        set((state) => {
-          state.workspaces = state.workspaces.filter(
-            (w) => w.id !== workspaceId,
-          );
-          if (state.activeWorkspaceId === workspaceId) {
-            state.activeWorkspaceId = state.workspaces[0]?.id ?? null;
+      }
+
+      return {
+        ...initialState,
+
+        get isCreating() {
+          return get().selectedPath?.startsWith("temp-") ?? false;
+        },
+
+        get activeWorkspace() {
+          const { workspaces, activeWorkspaceId } = get();
+          return workspaces.find((w) => w.id === activeWorkspaceId) ?? null;
+        },
+
+        loadWorkspaces: async () => {
+          try {
+            const config = await invoker<WorkspaceConfig>("get_workspaces");
+            set((state) => {
+              state.workspaces = config.workspaces;
+              state.activeWorkspaceId = config.active_workspace_id;
+              state.workspacesLoading = false;
+            });
+          } catch (err) {
+            console.error("Failed to load workspaces:", errorMessage(err));
+            set((state) => {
+              state.workspacesLoading = false;
+            });
           }
-        });
-      },
-
-      renameWorkspace: async (workspaceId: string, newName: string) => {
-        const updated = await invoker<Workspace>("rename_workspace", {
-          workspaceId,
-          newName,
-        });
-        set((state) => {
-          const idx = state.workspaces.findIndex((w) => w.id === workspaceId);
-          if (idx !== -1) state.workspaces[idx] = updated;
-        });
-        return updated;
-      },
-
-      loadNotes: async () => {
-        try {
-          await invoker("ensure_notes_dir");
-          const entries = await invoker<NoteEntry[]>("list_notes");
+        },
+
+        switchWorkspace: async (workspaceId: string) => {
+          await leaveNote();
+          await invoker("set_active_workspace", { workspaceId });
           set((state) => {
-            state.notes = entries;
-            state.notesLoading = false;
+            state.activeWorkspaceId = workspaceId;
+            state.selectedPath = null;
+            state.noteContent = null;
+          });
+        },
+
+        createWorkspace: async (name: string) => {
+          const workspace = await invoker<Workspace>("create_workspace", {
+            name,
           });
-        } catch (err) {
-          console.error("Failed to load notes:", err);
           set((state) => {
-            state.notesLoading = false;
+            state.workspaces.push(workspace);
           });
-        }
-      },
+          return workspace;
+        },
 
-      selectNote: async (path: string) => {
-        await get().flush();
-        try {
-          const text = await invoker<string>("read_note", { path });
-          const { title, body } = parseContent(text);
+        deleteWorkspace: async (workspaceId: string) => {
+          await invoker("delete_workspace", { workspaceId });
           set((state) => {
-            state.selectedPath = path;
-            state.noteContent = { title, body, isDirty: false };
+            state.workspaces = state.workspaces.filter(
+              (w) => w.id !== workspaceId,
+            );
+            if (state.activeWorkspaceId === workspaceId) {
+              state.activeWorkspaceId = state.workspaces[0]?.id ?? null;
+            }
           });
-        } catch (err) {
-          console.error("Failed to read note:", err);
-        }
-      },
+        },
 
-      deselectNote: () => {
-        set((state) => {
-          state.selectedPath = null;
-          state.noteContent = null;
-        });
-      },
-
-      createNote: async () => {
-        await get().flush();
-        const tempPath = `temp-${Date.now()}`;
-        const tempNote: NoteEntry = {
-          name: tempPath,
-          path: tempPath,
-          modified: Date.now(),
-          title: "New Page",
-        };
-        set((state) => {
-          state.notes.unshift(tempNote);
-          state.selectedPath = tempPath;
-          state.noteContent = { title: "", body: "", isDirty: false };
-        });
-
-        try {
-          const realPath = await invoker<string>("create_note");
+        renameWorkspace: async (workspaceId: string, newName: string) => {
+          const updated = await invoker<Workspace>("rename_workspace", {
+            workspaceId,
+            newName,
+          });
           set((state) => {
-            const idx = state.notes.findIndex((n) => n.path === tempPath);
-            if (idx !== -1) {
-              state.notes[idx].path = realPath;
-              state.notes[idx].name = realPath.split("/").pop()!;
-            }
-            state.selectedPath = realPath;
+            const idx = state.workspaces.findIndex((w) => w.id === workspaceId);
+            if (idx !== -1) state.workspaces[idx] = updated;
           });
-        } catch (err) {
-          console.error("Failed to create note:", err);
+          return updated;
+        },
+
+        loadNotes: async () => {
+          try {
+            await invoker("ensure_notes_dir");
+            const entries = await invoker<NoteEntry[]>("list_notes");
+            set((state) => {
+              state.notes = entries;
+              state.notesLoading = false;
+            });
+          } catch (err) {
+            console.error("Failed to load notes:", errorMessage(err));
+            set((state) => {
+              state.notesLoading = false;
+            });
+          }
+        },
+
+        selectNote: async (path: string) => {
+          await leaveNote();
+          try {
+            const text = await invoker<string>("read_note", { path });
+            const { title, body } = parseContent(text);
+            set((state) => {
+              state.selectedPath = path;
+              state.noteContent = { title, body, isDirty: false };
+              state.conflict = null;
+            });
+          } catch (err) {
+            if ((err as AppError)?.kind === "too_large") {
+              const chunk = await invoker<{
+                content: string;
+                next_offset: number;
+                total_size: number;
+              }>("read_note_chunk", { path, offset: 0, length: PREVIEW_BYTES });
+              const { title, body } = parseContent(chunk.content);
+              set((state) => {
+                state.selectedPath = path;
+                state.noteContent = {
+                  title,
+                  body,
+                  isDirty: false,
+                  readOnly: { size: chunk.total_size, shown: chunk.next_offset },
+                };
+                state.conflict = null;
+              });
+              return;
+            }
+            console.error("Failed to read note:", errorMessage(err));
+          }
+        },
+
+        deselectNote: () => {
+          leaveNote();
           set((state) => {
-            state.notes = state.notes.filter((n) => n.path !== tempPath);
             state.selectedPath = null;
             state.noteContent = null;
           });
-        }
-      },
-
-      deleteNote: async (path: string) => {
-        try {
-          await invoker("delete_note", { path });
+        },
+
+        createNote: async () => {
+          await leaveNote();
+          const tempPath = `temp-${Date.now()}`;
+          const tempNote: NoteEntry = {
+            name: tempPath,
+            path: tempPath,
+            modified: Date.now(),
+            title: "New Page",
+          };
           set((state) => {
-            state.notes = state.notes.filter((n) => n.path !== path);
-            if (state.selectedPath === path) {
+            state.notes.unshift(tempNote);
+            state.selectedPath = tempPath;
+            state.noteContent = { title: "", body: "", isDirty: false };
+          });
+
+          try {
+            const realPath = await invoker<string>("create_note");
+            set((state) => {
+              const idx = state.notes.findIndex((n) => n.path === tempPath);
+              if (idx !== -1) {
+                state.notes[idx].path = realPath;
+                state.notes[idx].name = realPath.split("/").pop()!;
+              }
+              state.selectedPath = realPath;
+            });
+          } catch (err) {
+            console.error("Failed to create note:", errorMessage(err));
+            set((state) => {
+              state.notes = state.notes.filter((n) => n.path !== tempPath);
               state.selectedPath = null;
               state.noteContent = null;
+            });
+          }
+        },
+
+        deleteNote: async (path: string) => {
+          try {
+            await invoker("delete_note", { path });
+            set((state) => {
+              state.notes = state.notes.filter((n) => n.path !== path);
+              if (state.selectedPath === path) {
+                state.selectedPath = null;
+                state.noteContent = null;
+              }
+            });
+          } catch (err) {
+            console.error("Failed to delete note:", errorMessage(err));
+          }
+        },
+
+        reorderNote: async (path: string, newIndex: number) => {
+          try {
+            const newPath = await invoker<string>("reorder_note", {
+              path,
+              newIndex,
+            });
+            set((state) => {
+              if (state.selectedPath === path) {
+                state.selectedPath = newPath;
+              }
+            });
+            await get().loadNotes();
+          } catch (err) {
+            console.error("Failed to reorder note:", errorMessage(err));
+          }
+        },
+
+        setTitle: (title: string) => {
+          set((state) => {
+            if (state.noteContent) {
+              state.noteContent.title = title;
+              state.noteContent.isDirty = true;
             }
+            const note = state.notes.find((n) => n.path === state.selectedPath);
+            if (note) note.title = title || "New Page";
           });
-        } catch (err) {
-          console.error("Failed to delete note:", err);
-        }
-      },
+          if (!get().isCreating) {
+            schedulePersist(store);
+          }
+        },
 
-      reorderNote: async (path: string, newIndex: number) => {
-        try {
-          const newPath = await invoker<string>("reorder_note", {
-            path,
-            newIndex,
-          });
+        setBody: (body: string) => {
           set((state) => {
-            if (state.selectedPath === path) {
-              state.selectedPath = newPath;
+            if (state.noteContent) {
+              state.noteContent.body = body;
+              state.noteContent.isDirty = true;
             }
           });
-          await get().loadNotes();
-        } catch (err) {
-          console.error("Failed to reorder note:", err);
-        }
-      },
+          if (!get().isCreating) {
+            schedulePersist(store);
+          }
+        },
+
+        flush: async () => {
+          const { noteContent, selectedPath, isCreating, conflict } = get();
+          if (
+            !noteContent ||
+            !selectedPath ||
+            !noteContent.isDirty ||
+            isCreating ||
+            conflict?.path === selectedPath
+          ) {
+            return;
+          }
 
-      setTitle: (title: string) => {
-        set((state) => {
-          if (state.noteContent) {
-            state.noteContent.title = title;
-            state.noteContent.isDirty = true;
+          const content = buildContent(noteContent.title, noteContent.body);
+          try {
+            const newPath = await invoker<string>("write_note", {
+              path: selectedPath,
+              content,
+              rename: get().renameOnSave,
+            });
+            set((state) => {
+              if (state.noteContent) state.noteContent.isDirty = false;
+              if (newPath !== selectedPath) {
+                state.selectedPath = newPath;
+                const note = state.notes.find((n) => n.path === selectedPath);
+                if (note) {
+                  note.path = newPath;
+                  note.name = newPath.split("/").pop()!;
+                }
+              }
+            });
+          } catch (err) {
+            console.error("Failed to save note:", errorMessage(err));
           }
-          const note = state.notes.find((n) => n.path === state.selectedPath);
-          if (note) note.title = title || "New Page";
-        });
-        if (!get().isCreating) {
-          schedulePersist(store);
-        }
-      },
+        },
+
+        setRenameOnSave: (enabled: boolean) => {
+          set((state) => {
+            state.renameOnSave = enabled;
+          });
+        },
 
-      setBody: (body: string) => {
-        set((state) => {
-          if (state.noteContent) {
-            state.noteContent.body = body;
-            state.noteContent.isDirty = true;
+        applyExternalChange: (path: string, content: string) => {
+          const { selectedPath, noteContent } = get();
+          if (path !== selectedPath || !noteContent) return;
+          if (content === buildContent(noteContent.title, noteContent.body)) {
+            return;
           }
-        });
-        if (!get().isCreating) {
-          schedulePersist(store);
-        }
-      },
-
-      flush: async () => {
-        const { noteContent, selectedPath, isCreating } = get();
-        if (
-          !noteContent ||
-          !selectedPath ||
-          !noteContent.isDirty ||
-          isCreating
-        ) {
-          return;
-        }
+          if (noteContent.isDirty) {
+            set((state) => {
+              state.conflict = { path, diskContent: content };
+            });
+            return;
+          }
+          const { title, body } = parseContent(content);
+          set((state) => {
+            state.noteContent = { title, body, isDirty: false };
+            state.contentRevision++;
+            const note = state.notes.find((n) => n.path === path);
+            if (note) note.title = title || "Untitled";
+          });
+        },
 
-        const content = buildContent(noteContent.title, noteContent.body);
-        try {
-          const newPath = await invoker<string>("write_note", {
-            path: selectedPath,
-            content,
+        applyRename: (oldPath: string, newPath: string) => {
+          set((state) => {
+            const note = state.notes.find((n) => n.path === oldPath);
+            if (note) {
+              note.path = newPath;
+              note.name = newPath.split("/").pop()!;
+            }
+            if (state.selectedPath === oldPath) state.selectedPath = newPath;
+            if (state.conflict?.path === oldPath) state.conflict.path = newPath;
           });
+        },
+
+        applyDeletion: (path: string) => {
           set((state) => {
-            if (state.noteContent) state.noteContent.isDirty = false;
-            if (newPath !== selectedPath) {
-              state.selectedPath = newPath;
-              const note = state.notes.find((n) => n.path === selectedPath);
-              if (note) {
-                note.path = newPath;
-                note.name = newPath.split("/").pop()!;
-              }
+            state.notes = state.notes.filter((n) => n.path !== path);
+            if (state.selectedPath === path) {
+              state.selectedPath = null;
+              state.noteContent = null;
             }
           });
-        } catch (err) {
-          console.error("Failed to save note:", err);
-        }
-      },
-    })),
+        },
+
+        resolveConflict: async (keep: "mine" | "disk") => {
+          const { conflict } = get();
+          if (!conflict) return;
+          if (keep === "mine") {
+            set((state) => {
+              state.conflict = null;
+            });
+            await get().flush();
+            return;
+          }
+          const { title, body } = parseContent(conflict.diskContent);
+          set((state) => {
+            state.conflict = null;
+            if (state.selectedPath === conflict.path) {
+              state.noteContent = { title, body, isDirty: false };
+              state.contentRevision++;
+            }
+          });
+        },
+      };
+    }),
   );
 
   return store;